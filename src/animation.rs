@@ -0,0 +1,79 @@
+/// Frames shown one after the other, each for so many ticks. What a frame is is up to
+/// whatever draws it: a part of a sprite sheet, or the size and color of a shape.
+pub struct Animation<F: 'static> {
+    /// each frame and how many ticks it's shown for
+    pub frames: &'static [(F, u32)],
+    /// going back to the first frame after the last one, rather than being finished
+    pub looping: bool,
+}
+
+impl<F> Animation<F> {
+    /// Ticks from the start of the first frame to the end of the last
+    pub fn length(&self) -> u32 {
+        self.frames.iter().map(|(_, ticks)| ticks).sum()
+    }
+
+    /// The frame showing `ticks` after the animation started, `None` once one that doesn't
+    /// loop is over
+    pub fn frame(&self, ticks: u32) -> Option<&F> {
+        let length = self.length();
+        if length == 0 || (!self.looping && ticks >= length) {
+            return None;
+        }
+        let mut left = ticks % length;
+        for (frame, ticks) in self.frames {
+            if left < *ticks {
+                return Some(frame);
+            }
+            left -= ticks;
+        }
+        unreachable!()
+    }
+}
+
+/// How far into an animation something is. It's counted in the level's ticks, so animations
+/// play at the same speed however fast the game renders, stand still while it's paused and
+/// look the same in a replay.
+#[derive(Clone, Copy, Default)]
+pub struct Playing {
+    ticks: u32,
+}
+
+impl Playing {
+    pub fn update(&mut self) {
+        self.ticks = self.ticks.saturating_add(1);
+    }
+
+    pub fn restart(&mut self) {
+        self.ticks = 0;
+    }
+
+    pub fn frame<'a, F>(&self, animation: &'a Animation<F>) -> Option<&'a F> {
+        animation.frame(self.ticks)
+    }
+
+    pub fn is_finished<F>(&self, animation: &Animation<F>) -> bool {
+        !animation.looping && self.ticks >= animation.length()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_last_their_ticks_and_only_loops_start_over() {
+        let mut animation = Animation {
+            frames: &[('a', 2), ('b', 1)],
+            looping: false,
+        };
+        let frames: Vec<_> = (0..4)
+            .map(|ticks| animation.frame(ticks).copied())
+            .collect();
+        assert_eq!(frames, [Some('a'), Some('a'), Some('b'), None]);
+
+        animation.looping = true;
+        assert_eq!(animation.frame(3), Some(&'a'));
+        assert_eq!(animation.frame(5), Some(&'b'));
+    }
+}
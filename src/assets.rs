@@ -0,0 +1,288 @@
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use sdl2::{
+    audio::{AudioCVT, AudioFormat, AudioSpecWAV},
+    image::LoadTexture,
+    pixels::{Color, PixelFormatEnum},
+    render::{BlendMode, Texture, TextureCreator},
+    ttf::{Font, Sdl2TtfContext},
+    video::WindowContext,
+};
+
+use crate::audio::{Sound, SAMPLE_RATE};
+
+/// How many lines written in fonts are kept as textures before they're all thrown away, for
+/// text that changes every so often not to pile up
+const WRITTEN: usize = 64;
+
+/// A sprite loaded by `Assets`, to draw it by without looking it up by its path every frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpriteId(usize);
+
+/// A font loaded by `Assets` at one size, to write with by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontId(usize);
+
+/// Everything the game draws and plays that isn't made from scratch every frame: the files
+/// under the assets directory, loaded once, and textures made once and handed out again every
+/// frame, as making new ones each frame is slow and has the GPU run out of room before long
+pub struct Assets<'a> {
+    creator: &'a TextureCreator<WindowContext>,
+    ttf: &'a Sdl2TtfContext,
+    /// where the game's files are, everything but the ones named on the command line being
+    /// looked up under it
+    directory: PathBuf,
+    /// kept in `RefCell`s, like the textures below, so they can be tinted while drawing
+    sprites: Vec<RefCell<Texture<'a>>>,
+    /// which sprite each one is, by the path it was loaded from
+    sprite_ids: HashMap<PathBuf, SpriteId>,
+    /// images read from their files off the main thread, by their paths, for loading them
+    /// to only have to make them into textures
+    read: HashMap<PathBuf, Vec<u8>>,
+    fonts: Vec<Font<'a, 'static>>,
+    /// which font each one is, by the path and the size in points it was loaded at
+    font_ids: HashMap<(PathBuf, u16), FontId>,
+    /// lines written in the fonts, by the font, the line and its color, so text that stays
+    /// the same from frame to frame is only made into a texture once
+    written: RefCell<HashMap<(FontId, String, Color), Texture<'a>>>,
+    /// render targets handed back, by their size
+    targets: RefCell<HashMap<(u32, u32), Vec<Texture<'a>>>>,
+    /// a single white pixel, tinted and stretched to fill shapes a canvas can't draw itself
+    pixel: RefCell<Texture<'a>>,
+    /// white light shining up from the bottom middle, spreading out and fading away
+    light_cone: RefCell<Texture<'a>>,
+    /// a round white glow fading out from the middle
+    glow: RefCell<Texture<'a>>,
+    /// every sound that loaded, as mono samples at the rate the audio plays them
+    sounds: HashMap<Sound, Vec<f32>>,
+}
+
+impl<'a> Assets<'a> {
+    pub fn new(
+        creator: &'a TextureCreator<WindowContext>,
+        ttf: &'a Sdl2TtfContext,
+        directory: impl Into<PathBuf>,
+    ) -> Assets<'a> {
+        let mut pixel = creator
+            .create_texture_static(PixelFormatEnum::RGBA8888, 1, 1)
+            .unwrap();
+        pixel.update(None, &[255; 4], 4).unwrap();
+        pixel.set_blend_mode(BlendMode::Blend);
+        let light_cone = gradient(creator, 64, 128, |x, y| {
+            // 0 where the light comes from at the bottom, 1 where it fades out at the top
+            let distance = 1. - y;
+            let spread = (x - 0.5).abs() / (0.5 * distance).max(f64::EPSILON);
+            (1. - spread).max(0.).sqrt() * (1. - distance).sqrt()
+        });
+        let glow = gradient(creator, 32, 32, |x, y| {
+            let distance = ((x - 0.5).powi(2) + (y - 0.5).powi(2)).sqrt() * 2.;
+            (1. - distance).max(0.).powi(2)
+        });
+        let directory = directory.into();
+        let mut sounds = HashMap::new();
+        for sound in Sound::ALL {
+            let path = directory.join(sound.path());
+            match load_samples(&path) {
+                Ok(samples) => {
+                    sounds.insert(sound, samples);
+                }
+                Err(error) => eprintln!("couldn't load the sound {}: {error}", path.display()),
+            }
+        }
+        Assets {
+            creator,
+            ttf,
+            directory,
+            sprites: Vec::new(),
+            sprite_ids: HashMap::new(),
+            read: HashMap::new(),
+            fonts: Vec::new(),
+            font_ids: HashMap::new(),
+            written: RefCell::new(HashMap::new()),
+            targets: RefCell::new(HashMap::new()),
+            pixel: RefCell::new(pixel),
+            light_cone: RefCell::new(light_cone),
+            glow: RefCell::new(glow),
+            sounds,
+        }
+    }
+
+    /// Where the file at `path` under the assets directory is
+    pub fn path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.directory.join(path)
+    }
+
+    /// Loads the image at `path` under the assets directory, unless it's loaded already. One
+    /// that fails to load is left out, for whatever draws it to fall back on something
+    /// plainer.
+    pub fn load_sprite(&mut self, path: &str) -> Option<SpriteId> {
+        self.load_image(self.path(path))
+    }
+
+    /// Loads the image at `path` as it is instead, for the ones next to a file named on the
+    /// command line
+    pub fn load_image(&mut self, path: PathBuf) -> Option<SpriteId> {
+        if let Some(&id) = self.sprite_ids.get(&path) {
+            return Some(id);
+        }
+        let texture = match self.read.remove(&path) {
+            Some(bytes) => self.creator.load_texture_bytes(&bytes),
+            None => self.creator.load_texture(&path),
+        };
+        match texture {
+            Ok(texture) => {
+                let id = SpriteId(self.sprites.len());
+                self.sprites.push(RefCell::new(texture));
+                self.sprite_ids.insert(path, id);
+                Some(id)
+            }
+            Err(error) => {
+                eprintln!("couldn't load the sprite {}: {error}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Keeps `images`, read from the files at their paths already, for when they're loaded
+    pub fn read_ahead(&mut self, images: Vec<(PathBuf, Vec<u8>)>) {
+        self.read.extend(images);
+    }
+
+    pub fn sprite(&self, id: SpriteId) -> RefMut<'_, Texture<'a>> {
+        self.sprites[id.0].borrow_mut()
+    }
+
+    /// Loads the font at `path` under the assets directory at `size` points, unless it's
+    /// loaded at that size already. One that fails to load is left out, for whatever writes
+    /// with it to fall back on the built-in font.
+    pub fn load_font(&mut self, path: &str, size: u16) -> Option<FontId> {
+        let path = self.path(path);
+        let key = (path, size);
+        if let Some(&id) = self.font_ids.get(&key) {
+            return Some(id);
+        }
+        match self.ttf.load_font(&key.0, size) {
+            Ok(font) => {
+                let id = FontId(self.fonts.len());
+                self.fonts.push(font);
+                self.font_ids.insert(key, id);
+                Some(id)
+            }
+            Err(error) => {
+                eprintln!("couldn't load the font {}: {error}", key.0.display());
+                None
+            }
+        }
+    }
+
+    pub fn font(&self, id: FontId) -> &Font<'a, 'static> {
+        &self.fonts[id.0]
+    }
+
+    /// `line` written in `font`, on a texture just big enough for it, made the first time it's
+    /// asked for and handed out again after. Nothing comes out for a blank line.
+    pub fn write(&self, font: FontId, line: &str, color: Color) -> Option<Ref<'_, Texture<'a>>> {
+        let key = (font, line.to_string(), color);
+        if !self.written.borrow().contains_key(&key) {
+            let surface = self.font(font).render(line).blended(color).ok()?;
+            let texture = self.creator.create_texture_from_surface(&surface).ok()?;
+            let mut written = self.written.borrow_mut();
+            if written.len() >= WRITTEN {
+                written.clear();
+            }
+            written.insert(key.clone(), texture);
+        }
+        Ref::filter_map(self.written.borrow(), |written| written.get(&key)).ok()
+    }
+
+    /// A render target `width` by `height` pixels, one handed back earlier if there is one.
+    /// Whatever was drawn on it last is still there. Hand it back with `give_back` once it's
+    /// been copied where it's going.
+    pub fn target(&self, width: u32, height: u32) -> Texture<'a> {
+        let reused = self
+            .targets
+            .borrow_mut()
+            .get_mut(&(width, height))
+            .and_then(Vec::pop);
+        reused.unwrap_or_else(|| {
+            self.creator
+                .create_texture_target(None, width, height)
+                .unwrap()
+        })
+    }
+
+    pub fn give_back(&self, texture: Texture<'a>) {
+        let query = texture.query();
+        self.targets
+            .borrow_mut()
+            .entry((query.width, query.height))
+            .or_default()
+            .push(texture);
+    }
+
+    pub fn pixel(&self) -> RefMut<'_, Texture<'a>> {
+        self.pixel.borrow_mut()
+    }
+
+    pub fn light_cone(&self) -> RefMut<'_, Texture<'a>> {
+        self.light_cone.borrow_mut()
+    }
+
+    pub fn glow(&self) -> RefMut<'_, Texture<'a>> {
+        self.glow.borrow_mut()
+    }
+
+    /// What `sound` sounds like, unless it didn't load
+    pub fn sound(&self, sound: Sound) -> Option<&[f32]> {
+        self.sounds.get(&sound).map(Vec::as_slice)
+    }
+}
+
+/// The wave file at `path`, in the one format the audio's played in whatever it was saved in
+fn load_samples(path: &Path) -> Result<Vec<f32>, String> {
+    let wav = AudioSpecWAV::load_wav(path)?;
+    let converter = AudioCVT::new(
+        wav.format,
+        wav.channels,
+        wav.freq,
+        AudioFormat::f32_sys(),
+        1,
+        SAMPLE_RATE,
+    )?;
+    let bytes = converter.convert(wav.buffer().to_vec());
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|sample| f32::from_ne_bytes(sample.try_into().unwrap()))
+        .collect())
+}
+
+/// A white texture that's as opaque as `alpha` says for each pixel, given where it is from
+/// 0 to 1 across and down. Lights up whatever it's drawn over rather than covering it.
+fn gradient<'a>(
+    creator: &'a TextureCreator<WindowContext>,
+    width: usize,
+    height: usize,
+    alpha: impl Fn(f64, f64) -> f64,
+) -> Texture<'a> {
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for row in 0..height {
+        for column in 0..width {
+            let (x, y) = (
+                (column as f64 + 0.5) / width as f64,
+                (row as f64 + 0.5) / height as f64,
+            );
+            let opacity = (alpha(x, y).clamp(0., 1.) * 255.) as u8;
+            pixels.extend([255, 255, 255, opacity]);
+        }
+    }
+    let mut texture = creator
+        .create_texture_static(PixelFormatEnum::RGBA32, width as u32, height as u32)
+        .unwrap();
+    texture.update(None, &pixels, width * 4).unwrap();
+    texture.set_blend_mode(BlendMode::Add);
+    texture
+}
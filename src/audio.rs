@@ -0,0 +1,93 @@
+use sdl2::{
+    audio::{AudioQueue, AudioSpecDesired},
+    Sdl,
+};
+
+use crate::assets::Assets;
+
+/// Samples a second, which every sound is converted to when it's loaded. The queue's opened
+/// at it without allowing for anything else, so SDL makes up the difference with the device.
+pub const SAMPLE_RATE: i32 = 44_100;
+
+/// Something for the game to play, loaded by `Assets` from its file under `sounds`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sound {
+    /// a short beep for each second counted down to the start
+    Countdown,
+    /// a long, higher one as the lights go green
+    Go,
+    /// a low buzz for going too early
+    JumpStart,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 3] = [Sound::Countdown, Sound::Go, Sound::JumpStart];
+
+    /// Where it's loaded from under the assets directory
+    pub fn path(self) -> &'static str {
+        match self {
+            Sound::Countdown => "sounds/countdown.wav",
+            Sound::Go => "sounds/go.wav",
+            Sound::JumpStart => "sounds/jump_start.wav",
+        }
+    }
+}
+
+/// Plays sounds one after the other on the default audio device. Without one the game goes on
+/// silently.
+pub struct Audio {
+    queue: Option<AudioQueue<f32>>,
+    /// how loud everything is, from 0 to 1
+    volume: f64,
+}
+
+impl Audio {
+    pub fn new(sdl: &Sdl) -> Audio {
+        let spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+        let queue = sdl.audio().and_then(|audio| audio.open_queue(None, &spec));
+        match queue {
+            Ok(queue) => {
+                queue.resume();
+                Audio {
+                    queue: Some(queue),
+                    volume: 1.,
+                }
+            }
+            Err(error) => {
+                eprintln!("couldn't open the audio device, so there's no sound: {error}");
+                Audio {
+                    queue: None,
+                    volume: 1.,
+                }
+            }
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f64) {
+        self.volume = volume.clamp(0., 1.);
+    }
+
+    /// Plays `sounds` with the samples `assets` loaded for them, leaving out any that didn't
+    /// load
+    pub fn play(&mut self, assets: &Assets, sounds: &[Sound]) {
+        let Some(queue) = &self.queue else {
+            return;
+        };
+        for &sound in sounds {
+            let Some(samples) = assets.sound(sound) else {
+                continue;
+            };
+            let samples: Vec<_> = samples
+                .iter()
+                .map(|sample| sample * self.volume as f32)
+                .collect();
+            if let Err(error) = queue.queue_audio(&samples) {
+                eprintln!("couldn't play a sound: {error}");
+            }
+        }
+    }
+}
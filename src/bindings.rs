@@ -0,0 +1,424 @@
+use std::path::Path;
+
+use sdl2::{controller::Button, keyboard::Scancode};
+
+use crate::{
+    config::{self, ConfigError, Table, Value},
+    input::{AxisResponse, Command},
+    wheel::WheelSpec,
+};
+
+/// Something the player can do by holding or pressing a key or button
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Throttle,
+    Brake,
+    SteerLeft,
+    SteerRight,
+    Handbrake,
+    Boost,
+    ShiftUp,
+    ShiftDown,
+    ToggleManualShifting,
+    ToggleTractionControl,
+    ToggleAbs,
+    ToggleStabilityControl,
+    Respawn,
+    Repair,
+    ToggleTowing,
+    Quit,
+    Pause,
+    CameraMode,
+    ToggleFreeCamera,
+    ZoomIn,
+    ZoomOut,
+    ToggleFullscreen,
+    ToggleRain,
+    ToggleDebugOverlay,
+    ToggleFrameCounter,
+    Screenshot,
+    ToggleTrails,
+    NextFilter,
+    NextTool,
+    Undo,
+    TestDrive,
+    SaveTrack,
+    Back,
+    ToggleConsole,
+}
+
+impl Action {
+    pub const ALL: [Action; 34] = [
+        Action::Throttle,
+        Action::Brake,
+        Action::SteerLeft,
+        Action::SteerRight,
+        Action::Handbrake,
+        Action::Boost,
+        Action::ShiftUp,
+        Action::ShiftDown,
+        Action::ToggleManualShifting,
+        Action::ToggleTractionControl,
+        Action::ToggleAbs,
+        Action::ToggleStabilityControl,
+        Action::Respawn,
+        Action::Repair,
+        Action::ToggleTowing,
+        Action::Quit,
+        Action::Pause,
+        Action::CameraMode,
+        Action::ToggleFreeCamera,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ToggleFullscreen,
+        Action::ToggleRain,
+        Action::ToggleDebugOverlay,
+        Action::ToggleFrameCounter,
+        Action::Screenshot,
+        Action::ToggleTrails,
+        Action::NextFilter,
+        Action::NextTool,
+        Action::Undo,
+        Action::TestDrive,
+        Action::SaveTrack,
+        Action::Back,
+        Action::ToggleConsole,
+    ];
+
+    /// What the action is called in the bindings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Throttle => "throttle",
+            Action::Brake => "brake",
+            Action::SteerLeft => "steer_left",
+            Action::SteerRight => "steer_right",
+            Action::Handbrake => "handbrake",
+            Action::Boost => "boost",
+            Action::ShiftUp => "shift_up",
+            Action::ShiftDown => "shift_down",
+            Action::ToggleManualShifting => "toggle_manual_shifting",
+            Action::ToggleTractionControl => "toggle_traction_control",
+            Action::ToggleAbs => "toggle_abs",
+            Action::ToggleStabilityControl => "toggle_stability_control",
+            Action::Respawn => "respawn",
+            Action::Repair => "repair",
+            Action::ToggleTowing => "toggle_towing",
+            Action::Quit => "quit",
+            Action::Pause => "pause",
+            Action::CameraMode => "camera_mode",
+            Action::ToggleFreeCamera => "toggle_free_camera",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::ToggleFullscreen => "toggle_fullscreen",
+            Action::ToggleRain => "toggle_rain",
+            Action::ToggleDebugOverlay => "toggle_debug_overlay",
+            Action::ToggleFrameCounter => "toggle_frame_counter",
+            Action::Screenshot => "screenshot",
+            Action::ToggleTrails => "toggle_trails",
+            Action::NextFilter => "next_filter",
+            Action::NextTool => "next_tool",
+            Action::Undo => "undo",
+            Action::TestDrive => "test_drive",
+            Action::SaveTrack => "save_track",
+            Action::Back => "back",
+            Action::ToggleConsole => "toggle_console",
+        }
+    }
+
+    /// The one-off request pressing this makes, `None` for the ones that are held
+    pub fn command(self) -> Option<Command> {
+        match self {
+            Action::Throttle
+            | Action::Brake
+            | Action::SteerLeft
+            | Action::SteerRight
+            | Action::Handbrake
+            | Action::Boost => None,
+            Action::ShiftUp => Some(Command::ShiftUp),
+            Action::ShiftDown => Some(Command::ShiftDown),
+            Action::ToggleManualShifting => Some(Command::ToggleManualShifting),
+            Action::ToggleTractionControl => Some(Command::ToggleTractionControl),
+            Action::ToggleAbs => Some(Command::ToggleAbs),
+            Action::ToggleStabilityControl => Some(Command::ToggleStabilityControl),
+            Action::Respawn => Some(Command::Respawn),
+            Action::Repair => Some(Command::Repair),
+            Action::ToggleTowing => Some(Command::ToggleTowing),
+            Action::Quit => Some(Command::Quit),
+            Action::Pause => Some(Command::TogglePause),
+            Action::CameraMode => Some(Command::NextCameraMode),
+            Action::ToggleFreeCamera => Some(Command::ToggleFreeCamera),
+            Action::ZoomIn => Some(Command::ZoomIn),
+            Action::ZoomOut => Some(Command::ZoomOut),
+            Action::ToggleFullscreen => Some(Command::ToggleFullscreen),
+            Action::ToggleRain => Some(Command::ToggleRain),
+            Action::ToggleDebugOverlay => Some(Command::ToggleDebugOverlay),
+            Action::ToggleFrameCounter => Some(Command::ToggleFrameCounter),
+            Action::Screenshot => Some(Command::Screenshot),
+            Action::ToggleTrails => Some(Command::ToggleTrails),
+            Action::NextFilter => Some(Command::NextFilter),
+            Action::NextTool => Some(Command::NextTool),
+            Action::Undo => Some(Command::Undo),
+            Action::TestDrive => Some(Command::TestDrive),
+            Action::SaveTrack => Some(Command::SaveTrack),
+            Action::Back => Some(Command::Back),
+            Action::ToggleConsole => Some(Command::ToggleConsole),
+        }
+    }
+}
+
+/// Which keys and controller buttons do what. An action can have any number of them.
+pub struct Bindings {
+    keys: Vec<(Action, Scancode)>,
+    buttons: Vec<(Action, Button)>,
+    /// buttons on the wheel by their number, as wheels don't have names for them
+    wheel_buttons: Vec<(Action, u8)>,
+    /// how to read a racing wheel, `None` to leave joysticks alone
+    wheel: Option<WheelSpec>,
+    /// how hard controllers rumble, 1 being full strength and 0 off
+    rumble: f64,
+    /// steers towards the mouse cursor, with the left button for throttle and the right one
+    /// for the brake
+    mouse_steering: bool,
+    /// how the left stick's travel turns into steering
+    steering_response: AxisResponse,
+    throttle_response: AxisResponse,
+    brake_response: AxisResponse,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings {
+            keys: vec![
+                (Action::Throttle, Scancode::W),
+                (Action::Brake, Scancode::S),
+                (Action::SteerLeft, Scancode::A),
+                (Action::SteerRight, Scancode::D),
+                (Action::Handbrake, Scancode::Space),
+                (Action::Boost, Scancode::N),
+                (Action::ShiftUp, Scancode::LShift),
+                (Action::ShiftDown, Scancode::LCtrl),
+                (Action::ToggleManualShifting, Scancode::M),
+                (Action::ToggleTractionControl, Scancode::F1),
+                (Action::ToggleAbs, Scancode::F2),
+                (Action::ToggleStabilityControl, Scancode::F6),
+                (Action::Respawn, Scancode::R),
+                (Action::Repair, Scancode::P),
+                (Action::ToggleTowing, Scancode::T),
+                (Action::Back, Scancode::Escape),
+                (Action::Quit, Scancode::Q),
+                (Action::Pause, Scancode::Return),
+                (Action::CameraMode, Scancode::C),
+                (Action::ToggleFreeCamera, Scancode::F4),
+                (Action::ZoomIn, Scancode::Equals),
+                (Action::ZoomOut, Scancode::Minus),
+                (Action::ToggleFullscreen, Scancode::F11),
+                (Action::ToggleRain, Scancode::F5),
+                (Action::ToggleDebugOverlay, Scancode::F3),
+                (Action::ToggleFrameCounter, Scancode::F10),
+                (Action::Screenshot, Scancode::F12),
+                (Action::ToggleTrails, Scancode::F7),
+                (Action::NextFilter, Scancode::F8),
+                (Action::NextTool, Scancode::Tab),
+                (Action::Undo, Scancode::Z),
+                (Action::TestDrive, Scancode::F9),
+                (Action::SaveTrack, Scancode::Home),
+                (Action::ToggleConsole, Scancode::Grave),
+            ],
+            buttons: vec![
+                (Action::Throttle, Button::A),
+                (Action::Brake, Button::X),
+                (Action::Handbrake, Button::B),
+                (Action::Boost, Button::Y),
+                (Action::ShiftUp, Button::RightShoulder),
+                (Action::ShiftDown, Button::LeftShoulder),
+                (Action::Respawn, Button::Back),
+                (Action::Pause, Button::Start),
+                (Action::CameraMode, Button::RightStick),
+            ],
+            wheel_buttons: Vec::new(),
+            wheel: None,
+            rumble: 1.,
+            mouse_steering: false,
+            steering_response: AxisResponse::STICK,
+            throttle_response: AxisResponse::TRIGGER,
+            brake_response: AxisResponse::TRIGGER,
+        }
+    }
+}
+
+impl Bindings {
+    /// Arrows and the keys around them, leaving the rest of the keyboard for the first player
+    fn second_player() -> Bindings {
+        Bindings {
+            keys: vec![
+                (Action::Throttle, Scancode::Up),
+                (Action::Brake, Scancode::Down),
+                (Action::SteerLeft, Scancode::Left),
+                (Action::SteerRight, Scancode::Right),
+                (Action::Handbrake, Scancode::RCtrl),
+                (Action::Boost, Scancode::RShift),
+                (Action::ShiftUp, Scancode::PageUp),
+                (Action::ShiftDown, Scancode::PageDown),
+                (Action::Respawn, Scancode::Backspace),
+                (Action::CameraMode, Scancode::End),
+            ],
+            ..Bindings::default()
+        }
+    }
+
+    /// Reads a bindings file, with the first player's bindings at the top and the second
+    /// player's under `[second_player]`. Every action it lists loses its built-in keys or
+    /// buttons, the rest keep them.
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<Bindings>, ConfigError> {
+        let file = config::load(path)?;
+        let mut first = Bindings::default();
+        first.read(&file)?;
+        let mut second = Bindings::second_player();
+        if let Some(table) = file.table("second_player")? {
+            second.read(table)?;
+        }
+        Ok(vec![first, second])
+    }
+
+    /// Built-in bindings for `players` players
+    pub fn for_players(players: usize) -> Vec<Bindings> {
+        let mut bindings = vec![Bindings::default(), Bindings::second_player()];
+        bindings.truncate(players);
+        bindings
+    }
+
+    /// Overwrites whatever `table` specifies
+    fn read(&mut self, table: &Table) -> Result<(), ConfigError> {
+        table.read_number("rumble", &mut self.rumble)?;
+        table.read_bool("mouse_steering", &mut self.mouse_steering)?;
+
+        if let Some(keyboard) = table.table("keyboard")? {
+            read_table(keyboard, "keyboard", &mut self.keys, |name| {
+                Scancode::from_name(name.as_str()?)
+            })?;
+        }
+        if let Some(controller) = table.table("controller")? {
+            read_table(controller, "controller", &mut self.buttons, |name| {
+                Button::from_string(name.as_str()?)
+            })?;
+        }
+        if let Some(axes) = table.table("axes")? {
+            for (name, response) in [
+                ("steering", &mut self.steering_response),
+                ("throttle", &mut self.throttle_response),
+                ("brake", &mut self.brake_response),
+            ] {
+                if let Some(axis) = axes.table(name)? {
+                    response.read(axis)?;
+                }
+            }
+        }
+        if let Some(wheel) = table.table("wheel")? {
+            let mut spec = WheelSpec::default();
+            spec.read(wheel)?;
+            self.wheel = Some(spec);
+            if let Some(buttons) = wheel.table("buttons")? {
+                read_table(
+                    buttons,
+                    "wheel.buttons",
+                    &mut self.wheel_buttons,
+                    |number| number.as_number().map(|number| number as u8),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `key` for `action` from now on, in place of the keys it had
+    pub fn bind_key(&mut self, action: Action, key: Scancode) {
+        self.keys.retain(|(bound, _)| *bound != action);
+        self.keys.push((action, key));
+    }
+
+    pub fn key_actions(&self, scancode: Scancode) -> impl Iterator<Item = Action> + '_ {
+        actions_of(&self.keys, scancode)
+    }
+
+    pub fn button_actions(&self, button: Button) -> impl Iterator<Item = Action> + '_ {
+        actions_of(&self.buttons, button)
+    }
+
+    pub fn wheel_button_actions(&self, button: u8) -> impl Iterator<Item = Action> + '_ {
+        actions_of(&self.wheel_buttons, button)
+    }
+
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = Scancode> + '_ {
+        bound_to(&self.keys, action)
+    }
+
+    pub fn buttons(&self, action: Action) -> impl Iterator<Item = Button> + '_ {
+        bound_to(&self.buttons, action)
+    }
+
+    pub fn wheel_buttons(&self, action: Action) -> impl Iterator<Item = u8> + '_ {
+        bound_to(&self.wheel_buttons, action)
+    }
+
+    pub fn wheel(&self) -> Option<&WheelSpec> {
+        self.wheel.as_ref()
+    }
+
+    pub fn rumble(&self) -> f64 {
+        self.rumble
+    }
+
+    pub fn mouse_steering(&self) -> bool {
+        self.mouse_steering
+    }
+
+    pub fn steering_response(&self) -> &AxisResponse {
+        &self.steering_response
+    }
+
+    pub fn throttle_response(&self) -> &AxisResponse {
+        &self.throttle_response
+    }
+
+    pub fn brake_response(&self) -> &AxisResponse {
+        &self.brake_response
+    }
+}
+
+fn actions_of<T: PartialEq>(bound: &[(Action, T)], input: T) -> impl Iterator<Item = Action> + '_ {
+    bound
+        .iter()
+        .filter(move |(_, bound_input)| *bound_input == input)
+        .map(|&(action, _)| action)
+}
+
+fn bound_to<T: Copy>(bound: &[(Action, T)], action: Action) -> impl Iterator<Item = T> + '_ {
+    bound
+        .iter()
+        .filter(move |(bound_action, _)| *bound_action == action)
+        .map(|&(_, input)| input)
+}
+
+/// Replaces the bindings of every action in `table` with the list it has there
+fn read_table<T>(
+    table: &Table,
+    table_name: &str,
+    bound: &mut Vec<(Action, T)>,
+    parse: impl Fn(&Value) -> Option<T>,
+) -> Result<(), ConfigError> {
+    for action in Action::ALL {
+        let Some(names) = table.array(action.name())? else {
+            continue;
+        };
+        let inputs = names
+            .iter()
+            .map(&parse)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ConfigError::WrongType {
+                key: format!("{table_name}.{}", action.name()),
+                expected: "a list of keys or buttons",
+            })?;
+        bound.retain(|(bound_action, _)| *bound_action != action);
+        bound.extend(inputs.into_iter().map(|input| (action, input)));
+    }
+    Ok(())
+}
@@ -0,0 +1,501 @@
+use std::path::Path;
+
+use nalgebra::{Point2, Rotation2, Vector2};
+use sdl2::rect::{FPoint, FRect, Rect};
+
+use crate::{
+    car::Car,
+    config::{self, ConfigError, Table},
+    track::Track,
+    TICKS_PER_SECOND,
+};
+
+/// The ways a camera can follow its car, switched between while playing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    /// hangs back loosely and looks ahead to where the car is going
+    Chase,
+    /// stays right on top of the car
+    Locked,
+    /// turns with the car so it always points up the screen
+    Hood,
+}
+
+impl CameraMode {
+    pub fn next(self) -> CameraMode {
+        match self {
+            CameraMode::Chase => CameraMode::Locked,
+            CameraMode::Locked => CameraMode::Hood,
+            CameraMode::Hood => CameraMode::Chase,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CameraMode> {
+        match name {
+            "chase" => Some(CameraMode::Chase),
+            "locked" => Some(CameraMode::Locked),
+            "hood" => Some(CameraMode::Hood),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Chase => "chase",
+            CameraMode::Locked => "locked",
+            CameraMode::Hood => "hood",
+        }
+    }
+}
+
+/// How one of the camera modes follows the car
+#[derive(Clone)]
+pub struct FollowSpec {
+    pub smoothing: f64,
+    /// how many ticks' worth of the car's velocity ahead of it the camera aims, 0 to stay on
+    /// the car itself
+    pub look_ahead: f64,
+    /// turns with the car instead of keeping north up
+    pub turns: bool,
+    pub rotation_smoothing: f64,
+}
+
+impl FollowSpec {
+    fn new(smoothing: f64, look_ahead: f64, turns: bool) -> FollowSpec {
+        FollowSpec {
+            smoothing,
+            look_ahead,
+            turns,
+            rotation_smoothing: 0.1,
+        }
+    }
+
+    /// Overwrites whatever `table` specifies
+    fn read(&mut self, table: &Table) -> Result<(), ConfigError> {
+        table.read_number("smoothing", &mut self.smoothing)?;
+        table.read_number("look_ahead", &mut self.look_ahead)?;
+        table.read_bool("turns", &mut self.turns)?;
+        table.read_number("rotation_smoothing", &mut self.rotation_smoothing)?;
+        Ok(())
+    }
+}
+
+/// How a camera chases its car. Every smoothing is the share of the way to its target it
+/// moves each tick.
+#[derive(Clone)]
+pub struct CameraSpec {
+    pub chase: FollowSpec,
+    pub locked: FollowSpec,
+    pub hood: FollowSpec,
+    /// furthest ahead of the car it aims, in pixels
+    pub max_look_ahead: f64,
+    /// how much more of the car's sideways slide it adds on top of that, to see more of where
+    /// a drift is taking it
+    pub drift_look_ahead: f64,
+    /// low, so bumps and spins don't throw the view around
+    pub look_ahead_smoothing: f64,
+    /// the zoom with the car standing still
+    pub zoom: f64,
+    /// the zoom at `top_speed` and above
+    pub top_speed_zoom: f64,
+    pub top_speed: f64,
+    pub zoom_smoothing: f64,
+    /// pixels of shaking for every pixel per tick a crash or landing hits the car with
+    pub impact_shake: f64,
+    /// pixels of shaking when the nitro kicks in
+    pub nitro_shake: f64,
+    /// the most it shakes however hard the hits
+    pub max_shake: f64,
+    /// how often it shakes back and forth, per second
+    pub shake_frequency: f64,
+    /// share of the shaking left after every tick
+    pub shake_decay: f64,
+    /// how fast a free camera flies, in screen pixels per tick
+    pub fly_speed: f64,
+    /// keeps the view from scrolling past the edges of tracks that have them
+    pub clamp_to_track: bool,
+    /// shows a rear-view mirror at the top of the screen
+    pub mirror: bool,
+    /// how far behind the car the mirror looks
+    pub mirror_distance: f64,
+    pub mirror_zoom: f64,
+}
+
+impl Default for CameraSpec {
+    fn default() -> CameraSpec {
+        CameraSpec {
+            chase: FollowSpec::new(0.2, 20., false),
+            locked: FollowSpec::new(1., 0., false),
+            hood: FollowSpec::new(1., 8., true),
+            max_look_ahead: 250.,
+            drift_look_ahead: 1.,
+            look_ahead_smoothing: 0.05,
+            zoom: 1.15,
+            top_speed_zoom: 0.75,
+            top_speed: 12.,
+            zoom_smoothing: 0.05,
+            impact_shake: 3.,
+            nitro_shake: 6.,
+            max_shake: 30.,
+            shake_frequency: 15.,
+            shake_decay: 0.9,
+            fly_speed: 20.,
+            clamp_to_track: true,
+            mirror: true,
+            mirror_distance: 200.,
+            mirror_zoom: 0.5,
+        }
+    }
+}
+
+impl CameraSpec {
+    /// Reads a spec from a TOML file. Anything the file leaves out keeps its default.
+    pub fn load(path: impl AsRef<Path>) -> Result<CameraSpec, ConfigError> {
+        let file = config::load(path)?;
+        let mut spec = CameraSpec::default();
+        file.read_bool("clamp_to_track", &mut spec.clamp_to_track)?;
+
+        for (name, follow) in [
+            ("chase", &mut spec.chase),
+            ("locked", &mut spec.locked),
+            ("hood", &mut spec.hood),
+        ] {
+            if let Some(table) = file.table(name)? {
+                follow.read(table)?;
+            }
+        }
+        if let Some(look_ahead) = file.table("look_ahead")? {
+            look_ahead.read_number("max", &mut spec.max_look_ahead)?;
+            look_ahead.read_number("drift", &mut spec.drift_look_ahead)?;
+            look_ahead.read_number("smoothing", &mut spec.look_ahead_smoothing)?;
+        }
+        if let Some(zoom) = file.table("zoom")? {
+            zoom.read_number("standing", &mut spec.zoom)?;
+            zoom.read_number("top_speed_zoom", &mut spec.top_speed_zoom)?;
+            zoom.read_number("top_speed", &mut spec.top_speed)?;
+            zoom.read_number("smoothing", &mut spec.zoom_smoothing)?;
+        }
+        if let Some(shake) = file.table("shake")? {
+            shake.read_number("impact", &mut spec.impact_shake)?;
+            shake.read_number("nitro", &mut spec.nitro_shake)?;
+            shake.read_number("max", &mut spec.max_shake)?;
+            shake.read_number("frequency", &mut spec.shake_frequency)?;
+            shake.read_number("decay", &mut spec.shake_decay)?;
+        }
+        if let Some(free) = file.table("free")? {
+            free.read_number("speed", &mut spec.fly_speed)?;
+        }
+        if let Some(mirror) = file.table("mirror")? {
+            mirror.read_bool("shown", &mut spec.mirror)?;
+            mirror.read_number("distance", &mut spec.mirror_distance)?;
+            mirror.read_number("zoom", &mut spec.mirror_zoom)?;
+        }
+        Ok(spec)
+    }
+}
+
+#[derive(Clone)]
+pub struct Camera {
+    pub pos: Point2<f64>,
+    previous_pos: Point2<f64>,
+    /// how far ahead of the car it aims
+    look_ahead: Vector2<f64>,
+    /// where it's heading for, ahead of the car
+    target: Point2<f64>,
+    /// how far the view is turned, the world being drawn turned the other way
+    rotation: Rotation2<f64>,
+    previous_rotation: Rotation2<f64>,
+    mode: CameraMode,
+    /// flies about on its own instead of following the car, for having a look around
+    free: bool,
+    /// screen pixels for every pixel in the world, lower showing more of it
+    zoom: f64,
+    previous_zoom: f64,
+    /// how far the view shakes, in pixels
+    shake: f64,
+    previous_shake: f64,
+    /// ticks since the camera was made, to know where in its shaking it is
+    ticks: u32,
+    /// whether the car's nitro was on, to know when it kicks in
+    boosting: bool,
+    /// width and height of the part of the screen it shows
+    pub view: (i32, i32),
+    spec: CameraSpec,
+}
+
+impl Camera {
+    pub fn new(pos: Point2<f64>, view: (i32, i32), spec: CameraSpec) -> Camera {
+        Camera {
+            pos,
+            previous_pos: pos,
+            look_ahead: Vector2::zeros(),
+            target: pos,
+            rotation: Rotation2::identity(),
+            previous_rotation: Rotation2::identity(),
+            mode: CameraMode::Chase,
+            free: false,
+            zoom: spec.zoom,
+            previous_zoom: spec.zoom,
+            shake: 0.,
+            previous_shake: 0.,
+            ticks: 0,
+            boosting: false,
+            view,
+            spec,
+        }
+    }
+
+    /// The camera as it would be `alpha` of the way between the last two ticks, shaking
+    /// included. Only drawing gets to see the shaking, so aiming at things stays steady.
+    pub fn interpolated(&self, alpha: f64) -> Camera {
+        let steady = self.previous_pos.coords.lerp(&self.pos.coords, alpha);
+        let pos = (steady + self.shake_offset(alpha)).into();
+        let rotation = self.previous_rotation.slerp(&self.rotation, alpha);
+        let zoom = self.previous_zoom + (self.zoom - self.previous_zoom) * alpha;
+        Camera {
+            pos,
+            previous_pos: pos,
+            rotation,
+            previous_rotation: rotation,
+            zoom,
+            previous_zoom: zoom,
+            shake: 0.,
+            previous_shake: 0.,
+            ..self.clone()
+        }
+    }
+
+    /// Two waves at slightly different frequencies across and down, so it doesn't just
+    /// wobble along a line
+    fn shake_offset(&self, alpha: f64) -> Vector2<f64> {
+        let shake = self.previous_shake + (self.shake - self.previous_shake) * alpha;
+        let seconds = (self.ticks as f64 - 1. + alpha) / TICKS_PER_SECOND;
+        let phase = seconds * self.spec.shake_frequency * std::f64::consts::TAU;
+        Vector2::new(phase.sin(), (phase * 1.3 + 1.7).sin()) * shake
+    }
+
+    /// A `view` sized camera looking back from `car`, turned so what's behind it is up the
+    /// screen. `None` with the mirror turned off.
+    pub fn mirror(&self, car: &Car, alpha: f64, view: (i32, i32)) -> Option<Camera> {
+        let spec = &self.spec;
+        if !spec.mirror {
+            return None;
+        }
+        let center = car.interpolated_center(alpha);
+        let rotation = car.interpolated_rotation(alpha) * Rotation2::new(std::f64::consts::PI);
+        let mut mirror = Camera::new(
+            center + rotation * Vector2::new(0., -spec.mirror_distance),
+            view,
+            spec.clone(),
+        );
+        (mirror.rotation, mirror.previous_rotation) = (rotation, rotation);
+        (mirror.zoom, mirror.previous_zoom) = (spec.mirror_zoom, spec.mirror_zoom);
+        Some(mirror)
+    }
+
+    /// The closest to `pos` the camera can be without showing anything outside `bounds`,
+    /// or the middle of them along the way they're narrower than the view
+    fn clamped(&self, pos: Point2<f64>, bounds: (Point2<f64>, Point2<f64>)) -> Point2<f64> {
+        // how far the view reaches out from its center along the world's axes, turned as it is
+        let half_view = self.view_center() / self.zoom;
+        let (cos, sin) = (
+            self.rotation.angle().cos().abs(),
+            self.rotation.angle().sin().abs(),
+        );
+        let reach = Vector2::new(
+            cos * half_view.x + sin * half_view.y,
+            sin * half_view.x + cos * half_view.y,
+        );
+        let (min, max) = (bounds.0 + reach, bounds.1 - reach);
+        let clamp = |pos: f64, min: f64, max: f64| {
+            if min > max {
+                (min + max) / 2.
+            } else {
+                pos.clamp(min, max)
+            }
+        };
+        Point2::new(clamp(pos.x, min.x, max.x), clamp(pos.y, min.y, max.y))
+    }
+
+    /// Jumps straight to looking at `pos` from `zoom`, north up
+    pub fn hold(&mut self, pos: Point2<f64>, zoom: f64) {
+        (self.pos, self.previous_pos) = (pos, pos);
+        (self.zoom, self.previous_zoom) = (zoom, zoom);
+        (self.rotation, self.previous_rotation) = (Rotation2::identity(), Rotation2::identity());
+        (self.shake, self.previous_shake) = (0., 0.);
+        self.look_ahead = Vector2::zeros();
+    }
+
+    /// Jumps straight onto `car`, to carry on following it from there
+    pub fn cut_to(&mut self, car: &Car) {
+        self.hold(car.center(), self.spec.zoom);
+    }
+
+    /// Where the camera's easing towards when it follows its car
+    pub fn target(&self) -> Point2<f64> {
+        self.target
+    }
+
+    pub fn rotation(&self) -> Rotation2<f64> {
+        self.rotation
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+    }
+
+    pub fn next_mode(&mut self) {
+        self.mode = self.mode.next();
+    }
+
+    fn follow(&self) -> &FollowSpec {
+        match self.mode {
+            CameraMode::Chase => &self.spec.chase,
+            CameraMode::Locked => &self.spec.locked,
+            CameraMode::Hood => &self.spec.hood,
+        }
+    }
+
+    pub fn is_free(&self) -> bool {
+        self.free
+    }
+
+    /// Leaving it eases back onto the car like after a respawn
+    pub fn toggle_free(&mut self) {
+        self.free = !self.free;
+    }
+
+    /// Zooms a free camera in for `factor` above 1 and out below
+    pub fn zoom_by(&mut self, factor: f64) {
+        if self.free {
+            self.zoom = (self.zoom * factor).clamp(0.1, 4.);
+        }
+    }
+
+    /// Moves a free camera for one tick, `direction` going from -1 to 1 across and down the
+    /// screen
+    pub fn fly(&mut self, direction: Vector2<f64>) {
+        self.previous_pos = self.pos;
+        self.previous_rotation = self.rotation;
+        self.previous_zoom = self.zoom;
+        self.previous_shake = 0.;
+        self.shake = 0.;
+        self.pos += self.rotation * direction * self.spec.fly_speed / self.zoom;
+        self.ticks += 1;
+    }
+
+    fn view_center(&self) -> Vector2<f64> {
+        Vector2::new(self.view.0 as f64, self.view.1 as f64) / 2.
+    }
+
+    /// The top left and bottom right corners of the part of the world in view, before the
+    /// view gets turned
+    pub fn visible_area(&self) -> (Point2<f64>, Point2<f64>) {
+        let reach = self.view_center() / self.zoom;
+        (self.pos - reach, self.pos + reach)
+    }
+
+    /// Where `point` in the world shows up in the camera's view. Ignores the rotation, which
+    /// gets applied to the whole view once it's drawn.
+    pub fn to_screen(&self, point: Point2<f64>) -> Point2<f64> {
+        ((point - self.pos) * self.zoom + self.view_center()).into()
+    }
+
+    /// Where `point` in the world shows up once the view's been turned, for drawing things
+    /// over it that should stay upright
+    pub fn to_view(&self, point: Point2<f64>) -> Point2<f64> {
+        (self.rotation.inverse() * (point - self.pos) * self.zoom + self.view_center()).into()
+    }
+
+    // Nothing gets rounded to whole pixels on the way to the screen, so things move across
+    // it smoothly however slowly they go
+
+    /// Rects that touch in the world touch on screen too
+    pub fn relative_rect(&self, rect: Rect) -> FRect {
+        let top_left = self.to_screen(Point2::new(rect.left() as f64, rect.top() as f64));
+        let size = Vector2::new(rect.width() as f64, rect.height() as f64) * self.zoom;
+        FRect::new(
+            top_left.x as f32,
+            top_left.y as f32,
+            size.x.max(1.) as f32,
+            size.y.max(1.) as f32,
+        )
+    }
+
+    /// An upright box `size` big around `center` in the world
+    pub fn relative_box(&self, center: Point2<f64>, size: Vector2<f64>) -> FRect {
+        let size = (size * self.zoom).map(|side| side.max(1.) as f32);
+        FRect::from_center(self.relative_point(center), size.x, size.y)
+    }
+
+    pub fn relative_point(&self, point: Point2<f64>) -> FPoint {
+        let on_screen = self.to_screen(point);
+        FPoint::new(on_screen.x as f32, on_screen.y as f32)
+    }
+
+    /// The point in the world shown at `point` in the camera's view
+    pub fn to_world(&self, point: Point2<f64>) -> Point2<f64> {
+        self.pos + self.rotation * (point.coords - self.view_center()) / self.zoom
+    }
+
+    pub fn update(&mut self, car: &Car, track: &Track) {
+        let spec = &self.spec;
+        let follow = self.follow().clone();
+
+        // aims where the car is going rather than where it is, and further out to the side
+        // it's sliding towards
+        let velocity = car.point_velocity(car.center());
+        let slide = car.rotation() * Vector2::new(car.lateral_slip(), 0.);
+        let ahead = (velocity + slide * spec.drift_look_ahead) * follow.look_ahead;
+        let ahead = ahead.cap_magnitude(spec.max_look_ahead);
+        self.look_ahead = self.look_ahead.lerp(&ahead, spec.look_ahead_smoothing);
+
+        self.previous_pos = self.pos;
+        let target = car.center() + self.look_ahead;
+        self.target = target;
+        self.pos = self
+            .pos
+            .coords
+            .lerp(&target.coords, follow.smoothing)
+            .into();
+
+        // eases round after the car, or back to north up, rather than snapping with every
+        // flick of the wheel
+        self.previous_rotation = self.rotation;
+        let target = if follow.turns {
+            car.rotation()
+        } else {
+            Rotation2::identity()
+        };
+        self.rotation = self.rotation.slerp(&target, follow.rotation_smoothing);
+
+        // pulls back to show more of what's coming the faster the car goes
+        self.previous_zoom = self.zoom;
+        let speed = (car.speed() / spec.top_speed).min(1.);
+        let target = spec.zoom + (spec.top_speed_zoom - spec.zoom) * speed;
+        self.zoom += (target - self.zoom) * spec.zoom_smoothing;
+
+        if let Some(bounds) = track.bounds.filter(|_| spec.clamp_to_track) {
+            self.pos = self.clamped(self.pos, bounds);
+        }
+
+        // hits add to the shaking that's left rather than starting it over
+        let boost_kicked_in = car.nitro().is_active() && !self.boosting;
+        self.boosting = car.nitro().is_active();
+        let mut kick = car.impact() * spec.impact_shake;
+        if boost_kicked_in {
+            kick += spec.nitro_shake;
+        }
+        self.previous_shake = self.shake;
+        self.shake = (self.shake * spec.shake_decay + kick).min(spec.max_shake);
+        self.ticks += 1;
+    }
+}
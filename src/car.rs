@@ -0,0 +1,1120 @@
+use std::path::Path;
+
+use nalgebra::{Point2, Rotation2, Vector2};
+
+use crate::{
+    collision::{self, Obb},
+    config::{self, ConfigError},
+    damage::{Damage, DamageSpec},
+    engine::{Engine, EngineSpec, Shift},
+    nitro::{Nitro, NitroSpec},
+    tire::{self, Tire, TireSpec},
+    track::{HazardKind, RespawnPoint, Track},
+};
+
+/// Pixels per tick squared pulling airborne cars back down
+const GRAVITY: f64 = 0.15;
+
+/// Ticks the steering stays dead for after the front wheels ran through oil
+const OILED_STEERING_TICKS: u32 = 45;
+
+/// Everything that makes one car handle differently from another. Distances are in pixels and
+/// times in ticks.
+#[derive(Clone)]
+pub struct CarSpec {
+    /// width and length of the body
+    pub dimensions: Vector2<f64>,
+    pub mass: f64,
+    /// distance between the front and rear axle
+    pub wheelbase: f64,
+    /// image to draw the car with under the assets directory, seen from above with the
+    /// front at the top
+    pub sprite: Option<String>,
+
+    /// how fast the drive builds up and dies down again
+    pub acceleration: f64,
+    /// how hard the wheels push at peak torque in first gear. The top speed is wherever the
+    /// drive in top gear can't beat the drag and rolling resistance anymore.
+    pub engine_force: f64,
+    pub max_reverse_speed: f64,
+    /// how much quicker the driven wheels speed up from drive the tyres can't put down than
+    /// the whole car would, so the lower the lighter the wheels
+    pub wheel_spin_up: f64,
+    pub engine: EngineSpec,
+    pub nitro: NitroSpec,
+
+    /// in radians
+    pub max_steering_angle: f64,
+    /// how fast the front wheels turn towards full lock
+    pub steering_speed: f64,
+    /// how fast the front wheels return to the center when letting go
+    pub centering_speed: f64,
+    /// share of the lock and steering speed left at `high_speed` and above, so the car isn't
+    /// twitchy when fast. It fades in linearly from a standstill.
+    pub high_speed_steering: f64,
+    pub high_speed: f64,
+
+    /// the most the tyres can push the car around in a tick on dry asphalt
+    pub tire_grip: f64,
+    /// share of the rear grip left with the handbrake pulled, and of any wheel's grip once it
+    /// locks up under braking
+    pub handbrake_grip: f64,
+    pub tires: TireSpec,
+    /// the hardest the brakes slow the car down in a tick, if the tyres can take it
+    pub brake_force: f64,
+    /// share of the braking done by the front wheels. Less than the weight they carry under
+    /// braking and the rears lock first and swing the tail out.
+    pub brake_bias: f64,
+    /// how far the weight shifts per unit of acceleration
+    pub weight_transfer: f64,
+    /// how fast the body settles into its lean, 1 being instantly
+    pub suspension_speed: f64,
+    /// how much of the speed the car keeps when bouncing off of something
+    pub restitution: f64,
+    /// share of the speed lost to the air every tick for every pixel per tick of speed
+    pub drag: f64,
+    /// how far behind the car the wake reaches and how wide it is
+    pub wake_length: f64,
+    pub wake_width: f64,
+    /// share of the drag a car right behind this one is spared
+    pub slipstream: f64,
+    pub damage: DamageSpec,
+
+    /// how many steps each tick's movement and collisions are split into
+    pub substeps: u32,
+}
+
+impl Default for CarSpec {
+    fn default() -> CarSpec {
+        CarSpec {
+            dimensions: Vector2::new(50., 100.),
+            mass: 1000.,
+            wheelbase: 70.,
+            sprite: Some("cars/default.png".to_string()),
+
+            acceleration: 0.2,
+            engine_force: 1.,
+            max_reverse_speed: 0.3,
+            wheel_spin_up: 8.,
+            engine: EngineSpec::default(),
+            nitro: NitroSpec::default(),
+
+            max_steering_angle: 0.6,
+            steering_speed: 0.05,
+            centering_speed: 0.08,
+            high_speed_steering: 0.4,
+            high_speed: 15.,
+
+            tire_grip: 0.9,
+            handbrake_grip: 0.3,
+            tires: TireSpec::default(),
+            brake_force: 0.7,
+            brake_bias: 0.65,
+            weight_transfer: 0.2,
+            suspension_speed: 0.3,
+            restitution: 0.3,
+            drag: 0.0002,
+            wake_length: 400.,
+            wake_width: 60.,
+            slipstream: 0.5,
+            damage: DamageSpec::default(),
+
+            substeps: 4,
+        }
+    }
+}
+
+impl CarSpec {
+    /// Reads a spec from a TOML file. Anything the file leaves out keeps its default.
+    pub fn load(path: impl AsRef<Path>) -> Result<CarSpec, ConfigError> {
+        let file = config::load(path)?;
+        let mut spec = CarSpec::default();
+
+        if let Some(body) = file.table("body")? {
+            body.read_number("width", &mut spec.dimensions.x)?;
+            body.read_number("length", &mut spec.dimensions.y)?;
+            body.read_number("mass", &mut spec.mass)?;
+            body.read_number("wheelbase", &mut spec.wheelbase)?;
+            body.read_number("restitution", &mut spec.restitution)?;
+            if let Some(sprite) = body.string("sprite")? {
+                spec.sprite = Some(sprite.to_string());
+            }
+        }
+        if let Some(engine) = file.table("engine")? {
+            engine.read_number("acceleration", &mut spec.acceleration)?;
+            engine.read_number("engine_force", &mut spec.engine_force)?;
+            engine.read_number("max_reverse_speed", &mut spec.max_reverse_speed)?;
+            engine.read_number("wheel_spin_up", &mut spec.wheel_spin_up)?;
+            spec.engine.read(engine)?;
+        }
+        if let Some(aero) = file.table("aero")? {
+            aero.read_number("drag", &mut spec.drag)?;
+            aero.read_number("wake_length", &mut spec.wake_length)?;
+            aero.read_number("wake_width", &mut spec.wake_width)?;
+            aero.read_number("slipstream", &mut spec.slipstream)?;
+        }
+        if let Some(steering) = file.table("steering")? {
+            steering.read_number("max_angle", &mut spec.max_steering_angle)?;
+            steering.read_number("speed", &mut spec.steering_speed)?;
+            steering.read_number("centering_speed", &mut spec.centering_speed)?;
+            steering.read_number("high_speed_steering", &mut spec.high_speed_steering)?;
+            steering.read_number("high_speed", &mut spec.high_speed)?;
+        }
+        if let Some(tires) = file.table("tires")? {
+            tires.read_number("grip", &mut spec.tire_grip)?;
+            tires.read_number("handbrake_grip", &mut spec.handbrake_grip)?;
+            spec.tires.read(tires)?;
+        }
+        if let Some(brakes) = file.table("brakes")? {
+            brakes.read_number("force", &mut spec.brake_force)?;
+            brakes.read_number("bias", &mut spec.brake_bias)?;
+        }
+        if let Some(nitro) = file.table("nitro")? {
+            spec.nitro.read(nitro)?;
+        }
+        if let Some(suspension) = file.table("suspension")? {
+            suspension.read_number("weight_transfer", &mut spec.weight_transfer)?;
+            suspension.read_number("speed", &mut spec.suspension_speed)?;
+        }
+        if let Some(damage) = file.table("damage")? {
+            spec.damage.read(damage)?;
+        }
+        if let Some(simulation) = file.table("simulation")? {
+            if let Some(substeps) = simulation.number("substeps")? {
+                spec.substeps = substeps as u32;
+            }
+        }
+
+        Ok(spec)
+    }
+
+    /// The number called `name`, by the field's name, for tuning it while driving. `None` for
+    /// names there's no number for.
+    pub fn number_mut(&mut self, name: &str) -> Option<&mut f64> {
+        Some(match name {
+            "mass" => &mut self.mass,
+            "wheelbase" => &mut self.wheelbase,
+            "acceleration" => &mut self.acceleration,
+            "engine_force" => &mut self.engine_force,
+            "max_reverse_speed" => &mut self.max_reverse_speed,
+            "wheel_spin_up" => &mut self.wheel_spin_up,
+            "max_steering_angle" => &mut self.max_steering_angle,
+            "steering_speed" => &mut self.steering_speed,
+            "centering_speed" => &mut self.centering_speed,
+            "high_speed_steering" => &mut self.high_speed_steering,
+            "high_speed" => &mut self.high_speed,
+            "tire_grip" => &mut self.tire_grip,
+            "handbrake_grip" => &mut self.handbrake_grip,
+            "brake_force" => &mut self.brake_force,
+            "brake_bias" => &mut self.brake_bias,
+            "weight_transfer" => &mut self.weight_transfer,
+            "suspension_speed" => &mut self.suspension_speed,
+            "restitution" => &mut self.restitution,
+            "drag" => &mut self.drag,
+            "wake_length" => &mut self.wake_length,
+            "wake_width" => &mut self.wake_width,
+            "slipstream" => &mut self.slipstream,
+            _ => return None,
+        })
+    }
+}
+
+pub struct Car {
+    spec: CarSpec,
+    pos: Point2<f64>,
+    rotation: Rotation2<f64>,
+    /// where the car was at the start of the last tick, to render in between ticks
+    previous_pos: Point2<f64>,
+    previous_rotation: Rotation2<f64>,
+    previous_height: f64,
+    velocity: Vector2<f64>,
+    /// radians per tick
+    angular_velocity: f64,
+    /// share of the weight resting on the front axle
+    front_load: f64,
+    /// share of the weight shifted onto the right wheels, negative when it leans left
+    side_load: f64,
+    /// angle of the front wheels in radians, positive to the right
+    steering_angle: f64,
+    /// front left, front right, rear left, rear right
+    tires: [Tire; 4],
+
+    /// how hard the engine is pushing the driven wheels round, in the same units as the push
+    /// it would give the car if the tyres put all of it down
+    drive_force: f64,
+    /// how fast the rear tyres' treads move, in pixels per tick, positive rolling forwards.
+    /// Anything off from the ground speed is wheelspin or lock-up.
+    wheel_speed: f64,
+    /// how much faster the rear wheels spin than the tyres can make use of, in pixels per tick
+    wheelspin: f64,
+    engine: Engine,
+    nitro: Nitro,
+    damage: Damage,
+    /// the hardest hit taken since the start of the last update, in pixels per tick
+    impact: f64,
+    /// where that hit was, in world space
+    impact_point: Point2<f64>,
+    /// how hard the brakes were on in the last tick, from 0 to 1
+    braking: f64,
+    assists: Assists,
+    off_track: bool,
+    /// share of the drag spared by driving in another car's wake
+    slipstream: f64,
+    /// ticks left until the oil is off of the front tyres
+    oiled: u32,
+    /// how far above the ground the car is, in pixels
+    height: f64,
+    /// pixels per tick, positive going up
+    vertical_velocity: f64,
+    airborne: bool,
+}
+
+/// Electronic helpers for players who'd rather not catch every slide themselves
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Assists {
+    /// cuts the throttle before the rear wheels spin up
+    pub traction_control: bool,
+    /// keeps the wheels turning under braking so the car still steers
+    pub abs: bool,
+    /// brakes the car out of spinning faster than the steering asks for
+    pub stability_control: bool,
+}
+
+/// How far each pedal is pushed down, from 0 for not at all to 1 for all the way
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CarPedals {
+    pub throttle: f64,
+    /// brakes while rolling forwards and reverses once stopped
+    pub brake: f64,
+}
+
+/// What a driver, be it the player, a replay or the AI, does with the car's controls for one
+/// tick
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CarInput {
+    pub pedals: CarPedals,
+    /// from -1 for full lock to the left to 1 for full lock to the right
+    pub steering: f64,
+    pub handbrake: bool,
+    pub boosting: bool,
+}
+
+impl Car {
+    pub fn from_spec(spec: CarSpec, pos: Point2<f64>) -> Car {
+        Car {
+            engine: Engine::new(&spec.engine),
+            nitro: Nitro::default(),
+            damage: Damage::default(),
+            impact: 0.,
+            impact_point: pos,
+            braking: 0.,
+            assists: Assists::default(),
+            off_track: false,
+            slipstream: 0.,
+            oiled: 0,
+            tires: [Tire::new(&spec.tires); 4],
+            spec,
+            pos,
+            rotation: Rotation2::new(0.),
+            previous_pos: pos,
+            previous_rotation: Rotation2::new(0.),
+            previous_height: 0.,
+            height: 0.,
+            vertical_velocity: 0.,
+            airborne: false,
+            velocity: Vector2::zeros(),
+            angular_velocity: 0.,
+            front_load: 0.5,
+            side_load: 0.,
+            steering_angle: 0.,
+            drive_force: 0.,
+            wheel_speed: 0.,
+            wheelspin: 0.,
+        }
+    }
+
+    pub fn center(&self) -> Point2<f64> {
+        self.pos + self.spec.dimensions / 2.
+    }
+
+    /// Where the middle of the car is, `alpha` of the way between the last two ticks
+    pub fn interpolated_center(&self, alpha: f64) -> Point2<f64> {
+        let pos = self.previous_pos.coords.lerp(&self.pos.coords, alpha);
+        Point2::from(pos) + self.spec.dimensions / 2.
+    }
+
+    pub fn interpolated_rotation(&self, alpha: f64) -> Rotation2<f64> {
+        self.previous_rotation.slerp(&self.rotation, alpha)
+    }
+
+    pub fn interpolated_height(&self, alpha: f64) -> f64 {
+        self.previous_height + (self.height - self.previous_height) * alpha
+    }
+
+    /// Whether the wheels have left the ground, which leaves the car without steering or grip
+    pub fn is_airborne(&self) -> bool {
+        self.airborne
+    }
+
+    pub fn obb(&self) -> Obb {
+        Obb::new(self.center(), self.spec.dimensions, self.rotation)
+    }
+
+    /// Moment of inertia of a uniform box
+    pub fn inertia(&self) -> f64 {
+        self.spec.mass * self.spec.dimensions.norm_squared() / 12.
+    }
+
+    /// Velocity of a point on the car body, including the part coming from its spin
+    pub fn point_velocity(&self, point: Point2<f64>) -> Vector2<f64> {
+        let arm = point - self.center();
+        self.velocity + Vector2::new(-arm.y, arm.x) * self.angular_velocity
+    }
+
+    pub fn rotation(&self) -> Rotation2<f64> {
+        self.rotation
+    }
+
+    /// Where a trailer hooks on, just behind the middle of the rear bumper
+    pub fn hitch(&self) -> Point2<f64> {
+        self.center() + self.rotation * Vector2::new(0., self.spec.dimensions.y / 2. + 5.)
+    }
+
+    /// Pushes the car at `point` with `impulse`, in mass times pixels per tick
+    pub fn apply_impulse(&mut self, point: Point2<f64>, impulse: Vector2<f64>) {
+        let arm = point - self.center();
+        self.velocity += impulse / self.spec.mass;
+        self.angular_velocity += arm.perp(&impulse) / self.inertia();
+    }
+
+    /// Pixels per tick in whatever direction the car is going
+    pub fn speed(&self) -> f64 {
+        self.velocity.norm()
+    }
+
+    /// Speed along the way the car is facing, negative when reversing
+    pub fn forward_speed(&self) -> f64 {
+        -(self.rotation.inverse() * self.velocity).y
+    }
+
+    /// Speed sideways to the way the car is facing, positive when sliding to the right
+    pub fn lateral_slip(&self) -> f64 {
+        (self.rotation.inverse() * self.velocity).x
+    }
+
+    /// Angle in radians between where the car is pointing and where it is going, positive
+    /// when it slides to its right. Going backwards counts the same as going forwards.
+    pub fn drift_angle(&self) -> f64 {
+        self.lateral_slip().atan2(self.forward_speed().abs())
+    }
+
+    /// The steering that turns the car towards `target`, tail first when reversing. Past
+    /// what the wheels can point at it's full lock.
+    pub fn steering_toward(&self, target: Point2<f64>) -> f64 {
+        let local = self.rotation.inverse() * (target - self.center());
+        // positive with the target off to the right of where the car is heading
+        let steering = if self.forward_speed() < 0. {
+            // backing up, the car turns the other way from the wheels
+            -local.x.atan2(local.y)
+        } else {
+            local.x.atan2(-local.y)
+        };
+        (steering / self.spec.max_steering_angle).clamp(-1., 1.)
+    }
+
+    /// Whether the car is sliding far and fast enough to count as a drift
+    pub fn is_drifting(&self) -> bool {
+        self.drift_angle().abs() > self.spec.nitro.drift_angle
+            && self.speed() > self.spec.nitro.drift_speed
+    }
+
+    pub fn spec(&self) -> &CarSpec {
+        &self.spec
+    }
+
+    /// For tuning the car while it's driven
+    pub fn spec_mut(&mut self) -> &mut CarSpec {
+        &mut self.spec
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn nitro(&self) -> &Nitro {
+        &self.nitro
+    }
+
+    pub fn damage(&self) -> &Damage {
+        &self.damage
+    }
+
+    /// Puts the car back on the track at `point`, standing still
+    pub fn respawn(&mut self, point: &RespawnPoint) {
+        self.pos = point.position - self.spec.dimensions / 2.;
+        self.rotation = point.rotation;
+        self.previous_pos = self.pos;
+        self.previous_rotation = self.rotation;
+        self.velocity = Vector2::zeros();
+        self.angular_velocity = 0.;
+        self.drive_force = 0.;
+        self.wheel_speed = 0.;
+        self.wheelspin = 0.;
+        self.steering_angle = 0.;
+        self.height = 0.;
+        self.previous_height = 0.;
+        self.vertical_velocity = 0.;
+        self.airborne = false;
+        self.front_load = 0.5;
+        self.side_load = 0.;
+    }
+
+    /// Mends `amount` of the damage, 1 for all of it. Left to the game mode to decide when.
+    pub fn repair(&mut self, amount: f64) {
+        self.damage.repair(amount);
+    }
+
+    /// Whether the car was off the track surface during the last tick
+    pub fn is_off_track(&self) -> bool {
+        self.off_track
+    }
+
+    /// How much of the drag `leader` spares this car by breaking the air in front of it. The
+    /// wake trails straight behind the leader and fades out with distance.
+    pub fn slipstream_from(&self, leader: &Car) -> f64 {
+        let leader_heading = leader.rotation * Vector2::new(0., -1.);
+        let heading = self.rotation * Vector2::new(0., -1.);
+        let offset = self.center() - leader.center();
+        let behind = -offset.dot(&leader_heading);
+        let aside = offset.perp(&leader_heading).abs();
+
+        let min_leader_speed = 3.;
+        if leader.speed() < min_leader_speed
+            || heading.dot(&leader_heading) < 0.9
+            || behind <= 0.
+            || behind > leader.spec.wake_length
+            || aside > leader.spec.wake_width / 2.
+        {
+            return 0.;
+        }
+        leader.spec.slipstream * (1. - behind / leader.spec.wake_length)
+    }
+
+    /// Sets how much of the drag is spared for the next tick
+    pub fn set_slipstream(&mut self, slipstream: f64) {
+        self.slipstream = slipstream;
+    }
+
+    pub fn assists(&self) -> Assists {
+        self.assists
+    }
+
+    pub fn assists_mut(&mut self) -> &mut Assists {
+        &mut self.assists
+    }
+
+    /// Front left, front right, rear left, rear right
+    pub fn tires(&self) -> &[Tire; 4] {
+        &self.tires
+    }
+
+    /// Where the wheels touch the ground, in the same order as `tires`
+    pub fn wheel_positions(&self) -> [Point2<f64>; 4] {
+        self.wheel_arms()
+            .map(|arm| self.center() + self.rotation * arm)
+    }
+
+    /// How fast each tyre slides over the ground, in the same order as `tires`: sideways
+    /// for all of them, and the rear ones spinning on top of that
+    pub fn wheel_slips(&self) -> [f64; 4] {
+        let inverse = self.rotation.inverse();
+        let mut slips = self
+            .wheel_positions()
+            .map(|wheel| (inverse * self.point_velocity(wheel)).x.abs());
+        for slip in &mut slips[2..] {
+            *slip = slip.max(self.wheelspin);
+        }
+        slips
+    }
+
+    /// How much faster the rear wheels spin than the tyres can put down, in pixels per tick.
+    /// Zero while they grip.
+    pub fn wheelspin(&self) -> f64 {
+        self.wheelspin
+    }
+
+    /// How hard the brakes were on in the last tick, from 0 to 1, for the brake lights
+    pub fn braking(&self) -> f64 {
+        self.braking
+    }
+
+    /// How hard the car hit something in the last tick, as the speed it went into it with.
+    /// Zero if it didn't.
+    pub fn impact(&self) -> f64 {
+        self.impact
+    }
+
+    pub fn impact_point(&self) -> Point2<f64> {
+        self.impact_point
+    }
+
+    /// Takes damage from hitting something at `point` at `impact_speed`, the speed along the
+    /// contact normal
+    fn hit(&mut self, point: Point2<f64>, impact_speed: f64) {
+        self.damage.hit(&self.spec.damage, impact_speed);
+        if impact_speed.abs() > self.impact {
+            self.impact = impact_speed.abs();
+            self.impact_point = point;
+        }
+    }
+
+    pub fn update(&mut self, input: &CarInput, track: &Track) {
+        let CarInput {
+            pedals,
+            steering,
+            handbrake,
+            boosting,
+        } = *input;
+        self.previous_pos = self.pos;
+        self.impact = 0.;
+        self.previous_rotation = self.rotation;
+        self.previous_height = self.height;
+
+        // every wheel grips as much as the ground right under it allows
+        let arms = self.wheel_arms();
+        let wheel_grips = arms.map(|arm| track.grip_at(self.center() + self.rotation * arm));
+        self.off_track = !track.is_on_track(self.center());
+        let start_velocity = self.velocity;
+
+        // a loaded wheel bites harder, and leaning on one side overloads the outside tyres
+        // so the car as a whole grips less in a hard corner
+        let loads = self.wheel_loads();
+        let rear_load = 1. - self.front_load;
+        let cornering_grip = 1. - self.side_load.powi(2);
+
+        // nothing to push against in the air
+        let peak_force = if self.airborne {
+            0.
+        } else {
+            self.spec.tire_grip
+        };
+        let tire_grip = self.tires.map(|tire| tire.grip(&self.spec.tires));
+
+        let forward_speed = self.forward_speed();
+        // how hard the brakes are pressed, from 0 to 1
+        let mut braking = 0.;
+        // the revs follow the driven wheels, which only match the ground speed while they grip
+        let drive = self
+            .engine
+            .update(&self.spec.engine, self.wheel_speed, forward_speed);
+
+        let drifting = self.is_drifting();
+        let boost = self.nitro.update(&self.spec.nitro, boosting, drifting);
+        let acceleration =
+            self.spec.acceleration * self.damage.acceleration_share(&self.spec.damage);
+        let engine_force = self.spec.engine_force * self.damage.speed_share(&self.spec.damage);
+
+        let throttle = pedals.throttle.clamp(0., 1.);
+        let brake = pedals.brake.clamp(0., 1.);
+        if throttle > 0. {
+            // reversing is done in its own gear, so coming out of it gets full torque
+            let drive = if self.drive_force < 0. { 1. } else { drive };
+            let target = engine_force * drive * boost * throttle;
+            let change = acceleration * boost;
+            self.drive_force += (target - self.drive_force).clamp(-change, change);
+        } else if brake > 0. {
+            let stopped_speed = 0.5;
+            if self.drive_force > 0. || forward_speed > stopped_speed {
+                // still rolling forwards, so the pedal is a brake until the car comes to a stop
+                self.drive_force *= 0.5;
+                if self.drive_force < 0.1 {
+                    self.drive_force = 0.;
+                }
+                braking = brake;
+            } else {
+                self.drive_force -= acceleration * 0.5 * brake;
+                self.drive_force = self.drive_force.max(-self.spec.max_reverse_speed * brake);
+            }
+        } else {
+            // with the throttle shut the engine holds the wheels back, and the car pitching
+            // forwards takes grip off of the rear, which is what lifting mid-corner is for
+            let target =
+                -engine_force * self.engine.braking(&self.spec.engine) * self.wheel_speed.signum();
+            self.drive_force += (target - self.drive_force).clamp(-acceleration, acceleration);
+        }
+
+        // the locked rear wheels stop driving the car and let the tail swing out
+        if handbrake {
+            self.drive_force *= 0.9;
+        }
+
+        let [front_left, front_right, ..] = self.wheel_positions();
+        if [front_left, front_right]
+            .iter()
+            .any(|&wheel| track.hazard_at(wheel) == Some(HazardKind::Oil))
+        {
+            self.oiled = OILED_STEERING_TICKS;
+        }
+        // with oily front tyres the wheels stay wherever they were pointing
+        if self.oiled > 0 {
+            self.oiled -= 1;
+        } else {
+            self.steer(steering);
+        }
+
+        // tyres
+        let mut local_velocity = self.rotation.inverse() * self.velocity;
+        let forward_speed = -local_velocity.y;
+
+        let rolling_resistance = wheel_grips
+            .iter()
+            .map(|grip| grip.rolling_resistance)
+            .sum::<f64>()
+            / wheel_grips.len() as f64;
+        self.drive_force *= 0.98 - rolling_resistance;
+        if !self.airborne {
+            local_velocity.y *= 1. - rolling_resistance;
+        }
+        // air drag grows with the square of the speed
+        let drag = self.spec.drag * (1. - self.slipstream) * local_velocity.norm();
+        local_velocity *= 1. - drag.min(1.);
+
+        // each wheel pushes against its own sideways sliding. Pushing at either end of the car
+        // turns it as well, so the front tyres steer it around and a rear losing its grip
+        // lets the tail come around.
+        let brake_grips: [f64; 4] = std::array::from_fn(|i| {
+            peak_force * wheel_grips[i].longitudinal * tire_grip[i] * loads[i]
+        });
+        let brake_demands = if braking > 0. {
+            let force = self.spec.brake_force * braking;
+            let front = force * self.spec.brake_bias / 2.;
+            let rear = force * (1. - self.spec.brake_bias) / 2.;
+            [front, front, rear, rear]
+        } else {
+            [0.; 4]
+        };
+        // without ABS a wheel braked harder than its tyre can take locks up
+        let locked: [bool; 4] =
+            std::array::from_fn(|i| !self.assists.abs && brake_demands[i] > brake_grips[i]);
+        let mut lateral_forces = [0.; 4];
+        let mut slips = [0.; 4];
+        for i in 0..4 {
+            let front = i < 2;
+            let mut capacity =
+                peak_force * wheel_grips[i].lateral * loads[i] * cornering_grip * tire_grip[i];
+            // the handbrake locks the rear wheels, and locked wheels slide straight on
+            if locked[i] || (!front && handbrake) {
+                capacity *= self.spec.handbrake_grip;
+            }
+            let wheel_angle = if front { self.steering_angle } else { 0. };
+            (lateral_forces[i], slips[i]) =
+                self.grip_wheel(&mut local_velocity, arms[i], wheel_angle, capacity);
+        }
+
+        // whatever grip the cornering leaves over can speed the car up or slow it down. The
+        // rear wheels are driven through an open differential, so neither pushes harder than
+        // the one with less grip can.
+        let rolling = Vector2::new(0., -1.);
+        let remaining_grip = [2, 3].map(|i| {
+            let share = loads[i] / rear_load;
+            let longitudinal_capacity =
+                peak_force * wheel_grips[i].longitudinal * tire_grip[i] * share;
+            let lateral_force = lateral_forces[i] / rear_load;
+            (longitudinal_capacity.powi(2) - lateral_force.powi(2))
+                .max(0.)
+                .sqrt()
+        });
+        let least_grip = remaining_grip[0].min(remaining_grip[1]);
+        if self.assists.traction_control {
+            let most_drive = least_grip * 2.;
+            self.drive_force = self.drive_force.clamp(-most_drive, most_drive);
+            // and once the wheels do spin up, it cuts the power until they bite again
+            if self.wheelspin > 0. {
+                self.drive_force = 0.;
+            }
+        }
+
+        // the engine spins the wheels up and the tyres drag them back towards the ground speed,
+        // pushing the car along as they do. Once a wheel spins past the peak slip ratio its
+        // tyre gives less the faster it spins, so it keeps spinning until the drive eases off.
+        let spin_up = self.spec.wheel_spin_up;
+        let ground_speed = forward_speed.abs().max(1.);
+        let tire_share = if self.wheelspin > 0. {
+            let slip_ratio = (self.wheel_speed - forward_speed) / ground_speed;
+            tire::LONGITUDINAL.force(slip_ratio.abs())
+        } else {
+            1.
+        };
+        self.wheel_speed += self.drive_force * spin_up;
+        let slip = self.wheel_speed - forward_speed;
+        // never more than it takes to match the wheels to the ground, or they'd overshoot
+        let matching_push = slip.abs() / (spin_up + 1.) / 2.;
+        let push = (least_grip * tire_share).min(matching_push);
+        self.wheel_speed -= push * 2. * slip.signum() * spin_up;
+        let slip_left = slip.abs() - push * 2. * (spin_up + 1.);
+        self.wheelspin = (slip_left - tire::PEAK_SLIP_RATIO * ground_speed).max(0.);
+
+        for i in [2, 3] {
+            self.push_at(&mut local_velocity, arms[i], rolling * push * slip.signum());
+            slips[i] += self.wheelspin;
+        }
+
+        // each wheel brakes with whatever the cornering leaves of its grip. A locked one slides
+        // instead, which grips less than a tyre that is still turning.
+        for i in 0..4 {
+            if brake_demands[i] == 0. {
+                continue;
+            }
+            let brake = if locked[i] {
+                brake_grips[i] * tire::LONGITUDINAL.force(1.)
+            } else {
+                let remaining_grip = (brake_grips[i].powi(2) - lateral_forces[i].powi(2))
+                    .max(0.)
+                    .sqrt();
+                brake_demands[i].min(remaining_grip)
+            };
+            // never past a standstill
+            let brake = brake.min(forward_speed.abs() / 4.);
+            self.push_at(
+                &mut local_velocity,
+                arms[i],
+                -rolling * brake * forward_speed.signum(),
+            );
+            if locked[i] {
+                slips[i] += forward_speed.abs() - brake;
+            }
+        }
+        // the brakes hold the wheels to the ground speed
+        if braking > 0. {
+            self.wheel_speed = -local_velocity.y;
+        }
+        self.braking = braking;
+
+        if self.assists.stability_control && !self.airborne {
+            // let the car turn as fast as the front wheels ask for and a little more to
+            // hold a slide, but take the edge off anything past that
+            let intended_yaw_rate = forward_speed * self.steering_angle.tan() / self.spec.wheelbase;
+            let allowed_excess = 0.01;
+            let excess = self.angular_velocity - intended_yaw_rate;
+            if excess.abs() > allowed_excess {
+                self.angular_velocity -= (excess - allowed_excess * excess.signum()) * 0.3;
+            }
+        }
+
+        self.velocity = self.rotation * local_velocity;
+        let acceleration = self.velocity - start_velocity;
+
+        // moving in a few smaller steps keeps fast cars from passing through thin walls
+        let substeps = self.spec.substeps.max(1);
+        for _ in 0..substeps {
+            self.pos += self.velocity / substeps as f64;
+            self.rotation *= Rotation2::new(self.angular_velocity / substeps as f64);
+            self.collide(track);
+        }
+
+        self.transfer_weight(acceleration);
+        if !self.airborne {
+            self.wear_tires(slips);
+        }
+        self.fly(track);
+    }
+
+    pub fn shift(&mut self, shift: Shift) {
+        self.engine.shift(&self.spec.engine, shift);
+    }
+
+    pub fn toggle_manual_shifting(&mut self) {
+        self.engine.manual = !self.engine.manual;
+    }
+
+    /// Turns the front wheels towards as much of the lock as `steering` asks for and lets them
+    /// return to the center otherwise. The faster the car goes the less lock there is.
+    fn steer(&mut self, steering: f64) {
+        let speed_share = (self.forward_speed().abs() / self.spec.high_speed).min(1.);
+        let authority = 1. - (1. - self.spec.high_speed_steering) * speed_share;
+        let lock = self.spec.max_steering_angle * authority;
+        // a bent steering rack turns slower
+        let steering_speed =
+            self.spec.steering_speed * authority * self.damage.steering_share(&self.spec.damage);
+        let target = lock * steering.clamp(-1., 1.);
+        // the wheels head back quicker when let go than when they're held part of the way
+        let speed = if steering == 0. {
+            self.spec.centering_speed
+        } else {
+            steering_speed
+        };
+        self.steering_angle += (target - self.steering_angle).clamp(-speed, speed);
+    }
+
+    /// Where the wheels touch the ground relative to the center, in the same order as `tires`
+    fn wheel_arms(&self) -> [Vector2<f64>; 4] {
+        let x = self.spec.dimensions.x * 0.4;
+        let y = self.spec.wheelbase / 2.;
+        [
+            Vector2::new(-x, -y),
+            Vector2::new(x, -y),
+            Vector2::new(-x, y),
+            Vector2::new(x, y),
+        ]
+    }
+
+    /// Share of the weight resting on each wheel, in the same order as `tires`
+    fn wheel_loads(&self) -> [f64; 4] {
+        let right = 0.5 + self.side_load;
+        let left = 1. - right;
+        let front = self.front_load;
+        let rear = 1. - front;
+        [front * left, front * right, rear * left, rear * right]
+    }
+
+    /// Pushes the car at `arm` from the center, which turns it unless the push goes straight
+    /// through the center
+    fn push_at(
+        &mut self,
+        local_velocity: &mut Vector2<f64>,
+        arm: Vector2<f64>,
+        push: Vector2<f64>,
+    ) {
+        *local_velocity += push;
+        self.angular_velocity += arm.perp(&push) * self.spec.mass / self.inertia();
+    }
+
+    /// Lets the tyre at `arm` from the center, turned by `wheel_angle`, push against the wheel
+    /// sliding sideways with at most `capacity`. Returns how hard it pushed and how fast the
+    /// wheel still slides.
+    fn grip_wheel(
+        &mut self,
+        local_velocity: &mut Vector2<f64>,
+        arm: Vector2<f64>,
+        wheel_angle: f64,
+        capacity: f64,
+    ) -> (f64, f64) {
+        let rolling = Rotation2::new(wheel_angle) * Vector2::new(0., -1.);
+        let sideways = Rotation2::new(wheel_angle) * Vector2::new(1., 0.);
+        let wheel_velocity = *local_velocity + Vector2::new(-arm.y, arm.x) * self.angular_velocity;
+        let sliding_speed = wheel_velocity.dot(&sideways);
+        let slip_angle = sliding_speed.atan2(wheel_velocity.dot(&rolling).abs());
+
+        // pushing off center both moves and turns the car, so the wheel gives way more easily
+        let leverage = arm.perp(&sideways);
+        let stiffness = 1. + leverage.powi(2) * self.spec.mass / self.inertia();
+        // never push past zero, or the car would wobble from side to side when nearly stopped
+        let force =
+            (tire::LATERAL.force(slip_angle.abs()) * capacity).min(sliding_speed.abs() / stiffness);
+        self.push_at(
+            local_velocity,
+            arm,
+            sideways * -force * sliding_speed.signum(),
+        );
+        (force, sliding_speed.abs() - force * stiffness)
+    }
+
+    /// Leans the body according to how hard it was pushed around this tick. Accelerating
+    /// puts weight on the rear, braking on the front and cornering on the outside wheels.
+    fn transfer_weight(&mut self, acceleration: Vector2<f64>) {
+        let local_acceleration = self.rotation.inverse() * acceleration;
+        let longitudinal = -local_acceleration.y;
+        let lateral = local_acceleration.x;
+
+        let transfer_per_acceleration = self.spec.weight_transfer;
+        let target_front_load = (0.5 - longitudinal * transfer_per_acceleration).clamp(0.2, 0.8);
+        // the centripetal pull points inwards, the weight goes the other way
+        let target_side_load = (-lateral * transfer_per_acceleration).clamp(-0.4, 0.4);
+
+        // the suspension takes a moment to settle
+        let suspension_speed = self.spec.suspension_speed;
+        self.front_load += (target_front_load - self.front_load) * suspension_speed;
+        self.side_load += (target_side_load - self.side_load) * suspension_speed;
+    }
+
+    /// Rides up the ramps, keeps going up when driving off their top edge and lands again
+    fn fly(&mut self, track: &Track) {
+        let ground = track.ground_height(self.center());
+        let was_airborne = self.airborne;
+
+        self.vertical_velocity -= GRAVITY;
+        self.height += self.vertical_velocity;
+        self.airborne = self.height > ground;
+        if self.airborne {
+            return;
+        }
+
+        if was_airborne {
+            // the suspension bottoms out, which scrubs off some speed and can break things
+            let impact_speed = -self.vertical_velocity;
+            self.hit(self.pos, impact_speed);
+            self.velocity *= 1. - (impact_speed * 0.05).min(0.5);
+            self.front_load = (self.front_load + 0.1).min(0.8);
+            self.vertical_velocity = 0.;
+        } else {
+            // following the ground carries the car upwards when it drives up a ramp
+            self.vertical_velocity = ground - self.previous_height;
+        }
+        self.height = ground;
+    }
+
+    /// Heats up and wears down the tyres by how much each one slid this tick, the ones
+    /// carrying more of the weight taking more of it
+    fn wear_tires(&mut self, slips: [f64; 4]) {
+        let speed = self.speed();
+        // compared to an even quarter of the weight on each tyre
+        let loads = self.wheel_loads().map(|load| load * 4.);
+
+        for ((tire, slip), load) in self.tires.iter_mut().zip(slips).zip(loads) {
+            tire.update(&self.spec.tires, slip, speed, load);
+        }
+    }
+
+    /// Pushes the car out of anything it drove into, or that drove into it, and bounces it off
+    fn collide(&mut self, track: &Track) {
+        let restitution = self.spec.restitution;
+        let scrape_friction = 0.1;
+
+        let still = Vector2::zeros();
+        let walls = track
+            .walls
+            .iter()
+            .map(|wall| (collision::obb_vs_segment(&self.obb(), wall), still));
+        let props = track
+            .props
+            .iter()
+            .map(|prop| (collision::obb_vs_obb(&self.obb(), prop), still));
+        let movers = track
+            .mover_areas()
+            .map(|(area, velocity)| (collision::obb_vs_obb(&self.obb(), &area), velocity));
+        let contacts: Vec<_> = walls
+            .chain(props)
+            .chain(movers)
+            .filter_map(|(contact, velocity)| Some((contact?, velocity)))
+            .collect();
+
+        for (contact, surface_velocity) in contacts {
+            self.pos += contact.normal * contact.depth;
+
+            // bouncing off it the way it'd look riding along with it
+            let velocity = self.velocity - surface_velocity;
+            let normal_speed = velocity.dot(&contact.normal);
+            if normal_speed < 0. {
+                let normal_velocity = contact.normal * normal_speed;
+                let tangent_velocity = velocity - normal_velocity;
+                self.velocity = surface_velocity + tangent_velocity * (1. - scrape_friction)
+                    - normal_velocity * restitution;
+                self.drive_force *= 0.5;
+                self.hit(contact.point, normal_speed);
+            }
+        }
+    }
+
+    /// Separates two overlapping cars and exchanges momentum between them
+    pub fn collide_with(&mut self, other: &mut Car) {
+        let Some(contact) = collision::obb_vs_obb(&self.obb(), &other.obb()) else {
+            return;
+        };
+        let restitution = (self.spec.restitution + other.spec.restitution) / 2.;
+
+        // push them apart in proportion to how easy each one is to move
+        let inverse_masses = (1. / self.spec.mass, 1. / other.spec.mass);
+        let total_inverse_mass = inverse_masses.0 + inverse_masses.1;
+        self.pos += contact.normal * contact.depth * inverse_masses.0 / total_inverse_mass;
+        other.pos -= contact.normal * contact.depth * inverse_masses.1 / total_inverse_mass;
+
+        let approach_speed = (self.point_velocity(contact.point)
+            - other.point_velocity(contact.point))
+        .dot(&contact.normal);
+        if approach_speed >= 0. {
+            return;
+        }
+        self.hit(contact.point, approach_speed);
+        other.hit(contact.point, approach_speed);
+
+        let cross = |arm: Vector2<f64>| arm.perp(&contact.normal);
+        let arms = (
+            contact.point - self.center(),
+            contact.point - other.center(),
+        );
+        let inertias = (self.inertia(), other.inertia());
+        let impulse = -(1. + restitution) * approach_speed
+            / (total_inverse_mass
+                + cross(arms.0).powi(2) / inertias.0
+                + cross(arms.1).powi(2) / inertias.1);
+
+        self.velocity += contact.normal * impulse * inverse_masses.0;
+        other.velocity -= contact.normal * impulse * inverse_masses.1;
+        self.angular_velocity += cross(arms.0) * impulse / inertias.0;
+        other.angular_velocity -= cross(arms.1) * impulse / inertias.1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collision::Segment, track::Mover};
+
+    /// An empty track with a single wall along y = 0
+    fn walled_track() -> Track {
+        let mut track = Track::new();
+        track.walls = vec![Segment::new(
+            Point2::new(-10_000., 0.),
+            Point2::new(10_000., 0.),
+        )];
+        track.props.clear();
+        track.ramps.clear();
+        track
+    }
+
+    #[test]
+    fn a_mover_shoves_a_parked_car_along() {
+        let mut track = walled_track();
+        track.walls.clear();
+        // a plate sweeping down the map through where the car's parked
+        track.movers.push(Mover {
+            size: Vector2::new(400., 20.),
+            path: vec![Point2::new(0., -200.), Point2::new(0., 400.)],
+            closed: false,
+            speed: 3.,
+            wait: 0,
+            offset: 0,
+        });
+        let spec = CarSpec::default();
+        let mut car = Car::from_spec(spec.clone(), Point2::from(-spec.dimensions / 2.));
+
+        for _ in 0..150 {
+            track.update();
+            car.update(&CarInput::default(), &track);
+        }
+        assert!(car.center().y > 150., "car left at {}", car.center());
+    }
+
+    /// Throws a car at the wall from below and checks it never ends up on the other side
+    fn assert_no_tunneling(rotation: f64, velocity: Vector2<f64>) {
+        let track = walled_track();
+        let mut car = Car::from_spec(CarSpec::default(), Point2::new(0., 100.));
+        car.rotation = Rotation2::new(rotation);
+        car.previous_rotation = car.rotation;
+        car.velocity = velocity;
+
+        for _ in 0..30 {
+            car.update(&CarInput::default(), &track);
+            assert!(
+                car.center().y > 0.,
+                "car passed through the wall to {}",
+                car.center()
+            );
+        }
+    }
+
+    #[test]
+    fn fast_car_does_not_pass_through_a_wall_head_on() {
+        assert_no_tunneling(0., Vector2::new(0., -90.));
+    }
+
+    #[test]
+    fn fast_car_does_not_pass_through_a_wall_sideways() {
+        assert_no_tunneling(std::f64::consts::FRAC_PI_2, Vector2::new(0., -90.));
+    }
+
+    #[test]
+    fn fast_car_does_not_pass_through_a_wall_at_an_angle() {
+        assert_no_tunneling(0.7, Vector2::new(60., -70.));
+    }
+}
@@ -0,0 +1,172 @@
+use nalgebra::{Point2, Rotation2, Vector2};
+
+/// A wall or any other static line that can't be driven through
+pub struct Segment {
+    pub a: Point2<f64>,
+    pub b: Point2<f64>,
+}
+
+/// Oriented bounding box
+pub struct Obb {
+    pub center: Point2<f64>,
+    pub half_extents: Vector2<f64>,
+    pub rotation: Rotation2<f64>,
+}
+
+pub struct Contact {
+    /// unit vector pointing out of the second shape, the direction the first shape has to move
+    /// to get unstuck
+    pub normal: Vector2<f64>,
+    pub depth: f64,
+    /// roughly where the shapes touch, in world space
+    pub point: Point2<f64>,
+}
+
+impl Segment {
+    pub fn new(a: Point2<f64>, b: Point2<f64>) -> Segment {
+        Segment { a, b }
+    }
+
+    /// How far `point` is from the nearest point on the segment
+    pub fn distance_to(&self, point: Point2<f64>) -> f64 {
+        let along = self.b - self.a;
+        let share = ((point - self.a).dot(&along) / along.norm_squared()).clamp(0., 1.);
+        // a segment with both ends in the same place is just a point
+        let share = if share.is_nan() { 0. } else { share };
+        (point - (self.a + along * share)).norm()
+    }
+
+    /// Whether the two segments cross, touching counting too
+    pub fn crosses(&self, other: &Segment) -> bool {
+        // which side of a line through `a` and `b` a point is on
+        let side = |a: Point2<f64>, b: Point2<f64>, point: Point2<f64>| (b - a).perp(&(point - a));
+        let (first, second) = (
+            side(self.a, self.b, other.a) * side(self.a, self.b, other.b),
+            side(other.a, other.b, self.a) * side(other.a, other.b, self.b),
+        );
+        // lined up with each other, they also have to overlap
+        let overlap = |a: f64, b: f64, c: f64, d: f64| a.max(b) >= c.min(d) && c.max(d) >= a.min(b);
+        first <= 0.
+            && second <= 0.
+            && overlap(self.a.x, self.b.x, other.a.x, other.b.x)
+            && overlap(self.a.y, self.b.y, other.a.y, other.b.y)
+    }
+
+    fn normal(&self) -> Vector2<f64> {
+        let direction = (self.b - self.a).normalize();
+        Vector2::new(-direction.y, direction.x)
+    }
+}
+
+impl Obb {
+    pub fn new(center: Point2<f64>, size: Vector2<f64>, rotation: Rotation2<f64>) -> Obb {
+        Obb {
+            center,
+            half_extents: size / 2.,
+            rotation,
+        }
+    }
+
+    pub fn axes(&self) -> [Vector2<f64>; 2] {
+        [self.rotation * Vector2::x(), self.rotation * Vector2::y()]
+    }
+
+    pub fn contains(&self, point: Point2<f64>) -> bool {
+        let local = self.rotation.inverse() * (point - self.center);
+        local.x.abs() <= self.half_extents.x && local.y.abs() <= self.half_extents.y
+    }
+
+    /// Corners in winding order
+    pub fn corners(&self) -> [Point2<f64>; 4] {
+        let [x, y] = self.axes();
+        let x = x * self.half_extents.x;
+        let y = y * self.half_extents.y;
+        [
+            self.center - x - y,
+            self.center + x - y,
+            self.center + x + y,
+            self.center - x + y,
+        ]
+    }
+}
+
+fn project(points: &[Point2<f64>], axis: &Vector2<f64>) -> (f64, f64) {
+    points
+        .iter()
+        .map(|point| point.coords.dot(axis))
+        .fold((f64::MAX, f64::MIN), |(min, max), p| {
+            (min.min(p), max.max(p))
+        })
+}
+
+/// The point furthest along `direction`, averaged when a whole edge is equally far
+fn support(points: &[Point2<f64>], direction: &Vector2<f64>) -> Point2<f64> {
+    let (_, max) = project(points, direction);
+    let furthest: Vec<_> = points
+        .iter()
+        .filter(|point| max - point.coords.dot(direction) < 1e-6)
+        .collect();
+    let sum = furthest
+        .iter()
+        .fold(Vector2::zeros(), |sum, point| sum + point.coords);
+    (sum / furthest.len() as f64).into()
+}
+
+/// Separating axis test between two convex polygons, `axes_a` and `axes_b` being their edge
+/// normals. Knowing which shape the separating axis came from tells us whose corner is doing
+/// the poking, which is where the contact point ends up.
+fn sat(
+    a: &[Point2<f64>],
+    b: &[Point2<f64>],
+    axes_a: &[Vector2<f64>],
+    axes_b: &[Vector2<f64>],
+) -> Option<Contact> {
+    let center = |points: &[Point2<f64>]| {
+        points
+            .iter()
+            .fold(Vector2::zeros(), |sum, p| sum + p.coords)
+            / points.len() as f64
+    };
+    let offset = center(a) - center(b);
+
+    let mut best: Option<(f64, Vector2<f64>, bool)> = None;
+    for (axis, from_a) in axes_a
+        .iter()
+        .map(|axis| (axis, true))
+        .chain(axes_b.iter().map(|axis| (axis, false)))
+    {
+        let (min_a, max_a) = project(a, axis);
+        let (min_b, max_b) = project(b, axis);
+        let overlap = (max_a - min_b).min(max_b - min_a);
+        if overlap <= 0. {
+            return None;
+        }
+        if best.is_none_or(|(depth, _, _)| overlap < depth) {
+            let normal = if offset.dot(axis) < 0. { -axis } else { *axis };
+            best = Some((overlap, normal, from_a));
+        }
+    }
+
+    best.map(|(depth, normal, from_a)| Contact {
+        normal,
+        depth,
+        point: if from_a {
+            support(b, &normal)
+        } else {
+            support(a, &-normal)
+        },
+    })
+}
+
+pub fn obb_vs_segment(obb: &Obb, segment: &Segment) -> Option<Contact> {
+    sat(
+        &obb.corners(),
+        &[segment.a, segment.b],
+        &obb.axes(),
+        &[segment.normal()],
+    )
+}
+
+pub fn obb_vs_obb(a: &Obb, b: &Obb) -> Option<Contact> {
+    sat(&a.corners(), &b.corners(), &a.axes(), &b.axes())
+}
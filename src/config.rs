@@ -0,0 +1,412 @@
+//! A small reader for the subset of TOML our data files use: `[tables]`, `[[arrays of
+//! tables]]`, and `key = value` pairs holding numbers, booleans, strings or (nested) arrays.
+//! It writes the same subset back out, for the files the game saves itself.
+
+use std::{fmt, fs, path::Path};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Table {
+    entries: Vec<(String, Value)>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String, std::io::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+    WrongType {
+        key: String,
+        expected: &'static str,
+    },
+    /// read fine, but `element` makes no sense the way it is, like a track the cars can't
+    /// get round
+    Invalid {
+        element: String,
+        problem: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => write!(f, "couldn't read {path}: {error}"),
+            ConfigError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            ConfigError::WrongType { key, expected } => write!(f, "`{key}` should be {expected}"),
+            ConfigError::Invalid { element, problem } => write!(f, "{element} {problem}"),
+        }
+    }
+}
+
+impl Value {
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+impl Table {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.entries
+            .iter_mut()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets `key`, replacing an earlier value
+    pub fn set(&mut self, key: &str, value: Value) {
+        match self.entries.iter_mut().find(|(name, _)| name == key) {
+            Some((_, old)) => *old = value,
+            None => self.entries.push((key.to_string(), value)),
+        }
+    }
+
+    fn get_or<'a, T>(
+        &'a self,
+        key: &str,
+        expected: &'static str,
+        convert: impl Fn(&'a Value) -> Option<T>,
+    ) -> Result<Option<T>, ConfigError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(value) => convert(value).map(Some).ok_or(ConfigError::WrongType {
+                key: key.to_string(),
+                expected,
+            }),
+        }
+    }
+
+    pub fn number(&self, key: &str) -> Result<Option<f64>, ConfigError> {
+        self.get_or(key, "a number", Value::as_number)
+    }
+
+    pub fn boolean(&self, key: &str) -> Result<Option<bool>, ConfigError> {
+        self.get_or(key, "true or false", Value::as_bool)
+    }
+
+    pub fn string(&self, key: &str) -> Result<Option<&str>, ConfigError> {
+        self.get_or(key, "a string", Value::as_str)
+    }
+
+    pub fn array(&self, key: &str) -> Result<Option<&[Value]>, ConfigError> {
+        self.get_or(key, "an array", |value| match value {
+            Value::Array(values) => Some(values.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// The tables of a `[[key]]` array, none if there isn't one
+    pub fn tables(&self, key: &str) -> Result<Vec<&Table>, ConfigError> {
+        let wrong_type = || ConfigError::WrongType {
+            key: key.to_string(),
+            expected: "an array of tables",
+        };
+        let Some(values) = self.array(key).map_err(|_| wrong_type())? else {
+            return Ok(Vec::new());
+        };
+        values
+            .iter()
+            .map(|value| match value {
+                Value::Table(table) => Ok(table),
+                _ => Err(wrong_type()),
+            })
+            .collect()
+    }
+
+    pub fn table(&self, key: &str) -> Result<Option<&Table>, ConfigError> {
+        self.get_or(key, "a table", |value| match value {
+            Value::Table(table) => Some(table),
+            _ => None,
+        })
+    }
+
+    /// Overwrites `target` with the number under `key` if there is one
+    pub fn read_number(&self, key: &str, target: &mut f64) -> Result<(), ConfigError> {
+        if let Some(number) = self.number(key)? {
+            *target = number;
+        }
+        Ok(())
+    }
+
+    pub fn read_bool(&self, key: &str, target: &mut bool) -> Result<(), ConfigError> {
+        if let Some(boolean) = self.boolean(key)? {
+            *target = boolean;
+        }
+        Ok(())
+    }
+}
+
+/// A `[x, y]` pair of numbers
+pub fn as_pair(value: &Value) -> Option<(f64, f64)> {
+    match value {
+        Value::Array(values) => match values.as_slice() {
+            [x, y] => Some((x.as_number()?, y.as_number()?)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Table, ConfigError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .map_err(|error| ConfigError::Io(path.display().to_string(), error))?;
+    parse(&text)
+}
+
+/// `table` as TOML that `parse` reads back the same
+pub fn write(table: &Table) -> String {
+    let mut text = String::new();
+    write_table(&mut text, table, &mut Vec::new());
+    text
+}
+
+fn write_table<'a>(text: &mut String, table: &'a Table, path: &mut Vec<&'a str>) {
+    let is_tables = |values: &[Value]| {
+        !values.is_empty() && values.iter().all(|value| matches!(value, Value::Table(_)))
+    };
+    // the plain values have to come before any headers, or they'd end up under them
+    for (key, value) in &table.entries {
+        match value {
+            Value::Table(_) => {}
+            Value::Array(values) if is_tables(values) => {}
+            _ => {
+                text.push_str(&format!("{key} = "));
+                write_value(text, value);
+                text.push('\n');
+            }
+        }
+    }
+    for (key, value) in &table.entries {
+        path.push(key);
+        match value {
+            Value::Table(inner) => {
+                text.push_str(&format!("\n[{}]\n", path.join(".")));
+                write_table(text, inner, path);
+            }
+            Value::Array(values) if is_tables(values) => {
+                for value in values {
+                    let Value::Table(inner) = value else {
+                        unreachable!()
+                    };
+                    text.push_str(&format!("\n[[{}]]\n", path.join(".")));
+                    write_table(text, inner, path);
+                }
+            }
+            _ => {}
+        }
+        path.pop();
+    }
+}
+
+fn write_value(text: &mut String, value: &Value) {
+    match value {
+        Value::Number(number) => text.push_str(&number.to_string()),
+        Value::Bool(boolean) => text.push_str(&boolean.to_string()),
+        Value::String(string) => {
+            text.push('"');
+            for c in string.chars() {
+                match c {
+                    '\n' => text.push_str("\\n"),
+                    '\t' => text.push_str("\\t"),
+                    '"' | '\\' => {
+                        text.push('\\');
+                        text.push(c);
+                    }
+                    _ => text.push(c),
+                }
+            }
+            text.push('"');
+        }
+        Value::Array(values) => {
+            text.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    text.push_str(", ");
+                }
+                write_value(text, value);
+            }
+            text.push(']');
+        }
+        // only ever written as `[[headers]]`, inline tables not being something we read
+        Value::Table(_) => {}
+    }
+}
+
+pub fn parse(text: &str) -> Result<Table, ConfigError> {
+    let mut root = Table::default();
+    // the path of the table header we're currently under
+    let mut current: Vec<String> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: &str| ConfigError::Parse {
+            line: line_number,
+            message: message.to_string(),
+        };
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix("[[") {
+            let header = header
+                .strip_suffix("]]")
+                .ok_or_else(|| error("unclosed `[[`"))?;
+            current = header.trim().split('.').map(str::to_string).collect();
+            let (last, parents) = current.split_last().unwrap();
+            let parent = table_at(&mut root, parents).ok_or_else(|| error("not a table"))?;
+            match parent.get_mut(last) {
+                None => parent.set(last, Value::Array(vec![Value::Table(Table::default())])),
+                Some(Value::Array(items)) => items.push(Value::Table(Table::default())),
+                Some(_) => return Err(error("already defined as something else")),
+            }
+        } else if let Some(header) = line.strip_prefix('[') {
+            let header = header
+                .strip_suffix(']')
+                .ok_or_else(|| error("unclosed `[`"))?;
+            current = header.trim().split('.').map(str::to_string).collect();
+            table_at(&mut root, &current).ok_or_else(|| error("not a table"))?;
+        } else {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `key = value`"))?;
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(error("missing key"));
+            }
+            let mut chars = value.trim().chars().peekable();
+            let value = parse_value(&mut chars).map_err(|message| error(&message))?;
+            if chars.any(|c| !c.is_whitespace()) {
+                return Err(error("unexpected characters after the value"));
+            }
+            let table = table_at(&mut root, &current).ok_or_else(|| error("not a table"))?;
+            if table.get(key).is_some() {
+                return Err(error(&format!("`{key}` is defined twice")));
+            }
+            table.set(key, value);
+        }
+    }
+
+    Ok(root)
+}
+
+/// Walks down `path`, creating tables on the way. Arrays of tables resolve to their last item.
+fn table_at<'a>(table: &'a mut Table, path: &[String]) -> Option<&'a mut Table> {
+    let Some((first, rest)) = path.split_first() else {
+        return Some(table);
+    };
+    if table.get(first).is_none() {
+        table.set(first, Value::Table(Table::default()));
+    }
+    match table.get_mut(first)? {
+        Value::Table(table) => table_at(table, rest),
+        Value::Array(items) => match items.last_mut() {
+            Some(Value::Table(table)) => table_at(table, rest),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Value, String> {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+    match chars.peek() {
+        None => Err("missing value".to_string()),
+        Some('"') => {
+            chars.next();
+            let mut string = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err("unclosed string".to_string()),
+                    Some('"') => return Ok(Value::String(string)),
+                    Some('\\') => match chars.next() {
+                        Some('n') => string.push('\n'),
+                        Some('t') => string.push('\t'),
+                        Some(c @ ('"' | '\\')) => string.push(c),
+                        _ => return Err("unknown escape in string".to_string()),
+                    },
+                    Some(c) => string.push(c),
+                }
+            }
+        }
+        Some('[') => {
+            chars.next();
+            let mut values = Vec::new();
+            loop {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                if chars.next_if_eq(&']').is_some() {
+                    return Ok(Value::Array(values));
+                }
+                values.push(parse_value(chars)?);
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                match chars.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Value::Array(values)),
+                    _ => return Err("expected `,` or `]` in array".to_string()),
+                }
+            }
+        }
+        Some(_) => {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| !matches!(c, ',' | ']') && !c.is_whitespace()) {
+                word.push(c);
+            }
+            match word.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => word
+                    .replace('_', "")
+                    .parse()
+                    .map(Value::Number)
+                    .map_err(|_| format!("`{word}` isn't a value")),
+            }
+        }
+    }
+}
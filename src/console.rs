@@ -0,0 +1,213 @@
+use sdl2::{
+    keyboard::Scancode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas, RenderTarget},
+};
+
+use crate::{input::TickInput, text, SCREEN_DIMENSIONS};
+
+/// How many lines of what's been run and said back stay on show
+const LOG_LINES: usize = 12;
+/// Screen pixels per font pixel
+const SCALE: u32 = 3;
+/// Around and between the lines, in pixels
+const PADDING: u32 = 12;
+
+/// Runs a command on whatever the console's for, given the words typed after its name,
+/// saying back how it went or what was wrong
+pub type Handler<T> = fn(&mut T, &[&str]) -> Result<String, String>;
+
+/// A command the console knows, by the name it's typed with
+struct Registered<T> {
+    name: &'static str,
+    /// how it's typed, for HELP to list
+    usage: &'static str,
+    handler: Handler<T>,
+}
+
+/// A line dropping down over the top of the screen to type commands into while playing, for
+/// trying things out without editing files and starting over. Each command is a handler
+/// registered under its name, and HELP lists them. Names are the same in any case, as the
+/// built-in font only has capitals anyway.
+pub struct Console<T> {
+    commands: Vec<Registered<T>>,
+    open: bool,
+    /// what's been typed so far
+    line: String,
+    /// the lines run and what they said back, the latest last, in white for what was typed
+    /// and red for what went wrong
+    log: Vec<(String, Color)>,
+}
+
+impl<T> Default for Console<T> {
+    fn default() -> Console<T> {
+        Console {
+            commands: Vec::new(),
+            open: false,
+            line: String::new(),
+            log: Vec::new(),
+        }
+    }
+}
+
+impl<T> Console<T> {
+    /// Runs `handler` for lines starting with `name` from now on
+    pub fn register(&mut self, name: &'static str, usage: &'static str, handler: Handler<T>) {
+        self.commands.push(Registered {
+            name,
+            usage,
+            handler,
+        });
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Takes what was typed during a tick, running the line on `target` when it's entered
+    pub fn update(&mut self, target: &mut T, input: &TickInput) {
+        // the key that opened the console types itself as well
+        let typed = input
+            .text
+            .chars()
+            .filter(|&character| !character.is_control() && !matches!(character, '`' | '~'));
+        self.line.extend(typed);
+        match input.key {
+            Some(Scancode::Backspace) => {
+                self.line.pop();
+            }
+            Some(Scancode::Return | Scancode::KpEnter) => {
+                let line = std::mem::take(&mut self.line);
+                self.run(target, &line);
+            }
+            _ => {}
+        }
+    }
+
+    /// Runs `line` on `target` with the handler its first word names
+    pub fn run(&mut self, target: &mut T, line: &str) {
+        let words: Vec<_> = line.split_whitespace().collect();
+        let Some((name, arguments)) = words.split_first() else {
+            return;
+        };
+        let reply = if name.eq_ignore_ascii_case("help") {
+            Ok(self
+                .commands
+                .iter()
+                .map(|command| command.usage)
+                .collect::<Vec<_>>()
+                .join("\n"))
+        } else {
+            match self
+                .commands
+                .iter()
+                .find(|command| command.name.eq_ignore_ascii_case(name))
+            {
+                Some(command) => (command.handler)(target, arguments),
+                None => Err(format!("no command called {name}, HELP lists them")),
+            }
+        };
+        self.log.push((words.join(" "), Color::WHITE));
+        let (reply, color) = match reply {
+            Ok(reply) => (reply, Color::RGB(200, 200, 200)),
+            Err(error) => (error, Color::RGB(255, 90, 80)),
+        };
+        self.log
+            .extend(reply.lines().map(|line| (line.to_string(), color)));
+        let over = self.log.len().saturating_sub(LOG_LINES);
+        self.log.drain(..over);
+    }
+
+    /// Drops down over the top of the screen while it's open
+    pub fn render<R: RenderTarget>(&self, canvas: &mut Canvas<R>) {
+        if !self.open {
+            return;
+        }
+        let width = SCREEN_DIMENSIONS.0 as u32;
+        let line_height = text::height(SCALE) + PADDING;
+        let height = (LOG_LINES as u32 + 1) * line_height + PADDING;
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 200));
+        canvas.fill_rect(Rect::new(0, 0, width, height)).unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let left = PADDING as i32;
+        // the latest at the bottom, just over the line being typed
+        let log_top = (LOG_LINES - self.log.len()) as u32 * line_height + PADDING;
+        for (i, (line, color)) in self.log.iter().enumerate() {
+            let top = (log_top + i as u32 * line_height) as i32;
+            text::draw(canvas, line, (left, top), SCALE, *color);
+        }
+        let top = (LOG_LINES as u32 * line_height + PADDING) as i32;
+        let prompt = format!(": {}", self.line);
+        text::draw(canvas, &prompt, (left, top), SCALE, Color::RGB(255, 220, 0));
+        // where the next character goes
+        let cursor = left + text::width(&prompt, SCALE) as i32 + SCALE as i32;
+        canvas.set_draw_color(Color::RGB(255, 220, 0));
+        canvas
+            .fill_rect(Rect::new(cursor, top, SCALE * 5, text::height(SCALE)))
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(total: &mut i32, arguments: &[&str]) -> Result<String, String> {
+        let [amount] = arguments else {
+            return Err("add takes one number".to_string());
+        };
+        *total += amount
+            .parse::<i32>()
+            .map_err(|_| format!("{amount} isn't a number"))?;
+        Ok(format!("{total} now"))
+    }
+
+    #[test]
+    fn entered_lines_run_the_command_they_name() {
+        let mut console = Console::default();
+        console.register("add", "add (number)", add);
+        console.toggle();
+        let mut total = 0;
+        let typing = |text: &str, key| TickInput {
+            text: text.to_string(),
+            key,
+            ..TickInput::default()
+        };
+        for input in [
+            typing("`ADD 3", None),
+            typing("", Some(Scancode::Backspace)),
+            typing("4", None),
+            typing("", Some(Scancode::Return)),
+        ] {
+            console.update(&mut total, &input);
+        }
+        assert_eq!(total, 4);
+        assert!(console.line.is_empty());
+
+        console.run(&mut total, "add x");
+        console.run(&mut total, "jump");
+        console.run(&mut total, "help");
+        let log: Vec<_> = console.log.iter().map(|(line, _)| line.as_str()).collect();
+        assert_eq!(
+            log,
+            [
+                "ADD 4",
+                "4 now",
+                "add x",
+                "x isn't a number",
+                "jump",
+                "no command called jump, HELP lists them",
+                "help",
+                "add (number)"
+            ]
+        );
+        assert_eq!(total, 4);
+    }
+}
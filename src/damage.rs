@@ -0,0 +1,79 @@
+use crate::config::{ConfigError, Table};
+
+#[derive(Clone)]
+pub struct DamageSpec {
+    /// impacts slower than this in pixels per tick leave no mark
+    pub threshold: f64,
+    /// damage for every pixel per tick of impact speed above the threshold, 1 being wrecked
+    pub per_speed: f64,
+    /// share of the engine's power, and with it the top speed, a wrecked car loses
+    pub speed_loss: f64,
+    /// share of the acceleration a wrecked car loses
+    pub acceleration_loss: f64,
+    /// share of the steering speed a wrecked car loses
+    pub steering_loss: f64,
+}
+
+impl Default for DamageSpec {
+    fn default() -> DamageSpec {
+        DamageSpec {
+            threshold: 2.,
+            per_speed: 0.02,
+            speed_loss: 0.4,
+            acceleration_loss: 0.3,
+            steering_loss: 0.5,
+        }
+    }
+}
+
+impl DamageSpec {
+    /// Overwrites whatever `table` specifies
+    pub fn read(&mut self, table: &Table) -> Result<(), ConfigError> {
+        table.read_number("threshold", &mut self.threshold)?;
+        table.read_number("per_speed", &mut self.per_speed)?;
+        table.read_number("speed_loss", &mut self.speed_loss)?;
+        table.read_number("acceleration_loss", &mut self.acceleration_loss)?;
+        table.read_number("steering_loss", &mut self.steering_loss)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct Damage {
+    amount: f64,
+}
+
+impl Damage {
+    /// From 0 for a pristine car to 1 for a wreck
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    /// Takes a hit at `impact_speed`, the speed along the contact normal
+    pub fn hit(&mut self, spec: &DamageSpec, impact_speed: f64) {
+        let excess = impact_speed.abs() - spec.threshold;
+        if excess > 0. {
+            self.amount = (self.amount + excess * spec.per_speed).min(1.);
+        }
+    }
+
+    /// Takes `amount` of damage off again, 1 mending the car completely
+    pub fn repair(&mut self, amount: f64) {
+        self.amount = (self.amount - amount).max(0.);
+    }
+
+    /// Share of the top speed left
+    pub fn speed_share(&self, spec: &DamageSpec) -> f64 {
+        1. - self.amount * spec.speed_loss
+    }
+
+    /// Share of the acceleration left
+    pub fn acceleration_share(&self, spec: &DamageSpec) -> f64 {
+        1. - self.amount * spec.acceleration_loss
+    }
+
+    /// Share of the steering speed left
+    pub fn steering_share(&self, spec: &DamageSpec) -> f64 {
+        1. - self.amount * spec.steering_loss
+    }
+}
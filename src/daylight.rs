@@ -0,0 +1,58 @@
+//! The time of day going round as the level plays, darkening the world at night for the
+//! cars' lights to show up in
+
+use sdl2::pixels::Color;
+
+use crate::TICKS_PER_SECOND;
+
+/// what the world is tinted at dead of night, when the sun's setting and in full daylight
+const NIGHT: Color = Color::RGB(45, 55, 100);
+const DUSK: Color = Color::RGB(255, 170, 120);
+const DAY: Color = Color::WHITE;
+/// Below this much daylight the cars turn their lights on
+const LIGHTS_ON: f64 = 0.6;
+
+pub struct TimeOfDay {
+    /// from 0 at midnight to just under 24
+    hour: f64,
+    hours_per_tick: f64,
+}
+
+impl TimeOfDay {
+    /// Starting at `hour`, with `hours_per_second` going by every second played. 0 stops the
+    /// clock.
+    pub fn new(hour: f64, hours_per_second: f64) -> TimeOfDay {
+        TimeOfDay {
+            hour: hour.rem_euclid(24.),
+            hours_per_tick: hours_per_second / TICKS_PER_SECOND,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.hour = (self.hour + self.hours_per_tick).rem_euclid(24.);
+    }
+
+    /// From 0 all night to 1 all day, with the sun coming up around 6 and going down
+    /// around 18
+    pub fn daylight(&self) -> f64 {
+        let sun = -(self.hour / 24. * std::f64::consts::TAU).cos();
+        ((sun + 0.2) / 0.6).clamp(0., 1.)
+    }
+
+    /// What the colors of the world get multiplied by, going orange around dawn and dusk
+    pub fn tint(&self) -> Color {
+        let daylight = self.daylight();
+        let (from, to, share) = if daylight < 0.5 {
+            (NIGHT, DUSK, daylight * 2.)
+        } else {
+            (DUSK, DAY, daylight * 2. - 1.)
+        };
+        let mix = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * share) as u8;
+        Color::RGB(mix(from.r, to.r), mix(from.g, to.g), mix(from.b, to.b))
+    }
+
+    /// How bright the cars' lights shine, from 0 in daylight to 1 at night
+    pub fn lights(&self) -> f64 {
+        (1. - self.daylight() / LIGHTS_ON).max(0.)
+    }
+}
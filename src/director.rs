@@ -0,0 +1,114 @@
+//! Films replays like a broadcast would, cutting between cameras by the side of the track,
+//! chase shots and the whole track from overhead
+
+use nalgebra::Point2;
+
+use crate::{camera::Camera, car::Car, track::Track, TICKS_PER_SECOND};
+
+/// how close a trackside camera is zoomed in
+const TRACKSIDE_ZOOM: f64 = 0.8;
+/// how far the car can get from a trackside camera before it cuts away, in pixels
+const TRACKSIDE_RANGE: f64 = 800.;
+/// how many ticks ahead of the car to look for the next trackside camera, so the car drives
+/// into the shot rather than out of it
+const TRACKSIDE_LEAD: f64 = 60.;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Shot {
+    /// standing still at one of the track's camera spots while the car goes by
+    Trackside(usize),
+    /// following the car the way the player would
+    Chase,
+    /// the whole track at once
+    Overhead,
+}
+
+/// Picks the shots for one car
+pub struct Director {
+    camera: Camera,
+    shot: Shot,
+    /// ticks since the last cut
+    held_for: u32,
+    trackside_shots: u32,
+}
+
+impl Director {
+    /// Takes over from `camera`, starting with a chase shot from where it is
+    pub fn new(camera: Camera) -> Director {
+        Director {
+            camera,
+            shot: Shot::Chase,
+            held_for: 0,
+            trackside_shots: 0,
+        }
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn update(&mut self, car: &Car, track: &Track) {
+        self.held_for += 1;
+        let seconds = self.held_for as f64 / TICKS_PER_SECOND;
+        let over = match self.shot {
+            // given a moment for the car to drive into the shot
+            Shot::Trackside(spot) => {
+                let distance = (car.center() - track.trackside_cameras[spot]).norm();
+                (seconds > 1. && distance > TRACKSIDE_RANGE) || seconds > 8.
+            }
+            Shot::Chase => seconds > 5.,
+            Shot::Overhead => seconds > 3.,
+        };
+        if over {
+            self.cut(car, track);
+        }
+
+        match self.shot {
+            Shot::Trackside(spot) => self
+                .camera
+                .hold(track.trackside_cameras[spot], TRACKSIDE_ZOOM),
+            Shot::Chase => self.camera.update(car, track),
+            Shot::Overhead => {
+                if let Some((top_left, bottom_right)) = track.bounds {
+                    let size = bottom_right - top_left;
+                    let (width, height) = self.camera.view;
+                    let zoom = (width as f64 / size.x).min(height as f64 / size.y);
+                    self.camera.hold(top_left + size / 2., zoom);
+                }
+            }
+        }
+    }
+
+    /// Chase shots alternate with trackside ones, and every other trackside shot is
+    /// followed by a look at the whole track
+    fn cut(&mut self, car: &Car, track: &Track) {
+        self.held_for = 0;
+        self.shot = match (self.shot, nearest_spot(car, track)) {
+            (Shot::Chase, Some(spot)) => {
+                self.trackside_shots += 1;
+                Shot::Trackside(spot)
+            }
+            (Shot::Trackside(_), _)
+                if self.trackside_shots.is_multiple_of(2) && track.bounds.is_some() =>
+            {
+                Shot::Overhead
+            }
+            _ => Shot::Chase,
+        };
+        if self.shot == Shot::Chase {
+            self.camera.cut_to(car);
+        }
+    }
+}
+
+/// The camera spot closest to where the car is about to be, if the track has any
+fn nearest_spot(car: &Car, track: &Track) -> Option<usize> {
+    let ahead: Point2<f64> = car.center() + car.point_velocity(car.center()) * TRACKSIDE_LEAD;
+    let distance = |spot: &Point2<f64>| (spot - ahead).norm();
+    track
+        .trackside_cameras
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+        .map(|(i, _)| i)
+}
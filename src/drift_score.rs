@@ -0,0 +1,155 @@
+use nalgebra::Point2;
+
+use crate::{car::Car, TICKS_PER_SECOND};
+
+/// Points for every tick of drifting, for each radian of drift angle at a pixel per tick
+const POINTS_PER_TICK: f64 = 1.;
+/// Ticks a combo holds on for once the car grips again, for the next drift to carry it on
+const COMBO_TIME: u32 = (1.5 * TICKS_PER_SECOND) as u32;
+/// Every drift chained on adds one to the multiplier, up to this
+const MAX_MULTIPLIER: u32 = 5;
+/// Hitting anything harder than this, in pixels per tick, loses the whole combo
+const CRASH_IMPACT: f64 = 1.;
+/// Ticks between the points made so far popping up by the car
+const POPUP_INTERVAL: u32 = 30;
+/// Ticks a popup floats up for before it's faded out
+const POPUP_LIFETIME: u32 = 60;
+
+/// Points made in a run of drifts, each one started before the last one's time ran out
+pub struct Combo {
+    points: f64,
+    multiplier: u32,
+    /// ticks left until the combo's banked, held full while drifting
+    left: u32,
+    drifting: bool,
+}
+
+impl Combo {
+    pub fn points(&self) -> u32 {
+        self.points as u32
+    }
+
+    pub fn multiplier(&self) -> u32 {
+        self.multiplier
+    }
+
+    /// How much of the combo's time is left, from 1 while drifting down to 0 when it's banked
+    pub fn time_left(&self) -> f64 {
+        self.left as f64 / COMBO_TIME as f64
+    }
+}
+
+/// Points made drifting, shown for a moment where they were made
+pub struct Popup {
+    pub position: Point2<f64>,
+    pub points: u32,
+    age: u32,
+}
+
+impl Popup {
+    /// From 0 when it popped up to 1 when it's gone
+    pub fn age(&self) -> f64 {
+        self.age as f64 / POPUP_LIFETIME as f64
+    }
+}
+
+/// One car's score from drifting: the faster and more sideways, the more points. Points
+/// go in a combo first and only count once it's banked, multiplied by how many drifts it
+/// took, which a crash puts a stop to before they're banked.
+#[derive(Default)]
+pub struct DriftScore {
+    banked: u64,
+    combo: Option<Combo>,
+    /// points made since the last popup
+    unshown: f64,
+    since_popup: u32,
+    popups: Vec<Popup>,
+}
+
+impl DriftScore {
+    pub fn banked(&self) -> u64 {
+        self.banked
+    }
+
+    pub fn combo(&self) -> Option<&Combo> {
+        self.combo.as_ref()
+    }
+
+    pub fn popups(&self) -> &[Popup] {
+        &self.popups
+    }
+
+    /// Loses the combo going without banking it, like when crashing
+    pub fn forfeit(&mut self) {
+        self.combo = None;
+        self.unshown = 0.;
+    }
+
+    /// Points for something other than drifting, like knocking things over, going on the
+    /// combo if there is one and banked straight away if not
+    pub fn bonus(&mut self, points: u32, position: Point2<f64>) {
+        match &mut self.combo {
+            Some(combo) => combo.points += points as f64,
+            None => self.banked += points as u64,
+        }
+        self.popups.push(Popup {
+            position,
+            points,
+            age: 0,
+        });
+    }
+
+    pub fn update(&mut self, car: &Car) {
+        for popup in &mut self.popups {
+            popup.age += 1;
+        }
+        self.popups.retain(|popup| popup.age < POPUP_LIFETIME);
+
+        if car.impact() > CRASH_IMPACT {
+            self.forfeit();
+            return;
+        }
+
+        let drifting = car.is_drifting() && !car.is_airborne();
+        if drifting {
+            let combo = self.combo.get_or_insert(Combo {
+                points: 0.,
+                multiplier: 0,
+                left: 0,
+                drifting: false,
+            });
+            if !combo.drifting {
+                combo.multiplier = (combo.multiplier + 1).min(MAX_MULTIPLIER);
+            }
+            let points = car.drift_angle().abs() * car.speed() * POINTS_PER_TICK;
+            combo.points += points;
+            combo.left = COMBO_TIME;
+            self.unshown += points;
+        }
+
+        self.since_popup += 1;
+        if self.since_popup >= POPUP_INTERVAL || (!drifting && self.unshown > 0.) {
+            if self.unshown >= 1. {
+                self.popups.push(Popup {
+                    position: car.center(),
+                    points: self.unshown as u32,
+                    age: 0,
+                });
+            }
+            self.unshown = 0.;
+            self.since_popup = 0;
+        }
+
+        let Some(combo) = &mut self.combo else {
+            return;
+        };
+        combo.drifting = drifting;
+        if !drifting {
+            combo.left = combo.left.saturating_sub(1);
+            if combo.left == 0 {
+                self.banked += (combo.points * combo.multiplier as f64) as u64;
+                self.combo = None;
+            }
+        }
+    }
+}
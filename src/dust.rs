@@ -0,0 +1,134 @@
+use nalgebra::{Point2, Vector2};
+use sdl2::pixels::Color;
+
+use crate::{rng::Rng, terrain::Surface};
+
+/// Ticks a cloud of dust hangs about for
+const CLOUD_LIFETIME: u32 = 90;
+/// Ticks a bit of debris flies for before it's back on the ground and out of sight
+const DEBRIS_LIFETIME: u32 = 25;
+/// Below this speed a tyre doesn't throw anything up, in pixels per tick
+const MIN_SPEED: f64 = 1.;
+/// Going this fast throws up as much as a tyre can
+const FULL_SPEED: f64 = 12.;
+/// Once there are this many particles the oldest ones go first
+const MAX_PARTICLES: usize = 800;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// big and slow, hanging in the air well after the car has gone
+    Cloud,
+    /// small bits of the ground flung out from under the tyres
+    Debris,
+}
+
+pub struct Particle {
+    pub kind: Kind,
+    pub position: Point2<f64>,
+    /// pixels per tick
+    velocity: Vector2<f64>,
+    pub color: Color,
+    size: f64,
+    age: u32,
+}
+
+impl Particle {
+    fn lifetime(&self) -> u32 {
+        match self.kind {
+            Kind::Cloud => CLOUD_LIFETIME,
+            Kind::Debris => DEBRIS_LIFETIME,
+        }
+    }
+
+    /// From 1 when it came up down to 0 when it's gone
+    pub fn thickness(&self) -> f64 {
+        1. - self.age as f64 / self.lifetime() as f64
+    }
+
+    pub fn size(&self) -> f64 {
+        match self.kind {
+            // spreading out as it thins
+            Kind::Cloud => self.size * (1. + 3. * (1. - self.thickness())),
+            Kind::Debris => self.size,
+        }
+    }
+}
+
+/// Dust and bits of dirt and gravel thrown up by tyres running off the track. Unlike tyre
+/// smoke it comes from rolling over loose ground rather than sliding, so it's there
+/// whenever a car is on the grass or in a gravel trap. Drawn on the level's seeded RNG too.
+#[derive(Default)]
+pub struct Dust {
+    particles: Vec<Particle>,
+}
+
+impl Dust {
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// Throws up whatever `surface` is made of from under a tyre at `position` moving at
+    /// `velocity`, the faster the more of it
+    pub fn emit(
+        &mut self,
+        rng: &mut Rng,
+        surface: Surface,
+        position: Point2<f64>,
+        velocity: Vector2<f64>,
+    ) {
+        let Some(color) = surface.dust_color() else {
+            return;
+        };
+        let amount = ((velocity.norm() - MIN_SPEED) / FULL_SPEED).min(1.);
+        if amount <= 0. {
+            return;
+        }
+
+        if rng.range(0., 1.) < amount * 0.5 {
+            let drift = Vector2::new(rng.range(-0.2, 0.2), rng.range(-0.2, 0.2));
+            self.particles.push(Particle {
+                kind: Kind::Cloud,
+                position: position + Vector2::new(rng.range(-6., 6.), rng.range(-6., 6.)),
+                velocity: velocity * 0.1 + drift,
+                color,
+                size: 16. + amount * 16.,
+                age: 0,
+            });
+        }
+        // gravel flies further than clumps of dirt
+        let (debris, spread) = match surface {
+            Surface::Gravel => (amount, 1.5),
+            _ => (amount * 0.4, 0.8),
+        };
+        if rng.range(0., 1.) < debris {
+            let flung = Vector2::new(rng.range(-spread, spread), rng.range(-spread, spread));
+            self.particles.push(Particle {
+                kind: Kind::Debris,
+                position,
+                velocity: velocity * 0.6 + flung * (1. + amount * 2.),
+                color: Color::RGB(color.r / 2, color.g / 2, color.b / 2),
+                size: rng.range(2., 4.),
+                age: 0,
+            });
+        }
+
+        if self.particles.len() > MAX_PARTICLES {
+            let extra = self.particles.len() - MAX_PARTICLES;
+            self.particles.drain(..extra);
+        }
+    }
+
+    /// Moves every particle on, debris slowing a lot quicker than dust hanging in the air
+    pub fn update(&mut self) {
+        for particle in &mut self.particles {
+            particle.position += particle.velocity;
+            particle.velocity *= match particle.kind {
+                Kind::Cloud => 0.97,
+                Kind::Debris => 0.9,
+            };
+            particle.age += 1;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime());
+    }
+}
@@ -0,0 +1,486 @@
+use std::path::{Path, PathBuf};
+
+use nalgebra::{Point2, Vector2};
+use sdl2::{
+    pixels::Color,
+    rect::{FPoint, FRect},
+    render::{BlendMode, Canvas, RenderTarget},
+    video::Window,
+};
+
+use crate::{
+    assets::Assets,
+    camera::{Camera, CameraSpec},
+    car::CarSpec,
+    input::{Command, Pointer, TickInput},
+    scene::{Scene, Transition},
+    terrain::Surface,
+    text,
+    track_toml::{Grid, Patch, Shape, Spot, TrackFile, Wall, Zone},
+    Level, SCREEN_DIMENSIONS,
+};
+
+/// Everything put down lands on a multiple of this many pixels, to keep things lined up
+const SNAP: f64 = 25.;
+/// Ending a wall this close to where it started closes it
+const CLOSE_DISTANCE: f64 = 40.;
+/// How thick the checkpoints dragged out across the track are
+const CHECKPOINT_DEPTH: f64 = 40.;
+/// Ticks a message stays up for
+const MESSAGE_TICKS: u32 = 180;
+
+/// What the mouse puts down, switched between with the tool key
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tool {
+    /// clicking along the wall, right clicking to end it
+    Wall,
+    /// dragging out a rectangle of the surface
+    Surface(Surface),
+    /// dragging across the track
+    Checkpoint,
+    /// dragging out a rectangle
+    Prop,
+    /// dragging from pole position the way the cars face
+    Grid,
+}
+
+impl Tool {
+    fn next(self) -> Tool {
+        match self {
+            Tool::Wall => Tool::Surface(Surface::Asphalt),
+            Tool::Surface(Surface::Asphalt) => Tool::Surface(Surface::Gravel),
+            Tool::Surface(Surface::Gravel) => Tool::Surface(Surface::Ice),
+            Tool::Surface(Surface::Ice) => Tool::Surface(Surface::Grass),
+            Tool::Surface(Surface::Grass) => Tool::Checkpoint,
+            Tool::Checkpoint => Tool::Prop,
+            Tool::Prop => Tool::Grid,
+            Tool::Grid => Tool::Wall,
+        }
+    }
+
+    fn name(self) -> String {
+        match self {
+            Tool::Wall => "WALL".to_string(),
+            Tool::Surface(surface) => surface.name().to_uppercase(),
+            Tool::Checkpoint => "CHECKPOINT".to_string(),
+            Tool::Prop => "PROP".to_string(),
+            Tool::Grid => "GRID".to_string(),
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            Tool::Wall => "CLICK ALONG THE WALL, RIGHT CLICK TO END IT",
+            Tool::Surface(_) => "DRAG OUT A PATCH OF IT",
+            Tool::Checkpoint => "DRAG ACROSS THE TRACK, IN THE ORDER THEY COME IN A LAP",
+            Tool::Prop => "DRAG OUT SOMETHING TO HIT",
+            Tool::Grid => "DRAG FROM POLE POSITION THE WAY THE CARS FACE",
+        }
+    }
+}
+
+/// Making tracks with the mouse: walls, patches of surface, checkpoints, props and the grid,
+/// saved to a track file and driven on at the press of a key. The camera flies about with
+/// the driving controls, like the free camera.
+pub struct Editor {
+    file: TrackFile,
+    /// where it's saved to
+    path: PathBuf,
+    /// the track as it was before each change, to undo them with
+    history: Vec<TrackFile>,
+    /// whether there are changes that haven't been saved
+    unsaved: bool,
+    tool: Tool,
+    camera: Camera,
+    /// the mouse as of the last tick, to tell when its buttons go down and come up
+    pointer: Pointer,
+    /// where the mouse went down for the tools that drag
+    drag: Option<Point2<f64>>,
+    /// the points of the wall being put down
+    wall: Vec<Point2<f64>>,
+    /// the track as it is, to draw it with
+    preview: Level,
+    spec: CarSpec,
+    camera_spec: CameraSpec,
+    players: usize,
+    /// what happened last, like the track being saved, and for how many more ticks it's up
+    message: Option<(String, u32)>,
+}
+
+impl Editor {
+    pub fn new(
+        file: TrackFile,
+        path: PathBuf,
+        spec: CarSpec,
+        camera_spec: CameraSpec,
+        players: usize,
+    ) -> Editor {
+        // the whole track in view to start with
+        let size = Vector2::new(file.size.0 as f64, file.size.1 as f64) * file.tile_size;
+        let mut camera = Camera::new((size / 2.).into(), SCREEN_DIMENSIONS, camera_spec.clone());
+        camera.toggle_free();
+        let fit = (SCREEN_DIMENSIONS.0 as f64 / size.x).min(SCREEN_DIMENSIONS.1 as f64 / size.y);
+        camera.zoom_by(fit * 0.9 / camera.zoom());
+
+        let track = file.track(path.parent().unwrap_or(Path::new("")));
+        let preview = Level::new(spec.clone(), camera_spec.clone(), track, players, false, 1);
+        Editor {
+            file,
+            path,
+            history: Vec::new(),
+            unsaved: false,
+            tool: Tool::Wall,
+            camera,
+            pointer: Pointer::default(),
+            drag: None,
+            wall: Vec::new(),
+            preview,
+            spec,
+            camera_spec,
+            players,
+            message: None,
+        }
+    }
+
+    /// Makes a change to the track, remembering how it was to undo it
+    fn change(&mut self, change: impl FnOnce(&mut TrackFile)) {
+        self.history.push(self.file.clone());
+        change(&mut self.file);
+        self.changed();
+    }
+
+    fn changed(&mut self) {
+        self.unsaved = true;
+        self.preview = self.level();
+    }
+
+    /// A level on the track as it is, to draw it with or drive on it
+    fn level(&self) -> Level {
+        let track = self.file.track(self.path.parent().unwrap_or(Path::new("")));
+        Level::new(
+            self.spec.clone(),
+            self.camera_spec.clone(),
+            track,
+            self.players,
+            false,
+            1,
+        )
+    }
+
+    fn undo(&mut self) {
+        // the wall going down goes a point at a time
+        if self.wall.pop().is_some() {
+            return;
+        }
+        if let Some(file) = self.history.pop() {
+            self.file = file;
+            self.changed();
+        }
+    }
+
+    fn save(&mut self) {
+        let message = match self.file.save(&self.path) {
+            Ok(()) => {
+                self.unsaved = false;
+                format!("SAVED {}", self.path.display())
+            }
+            Err(error) => {
+                eprintln!(
+                    "couldn't save the track to {}: {error}",
+                    self.path.display()
+                );
+                "COULDN'T SAVE THE TRACK".to_string()
+            }
+        };
+        self.message = Some((message, MESSAGE_TICKS));
+    }
+
+    /// The track to drive on as it is, going back to editing with the same key
+    fn test_drive(&self) -> Level {
+        let mut level = self.level();
+        level.test_drive = true;
+        level
+    }
+
+    /// Where in the world the mouse is, lined up to the snapping
+    fn cursor(&self, pointer: &Pointer) -> Point2<f64> {
+        let on_screen = Point2::new(
+            pointer.position.x * SCREEN_DIMENSIONS.0 as f64,
+            pointer.position.y * SCREEN_DIMENSIONS.1 as f64,
+        );
+        self.camera
+            .to_world(on_screen)
+            .map(|coordinate| (coordinate / SNAP).round() * SNAP)
+    }
+
+    /// Puts down whatever the mouse was dragged out from `start` to `end`
+    fn place(&mut self, start: Point2<f64>, end: Point2<f64>) {
+        let (corner, size) = (start.inf(&end), (end - start).abs());
+        let along = end - start;
+        match self.tool {
+            Tool::Wall => {}
+            Tool::Surface(surface) if size.x > 0. && size.y > 0. => self.change(|file| {
+                file.surfaces.push(Patch {
+                    surface,
+                    zone: Zone::Area { corner, size },
+                })
+            }),
+            Tool::Checkpoint if along.norm() > 0. => self.change(|file| {
+                file.checkpoints.push(Shape {
+                    center: nalgebra::center(&start, &end),
+                    size: Vector2::new(along.norm(), CHECKPOINT_DEPTH),
+                    angle: along.y.atan2(along.x).to_degrees(),
+                })
+            }),
+            Tool::Prop if size.x > 0. && size.y > 0. => self.change(|file| {
+                file.props.push(Shape {
+                    center: corner + size / 2.,
+                    size,
+                    angle: 0.,
+                })
+            }),
+            Tool::Grid => {
+                // clockwise from up the map, as the cars face up when unturned
+                let angle = if along.norm() > 0. {
+                    along.x.atan2(-along.y).to_degrees()
+                } else {
+                    0.
+                };
+                self.change(|file| {
+                    let grid = file.grid.get_or_insert(Grid {
+                        pole: Spot {
+                            position: start,
+                            angle,
+                        },
+                        slots: 6,
+                        spacing: 150.,
+                        across: 120.,
+                    });
+                    grid.pole = Spot {
+                        position: start,
+                        angle,
+                    };
+                })
+            }
+            // nothing to put down from a click that didn't drag
+            _ => {}
+        }
+    }
+
+    /// Puts the wall that's been clicked out down, if it's more than a point
+    fn end_wall(&mut self) {
+        let mut points = std::mem::take(&mut self.wall);
+        if points.len() < 2 {
+            return;
+        }
+        let closed =
+            points.len() > 2 && (points[points.len() - 1] - points[0]).norm() < CLOSE_DISTANCE;
+        if closed {
+            points.pop();
+        }
+        self.change(|file| file.walls.push(Wall { points, closed }));
+    }
+
+    fn handle_pointer(&mut self, pointer: Pointer) {
+        let cursor = self.cursor(&pointer);
+        let pressed = pointer.left && !self.pointer.left;
+        let released = !pointer.left && self.pointer.left;
+        let right_pressed = pointer.right && !self.pointer.right;
+        self.pointer = pointer;
+
+        match self.tool {
+            Tool::Wall => {
+                if pressed {
+                    self.wall.push(cursor);
+                    if self.wall.len() > 2 && (cursor - self.wall[0]).norm() < CLOSE_DISTANCE {
+                        self.end_wall();
+                    }
+                }
+                if right_pressed {
+                    self.end_wall();
+                }
+            }
+            _ => {
+                if pressed {
+                    self.drag = Some(cursor);
+                }
+                if released {
+                    if let Some(start) = self.drag.take() {
+                        self.place(start, cursor);
+                    }
+                }
+                if right_pressed {
+                    self.drag = None;
+                }
+            }
+        }
+    }
+
+    fn draw_overlay<T: RenderTarget>(&self, canvas: &mut Canvas<T>, camera: &Camera) {
+        let to_screen = |point: Point2<f64>| camera.relative_point(point);
+        let cursor = self.cursor(&self.pointer);
+        canvas.set_draw_color(Color::RGB(255, 220, 0));
+
+        if !self.wall.is_empty() {
+            let mut line: Vec<FPoint> = self.wall.iter().map(|&point| to_screen(point)).collect();
+            line.push(to_screen(cursor));
+            canvas.draw_flines(&line[..]).unwrap();
+        }
+        if let Some(start) = self.drag {
+            let (a, b) = (to_screen(start), to_screen(cursor));
+            match self.tool {
+                Tool::Checkpoint | Tool::Grid => canvas.draw_fline(a, b).unwrap(),
+                _ => canvas
+                    .draw_frect(FRect::new(
+                        a.x.min(b.x),
+                        a.y.min(b.y),
+                        (b.x - a.x).abs(),
+                        (b.y - a.y).abs(),
+                    ))
+                    .unwrap(),
+            }
+        }
+
+        let at = to_screen(cursor);
+        let reach = 12.;
+        canvas
+            .draw_fline(at - FPoint::new(reach, 0.), at + FPoint::new(reach, 0.))
+            .unwrap();
+        canvas
+            .draw_fline(at - FPoint::new(0., reach), at + FPoint::new(0., reach))
+            .unwrap();
+    }
+
+    fn draw_hud<T: RenderTarget>(&self, canvas: &mut Canvas<T>) {
+        let (width, height) = SCREEN_DIMENSIONS;
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+        canvas
+            .fill_rect(sdl2::rect::Rect::new(0, 0, width as u32, 110))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let title = format!(
+            "{}{}",
+            self.path.display().to_string().to_uppercase(),
+            if self.unsaved { " (UNSAVED)" } else { "" }
+        );
+        text::draw(canvas, &title, (20, 16), 3, Color::WHITE);
+        let tool = format!("{}: {}", self.tool.name(), self.tool.hint());
+        text::draw(canvas, &tool, (20, 56), 3, Color::RGB(255, 220, 0));
+        let keys = "TAB TOOL   Z UNDO   F9 TEST DRIVE   HOME SAVE   ESC QUIT";
+        let keys_width = text::width(keys, 2) as i32;
+        text::draw(
+            canvas,
+            keys,
+            (width - keys_width - 20, 20),
+            2,
+            Color::RGB(200, 200, 200),
+        );
+        if let Some((message, _)) = &self.message {
+            text::draw_centered(canvas, message, (width / 2, height - 60), 3, Color::WHITE);
+        }
+    }
+}
+
+impl Scene for Editor {
+    fn update(&mut self, inputs: &[TickInput]) -> Transition {
+        let commands = || inputs.iter().flat_map(|input| &input.commands);
+        if commands().any(|command| *command == Command::TestDrive) {
+            // quitting the drive goes back to editing
+            return Transition::Push(Box::new(self.test_drive()));
+        }
+
+        if let Some((_, ticks)) = &mut self.message {
+            *ticks = ticks.saturating_sub(1);
+            if *ticks == 0 {
+                self.message = None;
+            }
+        }
+        // the first player does the editing
+        let Some(input) = inputs.first() else {
+            return Transition::Stay;
+        };
+        for command in &input.commands {
+            match command {
+                Command::Quit | Command::Back => return Transition::Pop,
+                Command::NextTool => {
+                    self.tool = self.tool.next();
+                    self.drag = None;
+                    self.end_wall();
+                }
+                Command::Undo => self.undo(),
+                Command::SaveTrack => self.save(),
+                Command::ZoomIn => self.camera.zoom_by(1.25),
+                Command::ZoomOut => self.camera.zoom_by(0.8),
+                _ => {}
+            }
+        }
+        let pedals = input.car.pedals;
+        self.camera.fly(Vector2::new(
+            input.car.steering,
+            pedals.brake - pedals.throttle,
+        ));
+        if let Some(pointer) = input.pointer {
+            self.handle_pointer(pointer);
+        }
+        Transition::Stay
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>, assets: &Assets, alpha: f64) {
+        let camera = self.camera.interpolated(alpha);
+        self.preview.draw_world(canvas, assets, &camera, alpha);
+        self.draw_overlay(canvas, &camera);
+        self.draw_hud(canvas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clicks the left button at `point` in the world
+    fn click(editor: &mut Editor, point: Point2<f64>) {
+        let on_screen = editor.camera.to_screen(point);
+        let position = Point2::new(
+            on_screen.x / SCREEN_DIMENSIONS.0 as f64,
+            on_screen.y / SCREEN_DIMENSIONS.1 as f64,
+        );
+        for left in [true, false] {
+            editor.handle_pointer(Pointer {
+                position,
+                left,
+                right: false,
+            });
+        }
+    }
+
+    #[test]
+    fn walls_close_back_where_they_started_and_undo() {
+        let file = TrackFile::new((32, 20), 125.);
+        let walls = file.walls.len();
+        let mut editor = Editor::new(
+            file,
+            PathBuf::from("assets/tracks/new.toml"),
+            CarSpec::default(),
+            CameraSpec::default(),
+            1,
+        );
+        for (x, y) in [
+            (1000., 1000.),
+            (2000., 1000.),
+            (2000., 1500.),
+            (1005., 995.),
+        ] {
+            click(&mut editor, Point2::new(x, y));
+        }
+        let wall = editor.file.walls.last().unwrap();
+        assert_eq!(editor.file.walls.len(), walls + 1);
+        assert!(wall.closed);
+        assert_eq!(wall.points.len(), 3);
+        assert!(editor.unsaved);
+
+        editor.undo();
+        assert_eq!(editor.file.walls.len(), walls);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::config::{self, ConfigError, Table};
+
+#[derive(Clone)]
+pub struct EngineSpec {
+    pub idle_rpm: f64,
+    /// pulling away, the clutch slips and lets a floored engine rev up to this. Set high
+    /// enough it dumps more torque on the driven wheels than they can take and spins them up.
+    pub launch_rpm: f64,
+    /// the rev limiter cuts the power above this
+    pub redline_rpm: f64,
+    /// (rpm, share of the peak torque) points, sorted by rpm and linearly interpolated
+    pub torque_curve: Vec<(f64, f64)>,
+    /// forward gears from first to top
+    pub gear_ratios: Vec<f64>,
+    pub final_drive: f64,
+    /// engine rpm for every pixel per tick of speed at a 1:1 overall ratio
+    pub rpm_per_speed: f64,
+    pub shift_up_rpm: f64,
+    pub shift_down_rpm: f64,
+    /// ticks the clutch is disengaged for while changing gears
+    pub shift_time: u32,
+    /// share of the peak torque the engine holds the wheels back with at the redline when
+    /// coasting in gear, fading out towards idle
+    pub engine_braking: f64,
+}
+
+impl Default for EngineSpec {
+    fn default() -> EngineSpec {
+        EngineSpec {
+            idle_rpm: 1000.,
+            launch_rpm: 4500.,
+            redline_rpm: 7000.,
+            torque_curve: vec![
+                (1000., 0.5),
+                (3000., 0.8),
+                (5000., 1.),
+                (6500., 0.85),
+                (7000., 0.7),
+            ],
+            gear_ratios: vec![3.2, 2.1, 1.5, 1.15, 0.9],
+            final_drive: 3.5,
+            rpm_per_speed: 130.,
+            shift_up_rpm: 6500.,
+            shift_down_rpm: 3000.,
+            shift_time: 10,
+            engine_braking: 0.5,
+        }
+    }
+}
+
+impl EngineSpec {
+    /// Overwrites whatever `table` specifies
+    pub fn read(&mut self, table: &Table) -> Result<(), ConfigError> {
+        table.read_number("idle_rpm", &mut self.idle_rpm)?;
+        table.read_number("launch_rpm", &mut self.launch_rpm)?;
+        table.read_number("redline_rpm", &mut self.redline_rpm)?;
+        table.read_number("final_drive", &mut self.final_drive)?;
+        table.read_number("rpm_per_speed", &mut self.rpm_per_speed)?;
+        table.read_number("shift_up_rpm", &mut self.shift_up_rpm)?;
+        table.read_number("engine_braking", &mut self.engine_braking)?;
+        table.read_number("shift_down_rpm", &mut self.shift_down_rpm)?;
+        if let Some(shift_time) = table.number("shift_time")? {
+            self.shift_time = shift_time as u32;
+        }
+
+        if let Some(points) = table.array("torque_curve")? {
+            self.torque_curve = points
+                .iter()
+                .map(|point| {
+                    config::as_pair(point).ok_or(ConfigError::WrongType {
+                        key: "torque_curve".to_string(),
+                        expected: "a list of [rpm, torque] pairs",
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+            self.torque_curve.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        if let Some(ratios) = table.array("gear_ratios")? {
+            self.gear_ratios = ratios
+                .iter()
+                .map(|ratio| {
+                    ratio.as_number().ok_or(ConfigError::WrongType {
+                        key: "gear_ratios".to_string(),
+                        expected: "a list of numbers",
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        if self.gear_ratios.is_empty() {
+            return Err(ConfigError::WrongType {
+                key: "gear_ratios".to_string(),
+                expected: "at least one gear",
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Share of the peak torque available at `rpm`
+    fn torque(&self, rpm: f64) -> f64 {
+        let curve = &self.torque_curve;
+        let Some(i) = curve.iter().position(|(point_rpm, _)| *point_rpm > rpm) else {
+            return curve.last().map_or(0., |(_, torque)| *torque);
+        };
+        if i == 0 {
+            return curve[0].1;
+        }
+        let ((rpm_a, torque_a), (rpm_b, torque_b)) = (curve[i - 1], curve[i]);
+        torque_a + (torque_b - torque_a) * (rpm - rpm_a) / (rpm_b - rpm_a)
+    }
+}
+
+pub enum Shift {
+    Up,
+    Down,
+}
+
+pub struct Engine {
+    rpm: f64,
+    /// index into the gear ratios
+    gear: usize,
+    /// shifting is left to the driver instead of happening on its own
+    pub manual: bool,
+    /// ticks left until the clutch bites again
+    shifting: u32,
+}
+
+impl Engine {
+    pub fn new(spec: &EngineSpec) -> Engine {
+        Engine {
+            rpm: spec.idle_rpm,
+            gear: 0,
+            manual: false,
+            shifting: 0,
+        }
+    }
+
+    pub fn rpm(&self) -> f64 {
+        self.rpm
+    }
+
+    /// Which gear it's in, 0 being first
+    pub fn gear(&self) -> usize {
+        self.gear
+    }
+
+    /// Whether it's between two gears with the clutch out
+    pub fn is_shifting(&self) -> bool {
+        self.shifting > 0
+    }
+
+    pub fn shift(&mut self, spec: &EngineSpec, shift: Shift) {
+        let gear = match shift {
+            Shift::Up => (self.gear + 1).min(spec.gear_ratios.len() - 1),
+            Shift::Down => self.gear.saturating_sub(1),
+        };
+        if gear != self.gear {
+            self.gear = gear;
+            self.shifting = spec.shift_time;
+        }
+    }
+
+    /// How hard the engine drags the driven wheels back with the throttle shut, in the same
+    /// units as what `update` returns
+    pub fn braking(&self, spec: &EngineSpec) -> f64 {
+        if self.shifting > 0 {
+            return 0.;
+        }
+        let revs = (self.rpm - spec.idle_rpm) / (spec.redline_rpm - spec.idle_rpm);
+        spec.engine_braking * revs.clamp(0., 1.) * spec.gear_ratios[self.gear] / spec.gear_ratios[0]
+    }
+
+    /// Follows the driven wheels turning at `wheel_speed` with the revs, shifts if it's up to
+    /// us and returns how hard the engine pushes at full throttle, where 1 is peak torque in
+    /// first gear. The gearbox goes by the `road_speed`, so wheelspin doesn't make it hunt.
+    pub fn update(&mut self, spec: &EngineSpec, wheel_speed: f64, road_speed: f64) -> f64 {
+        let overall_ratio = |gear: usize| spec.gear_ratios[gear] * spec.final_drive;
+        let rpm_at =
+            |gear: usize, speed: f64| speed.abs() * overall_ratio(gear) * spec.rpm_per_speed;
+
+        if !self.manual && self.shifting == 0 {
+            let rpm = rpm_at(self.gear, road_speed);
+            if rpm > spec.shift_up_rpm && self.gear + 1 < spec.gear_ratios.len() {
+                self.shift(spec, Shift::Up);
+            } else if rpm < spec.shift_down_rpm && self.gear > 0 {
+                self.shift(spec, Shift::Down);
+            }
+        }
+
+        // below idle the clutch slips to keep the engine running
+        self.rpm = rpm_at(self.gear, wheel_speed).max(spec.idle_rpm);
+
+        if self.shifting > 0 {
+            self.shifting -= 1;
+            return 0.;
+        }
+        if self.rpm >= spec.redline_rpm {
+            return 0.;
+        }
+        // the drive is what comes out at full throttle, so the clutch is slipping at launch revs
+        let launch_rpm = spec.launch_rpm.min(spec.redline_rpm);
+        spec.torque(self.rpm.max(launch_rpm)) * overall_ratio(self.gear) / overall_ratio(0)
+    }
+}
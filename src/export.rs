@@ -0,0 +1,318 @@
+//! Videos of replays, for sharing. GIFs are written here, MP4s are left to ffmpeg, which has
+//! to be installed for them.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    process::{Child, Command, Stdio},
+};
+
+/// Levels of red, green and blue in the GIF palette, the eye telling greens apart best
+const RED_LEVELS: u32 = 6;
+const GREEN_LEVELS: u32 = 7;
+const BLUE_LEVELS: u32 = 6;
+/// GIF codes can't be any longer
+const MAX_CODE_BITS: u32 = 12;
+
+/// Writes frames of `width` by `height` RGB pixels out as a video, picking what kind by the
+/// file's extension
+pub enum Exporter {
+    Gif(Gif),
+    /// ffmpeg encoding what it's fed through its standard input
+    Ffmpeg(Child),
+}
+
+impl Exporter {
+    /// `fps` has to be a whole number of hundredths of a second per frame for a GIF
+    pub fn create(path: &str, width: u32, height: u32, fps: u32) -> io::Result<Exporter> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("gif") => Ok(Exporter::Gif(Gif::create(path, width, height, fps)?)),
+            Some("mp4") => {
+                let size = format!("{width}x{height}");
+                let child = Command::new("ffmpeg")
+                    .args(["-loglevel", "error", "-y", "-f", "rawvideo"])
+                    .args(["-pixel_format", "rgb24", "-video_size", &size])
+                    .args(["-framerate", &fps.to_string(), "-i", "-"])
+                    .args(["-pix_fmt", "yuv420p", path])
+                    .stdin(Stdio::piped())
+                    .spawn()?;
+                Ok(Exporter::Ffmpeg(child))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "videos can only be .gif or .mp4",
+            )),
+        }
+    }
+
+    /// `pixels` are row by row from the top, 3 bytes each
+    pub fn frame(&mut self, pixels: &[u8]) -> io::Result<()> {
+        match self {
+            Exporter::Gif(gif) => gif.frame(pixels),
+            Exporter::Ffmpeg(child) => child.stdin.as_mut().unwrap().write_all(pixels),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Exporter::Gif(gif) => gif.finish(),
+            Exporter::Ffmpeg(mut child) => {
+                // closing its input is what tells it the video's over
+                drop(child.stdin.take());
+                let status = child.wait()?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(io::Error::other(format!("ffmpeg failed with {status}")))
+                }
+            }
+        }
+    }
+}
+
+/// An animated GIF on a fixed palette of evenly spread colors, looping forever
+pub struct Gif {
+    writer: BufWriter<File>,
+    width: u32,
+    height: u32,
+    /// hundredths of a second each frame is shown for
+    delay: u16,
+}
+
+impl Gif {
+    fn create(path: &str, width: u32, height: u32, fps: u32) -> io::Result<Gif> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let mut header = b"GIF89a".to_vec();
+        header.extend((width as u16).to_le_bytes());
+        header.extend((height as u16).to_le_bytes());
+        // a global color table of 256 colors, 8 bits a channel
+        header.extend([0xf7, 0, 0]);
+        for index in 0..256 {
+            header.extend(palette_color(index));
+        }
+        // the application extension that has it loop forever
+        header.extend([0x21, 0xff, 11]);
+        header.extend(b"NETSCAPE2.0");
+        header.extend([3, 1, 0, 0, 0]);
+        writer.write_all(&header)?;
+        Ok(Gif {
+            writer,
+            width,
+            height,
+            delay: (100 / fps) as u16,
+        })
+    }
+
+    fn frame(&mut self, pixels: &[u8]) -> io::Result<()> {
+        let mut frame = vec![0x21, 0xf9, 4, 0];
+        frame.extend(self.delay.to_le_bytes());
+        frame.extend([0, 0]);
+        // the image takes the whole screen, with the global colors
+        frame.push(0x2c);
+        frame.extend([0, 0, 0, 0]);
+        frame.extend((self.width as u16).to_le_bytes());
+        frame.extend((self.height as u16).to_le_bytes());
+        frame.push(0);
+
+        let indices: Vec<u8> = pixels
+            .chunks_exact(3)
+            .map(|pixel| palette_index(pixel[0], pixel[1], pixel[2]))
+            .collect();
+        frame.push(8);
+        for block in lzw(&indices, 8).chunks(255) {
+            frame.push(block.len() as u8);
+            frame.extend(block);
+        }
+        frame.push(0);
+        self.writer.write_all(&frame)
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.writer.write_all(&[0x3b])?;
+        self.writer.flush()
+    }
+}
+
+fn level(value: u8, levels: u32) -> u32 {
+    (value as u32 * (levels - 1) + 127) / 255
+}
+
+/// The palette's nearest color to a pixel
+fn palette_index(red: u8, green: u8, blue: u8) -> u8 {
+    let (red, green, blue) = (
+        level(red, RED_LEVELS),
+        level(green, GREEN_LEVELS),
+        level(blue, BLUE_LEVELS),
+    );
+    ((red * GREEN_LEVELS + green) * BLUE_LEVELS + blue) as u8
+}
+
+/// The color at `index` in the palette, black past the end of it
+fn palette_color(index: u32) -> [u8; 3] {
+    if index >= RED_LEVELS * GREEN_LEVELS * BLUE_LEVELS {
+        return [0; 3];
+    }
+    let value = |level: u32, levels: u32| (level * 255 / (levels - 1)) as u8;
+    [
+        value(index / (GREEN_LEVELS * BLUE_LEVELS), RED_LEVELS),
+        value(index / BLUE_LEVELS % GREEN_LEVELS, GREEN_LEVELS),
+        value(index % BLUE_LEVELS, BLUE_LEVELS),
+    ]
+}
+
+/// Packs codes into bytes lowest bit first, the way GIFs have them
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u32, size: u32) {
+        self.buffer |= code << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// GIF's variable code length LZW, starting over whenever the codes run out
+fn lzw(indices: &[u8], min_code_bits: u32) -> Vec<u8> {
+    let clear = 1 << min_code_bits;
+    let end = clear + 1;
+    let mut output = BitWriter::default();
+    let mut table: HashMap<(u32, u8), u32> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut code_bits = min_code_bits + 1;
+
+    output.write(clear, code_bits);
+    let Some((&first, rest)) = indices.split_first() else {
+        output.write(end, code_bits);
+        return output.finish();
+    };
+    let mut prefix = first as u32;
+    for &index in rest {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        output.write(prefix, code_bits);
+        if next_code == 1 << MAX_CODE_BITS {
+            output.write(clear, code_bits);
+            table.clear();
+            next_code = end + 1;
+            code_bits = min_code_bits + 1;
+        } else {
+            table.insert((prefix, index), next_code);
+            // a decoder's a code behind, so it widens its codes once this one's in use
+            if next_code == 1 << code_bits {
+                code_bits += 1;
+            }
+            next_code += 1;
+        }
+        prefix = index as u32;
+    }
+    output.write(prefix, code_bits);
+    output.write(end, code_bits);
+    output.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The GIF way of unpacking it, from the spec
+    fn unlzw(bytes: &[u8], min_code_bits: u32) -> Vec<u8> {
+        let clear = 1 << min_code_bits;
+        let end = clear + 1;
+        let mut bit = 0;
+        let mut read = |size: u32| {
+            let mut code = 0;
+            for i in 0..size {
+                let byte = bytes[(bit / 8) as usize];
+                code |= ((byte >> (bit % 8)) as u32 & 1) << i;
+                bit += 1;
+            }
+            code
+        };
+        let mut output = Vec::new();
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut code_bits = min_code_bits + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        loop {
+            let code = read(code_bits);
+            if code == clear {
+                table = (0..clear).map(|index| vec![index as u8]).collect();
+                table.extend([vec![], vec![]]);
+                code_bits = min_code_bits + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return output;
+            }
+            let entry = match (table.get(code as usize), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => [previous.clone(), vec![previous[0]]].concat(),
+                (None, None) => panic!("a code for nothing"),
+            };
+            output.extend(&entry);
+            if let Some(previous) = previous {
+                table.push([previous, vec![entry[0]]].concat());
+                if table.len() == 1 << code_bits && code_bits < MAX_CODE_BITS {
+                    code_bits += 1;
+                }
+            }
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_unpacks_the_same() {
+        // long enough to run out of codes and start over a few times
+        let mut state = 1u32;
+        let indices: Vec<u8> = (0..40_000)
+            .map(|i| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                if i % 3 == 0 {
+                    (state >> 24) as u8
+                } else {
+                    (i / 50 % 7) as u8
+                }
+            })
+            .collect();
+        assert_eq!(unlzw(&lzw(&indices, 8), 8), indices);
+        assert_eq!(unlzw(&lzw(&[], 8), 8), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn the_palette_has_the_corners_of_the_color_cube() {
+        for color in [
+            [0, 0, 0],
+            [255, 255, 255],
+            [255, 0, 0],
+            [0, 255, 0],
+            [0, 0, 255],
+        ] {
+            let [red, green, blue] = color;
+            assert_eq!(palette_color(palette_index(red, green, blue) as u32), color);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use sdl2::{
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas, RenderTarget},
+};
+
+use crate::{
+    assets::{Assets, FontId},
+    text,
+};
+
+/// How often the shown numbers change, so they can be read rather than flickering
+const REFRESH: Duration = Duration::from_millis(500);
+/// What the numbers are written in, and how big
+pub const FONT: (&str, u16) = ("fonts/DejaVuSansMono.ttf", 20);
+/// Screen pixels per font pixel, for writing in the built-in font when `FONT` didn't load
+const SCALE: u32 = 3;
+
+/// How fast the game's rendering, as frames per second and how long the slowest frame took,
+/// which is where the stutters show up. Averaged over half a second at a time.
+#[derive(Default)]
+pub struct FrameCounter {
+    /// frames and time counted since the numbers last changed
+    frames: u32,
+    elapsed: Duration,
+    slowest: Duration,
+    /// what's shown
+    fps: f64,
+    slowest_shown: Duration,
+    font: Option<FontId>,
+}
+
+impl FrameCounter {
+    /// Writing in `font`, or the built-in font without one
+    pub fn new(font: Option<FontId>) -> FrameCounter {
+        FrameCounter {
+            font,
+            ..FrameCounter::default()
+        }
+    }
+
+    /// Counts a frame that took `frame_time` from the start of the last one
+    pub fn frame(&mut self, frame_time: Duration) {
+        self.frames += 1;
+        self.elapsed += frame_time;
+        self.slowest = self.slowest.max(frame_time);
+        if self.elapsed >= REFRESH {
+            self.fps = self.frames as f64 / self.elapsed.as_secs_f64();
+            self.slowest_shown = self.slowest;
+            self.frames = 0;
+            self.elapsed = Duration::ZERO;
+            self.slowest = Duration::ZERO;
+        }
+    }
+
+    /// In the top right corner of a `screen` sized screen
+    pub fn draw<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        screen: (i32, i32),
+    ) {
+        let line = format!(
+            "{:.0} FPS  {:.1} MS",
+            self.fps,
+            self.slowest_shown.as_secs_f64() * 1000.
+        );
+        let margin = 10;
+        let (width, height) = match self.font {
+            Some(font) => text::size_in(assets, font, &line),
+            None => (text::width(&line, SCALE), text::height(SCALE)),
+        };
+        let left = screen.0 - width as i32 - margin * 2;
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 150));
+        canvas
+            .fill_rect(Rect::new(
+                left,
+                0,
+                width + margin as u32 * 2,
+                height + margin as u32 * 2,
+            ))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+        let position = (left + margin, margin);
+        match self.font {
+            Some(font) => text::draw_in(canvas, assets, font, &line, position, Color::WHITE),
+            None => text::draw(canvas, &line, position, SCALE, Color::WHITE),
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::{fs, path::Path};
+
+use nalgebra::Rotation2;
+use sdl2::{
+    pixels::Color,
+    rect::{FRect, Rect},
+    render::Canvas,
+    video::Window,
+};
+
+use crate::{
+    assets::{Assets, SpriteId},
+    car::CarSpec,
+    fill_rotated_rect,
+    input::{Command, TickInput},
+    menu::Selection,
+    scene::{Scene, Transition},
+    text, SCREEN_DIMENSIONS,
+};
+
+/// How fast the car on show turns round, in radians per tick
+const TURN_SPEED: f64 = 0.01;
+/// How much bigger than on the track the car on show is drawn
+const PREVIEW_SCALE: f32 = 3.;
+/// How big each stat's bar is, in pixels
+const BAR: (u32, u32) = (480, 24);
+
+/// What's played next with the car chosen
+type Next = Box<dyn FnOnce(CarSpec) -> Box<dyn Scene>>;
+
+/// A car to choose
+struct Entry {
+    name: String,
+    spec: CarSpec,
+    /// what it's drawn with, once `load_sprites` has loaded it
+    sprite: Option<SpriteId>,
+}
+
+/// Choosing which car to drive before choosing the track, from the specs in a directory.
+/// Each one shows how it compares to the built-in car, with the one selected turning round
+/// next to the list. Up and down go by the throttle and brake and the pause button picks
+/// one, like in the track menu.
+pub struct Garage {
+    entries: Vec<Entry>,
+    selection: Selection,
+    /// ticks since the garage opened, for turning the car on show
+    ticks: u32,
+    /// `None` for the garage to just end once one's chosen
+    then: Option<Next>,
+}
+
+impl Garage {
+    /// Every car spec in `directory` that loads, in order of their file names
+    pub fn new(directory: &Path) -> Garage {
+        let mut paths: Vec<_> = fs::read_dir(directory)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| {
+                        path.extension()
+                            .is_some_and(|extension| extension == "toml")
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        let mut entries = Vec::new();
+        for path in paths {
+            let spec = match CarSpec::load(&path) {
+                Ok(spec) => spec,
+                Err(error) => {
+                    eprintln!("couldn't load the car {}: {error}", path.display());
+                    continue;
+                }
+            };
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            entries.push(Entry {
+                name: name.to_uppercase(),
+                spec,
+                sprite: None,
+            });
+        }
+        // starting on the one the game drives without a garage
+        let selected = entries
+            .iter()
+            .position(|entry| entry.name == "DEFAULT")
+            .unwrap_or(0);
+        Garage {
+            entries,
+            selection: Selection::new(selected),
+            ticks: 0,
+            then: None,
+        }
+    }
+
+    /// Goes on to whatever `next` makes of the car chosen
+    pub fn then(mut self, next: impl FnOnce(CarSpec) -> Box<dyn Scene> + 'static) -> Garage {
+        self.then = Some(Box::new(next));
+        self
+    }
+
+    /// Whether there's more than the one car to choose from
+    pub fn has_choice(&self) -> bool {
+        self.entries.len() > 1
+    }
+}
+
+/// How fast, grippy and nimble `spec` is next to the built-in car, which is 1 at each
+fn stats(spec: &CarSpec) -> [(&'static str, f64); 3] {
+    // the drive and the drag growing with the square of the speed even out at the top speed
+    let speed = |spec: &CarSpec| (spec.engine_force / spec.drag).sqrt();
+    // turning tighter on a shorter wheelbase, and getting to full lock quicker
+    let handling = |spec: &CarSpec| {
+        spec.max_steering_angle.tan() / spec.wheelbase
+            * spec.steering_speed.sqrt()
+            * spec.high_speed_steering.sqrt()
+    };
+    let built_in = CarSpec::default();
+    [
+        ("SPEED", speed(spec) / speed(&built_in)),
+        ("GRIP", spec.tire_grip / built_in.tire_grip),
+        ("HANDLING", handling(spec) / handling(&built_in)),
+    ]
+}
+
+impl Scene for Garage {
+    /// Ends once a car's chosen, as well as when a player wants out
+    fn update(&mut self, inputs: &[TickInput]) -> Transition {
+        self.ticks += 1;
+        // the first player does the choosing
+        let Some(input) = inputs.first() else {
+            return Transition::Stay;
+        };
+        for command in &input.commands {
+            match command {
+                Command::Quit | Command::Back => return Transition::Pop,
+                Command::TogglePause if !self.entries.is_empty() => {
+                    let spec = &self.entries[self.selection.selected()].spec;
+                    return match self.then.take() {
+                        Some(next) => Transition::Replace(next(spec.clone())),
+                        None => Transition::Pop,
+                    };
+                }
+                _ => {}
+            }
+        }
+        self.selection.update(input, self.entries.len());
+        Transition::Stay
+    }
+
+    fn load_sprites(&mut self, assets: &mut Assets) {
+        for entry in &mut self.entries {
+            entry.sprite = entry
+                .spec
+                .sprite
+                .as_deref()
+                .and_then(|sprite| assets.load_sprite(sprite));
+        }
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>, assets: &Assets, alpha: f64) {
+        let (width, height) = SCREEN_DIMENSIONS;
+        canvas.set_draw_color(Color::RGB(20, 24, 28));
+        canvas.clear();
+        text::draw_centered(canvas, "CHOOSE A CAR", (width / 2, 120), 8, Color::WHITE);
+
+        let list = width / 4;
+        for (i, entry) in self.entries.iter().enumerate() {
+            let top = 260 + i as i32 * 80;
+            let (name, color) = if i == self.selection.selected() {
+                (format!("- {} -", entry.name), Color::RGB(255, 220, 0))
+            } else {
+                (entry.name.clone(), Color::RGB(160, 160, 160))
+            };
+            text::draw_centered(canvas, &name, (list, top), 5, color);
+        }
+        let Some(entry) = self.entries.get(self.selection.selected()) else {
+            return;
+        };
+
+        // the one selected turning round in the first player's color, as big as it is next
+        // to the others
+        let center = (width as f32 * 5. / 8., 480.);
+        let size = entry.spec.dimensions * PREVIEW_SCALE as f64;
+        let rect = FRect::new(
+            center.0 - size.x as f32 / 2.,
+            center.1 - size.y as f32 / 2.,
+            size.x as f32,
+            size.y as f32,
+        );
+        let rotation = Rotation2::new((self.ticks as f64 + alpha) * TURN_SPEED);
+        match entry.sprite {
+            Some(id) => {
+                let mut sprite = assets.sprite(id);
+                sprite.set_color_mod(255, 0, 0);
+                canvas
+                    .copy_ex_f(
+                        &sprite,
+                        None,
+                        rect,
+                        rotation.angle().to_degrees(),
+                        None,
+                        false,
+                        false,
+                    )
+                    .unwrap();
+            }
+            None => fill_rotated_rect(canvas, assets, rect, rotation, Color::RED),
+        }
+
+        // how it compares, the built-in car filling half of each bar
+        let (bar_width, bar_height) = BAR;
+        let left = width * 5 / 8 - bar_width as i32 / 2;
+        for (i, (name, stat)) in stats(&entry.spec).into_iter().enumerate() {
+            let top = 720 + i as i32 * 70;
+            text::draw(canvas, name, (left - 260, top), 4, Color::WHITE);
+            canvas.set_draw_color(Color::RGB(60, 64, 70));
+            canvas
+                .fill_rect(Rect::new(left, top, bar_width, bar_height))
+                .unwrap();
+            let filled = (bar_width as f64 * (stat / 2.).clamp(0., 1.)) as u32;
+            if filled > 0 {
+                canvas.set_draw_color(Color::RGB(255, 220, 0));
+                canvas
+                    .fill_rect(Rect::new(left, top, filled, bar_height))
+                    .unwrap();
+            }
+        }
+        text::draw_centered(
+            canvas,
+            "UP AND DOWN TO CHOOSE, RETURN OR START TO PICK",
+            (width / 2, height - 80),
+            3,
+            Color::RGB(200, 200, 200),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        car::{CarInput, CarPedals},
+        scene::Outcome,
+    };
+
+    #[test]
+    fn the_sample_cars_compare_to_the_built_in_one() {
+        let chosen = Outcome::default();
+        let mut garage = Garage::new(Path::new("assets/cars")).then({
+            let chosen = chosen.clone();
+            move |spec| {
+                chosen.set(spec);
+                Box::new(Garage::new(Path::new("no_cars")))
+            }
+        });
+        let names: Vec<_> = garage
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, ["DEFAULT", "VAN"]);
+        assert!(garage.has_choice());
+
+        // the default car's file has the built-in numbers
+        for (_, stat) in stats(&garage.entries[0].spec) {
+            assert!((stat - 1.).abs() < 1e-9);
+        }
+        let [(_, speed), (_, grip), (_, handling)] = stats(&garage.entries[1].spec);
+        assert!(speed < 1. && grip > 1. && handling < 1.);
+
+        let down = TickInput {
+            car: CarInput {
+                pedals: CarPedals {
+                    throttle: 0.,
+                    brake: 1.,
+                },
+                ..CarInput::default()
+            },
+            ..TickInput::default()
+        };
+        let pick = TickInput {
+            commands: vec![Command::TogglePause],
+            ..TickInput::default()
+        };
+        assert!(matches!(
+            garage.update(std::slice::from_ref(&down)),
+            Transition::Stay
+        ));
+        assert!(chosen.take().is_none());
+        assert!(matches!(
+            garage.update(std::slice::from_ref(&pick)),
+            Transition::Replace(_)
+        ));
+        assert_eq!(chosen.take().map(|spec: CarSpec| spec.mass), Some(1800.));
+    }
+}
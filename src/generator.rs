@@ -0,0 +1,237 @@
+use std::{
+    f64::consts::TAU,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use nalgebra::{Point2, Vector2};
+
+use crate::{
+    rng::Rng,
+    terrain::Surface,
+    track::Waypoint,
+    track_toml::{Grid, Patch, Shape, Spot, TrackFile, Wall, Zone},
+};
+
+/// Small tiles, for the road's edges to follow its curves and changes of width
+const TILE_SIZE: f64 = 50.;
+/// How far the corners are from the middle of the map on average, stretched across
+const RADIUS: f64 = 2400.;
+const STRETCH: f64 = 1.4;
+/// How wide the road gets, in pixels
+const WIDTH: (f64, f64) = (260., 420.);
+/// How far it is from the edge of the road to the walls either side
+const RUN_OFF: f64 = 250.;
+/// Points the centerline's broken into between each of the corners it goes through
+const STEPS: usize = 12;
+/// Points in each stretch of road that's the same width
+const STRETCH_POINTS: usize = 3;
+/// Turning more than this many radians from one point to the next makes a corner worth a
+/// gravel trap
+const SHARP_TURN: f64 = 0.12;
+/// Room from the road out to the edge of the map
+const MARGIN: f64 = 600.;
+/// Gates round a lap, the first one being the line
+const CHECKPOINTS: usize = 8;
+/// How deep the gates are along the road
+const CHECKPOINT_DEPTH: f64 = 40.;
+
+/// A closed loop of a track made up from `seed`, the same one every time for the same seed:
+/// a smooth centerline through corners at random around the middle of the map, the road
+/// narrowing and widening along it, gravel on the sharper corners, walls either side and the
+/// checkpoints spread round it.
+pub fn generate(seed: u64) -> TrackFile {
+    let mut rng = Rng::new(seed);
+    let corner_count = 8 + (rng.next_u64() % 6) as usize;
+    let corners: Vec<_> = (0..corner_count)
+        .map(|i| {
+            let angle = TAU * (i as f64 + rng.range(-0.3, 0.3)) / corner_count as f64;
+            let radius = RADIUS * rng.range(0.55, 1.);
+            Point2::new(angle.cos() * radius * STRETCH, angle.sin() * radius)
+        })
+        .collect();
+    let widths: Vec<_> = (0..corner_count)
+        .map(|_| rng.range(WIDTH.0, WIDTH.1))
+        .collect();
+
+    // through every corner and back round to the first
+    let mut centerline = Vec::new();
+    let mut point_widths = Vec::new();
+    for i in 0..corner_count {
+        let corner = |offset: usize| corners[(i + offset) % corner_count];
+        let (before, from, to, after) = (corner(corner_count - 1), corner(0), corner(1), corner(2));
+        for step in 0..STEPS {
+            let t = step as f64 / STEPS as f64;
+            centerline.push(catmull_rom(before, from, to, after, t));
+            point_widths.push(widths[i] + (widths[(i + 1) % corner_count] - widths[i]) * t);
+        }
+    }
+
+    // moved into the map with room round it
+    let low = centerline
+        .iter()
+        .fold(Point2::new(f64::MAX, f64::MAX), |low, point| low.inf(point));
+    let high = centerline
+        .iter()
+        .fold(Point2::new(f64::MIN, f64::MIN), |high, point| {
+            high.sup(point)
+        });
+    let shift = Vector2::repeat(MARGIN + WIDTH.1 / 2. + RUN_OFF) - low.coords;
+    for point in &mut centerline {
+        *point += shift;
+    }
+    let extent = high - low + Vector2::repeat(2. * (MARGIN + WIDTH.1 / 2. + RUN_OFF));
+    let size = (
+        (extent.x / TILE_SIZE).ceil() as usize,
+        (extent.y / TILE_SIZE).ceil() as usize,
+    );
+
+    let count = centerline.len();
+    let at = |i: usize| centerline[i % count];
+    let tangent = |i: usize| (at(i + 1) - at(i + count - 1)).normalize();
+    // to the right of the way round, the map's y going down
+    let normal = |i: usize| {
+        let along = tangent(i);
+        Vector2::new(-along.y, along.x)
+    };
+    let turn = |i: usize| {
+        let (a, b) = (at(i) - at(i + count - 1), at(i + 1) - at(i));
+        a.angle(&b)
+    };
+
+    let mut file = TrackFile::new(size, TILE_SIZE);
+    file.name = Some(format!("Generated {seed}"));
+    file.laps = Some(3);
+
+    // gravel traps round the sharper corners, under the road
+    let mut trap: Vec<Point2<f64>> = Vec::new();
+    for i in 0..=count {
+        if turn(i) > SHARP_TURN {
+            if trap.is_empty() {
+                trap.push(at(i + count - 1));
+            }
+            trap.push(at(i));
+        } else if !trap.is_empty() {
+            trap.push(at(i));
+            file.surfaces.push(Patch {
+                surface: Surface::Gravel,
+                zone: Zone::Line {
+                    points: std::mem::take(&mut trap),
+                    width: WIDTH.1 + 2. * RUN_OFF,
+                },
+            });
+        }
+    }
+    // the road in short stretches, each as wide as the road is on average along it
+    for start in (0..count).step_by(STRETCH_POINTS) {
+        let points: Vec<_> = (start..=start + STRETCH_POINTS).map(at).collect();
+        let width = (start..start + STRETCH_POINTS)
+            .map(|i| point_widths[i % count])
+            .sum::<f64>()
+            / STRETCH_POINTS as f64;
+        file.surfaces.push(Patch {
+            surface: Surface::Asphalt,
+            zone: Zone::Line { points, width },
+        });
+    }
+
+    for side in [-1., 1.] {
+        let points = (0..count)
+            .map(|i| at(i) + normal(i) * side * (point_widths[i] / 2. + RUN_OFF))
+            .collect();
+        file.walls.push(Wall {
+            points,
+            closed: true,
+        });
+    }
+
+    for gate in 0..CHECKPOINTS {
+        let i = gate * count / CHECKPOINTS;
+        let across = normal(i);
+        file.checkpoints.push(Shape {
+            center: at(i),
+            size: Vector2::new(point_widths[i] + 2. * RUN_OFF, CHECKPOINT_DEPTH),
+            angle: across.y.atan2(across.x).to_degrees(),
+        });
+    }
+
+    // lined up behind the line, facing the way round
+    let behind = count - 2;
+    let facing = tangent(behind);
+    let across = 120.;
+    file.grid = Some(Grid {
+        pole: Spot {
+            position: at(behind) - normal(behind) * across / 2.,
+            angle: facing.x.atan2(-facing.y).to_degrees(),
+        },
+        slots: 6,
+        spacing: 150.,
+        across,
+    });
+    file.waypoints = (0..=count)
+        .map(|i| Waypoint {
+            position: at(i),
+            speed: None,
+            width: point_widths[i % count],
+        })
+        .collect();
+    file
+}
+
+/// The same seed for everyone on the same day, UTC, for a track of the day
+pub fn daily_seed() -> u64 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    seconds / (24 * 60 * 60)
+}
+
+/// `t` of the way from `from` to `to` along a curve that also goes through `before` and
+/// `after`, for the corners to flow into each other
+fn catmull_rom(
+    before: Point2<f64>,
+    from: Point2<f64>,
+    to: Point2<f64>,
+    after: Point2<f64>,
+    t: f64,
+) -> Point2<f64> {
+    let (t2, t3) = (t * t, t * t * t);
+    let weights = [
+        -0.5 * t3 + t2 - 0.5 * t,
+        1.5 * t3 - 2.5 * t2 + 1.,
+        -1.5 * t3 + 2. * t2 + 0.5 * t,
+        0.5 * t3 - 0.5 * t2,
+    ];
+    Point2::from(
+        before.coords * weights[0]
+            + from.coords * weights[1]
+            + to.coords * weights[2]
+            + after.coords * weights[3],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn the_same_seed_makes_the_same_drivable_loop() {
+        let file = generate(7);
+        assert_eq!(file, generate(7));
+        assert_ne!(file, generate(8));
+        assert_eq!(file.checkpoints.len(), CHECKPOINTS);
+
+        let track = file.track(Path::new(""));
+        // the road goes all the way round, with the cars on it at the start
+        for waypoint in &track.waypoints {
+            assert_eq!(
+                track.terrain.surface_at(waypoint.position),
+                Surface::Asphalt
+            );
+        }
+        for spawn in &track.spawn_points[..2] {
+            assert_eq!(track.terrain.surface_at(spawn.position), Surface::Asphalt);
+        }
+    }
+}
@@ -0,0 +1,507 @@
+use nalgebra::Point2;
+use sdl2::{
+    controller::{Axis, GameController},
+    event::{Event, WindowEvent},
+    joystick::Joystick,
+    keyboard::{KeyboardState, Mod, Scancode},
+    mouse::{MouseButton, MouseState},
+    EventPump, GameControllerSubsystem, JoystickSubsystem, Sdl,
+};
+
+use crate::{
+    bindings::{Action, Bindings},
+    car::{CarInput, CarPedals},
+    config::{ConfigError, Table},
+    letterbox,
+};
+
+/// How the travel of a controller's stick or trigger turns into steering or pedal
+#[derive(Clone, Copy)]
+pub struct AxisResponse {
+    /// travel smaller than this share of the full throw is ignored
+    pub dead_zone: f64,
+    /// travel past this share counts as all the way, for sticks that don't quite reach the edge
+    pub saturation: f64,
+    /// 0 responds linearly, up to 1 makes small movements gentler and the end of the travel
+    /// steeper
+    pub expo: f64,
+}
+
+impl AxisResponse {
+    /// A worn stick keeps the car creeping to one side without a good dead zone
+    pub const STICK: AxisResponse = AxisResponse {
+        dead_zone: 0.15,
+        saturation: 1.,
+        expo: 0.,
+    };
+    /// A resting finger only needs a small one to not drag the brakes
+    pub const TRIGGER: AxisResponse = AxisResponse {
+        dead_zone: 0.05,
+        saturation: 1.,
+        expo: 0.,
+    };
+
+    /// Overwrites whatever `table` specifies
+    pub fn read(&mut self, table: &Table) -> Result<(), ConfigError> {
+        table.read_number("dead_zone", &mut self.dead_zone)?;
+        table.read_number("saturation", &mut self.saturation)?;
+        table.read_number("expo", &mut self.expo)?;
+        Ok(())
+    }
+
+    /// Past the dead zone the response starts again from zero, so there's no jump when the
+    /// stick leaves it
+    pub fn apply(&self, travel: f64) -> f64 {
+        let span = (self.saturation - self.dead_zone).max(f64::EPSILON);
+        let beyond = ((travel.abs() - self.dead_zone) / span).clamp(0., 1.);
+        let expo = self.expo.clamp(0., 1.);
+        let curved = (1. - expo) * beyond + expo * beyond.powi(3);
+        curved * travel.signum()
+    }
+}
+
+/// One-off requests from the player, handled at the start of the tick they were made in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    ShiftUp,
+    ShiftDown,
+    ToggleManualShifting,
+    ToggleTractionControl,
+    ToggleAbs,
+    ToggleStabilityControl,
+    Respawn,
+    Repair,
+    ToggleTowing,
+    Quit,
+    TogglePause,
+    /// sent on the player's behalf when their controller or wheel is unplugged, to pause
+    /// until they're back
+    ControllerLost,
+    NextCameraMode,
+    ToggleFreeCamera,
+    ZoomIn,
+    ZoomOut,
+    /// switches the window between fullscreen and windowed, which is up to the game rather
+    /// than the level
+    ToggleFullscreen,
+    /// starts or stops the rain, for everyone at once
+    ToggleRain,
+    /// shows or hides the physics debug overlay, for everyone at once
+    ToggleDebugOverlay,
+    /// shows or hides how fast the game's rendering, which is up to the game too
+    ToggleFrameCounter,
+    /// saves what's on the screen, which is the game's business as well
+    Screenshot,
+    /// shows or hides the trails behind the players' cars and the racing line, for everyone
+    /// at once
+    ToggleTrails,
+    /// switches to the next filter the picture goes through, which is up to the game
+    NextFilter,
+    /// switches to the track editor's next tool
+    NextTool,
+    /// takes back the last thing done in the track editor
+    Undo,
+    /// drives the track being edited, or goes back to editing it
+    TestDrive,
+    /// saves the track being edited
+    SaveTrack,
+    /// out of whatever menu's open, or into the pause menu while driving
+    Back,
+    /// opens or closes the developer console over the level
+    ToggleConsole,
+}
+
+impl Command {
+    pub const ALL: [Command; 29] = [
+        Command::ShiftUp,
+        Command::ShiftDown,
+        Command::ToggleManualShifting,
+        Command::ToggleTractionControl,
+        Command::ToggleAbs,
+        Command::ToggleStabilityControl,
+        Command::Respawn,
+        Command::Repair,
+        Command::ToggleTowing,
+        Command::Quit,
+        Command::TogglePause,
+        Command::ControllerLost,
+        Command::NextCameraMode,
+        Command::ToggleFreeCamera,
+        Command::ZoomIn,
+        Command::ZoomOut,
+        Command::ToggleFullscreen,
+        Command::ToggleRain,
+        Command::ToggleDebugOverlay,
+        Command::ToggleFrameCounter,
+        Command::Screenshot,
+        Command::ToggleTrails,
+        Command::NextFilter,
+        Command::NextTool,
+        Command::Undo,
+        Command::TestDrive,
+        Command::SaveTrack,
+        // after the rest, for the replays recorded before them to keep their commands
+        Command::Back,
+        Command::ToggleConsole,
+    ];
+}
+
+/// Everything the player did during one tick. A level fed the same inputs from the same seed
+/// plays out the same way every time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TickInput {
+    /// how the player's car is driven
+    pub car: CarInput,
+    /// where the player points the car with the mouse, as a share of the screen's width and
+    /// height from its top left corner. Overrides `car.steering`.
+    pub aim: Option<Point2<f64>>,
+    pub commands: Vec<Command>,
+    /// the mouse, for the first player only, for the scenes that are clicked about in rather
+    /// than driven. Left out of replays.
+    pub pointer: Option<Pointer>,
+    /// the first key pressed during the tick, for the first player only, for keys to be
+    /// rebound in the settings. Left out of replays too.
+    pub key: Option<Scancode>,
+    /// what was typed during the tick, for the first player only, for the console. Left out
+    /// of replays as well.
+    pub text: String,
+}
+
+/// Where the mouse is and which of its buttons are held
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pointer {
+    /// as a share of the screen's width and height from its top left corner, like `aim`
+    pub position: Point2<f64>,
+    pub left: bool,
+    pub right: bool,
+}
+
+/// How hard to shake the player's controller, each motor from 0 to 1
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rumble {
+    /// the heavy motor, for thumps
+    pub low: f64,
+    /// the light motor, for buzzing
+    pub high: f64,
+}
+
+/// Anything that can play the game: the controls, a recording, a test script
+pub trait InputSource {
+    /// What every player does in the next tick, in player order
+    fn next_tick(&mut self) -> Vec<TickInput>;
+}
+
+/// The keyboard and whatever game controllers and racing wheels are plugged in, shared out
+/// between the players. For steering the keyboard wins over a wheel, and a wheel over a
+/// stick.
+pub struct Controls {
+    events: EventPump,
+    controller_subsystem: GameControllerSubsystem,
+    joystick_subsystem: JoystickSubsystem,
+    /// in the order they were plugged in
+    controllers: Vec<GameController>,
+    /// joysticks SDL doesn't know as game controllers, only opened when the bindings say how
+    /// to read a wheel
+    wheels: Vec<Joystick>,
+    /// one per player
+    bindings: Vec<Bindings>,
+    /// for telling where in the game's picture the mouse is, as it doesn't necessarily
+    /// fill the whole window. In points like the mouse, not in the pixels the picture's
+    /// drawn in, which there can be more of on HiDPI displays.
+    window_size: (u32, u32),
+}
+
+impl Controls {
+    /// Reads input for as many players as there are `bindings`, in a window of `window_size`
+    pub fn new(
+        sdl: &Sdl,
+        window_size: (u32, u32),
+        bindings: Vec<Bindings>,
+    ) -> Result<Controls, String> {
+        Ok(Controls {
+            events: sdl.event_pump()?,
+            controller_subsystem: sdl.game_controller()?,
+            joystick_subsystem: sdl.joystick()?,
+            controllers: Vec::new(),
+            wheels: Vec::new(),
+            bindings,
+            window_size,
+        })
+    }
+
+    /// `key` for the first player's `action` from now on, in place of the keys it had
+    pub fn bind_key(&mut self, action: Action, key: Scancode) {
+        if let Some(bindings) = self.bindings.first_mut() {
+            bindings.bind_key(action, key);
+        }
+    }
+
+    /// Shakes every player's controllers and wheels that can until told otherwise
+    pub fn rumble(&mut self, rumbles: &[Rumble]) {
+        let motors = |bindings: &Bindings, player: usize| {
+            let rumble = rumbles.get(player).copied().unwrap_or_default();
+            let motor =
+                |share: f64| ((share * bindings.rumble()).clamp(0., 1.) * u16::MAX as f64) as u16;
+            (motor(rumble.low), motor(rumble.high))
+        };
+        // long enough to last until the next frame asks again, short enough to stop soon after
+        // the game does
+        let duration_ms = 200;
+        // not every device has motors, and there's nothing to do about the ones that don't
+        for i in 0..self.controllers.len() {
+            let player = controller_player(&self.bindings, i);
+            let (low, high) = motors(&self.bindings[player], player);
+            let _ = self.controllers[i].set_rumble(low, high, duration_ms);
+        }
+        for i in 0..self.wheels.len() {
+            if let Some(player) = wheel_player(&self.bindings, i) {
+                let (low, high) = motors(&self.bindings[player], player);
+                let _ = self.wheels[i].set_rumble(low, high, duration_ms);
+            }
+        }
+    }
+
+    /// The player's controls as they are being held right now
+    fn read_player(
+        &self,
+        player: usize,
+        key_state: &KeyboardState,
+        mouse_state: &MouseState,
+        commands: Vec<Command>,
+    ) -> TickInput {
+        let bindings = &self.bindings[player];
+        let pads: Vec<_> = (0..self.controllers.len())
+            .filter(|&i| controller_player(&self.bindings, i) == player)
+            .map(|i| &self.controllers[i])
+            .collect();
+        let wheel = (0..self.wheels.len())
+            .find(|&i| wheel_player(&self.bindings, i) == Some(player))
+            .and_then(|i| bindings.wheel().zip(self.wheels.get(i)));
+
+        let mouse = |button: MouseButton| {
+            bindings.mouse_steering() && mouse_state.is_mouse_button_pressed(button)
+        };
+        let held = |action| {
+            bindings
+                .keys(action)
+                .any(|scancode| key_state.is_scancode_pressed(scancode))
+                || bindings
+                    .buttons(action)
+                    .any(|button| pads.iter().any(|pad| pad.button(button)))
+                || bindings.wheel_buttons(action).any(|button| {
+                    wheel.is_some_and(|(_, wheel)| wheel.button(button as u32).unwrap_or(false))
+                })
+        };
+        // the controller pulled furthest off center gets to say
+        let axis = |axis| {
+            pads.iter()
+                .map(|pad| pad.axis(axis) as f64 / i16::MAX as f64)
+                .fold(0., |a: f64, b: f64| if b.abs() > a.abs() { b } else { a })
+        };
+
+        // bound keys and buttons push the pedal all the way down, the triggers and the wheel's
+        // pedals as far as they're pressed
+        let pedal = |action, mouse_button, trigger, response: &AxisResponse, wheel_pedal: f64| {
+            if held(action) || mouse(mouse_button) {
+                1.
+            } else {
+                let trigger = response.apply(axis(trigger).clamp(0., 1.));
+                trigger.max(wheel_pedal)
+            }
+        };
+        let pedals = CarPedals {
+            throttle: pedal(
+                Action::Throttle,
+                MouseButton::Left,
+                Axis::TriggerRight,
+                bindings.throttle_response(),
+                wheel.map_or(0., |(spec, joystick)| spec.throttle(joystick)),
+            ),
+            brake: pedal(
+                Action::Brake,
+                MouseButton::Right,
+                Axis::TriggerLeft,
+                bindings.brake_response(),
+                wheel.map_or(0., |(spec, joystick)| spec.brake(joystick)),
+            ),
+        };
+
+        let picture = letterbox(self.window_size);
+        let position = Point2::new(
+            (mouse_state.x() - picture.x()) as f64 / picture.width() as f64,
+            (mouse_state.y() - picture.y()) as f64 / picture.height() as f64,
+        );
+        let steering = match (held(Action::SteerLeft), held(Action::SteerRight), wheel) {
+            (true, false, _) => -1.,
+            (false, true, _) => 1.,
+            (_, _, Some((spec, joystick))) => spec.steering(joystick),
+            _ => bindings
+                .steering_response()
+                .apply(axis(Axis::LeftX).clamp(-1., 1.)),
+        };
+
+        TickInput {
+            car: CarInput {
+                pedals,
+                steering,
+                handbrake: held(Action::Handbrake),
+                boosting: held(Action::Boost),
+            },
+            aim: bindings.mouse_steering().then_some(position),
+            commands,
+            pointer: (player == 0).then_some(Pointer {
+                position,
+                left: mouse_state.left(),
+                right: mouse_state.right(),
+            }),
+            key: None,
+            text: String::new(),
+        }
+    }
+}
+
+impl InputSource for Controls {
+    /// Turns this tick's key presses, stick movement and button presses into input
+    fn next_tick(&mut self) -> Vec<TickInput> {
+        let mut commands = vec![Vec::new(); self.bindings.len()];
+        let mut press = |player: usize, actions: &mut dyn Iterator<Item = Action>| {
+            commands[player].extend(actions.filter_map(Action::command));
+        };
+        // unplugged controllers, by the player they belonged to
+        let mut lost = Vec::new();
+        let mut first_key = None;
+        let mut text = String::new();
+        for event in self.events.poll_iter() {
+            match event {
+                // closing the window quits for everyone
+                Event::Quit { .. } => press(0, &mut std::iter::once(Action::Quit)),
+                // the usual other way of going fullscreen, rather than pausing too
+                Event::KeyDown {
+                    scancode: Some(Scancode::Return),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    press(0, &mut std::iter::once(Action::ToggleFullscreen))
+                }
+                Event::KeyDown {
+                    scancode: Some(scancode),
+                    repeat: false,
+                    ..
+                } => {
+                    first_key = first_key.or(Some(scancode));
+                    for (player, bindings) in self.bindings.iter().enumerate() {
+                        press(player, &mut bindings.key_actions(scancode));
+                    }
+                }
+                Event::TextInput { text: typed, .. } => text.push_str(&typed),
+                // the mouse wheel zooms the first player's free camera
+                Event::MouseWheel { y, .. } if y != 0 => {
+                    let zoom = if y > 0 {
+                        Action::ZoomIn
+                    } else {
+                        Action::ZoomOut
+                    };
+                    press(0, &mut std::iter::once(zoom));
+                }
+                Event::ControllerButtonDown { which, button, .. } => {
+                    let index = self
+                        .controllers
+                        .iter()
+                        .position(|pad| pad.instance_id() == which);
+                    if let Some(player) = index.map(|i| controller_player(&self.bindings, i)) {
+                        press(player, &mut self.bindings[player].button_actions(button));
+                    }
+                }
+                Event::JoyButtonDown {
+                    which, button_idx, ..
+                } => {
+                    let index = self
+                        .wheels
+                        .iter()
+                        .position(|wheel| wheel.instance_id() == which);
+                    if let Some(player) = index.and_then(|i| wheel_player(&self.bindings, i)) {
+                        press(
+                            player,
+                            &mut self.bindings[player].wheel_button_actions(button_idx),
+                        );
+                    }
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    match self.controller_subsystem.open(which) {
+                        Ok(controller) => self.controllers.push(controller),
+                        Err(error) => eprintln!("couldn't open game controller {which}: {error}"),
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    if let Some(index) = self
+                        .controllers
+                        .iter()
+                        .position(|controller| controller.instance_id() == which)
+                    {
+                        lost.push(controller_player(&self.bindings, index));
+                        self.controllers.remove(index);
+                    }
+                }
+                // game controllers show up as joysticks too
+                Event::JoyDeviceAdded { which, .. }
+                    if self
+                        .bindings
+                        .iter()
+                        .any(|bindings| bindings.wheel().is_some())
+                        && !self.controller_subsystem.is_game_controller(which) =>
+                {
+                    match self.joystick_subsystem.open(which) {
+                        Ok(wheel) => self.wheels.push(wheel),
+                        Err(error) => eprintln!("couldn't open wheel {which}: {error}"),
+                    }
+                }
+                Event::JoyDeviceRemoved { which, .. } => {
+                    if let Some(index) = self
+                        .wheels
+                        .iter()
+                        .position(|wheel| wheel.instance_id() == which)
+                    {
+                        lost.extend(wheel_player(&self.bindings, index));
+                        self.wheels.remove(index);
+                    }
+                }
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(width, height),
+                    ..
+                } => self.window_size = (width as u32, height as u32),
+                _ => {}
+            }
+        }
+        for player in lost {
+            commands[player].push(Command::ControllerLost);
+        }
+
+        let key_state = self.events.keyboard_state();
+        let mouse_state = self.events.mouse_state();
+        let mut inputs: Vec<_> = commands
+            .into_iter()
+            .enumerate()
+            .map(|(player, commands)| self.read_player(player, &key_state, &mouse_state, commands))
+            .collect();
+        if let Some(input) = inputs.first_mut() {
+            input.key = first_key;
+            input.text = text;
+        }
+        inputs
+    }
+}
+
+/// The player the `index`th controller belongs to. The first one goes to the second player,
+/// so two can play with one controller and the keyboard between them.
+fn controller_player(bindings: &[Bindings], index: usize) -> usize {
+    (index + 1) % bindings.len()
+}
+
+/// The player the `index`th wheel belongs to: the wheels are handed out in turn to the
+/// players whose bindings say how to read one
+fn wheel_player(bindings: &[Bindings], index: usize) -> Option<usize> {
+    (0..bindings.len())
+        .filter(|&player| bindings[player].wheel().is_some())
+        .nth(index)
+}
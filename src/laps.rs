@@ -0,0 +1,300 @@
+use nalgebra::Point2;
+
+use crate::{collision::Obb, TICKS_PER_SECOND};
+
+/// How one split compares with the same one on the best lap
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Split {
+    /// which checkpoint it was, 0 being the finish line
+    pub checkpoint: usize,
+    /// ticks into the lap it was reached at
+    pub time: u32,
+    /// ticks faster (negative) or slower than on the best lap, `None` without one yet
+    pub delta: Option<i64>,
+}
+
+/// A car going through a checkpoint, for the timing, the scoring and whatever else keeps
+/// track of how the car's getting on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crossing {
+    pub checkpoint: usize,
+    /// ticks into the lap, `None` for the first time over the line, which starts the clock
+    pub time: Option<u32>,
+    /// how many checkpoints were missed out on the way here from the last one
+    pub skipped: usize,
+    /// whether every checkpoint's been gone through in order so far this lap. One missed
+    /// out and the lap doesn't count.
+    pub valid: bool,
+}
+
+/// Times a car's laps by the checkpoints it goes through, the first one being the start and
+/// finish line. A lap starts the first time the car crosses the line and from then on runs
+/// from one crossing to the next, each checkpoint on the way splitting it into sectors.
+/// They have to be gone through in order: a lap that misses one out isn't timed or counted.
+#[derive(Default)]
+pub struct LapTimer {
+    /// the checkpoint to go through next
+    next: usize,
+    /// the checkpoint the car was in last tick, so sitting in one counts once
+    inside: Option<usize>,
+    /// ticks into the lap going, `None` before the car first crossed the line
+    lap: Option<u32>,
+    /// whether a checkpoint's been missed out on the lap going
+    missed: bool,
+    /// ticks into the lap going each checkpoint was reached at
+    splits: Vec<u32>,
+    last_lap: Option<u32>,
+    best_lap: Option<u32>,
+    /// how long each lap finished took, in order
+    laps: Vec<u32>,
+    /// the quickest each sector's been gone through on any lap finished, in ticks
+    best_sectors: Vec<u32>,
+    best_splits: Vec<u32>,
+    /// the checkpoint reached last, and how long ago in ticks
+    last_split: Option<(Split, u32)>,
+    laps_finished: u32,
+    /// checkpoints gone through in order since first crossing the line
+    progress: u32,
+}
+
+impl LapTimer {
+    /// Laps all the way round from the line back to it, without missing a checkpoint
+    pub fn laps_finished(&self) -> u32 {
+        self.laps_finished
+    }
+
+    pub fn lap(&self) -> Option<u32> {
+        self.lap
+    }
+
+    /// Whether the lap going still counts
+    pub fn is_lap_valid(&self) -> bool {
+        !self.missed
+    }
+
+    pub fn last_lap(&self) -> Option<u32> {
+        self.last_lap
+    }
+
+    pub fn best_lap(&self) -> Option<u32> {
+        self.best_lap
+    }
+
+    /// How long each lap finished took, the first first
+    pub fn laps(&self) -> &[u32] {
+        &self.laps
+    }
+
+    /// The quickest each sector's been, from the line to the first checkpoint first, even
+    /// when they weren't all on the same lap
+    pub fn best_sectors(&self) -> &[u32] {
+        &self.best_sectors
+    }
+
+    /// The checkpoint reached last and how many ticks ago
+    pub fn last_split(&self) -> Option<(Split, u32)> {
+        self.last_split
+    }
+
+    /// How far round the car's got, in checkpoints gone through in order, for telling who's
+    /// ahead
+    pub fn progress(&self) -> u32 {
+        self.progress
+    }
+
+    /// Moves the clock on a tick with the car at `position`, telling of any checkpoint it
+    /// went through
+    pub fn update(&mut self, position: Point2<f64>, checkpoints: &[Obb]) -> Option<Crossing> {
+        if let Some(lap) = &mut self.lap {
+            *lap += 1;
+        }
+        if let Some((_, since)) = &mut self.last_split {
+            *since += 1;
+        }
+        let inside = checkpoints
+            .iter()
+            .position(|checkpoint| checkpoint.contains(position));
+        let entered = inside.filter(|&checkpoint| self.inside != Some(checkpoint));
+        self.inside = inside;
+        let reached = entered?;
+
+        let count = checkpoints.len();
+        let Some(time) = self.lap else {
+            // only the line starts the clock
+            if reached != 0 {
+                return None;
+            }
+            self.lap = Some(0);
+            self.next = 1 % count;
+            return Some(Crossing {
+                checkpoint: 0,
+                time: None,
+                skipped: 0,
+                valid: true,
+            });
+        };
+        // backing out of the last one and going through it again, as after a spin
+        if count > 1 && reached == (self.next + count - 1) % count {
+            return None;
+        }
+
+        let skipped = (reached + count - self.next) % count;
+        self.next = (reached + 1) % count;
+        if skipped > 0 {
+            self.missed = true;
+        } else {
+            self.progress += 1;
+        }
+        let crossing = Crossing {
+            checkpoint: reached,
+            time: Some(time),
+            skipped,
+            valid: !self.missed,
+        };
+
+        if !self.missed {
+            let sector = self.splits.len();
+            let delta = self
+                .best_splits
+                .get(sector)
+                .map(|best| time as i64 - *best as i64);
+            self.last_split = Some((
+                Split {
+                    checkpoint: reached,
+                    time,
+                    delta,
+                },
+                0,
+            ));
+            self.splits.push(time);
+        }
+
+        if reached == 0 {
+            if !self.missed {
+                self.laps_finished += 1;
+                self.last_lap = Some(time);
+                self.laps.push(time);
+                let mut previous = 0;
+                for (sector, &split) in self.splits.iter().enumerate() {
+                    let length = split - previous;
+                    previous = split;
+                    match self.best_sectors.get_mut(sector) {
+                        Some(best) => *best = (*best).min(length),
+                        None => self.best_sectors.push(length),
+                    }
+                }
+                if self.best_lap.is_none_or(|best| time < best) {
+                    self.best_lap = Some(time);
+                    self.best_splits = std::mem::take(&mut self.splits);
+                }
+            }
+            self.splits.clear();
+            self.missed = false;
+            self.lap = Some(0);
+        }
+        Some(crossing)
+    }
+}
+
+/// `ticks` as minutes, seconds and hundredths, like 1:02.35
+pub fn format_time(ticks: u32) -> String {
+    let hundredths = (ticks as f64 * 100. / TICKS_PER_SECOND).round() as u32;
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        hundredths / 100 % 60,
+        hundredths % 100
+    )
+}
+
+/// A difference in time like +0.35 or -1.20
+pub fn format_delta(ticks: i64) -> String {
+    let hundredths = (ticks.abs() as f64 * 100. / TICKS_PER_SECOND).round() as u32;
+    let sign = if ticks < 0 { '-' } else { '+' };
+    format!("{sign}{}.{:02}", hundredths / 100, hundredths % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nalgebra::{Rotation2, Vector2};
+
+    #[test]
+    fn laps_run_from_line_to_line_split_at_the_checkpoints() {
+        let gate = |x| {
+            Obb::new(
+                Point2::new(x, 0.),
+                Vector2::new(10., 10.),
+                Rotation2::identity(),
+            )
+        };
+        let checkpoints = [gate(0.), gate(100.)];
+        let mut timer = LapTimer::default();
+        // out and back through both twice, slower the second time round
+        let drive = |timer: &mut LapTimer, ticks_between: u32| {
+            for x in [0., 100.] {
+                timer.update(Point2::new(x, 0.), &checkpoints);
+                for _ in 0..ticks_between {
+                    timer.update(Point2::new(50., 50.), &checkpoints);
+                }
+            }
+        };
+        drive(&mut timer, 9);
+        assert_eq!(timer.last_split().unwrap().0.time, 10);
+        drive(&mut timer, 14);
+        assert_eq!(timer.last_lap(), Some(20));
+        assert_eq!(timer.best_lap(), Some(20));
+        timer.update(Point2::new(0., 0.), &checkpoints);
+        assert_eq!(timer.last_lap(), Some(30));
+        assert_eq!(timer.best_lap(), Some(20));
+        assert_eq!(timer.laps(), [20, 30]);
+        assert_eq!(timer.best_sectors(), [10, 10]);
+        assert_eq!(
+            timer.last_split().unwrap().0,
+            Split {
+                checkpoint: 0,
+                time: 30,
+                delta: Some(10),
+            }
+        );
+
+        assert_eq!(format_time(3750), "1:02.50");
+        assert_eq!(format_delta(-3), "-0.05");
+    }
+
+    #[test]
+    fn missing_a_checkpoint_out_loses_the_lap() {
+        let gate = |x| {
+            Obb::new(
+                Point2::new(x, 0.),
+                Vector2::new(10., 10.),
+                Rotation2::identity(),
+            )
+        };
+        let checkpoints = [gate(0.), gate(100.), gate(200.)];
+        let mut timer = LapTimer::default();
+        let through = |timer: &mut LapTimer, x| {
+            let crossing = timer.update(Point2::new(x, 0.), &checkpoints);
+            timer.update(Point2::new(x, 50.), &checkpoints);
+            crossing
+        };
+        assert_eq!(through(&mut timer, 0.).unwrap().time, None);
+        // straight from the line to the last one
+        let cut = through(&mut timer, 200.).unwrap();
+        assert_eq!((cut.checkpoint, cut.skipped, cut.valid), (2, 1, false));
+        let finish = through(&mut timer, 0.).unwrap();
+        assert_eq!((finish.checkpoint, finish.valid), (0, false));
+        assert_eq!(timer.laps_finished(), 0);
+        assert_eq!(timer.last_lap(), None);
+
+        // and all the way round the next time
+        for x in [100., 200., 0.] {
+            assert!(through(&mut timer, x).unwrap().valid);
+        }
+        assert_eq!(timer.laps_finished(), 1);
+        assert_eq!(timer.last_lap(), Some(6));
+        // the one missed out doesn't count, nor the one gone to instead
+        assert_eq!(timer.progress(), 4);
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+};
+
+use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+
+use crate::{
+    assets::Assets,
+    generator,
+    input::{Command, TickInput},
+    scene::{Scene, Transition},
+    text, thumbnail,
+    track::Track,
+    Level, SCREEN_DIMENSIONS,
+};
+
+/// How big the progress bar is, in pixels
+const BAR: (u32, u32) = (1200, 40);
+
+/// Where the track comes from
+pub enum Source {
+    /// a Tiled map or TOML track
+    File(String),
+    /// made up from the seed
+    Generated(u64),
+    BuiltIn,
+}
+
+/// Everything read for a level, for the main thread to start it with
+pub struct Loaded {
+    pub track: Track,
+    /// the track from above, to draw the minimap with
+    pub thumbnail: Option<PathBuf>,
+    /// the thumbnail and the track's backgrounds and decorations as they are in their files,
+    /// for the main thread to only have to make them into textures
+    pub images: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// What the worker sends back as it goes
+enum Message {
+    /// how far along it is, from 0 to 1, and what it's doing
+    Progress(f64, &'static str),
+    Done(Box<Result<Loaded, String>>),
+}
+
+/// Shows how far along loading a track is while a worker thread does it, so the window keeps
+/// answering however big the track is. Going back gives up on it, the worker finishing in
+/// the background and what it loaded being thrown away.
+pub struct Loading {
+    receiver: Receiver<Message>,
+    progress: f64,
+    doing: &'static str,
+    loaded: Option<Result<Loaded, String>>,
+    /// how many ticks it's been going, for the dots after what it's doing
+    ticks: u32,
+    /// the file the track's loaded from, to drive on it again whenever it's saved
+    path: Option<String>,
+    /// set up like the level to drive on the track once it's loaded, rather than ending the
+    /// loading for whoever started it to take what was loaded
+    then: Option<Box<Level>>,
+}
+
+impl Loading {
+    pub fn new(source: Source) -> Loading {
+        let path = match &source {
+            Source::File(path) => Some(path.clone()),
+            _ => None,
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let loaded = load(&source, &sender);
+            // nobody to send it to once the loading's been given up on
+            let _ = sender.send(Message::Done(Box::new(loaded)));
+        });
+        Loading {
+            receiver,
+            progress: 0.,
+            doing: "READING THE TRACK",
+            loaded: None,
+            ticks: 0,
+            path,
+            then: None,
+        }
+    }
+
+    /// Goes on to drive on the track with a level set up like `level` once it's loaded
+    pub fn then_drive(mut self, level: Level) -> Loading {
+        self.then = Some(Box::new(level));
+        self
+    }
+
+    /// What was loaded, or why it couldn't be. `None` if the loading was given up on.
+    pub fn take(&mut self) -> Option<Result<Loaded, String>> {
+        self.loaded.take()
+    }
+}
+
+/// Loads the track from `source`, its thumbnail and its images, saying how far along it is
+fn load(source: &Source, sender: &Sender<Message>) -> Result<Loaded, String> {
+    let progress = |progress, doing| {
+        let _ = sender.send(Message::Progress(progress, doing));
+    };
+    let (track, path) = match source {
+        Source::File(path) => {
+            // up to the minimap, as reading the file is most of the work
+            let track = Track::load_reporting(path, |track_progress, doing| {
+                progress(0.4 * track_progress, doing)
+            })
+            .map_err(|error| format!("couldn't load the track {path}: {error}"))?;
+            (track, Some(Path::new(path)))
+        }
+        Source::Generated(seed) => (generator::generate(*seed).track(Path::new("")), None),
+        Source::BuiltIn => (Track::new(), None),
+    };
+
+    progress(0.4, "DRAWING THE MINIMAP");
+    // a generated track is made up again every time, with nothing to keep its thumbnail for
+    let thumbnail = match source {
+        Source::Generated(_) => None,
+        _ => thumbnail::cached(&track, path),
+    };
+
+    let mut paths: Vec<PathBuf> = thumbnail.iter().cloned().collect();
+    paths.extend(
+        track
+            .backgrounds
+            .iter()
+            .map(|background| background.image.clone()),
+    );
+    paths.extend(
+        track
+            .decorations
+            .iter()
+            .filter_map(|decoration| decoration.image.clone()),
+    );
+    paths.sort();
+    paths.dedup();
+    let mut images = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        progress(
+            0.6 + 0.4 * i as f64 / paths.len() as f64,
+            "READING THE PICTURES",
+        );
+        // ones that can't be read are left for the main thread to say so when it tries
+        if let Ok(bytes) = fs::read(path) {
+            images.push((path.clone(), bytes));
+        }
+    }
+    Ok(Loaded {
+        track,
+        thumbnail,
+        images,
+    })
+}
+
+impl Scene for Loading {
+    /// Ends once everything's loaded, as well as when a player wants out
+    fn update(&mut self, inputs: &[TickInput]) -> Transition {
+        self.ticks += 1;
+        let commands = || inputs.iter().flat_map(|input| &input.commands);
+        if commands().any(|command| matches!(command, Command::Quit | Command::Back)) {
+            return Transition::Pop;
+        }
+        loop {
+            let message = match self.receiver.try_recv() {
+                Ok(message) => message,
+                Err(TryRecvError::Empty) => break,
+                // the worker went without saying it was done, so it'll never finish
+                Err(TryRecvError::Disconnected) => {
+                    let error = "the loading stopped without finishing the track".to_string();
+                    match self.then {
+                        Some(_) => eprintln!("{error}"),
+                        None => self.loaded = Some(Err(error)),
+                    }
+                    return Transition::Pop;
+                }
+            };
+            match message {
+                Message::Progress(progress, doing) => {
+                    self.progress = progress;
+                    self.doing = doing;
+                }
+                Message::Done(loaded) => {
+                    let Some(mut from) = self.then.take() else {
+                        self.loaded = Some(*loaded);
+                        return Transition::Pop;
+                    };
+                    let loaded = match *loaded {
+                        Ok(loaded) => loaded,
+                        Err(error) => {
+                            eprintln!("{error}");
+                            return Transition::Pop;
+                        }
+                    };
+                    let mut level = from.on_track(loaded.track);
+                    level.set_pictures(loaded.thumbnail, loaded.images);
+                    // a recording has to stay on the track it started on, and so does a replay
+                    if let Some(path) = self.path.as_ref().filter(|_| !level.recorded) {
+                        level.watch_track(path);
+                    }
+                    return Transition::Replace(Box::new(level));
+                }
+            }
+        }
+        Transition::Stay
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>, _assets: &Assets, _alpha: f64) {
+        let (width, height) = SCREEN_DIMENSIONS;
+        canvas.set_draw_color(Color::RGB(20, 24, 28));
+        canvas.clear();
+        text::draw_centered(canvas, "LOADING", (width / 2, 380), 8, Color::WHITE);
+
+        let (bar_width, bar_height) = BAR;
+        let left = (width - bar_width as i32) / 2;
+        let top = height / 2;
+        canvas.set_draw_color(Color::RGB(60, 64, 70));
+        canvas
+            .fill_rect(Rect::new(left, top, bar_width, bar_height))
+            .unwrap();
+        let filled = (bar_width as f64 * self.progress.clamp(0., 1.)) as u32;
+        if filled > 0 {
+            canvas.set_draw_color(Color::RGB(255, 220, 0));
+            canvas
+                .fill_rect(Rect::new(left, top, filled, bar_height))
+                .unwrap();
+        }
+
+        let dots = ".".repeat((self.ticks / 20 % 4) as usize);
+        text::draw_centered(
+            canvas,
+            &format!("{}{dots}", self.doing),
+            (width / 2, top + 90),
+            4,
+            Color::RGB(200, 200, 200),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn tracks_load_in_the_background_until_the_loading_is_done() {
+        let mut loading = Loading::new(Source::Generated(3));
+        let started = Instant::now();
+        while matches!(loading.update(&[]), Transition::Stay) {
+            assert!(started.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(1));
+        }
+        let loaded = loading.take().unwrap().unwrap();
+        assert!(!loaded.track.spawn_points.is_empty());
+        assert!(loaded.thumbnail.is_none());
+        assert!(loading.take().is_none());
+
+        let mut loading = Loading::new(Source::File("no such track.toml".to_string()));
+        while matches!(loading.update(&[]), Transition::Stay) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(loading.take().unwrap().is_err());
+
+        // a worker that panicked never says it's done
+        let mut loading = Loading::new(Source::Generated(3));
+        let (sender, receiver) = mpsc::channel();
+        drop(sender);
+        loading.receiver = receiver;
+        assert!(matches!(loading.update(&[]), Transition::Pop));
+        assert!(loading.take().unwrap().is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use nalgebra::{Point2, Rotation2, Vector2};
+use sdl2::pixels::Color;
+
+use crate::{
+    car::Car,
+    collision::{self, Obb},
+    track::{PropSpot, Track},
+    TICKS_PER_SECOND,
+};
+
+/// Share of a loose prop's speed it keeps every tick sliding over the ground
+const FRICTION: f64 = 0.94;
+/// and of its spin
+const SPIN_FRICTION: f64 = 0.9;
+/// How much of the speed it hits things with it bounces back with
+const RESTITUTION: f64 = 0.4;
+/// Slower than this, in pixels per tick, it's come to rest
+const REST_SPEED: f64 = 0.05;
+/// Ticks a knocked prop lies still for before it's put back where it stood
+const RESPAWN_TIME: u32 = 8 * TICKS_PER_SECOND as u32;
+/// How far every car has to be from where a prop stood for it to be put back there, so it
+/// doesn't pop up under anyone
+const RESPAWN_CLEARANCE: f64 = 400.;
+/// Hitting one slower than this, in pixels per tick, only nudges it
+const KNOCK_SPEED: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PropKind {
+    /// light, marking out where to go, and a penalty to knock over
+    Cone,
+    Barrel,
+    /// heavy enough to feel, and soaking up a good part of a hit
+    Tires,
+}
+
+impl PropKind {
+    pub fn from_name(name: &str) -> Option<PropKind> {
+        match name {
+            "cone" => Some(PropKind::Cone),
+            "barrel" => Some(PropKind::Barrel),
+            "tires" => Some(PropKind::Tires),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PropKind::Cone => "cone",
+            PropKind::Barrel => "barrel",
+            PropKind::Tires => "tires",
+        }
+    }
+
+    /// Across and along, in pixels
+    pub fn size(self) -> Vector2<f64> {
+        match self {
+            PropKind::Cone => Vector2::new(24., 24.),
+            PropKind::Barrel => Vector2::new(44., 44.),
+            PropKind::Tires => Vector2::new(60., 60.),
+        }
+    }
+
+    /// Next to a car's thousand
+    fn mass(self) -> f64 {
+        match self {
+            PropKind::Cone => 5.,
+            PropKind::Barrel => 40.,
+            PropKind::Tires => 120.,
+        }
+    }
+
+    /// Points for knocking one about, `None` for the ones it's a penalty to hit
+    pub fn points(self) -> Option<u32> {
+        match self {
+            PropKind::Cone => None,
+            PropKind::Barrel => Some(50),
+            PropKind::Tires => Some(100),
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            PropKind::Cone => Color::RGB(255, 120, 20),
+            PropKind::Barrel => Color::RGB(40, 90, 200),
+            PropKind::Tires => Color::RGB(40, 40, 40),
+        }
+    }
+}
+
+/// A car knocking a prop off its spot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Knock {
+    pub car: usize,
+    pub kind: PropKind,
+    pub position: Point2<f64>,
+}
+
+/// Something light standing on the track that gets knocked about by the cars rather than
+/// stopping them, sliding and spinning to a halt and put back where it stood once it's been
+/// lying about a while
+pub struct LooseProp {
+    pub kind: PropKind,
+    position: Point2<f64>,
+    rotation: Rotation2<f64>,
+    previous_position: Point2<f64>,
+    previous_rotation: Rotation2<f64>,
+    /// pixels per tick
+    velocity: Vector2<f64>,
+    /// radians per tick
+    angular_velocity: f64,
+    /// where it stood on the track
+    home: Point2<f64>,
+    home_rotation: Rotation2<f64>,
+    /// whether some car's knocked it since it was last put back, for it only to count once
+    knocked: bool,
+    /// ticks since it came to rest off its spot
+    resting: u32,
+}
+
+impl LooseProp {
+    pub fn new(spot: &PropSpot) -> LooseProp {
+        LooseProp {
+            kind: spot.kind,
+            position: spot.position,
+            rotation: spot.rotation,
+            previous_position: spot.position,
+            previous_rotation: spot.rotation,
+            velocity: Vector2::zeros(),
+            angular_velocity: 0.,
+            home: spot.position,
+            home_rotation: spot.rotation,
+            knocked: false,
+            resting: 0,
+        }
+    }
+
+    pub fn obb(&self) -> Obb {
+        Obb::new(self.position, self.kind.size(), self.rotation)
+    }
+
+    /// Where it is, `alpha` of the way between the last two ticks
+    pub fn interpolated_obb(&self, alpha: f64) -> Obb {
+        let position = self
+            .previous_position
+            .coords
+            .lerp(&self.position.coords, alpha);
+        let rotation = self.previous_rotation.slerp(&self.rotation, alpha);
+        Obb::new(Point2::from(position), self.kind.size(), rotation)
+    }
+
+    fn inertia(&self) -> f64 {
+        self.kind.mass() * self.kind.size().norm_squared() / 12.
+    }
+
+    fn point_velocity(&self, point: Point2<f64>) -> Vector2<f64> {
+        let arm = point - self.position;
+        self.velocity + Vector2::new(-arm.y, arm.x) * self.angular_velocity
+    }
+
+    fn apply_impulse(&mut self, point: Point2<f64>, impulse: Vector2<f64>) {
+        let arm = point - self.position;
+        self.velocity += impulse / self.kind.mass();
+        self.angular_velocity += arm.perp(&impulse) / self.inertia();
+    }
+
+    /// Slides the prop on and out of whatever's in its way on the track
+    fn update(&mut self, track: &Track) {
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
+        self.position += self.velocity;
+        self.rotation *= Rotation2::new(self.angular_velocity);
+        self.velocity *= FRICTION;
+        self.angular_velocity *= SPIN_FRICTION;
+
+        let still = Vector2::zeros();
+        let walls = track
+            .walls
+            .iter()
+            .map(|wall| (collision::obb_vs_segment(&self.obb(), wall), still));
+        let props = track
+            .props
+            .iter()
+            .map(|prop| (collision::obb_vs_obb(&self.obb(), prop), still));
+        let movers = track
+            .mover_areas()
+            .map(|(area, velocity)| (collision::obb_vs_obb(&self.obb(), &area), velocity));
+        let contacts: Vec<_> = walls
+            .chain(props)
+            .chain(movers)
+            .filter_map(|(contact, velocity)| Some((contact?, velocity)))
+            .collect();
+        for (contact, surface_velocity) in contacts {
+            self.position += contact.normal * contact.depth;
+            let normal_speed = (self.velocity - surface_velocity).dot(&contact.normal);
+            if normal_speed < 0. {
+                self.velocity -= contact.normal * normal_speed * (1. + RESTITUTION);
+            }
+        }
+    }
+
+    /// Pushes the prop out of `car` and knocks it away, the car feeling as much of it as a
+    /// prop that light can give. Whether it was hit hard enough to count.
+    fn collide(&mut self, car: &mut Car) -> bool {
+        let Some(contact) = collision::obb_vs_obb(&self.obb(), &car.obb()) else {
+            return false;
+        };
+        // the car's so much heavier it's for the prop to get out of the way
+        self.position += contact.normal * contact.depth;
+
+        let approach_speed = (self.point_velocity(contact.point)
+            - car.point_velocity(contact.point))
+        .dot(&contact.normal);
+        if approach_speed >= 0. {
+            return false;
+        }
+        let inverse_masses = (1. / self.kind.mass(), 1. / car.spec().mass);
+        let impulse = -(1. + RESTITUTION) * approach_speed / (inverse_masses.0 + inverse_masses.1);
+        self.apply_impulse(contact.point, contact.normal * impulse);
+        car.apply_impulse(contact.point, -contact.normal * impulse);
+        -approach_speed > KNOCK_SPEED
+    }
+
+    /// Props bumping into each other, like a stack of tyres knocked into the next one
+    fn collide_with(&mut self, other: &mut LooseProp) {
+        let Some(contact) = collision::obb_vs_obb(&self.obb(), &other.obb()) else {
+            return;
+        };
+        let inverse_masses = (1. / self.kind.mass(), 1. / other.kind.mass());
+        let total_inverse_mass = inverse_masses.0 + inverse_masses.1;
+        self.position += contact.normal * contact.depth * inverse_masses.0 / total_inverse_mass;
+        other.position -= contact.normal * contact.depth * inverse_masses.1 / total_inverse_mass;
+
+        let approach_speed = (self.velocity - other.velocity).dot(&contact.normal);
+        if approach_speed >= 0. {
+            return;
+        }
+        let impulse = -(1. + RESTITUTION) * approach_speed / total_inverse_mass;
+        self.apply_impulse(contact.point, contact.normal * impulse);
+        other.apply_impulse(contact.point, -contact.normal * impulse);
+    }
+
+    /// Back where it stood, still
+    fn reset(&mut self) {
+        self.position = self.home;
+        self.rotation = self.home_rotation;
+        self.previous_position = self.home;
+        self.previous_rotation = self.home_rotation;
+        self.velocity = Vector2::zeros();
+        self.angular_velocity = 0.;
+        self.knocked = false;
+        self.resting = 0;
+    }
+}
+
+/// Moves every loose prop on a tick, knocked about by `cars`, and says which ones a car
+/// knocked off their spot for the first time since they were last put back
+pub fn update(props: &mut [LooseProp], cars: &mut [Car], track: &Track) -> Vec<Knock> {
+    let mut knocks = Vec::new();
+    for prop in props.iter_mut() {
+        prop.update(track);
+        for (i, car) in cars.iter_mut().enumerate() {
+            if prop.collide(car) && !prop.knocked {
+                prop.knocked = true;
+                knocks.push(Knock {
+                    car: i,
+                    kind: prop.kind,
+                    position: prop.position,
+                });
+            }
+        }
+    }
+    for i in 1..props.len() {
+        let (done, rest) = props.split_at_mut(i);
+        for other in rest {
+            done[i - 1].collide_with(other);
+        }
+    }
+
+    for prop in props.iter_mut() {
+        let away = prop.position != prop.home || prop.rotation != prop.home_rotation;
+        let still = prop.velocity.norm() < REST_SPEED;
+        prop.resting = if away && still { prop.resting + 1 } else { 0 };
+        let clear = cars
+            .iter()
+            .all(|car| (car.center() - prop.home).norm() > RESPAWN_CLEARANCE);
+        if prop.resting >= RESPAWN_TIME && clear {
+            prop.reset();
+        }
+    }
+    knocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::car::{CarInput, CarPedals, CarSpec};
+
+    #[test]
+    fn a_car_knocks_a_cone_away_once_and_it_goes_back() {
+        let mut track = Track::new();
+        track.props.clear();
+        let home = Point2::new(2500., 690.);
+        let mut props = vec![LooseProp::new(&PropSpot {
+            kind: PropKind::Cone,
+            position: home,
+            rotation: Rotation2::identity(),
+        })];
+        // driving up the map straight at it
+        let mut cars = vec![Car::from_spec(
+            CarSpec::default(),
+            home + Vector2::new(0., 300.),
+        )];
+        let input = CarInput {
+            pedals: CarPedals {
+                throttle: 1.,
+                ..CarPedals::default()
+            },
+            ..CarInput::default()
+        };
+
+        let mut knocks = Vec::new();
+        for _ in 0..2 * TICKS_PER_SECOND as usize {
+            cars[0].update(&input, &track);
+            knocks.extend(update(&mut props, &mut cars, &track));
+        }
+        assert_eq!(knocks.len(), 1);
+        assert_eq!(knocks[0].kind, PropKind::Cone);
+        assert!((props[0].position - home).norm() > 100.);
+
+        // out of the way, it's put back before long
+        cars[0] = Car::from_spec(CarSpec::default(), home + Vector2::new(0., 2000.));
+        for _ in 0..RESPAWN_TIME as usize * 2 {
+            update(&mut props, &mut cars, &track);
+        }
+        assert_eq!(props[0].position, home);
+    }
+}
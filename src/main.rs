@@ -1,289 +1,3440 @@
-use std::time::Duration;
+mod animation;
+mod assets;
+mod audio;
+mod bindings;
+mod camera;
+mod car;
+mod collision;
+mod config;
+mod console;
+mod damage;
+mod daylight;
+mod director;
+mod drift_score;
+mod dust;
+mod editor;
+mod engine;
+mod export;
+mod frame_counter;
+mod garage;
+mod generator;
+mod input;
+mod laps;
+mod loading;
+mod loose_props;
+mod menu;
+mod nitro;
+mod pause_menu;
+mod race_start;
+mod replay;
+mod results;
+mod rng;
+mod scene;
+mod screenshot;
+mod settings;
+mod settings_menu;
+mod skids;
+mod smoke;
+mod sparks;
+mod terrain;
+mod text;
+mod thumbnail;
+mod tire;
+mod tmx;
+mod track;
+mod track_menu;
+mod track_toml;
+mod track_watch;
+mod trail;
+mod trailer;
+mod validation;
+mod video;
+mod weather;
+mod wheel;
 
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use animation::{Animation, Playing};
+use assets::{Assets, FontId, SpriteId};
+use audio::{Audio, Sound};
+use bindings::{Action, Bindings};
+use camera::{Camera, CameraSpec};
+use car::{Car, CarInput, CarPedals, CarSpec};
+use collision::Obb;
+use config::ConfigError;
+use console::Console;
+use daylight::TimeOfDay;
+use director::Director;
+use drift_score::DriftScore;
+use dust::Dust;
+use editor::Editor;
+use engine::Shift;
+use export::Exporter;
+use frame_counter::FrameCounter;
+use garage::Garage;
+use input::{Command, Controls, InputSource, Rumble, TickInput};
+use laps::{Crossing, LapTimer};
+use loading::{Loading, Source};
+use loose_props::{Knock, LooseProp, PropKind};
 use nalgebra::{Point2, Rotation2, Vector2};
+use pause_menu::{PauseChoice, PauseMenu, Paused};
+use race_start::RaceStart;
+use replay::{Playback, Recording};
+use results::{Finish, PlayerResult, Results, ResultsChoice};
+use rng::Rng;
+use scene::{Outcome, Scene, SceneManager, Transition};
 use sdl2::{
-    event::Event,
-    keyboard::{Keycode, Scancode},
-    pixels::Color,
-    rect::Rect,
-    render::{Canvas, RenderTarget, TextureCreator},
-    video::WindowContext,
-    EventPump,
+    keyboard::Scancode,
+    pixels::{Color, PixelFormatEnum},
+    rect::{FPoint, FRect, Point, Rect},
+    render::{BlendMode, Canvas, RenderTarget},
+    video::{FullscreenType, Window},
 };
+use settings::Settings;
+use skids::SkidMarks;
+use smoke::Smoke;
+use sparks::Sparks;
+use track::{HazardKind, RespawnPoint, Track};
+use track_menu::TrackMenu;
+use track_toml::TrackFile;
+use track_watch::TrackWatch;
+use trail::{Trail, TrailSpec};
+use trailer::Trailer;
+use video::{Filter, VideoSpec};
+use weather::Rain;
+
+/// the size everything is laid out and drawn at, before being scaled to fit the window
 const SCREEN_DIMENSIONS: (i32, i32) = (1920, 1080);
+/// how far curbs reach into the track and how long each of their stripes is, in pixels
+const CURB_WIDTH: u32 = 12;
+const CURB_STRIPE: u32 = 25;
+/// how long the nitro flame is, as a share of its usual length, and its color, flickering
+const FLAME: Animation<(f32, Color)> = Animation {
+    frames: &[
+        ((1., Color::RGB(255, 150, 30)), 3),
+        ((1.3, Color::RGB(255, 200, 60)), 2),
+        ((0.8, Color::RGB(255, 120, 20)), 3),
+        ((1.15, Color::RGB(255, 170, 40)), 2),
+    ],
+    looping: true,
+};
+/// how bright the warning lamps on the props are, flashing all together every second
+const BEACON: Animation<u8> = Animation {
+    frames: &[(255, 8), (150, 4), (60, 4), (0, 40), (120, 4)],
+    looping: true,
+};
+/// km/h for every pixel per tick, a car being 100 pixels or about 4.5 m long
+const KMH_PER_SPEED: f64 = 9.72;
+/// the simulation always advances in steps of this size, no matter how fast we render
+const TICKS_PER_SECOND: f64 = 60.;
+/// Cars on the grid, the players' and the AI's making up the rest
+const FIELD_SIZE: usize = 3;
+/// How far along the racing line the AI aims, in pixels
+const AI_LOOK_AHEAD: f64 = 250.;
+/// How fast the AI goes where the racing line doesn't say, in pixels per tick
+const AI_TOP_SPEED: f64 = 9.;
+/// The most cars spawning AI from the console fills the field up to
+const MAX_CARS: usize = 24;
+/// Ticks from the last player crossing the line at the end of a race to the results coming up
+const RESULTS_DELAY: u32 = 3 * TICKS_PER_SECOND as u32;
 
-struct Car {
-    dimensions: Vector2<f64>,
-    pos: Point2<f64>,
-    rotation: Rotation2<f64>,
-    velocity: Vector2<f64>,
+/// Things that happened during a tick, for whoever keeps score. `car` indexes `Level::cars`.
+enum LevelEvent {
+    LeftTrack { car: usize },
+    RejoinedTrack { car: usize },
+    Crossed { car: usize, crossing: Crossing },
+    Knocked { knock: Knock },
+}
 
-    wheel_speed: f64,
-    acceleration: f64,
-    max_speed: f64,
+struct Level {
+    /// the first `players` cars are driven by the players, in player order
+    cars: Vec<Car>,
+    players: usize,
+    /// one per player, following their car
+    cameras: Vec<Camera>,
+    track: Track,
+    /// the track's loose props, wherever they've been knocked to
+    loose_props: Vec<LooseProp>,
+    /// what happened during the last tick
+    events: Vec<LevelEvent>,
+    /// one for each car
+    lap_timers: Vec<LapTimer>,
+    /// one for each player
+    drift_scores: Vec<DriftScore>,
+    /// ticks left to flash each player's off track warning for
+    off_track_warnings: Vec<u32>,
+    /// ticks left to show each player they missed a checkpoint out for
+    missed_warnings: Vec<u32>,
+    /// the cars in race order, the one furthest round first
+    standings: Vec<usize>,
+    /// ticks since the lights went green, for timing the race
+    race_clock: u32,
+    /// what the race clock said as each car finished the race, `None` until it has
+    finish_times: Vec<Option<u32>>,
+    /// starts jumped, checkpoints missed out and cones hit by each car
+    penalties: Vec<u32>,
+    /// ticks since the last player finished the race, the results coming up after a while
+    finished_for: u32,
+    /// where the results hand back what the players chose to do next, while they're up
+    results: Option<Outcome<ResultsChoice>>,
+    /// where to choose another track from after the race, `None` for levels that have to
+    /// stay on their track, like replays and recordings
+    tracks: Option<PathBuf>,
+    /// the directory of cars to choose another one from after the race, `None` when there
+    /// was no choice of them and for levels that have to keep theirs, like replays and
+    /// recordings
+    garage: Option<PathBuf>,
+    /// counting down to the start of a race, `None` once everyone's away or when just
+    /// driving about
+    start: Option<RaceStart>,
+    /// what to play for the last tick
+    sounds: Vec<Sound>,
+    /// hitched to the first player's car, if the game mode hands out one
+    trailer: Option<Trailer>,
+    skids: SkidMarks,
+    smoke: Smoke,
+    dust: Dust,
+    sparks: Sparks,
+    /// how far into the nitro flame's flicker each car is, starting over every boost
+    flames: Vec<Playing>,
+    beacons: Playing,
+    /// what each car is drawn with, `None` until loaded and for cars whose sprite didn't load
+    car_sprites: Vec<Option<SpriteId>>,
+    /// one for each of the track's backgrounds and decorations, like the cars'
+    background_sprites: Vec<Option<SpriteId>>,
+    decoration_sprites: Vec<Option<SpriteId>>,
+    /// the track from above for the minimap, drawn from its tiles without one
+    minimap_sprite: Option<SpriteId>,
+    /// where the minimap's picture is, to load it from
+    thumbnail: Option<PathBuf>,
+    /// pictures for the sprites read from their files ahead of loading them
+    images: Vec<(PathBuf, Vec<u8>)>,
+    time: TimeOfDay,
+    /// `None` while it's dry
+    rain: Option<Rain>,
+    /// how hard each player's controller should shake after the last tick
+    rumbles: Vec<Rumble>,
+    /// the only source of randomness the simulation may use
+    rng: Rng,
+    /// the player whose controller or wheel got unplugged, the level stopping until they
+    /// unpause
+    lost_controller: Option<usize>,
+    /// the player who paused during the tick, for the pause menu to open over the level
+    /// after it
+    pausing: Option<usize>,
+    /// who paused and where the pause menu hands back what they chose, while it's open
+    paused: Option<(usize, Outcome<Paused>)>,
+    /// drawing what the physics is up to over the world
+    debug_overlay: bool,
+    trail_spec: TrailSpec,
+    /// where each player's car has been lately
+    trails: Vec<Trail>,
+    /// drawing the trails and the racing line on the ground
+    show_trails: bool,
+    /// one per player when watching a replay, filming it in place of the players' cameras.
+    /// Those keep following along, as recorded aiming goes by them.
+    directors: Option<Vec<Director>>,
+    /// the file the track came from, to drive on it again as soon as it's saved
+    watch: Option<TrackWatch>,
+    setup: Setup,
+    /// recorded or played back, which has to play out the same from the same input every
+    /// time, so it stays on the track it started on
+    recorded: bool,
+    /// what the window's called, after the track
+    title: Option<String>,
+    /// driven from the editor, going back to it with the test drive key too
+    test_drive: bool,
+    /// as they were when the level started or last left, the players' cameras and cars
+    /// starting off with them
+    settings: Settings,
+    /// the first player's keys as the bindings have them, to show in the settings
+    keyboard: Vec<(Action, Scancode)>,
+    /// typed into by the first player, `None` for replays and recordings, which wouldn't
+    /// play out the same without what was done from it
+    console: Option<Console<Level>>,
+    /// picked from the console, for a level on it to take over once it's loaded
+    next_track: Option<String>,
 }
 
-struct Camera {
-    pub pos: Point2<f64>,
+/// What a level was made with, to start it over the same way
+#[derive(Clone)]
+struct Setup {
+    spec: CarSpec,
+    camera: CameraSpec,
+    players: usize,
+    towing: bool,
+    seed: u64,
 }
 
-impl Camera {
-    pub fn new() -> Camera {
-        Camera {
-            pos: Point2::new(1000., 700.),
-        }
+impl Level {
+    /// A level for 1 or 2 `players` on `track`, with the AI filling up the rest of the grid
+    pub fn new(
+        spec: CarSpec,
+        camera: CameraSpec,
+        track: Track,
+        players: usize,
+        towing: bool,
+        seed: u64,
+    ) -> Level {
+        let mut rng = Rng::new(seed);
+        let cars: Vec<_> = (0..FIELD_SIZE)
+            .map(|i| {
+                let mut slot = track.grid_slot(i);
+                // the rest of the field lines up a little unevenly, differently for every seed
+                if i > 0 {
+                    slot.position += slot.rotation * Vector2::new(0., rng.range(-40., 40.));
+                }
+                let mut car = Car::from_spec(spec.clone(), slot.position);
+                car.respawn(&slot);
+                car
+            })
+            .collect();
+        // the screen is split into side by side views, one for each player
+        let view = (SCREEN_DIMENSIONS.0 / players as i32, SCREEN_DIMENSIONS.1);
+        let cameras = cars[..players]
+            .iter()
+            .map(|car| Camera::new(car.center(), view, camera.clone()))
+            .collect();
+        let setup = Setup {
+            spec,
+            camera,
+            players,
+            towing,
+            seed,
+        };
+        let flames = vec![Playing::default(); cars.len()];
+        let car_sprites = vec![None; cars.len()];
+        let lap_timers = cars.iter().map(|_| LapTimer::default()).collect();
+        let standings = (0..cars.len()).collect();
+        // a track with a number of laps to it is raced, from a standing start
+        let start = track.laps.map(|_| RaceStart::new(cars.len()));
+        let time = TimeOfDay::new(track.start_hour, track.hours_per_second);
+        let loose_props = track.loose_props.iter().map(LooseProp::new).collect();
+        let title = track.name.as_ref().map(|name| match &track.author {
+            Some(author) => format!("{name} by {author}"),
+            None => name.clone(),
+        });
+        let mut level = Level {
+            cars,
+            players,
+            cameras,
+            track,
+            loose_props,
+            events: Vec::new(),
+            lap_timers,
+            drift_scores: (0..players).map(|_| DriftScore::default()).collect(),
+            off_track_warnings: vec![0; players],
+            missed_warnings: vec![0; players],
+            standings,
+            race_clock: 0,
+            finish_times: vec![None; FIELD_SIZE],
+            penalties: vec![0; FIELD_SIZE],
+            finished_for: 0,
+            results: None,
+            tracks: None,
+            garage: None,
+            start,
+            sounds: Vec::new(),
+            trailer: None,
+            skids: SkidMarks::default(),
+            smoke: Smoke::default(),
+            dust: Dust::default(),
+            sparks: Sparks::default(),
+            flames,
+            beacons: Playing::default(),
+            car_sprites,
+            background_sprites: Vec::new(),
+            decoration_sprites: Vec::new(),
+            minimap_sprite: None,
+            thumbnail: None,
+            images: Vec::new(),
+            time,
+            rain: None,
+            rumbles: vec![Rumble::default(); players],
+            rng,
+            lost_controller: None,
+            pausing: None,
+            paused: None,
+            debug_overlay: false,
+            trail_spec: TrailSpec::default(),
+            trails: (0..players).map(|_| Trail::default()).collect(),
+            show_trails: false,
+            directors: None,
+            watch: None,
+            setup,
+            recorded: false,
+            title,
+            test_drive: false,
+            settings: Settings::default(),
+            keyboard: Vec::new(),
+            console: Some(Level::console()),
+            next_track: None,
+        };
+        level.set_towing(towing);
+        level
     }
 
-    pub fn relative_rect(&self, rect: Rect) -> Rect {
-        Rect::new(
-            rect.x - (self.pos.x as i32 - SCREEN_DIMENSIONS.0 / 2),
-            rect.y - (self.pos.y as i32 - SCREEN_DIMENSIONS.1 / 2),
-            rect.width(),
-            rect.height(),
-        )
+    /// Hitches a trailer to the player's car or drops the one it has
+    fn set_towing(&mut self, towing: bool) {
+        self.trailer = towing.then(|| Trailer::hitched_to(&self.cars[0]));
     }
 
-    fn update(&mut self, car: &Car) {
-        self.pos = self.pos.coords.lerp(&car.center().coords, 0.2).into();
+    /// Starts or stops the rain, the track getting wet or drying off straight away
+    fn set_raining(&mut self, raining: bool) {
+        self.track.wet = raining;
+        self.rain = raining.then(Rain::default);
     }
-}
 
-enum CarSteering {
-    Left,
-    Right,
-    None,
-}
+    /// Draws the trails the way `spec` says, shown to start with if it says so
+    fn set_trail_spec(&mut self, spec: TrailSpec) {
+        self.show_trails = spec.shown;
+        self.trail_spec = spec;
+    }
 
-enum CarPedal {
-    Forward,
-    Backward,
-    None,
-}
+    /// Draws the minimap with the picture at `thumbnail` and keeps `images`, read from the
+    /// files of the track's pictures already, for loading the sprites with
+    fn set_pictures(&mut self, thumbnail: Option<PathBuf>, images: Vec<(PathBuf, Vec<u8>)>) {
+        self.thumbnail = thumbnail;
+        self.images = images;
+    }
 
-impl Car {
-    pub fn new() -> Car {
-        Car {
-            dimensions: Vector2::new(50., 100.),
-            pos: Point2::new(1000., 700.),
-            rotation: Rotation2::new(0.),
-            velocity: Vector2::zeros(),
+    /// Drives on whatever's saved to the track's file from now on, as soon as it's saved
+    fn watch_track(&mut self, path: impl AsRef<Path>) {
+        self.watch = Some(TrackWatch::new(path));
+    }
 
-            wheel_speed: 0.,
-            max_speed: 1.,
-            acceleration: 0.1,
+    /// Swaps the track for a new version of it, the cars carrying on where they are. Ones
+    /// left stuck in what's in the way now are put back on the track, the loose props go
+    /// back to where they stand on the new one and the laps start over if the checkpoints
+    /// changed. The decorations keep the pictures they had.
+    fn reload_track(&mut self, mut track: Track) {
+        track.wet = self.track.wet;
+        track.clock = self.track.clock;
+        let checkpoints_changed = track.checkpoints.len() != self.track.checkpoints.len()
+            || track
+                .checkpoints
+                .iter()
+                .zip(&self.track.checkpoints)
+                .any(|(new, old)| new.center != old.center || new.half_extents != old.half_extents);
+        self.track = track;
+        // drawn for the track as it was
+        self.minimap_sprite = None;
+        self.loose_props = self.track.loose_props.iter().map(LooseProp::new).collect();
+        if checkpoints_changed {
+            self.lap_timers = self.cars.iter().map(|_| LapTimer::default()).collect();
+        }
+        for i in 0..self.cars.len() {
+            let body = self.cars[i].obb();
+            let walled = self
+                .track
+                .walls
+                .iter()
+                .any(|wall| collision::obb_vs_segment(&body, wall).is_some());
+            let propped = self
+                .track
+                .props
+                .iter()
+                .any(|prop| collision::obb_vs_obb(&body, prop).is_some());
+            if walled || propped {
+                self.respawn_player(i);
+            }
         }
     }
 
-    pub fn center(&self) -> Point2<f64> {
-        self.pos + self.dimensions / 2.
+    /// The pause menu for `player` to open over the level, with the settings starting from
+    /// how they have things now
+    fn pause_menu(&mut self, player: usize) -> PauseMenu {
+        let mut settings = self.settings.clone();
+        settings.assists = self.cars[player].assists();
+        settings.camera_mode = self.cameras[player].mode();
+        let menu = PauseMenu::new(player, settings, self.keyboard.clone());
+        self.paused = Some((player, menu.outcome()));
+        menu
     }
 
-    pub fn rect(&self) -> Rect {
-        Rect::new(
-            self.pos.x as i32,
-            self.pos.y as i32,
-            self.dimensions.x as u32,
-            self.dimensions.y as u32,
-        )
+    /// Does what `player` picked from the pause menu once it's over, saying what's played
+    /// next
+    fn unpause(&mut self, player: usize, paused: Paused) -> Transition {
+        if let Some(settings) = paused.settings {
+            self.apply_settings(player, &settings);
+            // a replay's were changed by whoever recorded it, not whoever's watching
+            if self.directors.is_none() {
+                if let Err(error) = settings.save(settings::FILE) {
+                    eprintln!("couldn't keep the settings in {}: {error}", settings::FILE);
+                }
+            }
+            self.settings = settings;
+        }
+        match paused.choice {
+            PauseChoice::Resume => Transition::Stay,
+            PauseChoice::Restart => Transition::Replace(Box::new(self.restarted())),
+            PauseChoice::Quit => Transition::Pop,
+        }
     }
 
-    fn update(&mut self, pedal: CarPedal, steering: CarSteering) {
-        if let CarPedal::Forward = pedal {
-            self.wheel_speed += self.acceleration;
-            let max_backwards_speed = -5.;
-            self.wheel_speed = self.wheel_speed.clamp(max_backwards_speed, self.max_speed);
-        } else if let CarPedal::Backward = pedal {
-            // only gonna implement brake for now, but will also need to detect when to go into
-            // reverse some time
-            // let brake_force = 0.5;
-            // self.velocity += -self.velocity.normalize() * brake_force;
-            self.wheel_speed *= 0.5;
-            if self.wheel_speed < 0.1 {
-                self.wheel_speed = 0.;
+    /// Starts off from `settings` and changes them from the pause menu, showing `keyboard`
+    /// for the first player's keys that haven't been rebound. Every player starts with their
+    /// assists and camera, unless the level's recorded or played back, as the recording
+    /// doesn't know what they were.
+    fn set_settings(&mut self, settings: Settings, keyboard: Vec<(Action, Scancode)>) {
+        if !self.recorded {
+            for player in 0..self.players {
+                self.apply_settings(player, &settings);
             }
         }
+        self.settings = settings;
+        self.keyboard = keyboard;
+    }
+
+    /// Puts the settings that are up to the level into effect for `player`
+    fn apply_settings(&mut self, player: usize, settings: &Settings) {
+        *self.cars[player].assists_mut() = settings.assists;
+        self.cameras[player].set_mode(settings.camera_mode);
+    }
+
+    /// Starts the level over from the grid, everything but how it's drawn and watched as if
+    /// it had been made afresh
+    fn restarted(&mut self) -> Level {
+        let mut track = std::mem::replace(&mut self.track, Track::new());
+        track.clock = 0;
+        let mut level = self.on_track(track);
+        level.thumbnail = self.thumbnail.take();
+        level.watch = self.watch.take();
+        level
+    }
 
-        self.pos -= self.dimensions / 2.; // to center the rotation
-        let rotation_strength = (self.rotation * self.velocity).magnitude().abs();
-        if let CarSteering::Left = steering {
-            self.rotation *= Rotation2::new(-0.005 * rotation_strength);
-        } else if let CarSteering::Right = steering {
-            self.rotation *= Rotation2::new(0.005 * rotation_strength);
+    /// A new level on `track` set up like this one, with the same cars, players, weather and
+    /// settings. Its sprites are loaded again once it's on, the ones already loaded being
+    /// handed out again.
+    fn on_track(&mut self, track: Track) -> Level {
+        let Setup {
+            spec,
+            camera,
+            players,
+            towing,
+            seed,
+        } = self.setup.clone();
+        let mut level = Level::new(spec, camera, track, players, towing, seed);
+        level.set_raining(self.rain.is_some());
+        level.debug_overlay = self.debug_overlay;
+        level.trail_spec = self.trail_spec.clone();
+        level.show_trails = self.show_trails;
+        level.settings = self.settings.clone();
+        level.keyboard = std::mem::take(&mut self.keyboard);
+        // the players keep the assists and camera they had
+        for player in 0..players {
+            *level.cars[player].assists_mut() = self.cars[player].assists();
+            level.cameras[player].set_mode(self.cameras[player].mode());
+        }
+        level.recorded = self.recorded;
+        level.test_drive = self.test_drive;
+        level.tracks = self.tracks.take();
+        level.garage = self.garage.take();
+        level.console = self.console.take();
+        if self.directors.is_some() {
+            level.direct_replay();
         }
-        self.pos += self.dimensions / 2.; // to bring the car back to where it should be
+        level
+    }
 
-        // friction
-        let mut local_velocity = self.rotation.inverse() * self.velocity;
+    /// How the race went, for the results
+    fn results(&self) -> Results {
+        let mut finishes: Vec<_> = (0..self.cars.len())
+            .map(|car| Finish {
+                car,
+                time: self.finish_times[car],
+            })
+            .collect();
+        // the ones over the line in the order they crossed it, the rest by how far round
+        let place = |car| self.standings.iter().position(|&other| other == car);
+        finishes.sort_by_key(|finish| (finish.time.is_none(), finish.time, place(finish.car)));
+        let players = (0..self.players)
+            .map(|player| {
+                let timer = &self.lap_timers[player];
+                PlayerResult {
+                    laps: timer.laps().to_vec(),
+                    best_sectors: timer.best_sectors().to_vec(),
+                    drift_score: self.drift_scores[player].banked(),
+                    penalties: self.penalties[player],
+                }
+            })
+            .collect();
+        let change_track = self.tracks.is_some();
+        Results::new(
+            finishes,
+            players,
+            change_track,
+            change_track || self.garage.is_some(),
+        )
+    }
 
-        let vertical_friction = 0.02;
-        local_velocity.y -= self.wheel_speed;
+    /// The menus to set up the next race from, going on to a level set up like this one: the
+    /// garage first when there's a choice of car, and the track menu when there are tracks
+    /// to choose from, the track staying the same without them. `None` without either.
+    fn menus(&mut self) -> Option<Box<dyn Scene>> {
+        let tracks = self.tracks.clone();
+        let garage = self.garage.as_deref().map(Garage::new);
+        let mut next = self.restarted();
+        match (garage, tracks) {
+            (Some(garage), tracks) => Some(Box::new(garage.then(move |spec| {
+                next.setup.spec = spec;
+                match tracks {
+                    Some(tracks) => Box::new(TrackMenu::new(&tracks).then_drive(next)),
+                    None => Box::new(next.restarted()),
+                }
+            }))),
+            (None, Some(tracks)) => Some(Box::new(TrackMenu::new(&tracks).then_drive(next))),
+            (None, None) => None,
+        }
+    }
 
-        self.wheel_speed *= 0.98 - vertical_friction;
-        local_velocity.y *= 1. - vertical_friction;
+    /// Films the level like a replay from now on
+    fn direct_replay(&mut self) {
+        self.directors = Some(self.cameras.iter().cloned().map(Director::new).collect());
+        self.console = None;
+    }
 
-        let horizontal_friction = 0.05;
-        local_velocity.x *= 1.0 - horizontal_friction;
+    /// The developer console, with the commands it has for a level
+    fn console() -> Console<Level> {
+        let mut console = Console::default();
+        console.register("set", "set car.(setting) (number)", Level::set_command);
+        console.register("spawn", "spawn ai (count)", Level::spawn_command);
+        console.register("load", "load track (name)", Level::load_command);
+        console.register("tp", "tp (x) (y)", Level::teleport_command);
+        console
+    }
 
-        self.velocity = self.rotation * local_velocity;
-        self.pos += self.velocity;
+    /// Tunes the first player's car, by the name of the spec's field
+    fn set_command(&mut self, arguments: &[&str]) -> Result<String, String> {
+        let [setting, number] = arguments else {
+            return Err("set takes a setting and a number".to_string());
+        };
+        let setting = setting.to_ascii_lowercase();
+        let Some(name) = setting.strip_prefix("car.") else {
+            return Err("only the car can be set, like car.engine_force".to_string());
+        };
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("{number} isn't a number"))?;
+        let value = self.cars[0]
+            .spec_mut()
+            .number_mut(name)
+            .ok_or_else(|| format!("the car has nothing called {name}"))?;
+        *value = number;
+        Ok(format!("car.{name} is {number} now"))
     }
-}
 
-trait Scene {
-    fn update(&mut self, events: &mut EventPump) -> Result<Option<impl Scene>, ()>;
-    fn render<T: RenderTarget>(
-        &self,
-        canvas: &mut Canvas<T>,
-        texture_creator: &TextureCreator<WindowContext>,
-    );
-}
+    /// Lines up more AI cars at the back of the grid, one if not told how many
+    fn spawn_command(&mut self, arguments: &[&str]) -> Result<String, String> {
+        let (kind, count) = match arguments {
+            [kind] => (kind, "1"),
+            [kind, count] => (kind, *count),
+            _ => return Err("spawn takes ai and how many".to_string()),
+        };
+        if !kind.eq_ignore_ascii_case("ai") {
+            return Err(format!("there's no {kind} to spawn, only ai"));
+        }
+        let count: usize = count
+            .parse()
+            .map_err(|_| format!("{count} isn't a number"))?;
+        let count = count.min(MAX_CARS - self.cars.len());
+        self.spawn_ai(count);
+        Ok(format!("{count} more, {} cars now", self.cars.len()))
+    }
 
-struct Level {
-    car: Car,
-    camera: Camera,
-}
+    /// Drives a track from a file, or one in the tracks directory by its name
+    fn load_command(&mut self, arguments: &[&str]) -> Result<String, String> {
+        let [kind, name] = arguments else {
+            return Err("load takes track and its name".to_string());
+        };
+        if !kind.eq_ignore_ascii_case("track") {
+            return Err(format!("there's no {kind} to load, only a track"));
+        }
+        let mut paths = vec![PathBuf::from(name)];
+        if let Some(directory) = &self.tracks {
+            paths.extend(
+                ["", ".toml", ".tmx"].map(|extension| directory.join(format!("{name}{extension}"))),
+            );
+        }
+        let path = paths
+            .into_iter()
+            .find(|path| path.is_file())
+            .ok_or_else(|| format!("there's no track called {name}"))?;
+        let path = path.to_string_lossy().into_owned();
+        let reply = format!("loading {path}");
+        self.next_track = Some(path);
+        Ok(reply)
+    }
 
-impl Level {
-    pub fn new() -> Level {
-        Level {
-            car: Car::new(),
-            camera: Camera::new(),
+    /// Puts the first player's car at a spot on the track, in pixels, facing the way it was
+    fn teleport_command(&mut self, arguments: &[&str]) -> Result<String, String> {
+        let [x, y] = arguments else {
+            return Err("tp takes an x and a y".to_string());
+        };
+        let parse = |number: &str| {
+            number
+                .parse::<f64>()
+                .map_err(|_| format!("{number} isn't a number"))
+        };
+        let position = Point2::new(parse(x)?, parse(y)?);
+        let rotation = self.cars[0].rotation();
+        self.cars[0].respawn(&RespawnPoint { position, rotation });
+        if self.trailer.is_some() {
+            self.set_towing(true);
         }
+        Ok(format!("moved to {x}, {y}"))
     }
 
-    fn draw_checkerboard<T: RenderTarget>(&self, canvas: &mut Canvas<T>) {
-        let square_size: u32 = 125;
-        let (width, height) = canvas.output_size().unwrap();
+    /// Lines up `count` more AI cars on the grid behind the rest
+    fn spawn_ai(&mut self, count: usize) {
+        for _ in 0..count {
+            let car = self.cars.len();
+            let slot = self.track.grid_slot(car);
+            let mut spawned = Car::from_spec(self.setup.spec.clone(), slot.position);
+            spawned.respawn(&slot);
+            self.cars.push(spawned);
+            // drawn like the AI cars there already
+            let sprite = self.car_sprites.last().copied().flatten();
+            self.car_sprites.push(sprite);
+            self.flames.push(Playing::default());
+            self.lap_timers.push(LapTimer::default());
+            self.standings.push(car);
+            self.finish_times.push(None);
+            self.penalties.push(0);
+        }
+    }
 
-        (0..width).step_by(square_size as usize).for_each(|x| {
-            (0..height).step_by(square_size as usize).for_each(|y| {
-                canvas.set_draw_color(if (x / square_size + y / square_size) % 2 == 0 {
-                    Color::RGB(60, 180, 35)
-                } else {
-                    Color::RGB(60, 200, 35)
-                });
-                canvas
-                    .fill_rect(self.camera.relative_rect(Rect::new(
-                        x as i32,
-                        y as i32,
-                        square_size,
-                        square_size,
-                    )))
-                    .unwrap();
+    /// Puts a player back on the nearest bit of track that isn't blocked by another car
+    fn respawn_player(&mut self, player: usize) {
+        let (player_car, others) = {
+            let (before, rest) = self.cars.split_at_mut(player);
+            let (player_car, after) = rest.split_first_mut().unwrap();
+            (player_car, before.iter().chain(after.iter()))
+        };
+        let clearance = 150.;
+        let distance = |position: Point2<f64>| (position - player_car.center()).norm();
+        let nearest = self
+            .track
+            .respawn_points
+            .iter()
+            .filter(|point| {
+                others
+                    .clone()
+                    .all(|car| (car.center() - point.position).norm() > clearance)
             })
-        })
+            .min_by(|a, b| distance(a.position).total_cmp(&distance(b.position)));
+        if let Some(point) = nearest {
+            player_car.respawn(point);
+            if player == 0 && self.trailer.is_some() {
+                self.set_towing(true);
+            }
+        }
     }
-}
 
-impl Scene for Level {
-    fn update(&mut self, events: &mut EventPump) -> Result<Option<Level>, ()> {
-        for event in events.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape | Keycode::Q),
-                    ..
-                } => return Err(()),
-                _ => {}
+    fn run_command(&mut self, player: usize, command: Command) {
+        let car = &mut self.cars[player];
+        match command {
+            Command::ShiftUp => car.shift(Shift::Up),
+            Command::ShiftDown => car.shift(Shift::Down),
+            Command::ToggleManualShifting => car.toggle_manual_shifting(),
+            Command::ToggleTractionControl => {
+                let assists = car.assists_mut();
+                assists.traction_control = !assists.traction_control;
+            }
+            Command::ToggleAbs => {
+                let assists = car.assists_mut();
+                assists.abs = !assists.abs;
             }
+            Command::ToggleStabilityControl => {
+                let assists = car.assists_mut();
+                assists.stability_control = !assists.stability_control;
+            }
+            Command::Respawn => self.respawn_player(player),
+            // a free pit stop until there are game modes with rules for it
+            Command::Repair => car.repair(1.),
+            // only the first player's car has a hitch
+            Command::ToggleTowing if player == 0 => self.set_towing(self.trailer.is_none()),
+            Command::NextCameraMode => self.cameras[player].next_mode(),
+            Command::ToggleFreeCamera => self.cameras[player].toggle_free(),
+            Command::ZoomIn => self.cameras[player].zoom_by(1.25),
+            Command::ZoomOut => self.cameras[player].zoom_by(0.8),
+            Command::TogglePause | Command::Back => match self.lost_controller {
+                Some(_) => self.lost_controller = None,
+                None => {
+                    self.pausing.get_or_insert(player);
+                }
+            },
+            Command::ControllerLost => self.lost_controller = Some(player),
+            Command::ToggleRain => self.set_raining(self.rain.is_none()),
+            Command::ToggleDebugOverlay => self.debug_overlay = !self.debug_overlay,
+            Command::ToggleTrails => self.show_trails = !self.show_trails,
+            Command::ToggleTowing
+            | Command::Quit
+            | Command::ToggleFullscreen
+            | Command::ToggleFrameCounter
+            | Command::Screenshot
+            | Command::NextFilter
+            | Command::NextTool
+            | Command::Undo
+            | Command::TestDrive
+            | Command::SaveTrack
+            | Command::ToggleConsole => {}
         }
+    }
 
-        let key_state = events.keyboard_state();
+    /// Advances the simulation by one tick. Reads nothing but `inputs`, one for each player,
+    /// and the level itself, so it never depends on timing or on how fast we render.
+    fn step(&mut self, inputs: &[TickInput]) {
+        self.sounds.clear();
+        for (i, input) in inputs.iter().enumerate().take(self.players) {
+            for command in &input.commands {
+                // a stopped level only listens for being unpaused
+                let heard = self.lost_controller.is_none()
+                    || matches!(
+                        command,
+                        Command::TogglePause | Command::Back | Command::ControllerLost
+                    );
+                if heard {
+                    self.run_command(i, *command);
+                }
+            }
+        }
+        if self.lost_controller.is_some() || self.pausing.is_some() {
+            // nothing moves, so nothing should shake either
+            self.rumbles.fill(Rumble::default());
+            return;
+        }
 
-        let pedal = if key_state.is_scancode_pressed(Scancode::W) {
-            CarPedal::Forward
-        } else if key_state.is_scancode_pressed(Scancode::S) {
-            CarPedal::Backward
-        } else {
-            CarPedal::None
-        };
-        let steering = if key_state.is_scancode_pressed(Scancode::A)
-            && !key_state.is_scancode_pressed(Scancode::D)
+        // drafting off of whichever car gives the best tow
+        let slipstreams: Vec<_> = self
+            .cars
+            .iter()
+            .enumerate()
+            .map(|(i, car)| {
+                self.cars
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, leader)| car.slipstream_from(leader))
+                    .fold(0., f64::max)
+            })
+            .collect();
+        for (car, slipstream) in self.cars.iter_mut().zip(slipstreams) {
+            car.set_slipstream(slipstream);
+        }
+
+        if let Some(start) = &mut self.start {
+            let throttles: Vec<_> = (0..self.cars.len())
+                .map(|i| match inputs.get(i) {
+                    Some(input) if i < self.players => input.car.pedals.throttle,
+                    _ => 0.,
+                })
+                .collect();
+            start.update(&throttles, &mut self.sounds);
+        }
+
+        self.track.update();
+        // a race is timed from the lights going green
+        if self.track.laps.is_some() && self.start.as_ref().is_none_or(RaceStart::is_green) {
+            self.race_clock += 1;
+        }
+        let was_off_track: Vec<_> = self.cars.iter().map(Car::is_off_track).collect();
+        let aims: Vec<_> = inputs
+            .iter()
+            .take(self.players)
+            .enumerate()
+            .map(|(i, input)| input.aim.map(|aim| self.aim_point(i, aim)))
+            .collect();
+        for (i, car) in self.cars.iter_mut().enumerate() {
+            let held = self.start.as_ref().is_some_and(|start| start.is_held(i));
+            let input = match inputs.get(i) {
+                // the controls do nothing on the grid
+                _ if held => CarInput {
+                    handbrake: true,
+                    ..CarInput::default()
+                },
+                // a player flying the camera about leaves their car to roll
+                Some(input) if i < self.players && !self.cameras[i].is_free() => {
+                    let mut car_input = input.car;
+                    if let Some(target) = aims[i] {
+                        car_input.steering = car.steering_toward(target);
+                    }
+                    car_input
+                }
+                _ if i >= self.players => ai_input(car, &self.track),
+                _ => CarInput::default(),
+            };
+            car.update(&input, &self.track);
+        }
+        if let Some(start) = self.start.take_if(|start| start.is_over()) {
+            for (car, penalties) in self.penalties.iter_mut().enumerate() {
+                if start.jumped(car) {
+                    *penalties += 1;
+                }
+            }
+        }
+        if let Some(trailer) = &mut self.trailer {
+            trailer.update(&mut self.cars[0], &self.track);
+        }
+        let knocks = loose_props::update(&mut self.loose_props, &mut self.cars, &self.track);
+
+        self.time.update();
+        if let Some(rain) = &mut self.rain {
+            rain.update();
+        }
+        self.skids.update(&self.cars);
+        for (trail, car) in self.trails.iter_mut().zip(&self.cars) {
+            trail.update(car.center(), &self.trail_spec);
+        }
+        self.smoke.update();
+        self.dust.update();
+        self.sparks.update();
+        self.beacons.update();
+        for (car, flame) in self.cars.iter().zip(&mut self.flames) {
+            if car.nitro().is_active() {
+                flame.update();
+            } else {
+                flame.restart();
+            }
+        }
+        for car in &self.cars {
+            let point = car.impact_point();
+            let velocity = car.point_velocity(point);
+            self.sparks
+                .emit(&mut self.rng, point, velocity, car.impact());
+            if car.is_airborne() {
+                continue;
+            }
+            for wheel in car.wheel_positions() {
+                let surface = self.track.terrain.surface_at(wheel);
+                let velocity = car.point_velocity(wheel);
+                self.dust.emit(&mut self.rng, surface, wheel, velocity);
+            }
+            let [.., rear_left, rear_right] = car.wheel_positions();
+            let [.., left_slip, right_slip] = car.wheel_slips();
+            for (wheel, slip) in [(rear_left, left_slip), (rear_right, right_slip)] {
+                let velocity = car.point_velocity(wheel);
+                self.smoke.emit(&mut self.rng, wheel, velocity, slip);
+            }
+        }
+
+        self.events.clear();
+        for (i, car) in self.cars.iter().enumerate() {
+            match (was_off_track[i], car.is_off_track()) {
+                (false, true) => self.events.push(LevelEvent::LeftTrack { car: i }),
+                (true, false) => self.events.push(LevelEvent::RejoinedTrack { car: i }),
+                _ => {}
+            }
+        }
+        for (i, (timer, car)) in self.lap_timers.iter_mut().zip(&self.cars).enumerate() {
+            if let Some(crossing) = timer.update(car.center(), &self.track.checkpoints) {
+                self.events.push(LevelEvent::Crossed { car: i, crossing });
+            }
+        }
+        self.events.extend(
+            knocks
+                .into_iter()
+                .map(|knock| LevelEvent::Knocked { knock }),
+        );
+        for warning in self
+            .off_track_warnings
+            .iter_mut()
+            .chain(&mut self.missed_warnings)
         {
-            CarSteering::Left
-        } else if key_state.is_scancode_pressed(Scancode::D)
-            && !key_state.is_scancode_pressed(Scancode::A)
+            *warning = warning.saturating_sub(1);
+        }
+        for event in &self.events {
+            match *event {
+                LevelEvent::LeftTrack { car } if car < self.players => {
+                    self.off_track_warnings[car] = TICKS_PER_SECOND as u32
+                }
+                LevelEvent::RejoinedTrack { car } if car < self.players => {
+                    self.off_track_warnings[car] = 0
+                }
+                LevelEvent::Crossed { car, crossing } => {
+                    // cutting the track doesn't pay
+                    if crossing.skipped > 0 && car < self.players {
+                        self.drift_scores[car].forfeit();
+                        self.missed_warnings[car] = 2 * TICKS_PER_SECOND as u32;
+                    }
+                    if crossing.skipped > 0 {
+                        self.penalties[car] += 1;
+                    }
+                    let done = self.track.laps.is_some_and(|laps| {
+                        crossing.checkpoint == 0 && self.lap_timers[car].laps_finished() >= laps
+                    });
+                    if done && self.finish_times[car].is_none() {
+                        self.finish_times[car] = Some(self.race_clock);
+                    }
+                    // whoever got somewhere first stays ahead of the ones getting there later
+                    let timers = &self.lap_timers;
+                    self.standings
+                        .sort_by_key(|&car| std::cmp::Reverse(timers[car].progress()));
+                }
+                LevelEvent::Knocked { knock } if knock.car < self.players => {
+                    let score = &mut self.drift_scores[knock.car];
+                    match knock.kind.points() {
+                        Some(points) => score.bonus(points, knock.position),
+                        // the cones mark the way, so hitting one costs like cutting the track
+                        None => {
+                            score.forfeit();
+                            self.penalties[knock.car] += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        // the results come up a while after the last player's over the line
+        if self.finish_times[..self.players]
+            .iter()
+            .all(Option::is_some)
         {
-            CarSteering::Right
-        } else {
-            CarSteering::None
-        };
+            self.finished_for += 1;
+        }
+
+        for i in 1..self.cars.len() {
+            let (done, rest) = self.cars.split_at_mut(i);
+            for other in rest {
+                done[i - 1].collide_with(other);
+            }
+        }
+        for (score, car) in self.drift_scores.iter_mut().zip(&self.cars) {
+            score.update(car);
+        }
+
+        for (rumble, car) in self.rumbles.iter_mut().zip(&self.cars) {
+            // the light motor buzzes while the tyres slide, the heavy one thumps on hits and
+            // dies down again over a few ticks
+            let sliding =
+                ((car.drift_angle().abs() - 0.1) / 0.5).clamp(0., 1.) * (car.speed() / 6.).min(1.);
+            let spinning = (car.wheelspin() / 6.).min(1.);
+            *rumble = Rumble {
+                low: (rumble.low * 0.85).max((car.impact() / 8.).min(1.)),
+                high: if car.is_airborne() {
+                    0.
+                } else {
+                    sliding.max(spinning)
+                },
+            };
+        }
 
-        self.car.update(pedal, steering);
-        self.camera.update(&self.car);
+        for ((camera, car), input) in self.cameras.iter_mut().zip(&self.cars).zip(inputs) {
+            if camera.is_free() {
+                let pedals = input.car.pedals;
+                camera.fly(Vector2::new(
+                    input.car.steering,
+                    pedals.brake - pedals.throttle,
+                ));
+            } else {
+                camera.update(car, &self.track);
+            }
+        }
+        for (director, car) in self.directors.iter_mut().flatten().zip(&self.cars) {
+            director.update(car, &self.track);
+        }
+    }
 
-        Ok(None)
+    /// Where in the world `player` points at, `aim` being a share of the whole screen's width
+    /// and height
+    fn aim_point(&self, player: usize, aim: Point2<f64>) -> Point2<f64> {
+        let camera = &self.cameras[player];
+        let (view_width, view_height) = camera.view;
+        let on_screen = Point2::new(
+            aim.x * SCREEN_DIMENSIONS.0 as f64,
+            aim.y * SCREEN_DIMENSIONS.1 as f64,
+        );
+        // the views sit side by side
+        let in_view = on_screen - Vector2::new((player as i32 * view_width) as f64, 0.);
+        camera.to_world(Point2::new(
+            in_view.x.clamp(0., view_width as f64),
+            in_view.y.clamp(0., view_height as f64),
+        ))
     }
 
-    fn render<T: RenderTarget>(
+    /// Draws what `player`'s camera sees along with their HUD
+    fn render_view<T: RenderTarget>(
         &self,
         canvas: &mut Canvas<T>,
-        texture_creator: &TextureCreator<WindowContext>,
+        assets: &Assets,
+        player: usize,
+        alpha: f64,
     ) {
-        canvas.set_draw_color(Color::GREY);
-        canvas.clear();
-        self.draw_checkerboard(canvas);
+        let camera = match &self.directors {
+            Some(directors) => directors[player].camera(),
+            None => &self.cameras[player],
+        }
+        .interpolated(alpha);
+        self.draw_turned_world(canvas, assets, &camera, alpha);
+        if let Some(rain) = &self.rain {
+            draw_rain(canvas, rain, camera.view, alpha);
+        }
 
-        let mut car_texture = texture_creator
-            .create_texture_target(None, self.car.rect().width(), self.car.rect().height())
-            .unwrap();
+        self.draw_nitro_meter(canvas, player, camera.view);
+        self.draw_speedometer(canvas, player, camera.view);
+        self.draw_drift_score(canvas, player, &camera);
+        self.draw_lap_times(canvas, player, camera.view);
+        self.draw_minimap(canvas, assets, player, camera.view);
+        self.draw_tires(canvas, player, camera.view);
+        self.draw_off_track_warning(canvas, player, camera.view);
+        self.draw_countdown(canvas, player, camera.view);
+        self.draw_mirror(canvas, assets, player, camera.view, alpha);
+    }
+
+    /// Fills `camera`'s view with the world, turned to the camera's heading
+    fn draw_turned_world<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        camera: &Camera,
+        alpha: f64,
+    ) {
+        let (width, height) = camera.view;
+
+        // the world is drawn unturned on a square that covers the view at any angle, which
+        // then gets turned to the camera's heading
+        let side = ((width * width + height * height) as f64).sqrt().ceil() as i32;
+        let mut world_camera = camera.clone();
+        world_camera.view = (side, side);
+        let mut world = assets.target(side as u32, side as u32);
         canvas
-            .with_texture_canvas(&mut car_texture, |texture_canvas| {
-                texture_canvas.set_draw_color(Color::RED);
-                texture_canvas.clear();
+            .with_texture_canvas(&mut world, |world_canvas| {
+                self.draw_world(world_canvas, assets, &world_camera, alpha)
             })
             .unwrap();
-
-        canvas.set_draw_color(Color::RED);
-        // let mut car_rect = self.car.rect();
-        // car_rect.reposition(self.camera.relative_rect(car_rect.top_left()));
+        let mut area = Rect::new(0, 0, side as u32, side as u32);
+        area.center_on(Point::new(width / 2, height / 2));
         canvas
             .copy_ex(
-                &car_texture,
+                &world,
                 None,
-                // Some(self.car.rect()),
-                Some(self.camera.relative_rect(self.car.rect())),
-                self.car.rotation.angle() * 180. / std::f64::consts::PI,
-                // Some(self.camera.relative_rect(self.car.rect()).top_left()),
+                area,
+                -camera.rotation().angle().to_degrees(),
                 None,
                 false,
                 false,
             )
             .unwrap();
+        assets.give_back(world);
     }
-}
 
-fn main() {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
+    /// A rear-view mirror at the top of the player's view, to see who's coming up behind
+    fn draw_mirror<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        player: usize,
+        view: (i32, i32),
+        alpha: f64,
+    ) {
+        let size = (view.0 / 4, view.1 / 8);
+        let Some(camera) = self.cameras[player].mirror(&self.cars[player], alpha, size) else {
+            return;
+        };
+        let mut mirror = assets.target(size.0 as u32, size.1 as u32);
+        canvas
+            .with_texture_canvas(&mut mirror, |mirror_canvas| {
+                self.draw_turned_world(mirror_canvas, assets, &camera, alpha)
+            })
+            .unwrap();
 
-    let window = video_subsystem
-        .window("Sdl2 test", 2550, 1440)
-        .build()
-        .unwrap();
+        let margin = 20;
+        let area = Rect::new((view.0 - size.0) / 2, margin, size.0 as u32, size.1 as u32);
+        // flipped, as mirrors show things the wrong way round
+        canvas
+            .copy_ex(&mirror, None, area, 0., None, true, false)
+            .unwrap();
+        assets.give_back(mirror);
+        canvas.set_draw_color(Color::RGB(40, 40, 40));
+        canvas.draw_rect(area).unwrap();
+    }
 
-    let mut canvas = window.into_canvas().build().unwrap();
-    let texture_creator = canvas.texture_creator();
-    let mut event_pump = sdl_context.event_pump().unwrap();
+    /// Everything in the world as `camera` sees it, leaving its rotation to the caller
+    fn draw_world<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        camera: &Camera,
+        alpha: f64,
+    ) {
+        canvas.set_draw_color(Color::GREY);
+        canvas.clear();
+        self.draw_backgrounds(canvas, assets, camera);
+        self.draw_ground(canvas, camera);
+        self.draw_hazards(canvas, assets, camera);
+        if self.track.wet {
+            self.draw_wet(canvas, assets, camera);
+        }
+        self.draw_skids(canvas, camera);
+        if self.show_trails {
+            self.draw_trails(canvas, camera);
+        }
+        self.draw_obstacles(canvas, camera);
+        self.draw_loose_props(canvas, camera, alpha);
+        self.draw_decorations(canvas, assets, camera);
+        self.draw_dust(canvas, camera);
+        self.draw_smoke(canvas, camera);
+
+        if let Some(trailer) = &self.trailer {
+            self.draw_trailer(canvas, assets, camera, trailer, alpha);
+        }
+        for i in 0..self.cars.len() {
+            self.draw_car(canvas, assets, camera, i, alpha);
+        }
+        self.draw_night(canvas, assets, camera, alpha);
+        // glowing in the dark
+        self.draw_beacons(canvas, assets, camera);
+        self.draw_sparks(canvas, camera);
+        if self.debug_overlay {
+            self.draw_debug_overlay(canvas, camera);
+        }
+    }
+
+    /// The physics as of the last tick, so it runs a little behind the smoothed out cars:
+    /// - what can be hit, outlined in magenta
+    /// - where each car is heading in white, and where it's going and how fast in cyan, as
+    ///   far as it gets in 10 ticks
+    /// - how fast each tyre slides over the ground, from green for gripping to red for
+    ///   leaving skid marks
+    /// - where the camera is in yellow, and the point it's easing towards
+    fn draw_debug_overlay<T: RenderTarget>(&self, canvas: &mut Canvas<T>, camera: &Camera) {
+        let to_screen = |point: Point2<f64>| camera.relative_point(point);
+        let outline = |canvas: &mut Canvas<T>, obb: &Obb| {
+            let corners = obb.corners().map(to_screen);
+            let outline = [corners[0], corners[1], corners[2], corners[3], corners[0]];
+            canvas.draw_flines(&outline[..]).unwrap();
+        };
+
+        canvas.set_draw_color(Color::MAGENTA);
+        for wall in &self.track.walls {
+            canvas
+                .draw_fline(to_screen(wall.a), to_screen(wall.b))
+                .unwrap();
+        }
+        for prop in &self.track.props {
+            outline(canvas, prop);
+        }
+        for car in &self.cars {
+            outline(canvas, &car.obb());
+        }
+        if let Some(trailer) = &self.trailer {
+            outline(canvas, &trailer.obb());
+        }
+
+        for car in &self.cars {
+            let center = car.center();
+            canvas.set_draw_color(Color::WHITE);
+            let heading = car.rotation() * Vector2::new(0., -60.);
+            canvas
+                .draw_fline(to_screen(center), to_screen(center + heading))
+                .unwrap();
+            canvas.set_draw_color(Color::CYAN);
+            let velocity = car.point_velocity(center) * 10.;
+            canvas
+                .draw_fline(to_screen(center), to_screen(center + velocity))
+                .unwrap();
+
+            for (wheel, slip) in car.wheel_positions().into_iter().zip(car.wheel_slips()) {
+                let redness = (slip / skids::SLIP_THRESHOLD).min(1.);
+                canvas.set_draw_color(Color::RGB(
+                    (255. * redness) as u8,
+                    (255. * (1. - redness)) as u8,
+                    0,
+                ));
+                // across the way the tyre's sliding, as long as it's sliding fast
+                let sliding = car.point_velocity(wheel).try_normalize(f64::EPSILON);
+                let across = sliding.map_or(Vector2::zeros(), |direction| {
+                    Vector2::new(-direction.y, direction.x)
+                });
+                let reach = across * (4. + slip * 6.);
+                canvas
+                    .draw_fline(to_screen(wheel - reach), to_screen(wheel + reach))
+                    .unwrap();
+            }
+        }
 
-    let mut level = Level::new();
-    loop {
-        let mut texture = texture_creator
-            .create_texture_target(None, 1920, 1080)
+        canvas.set_draw_color(Color::YELLOW);
+        let target = to_screen(camera.target());
+        let position = to_screen(camera.pos);
+        canvas
+            .draw_fline(target.offset(-10., -10.), target.offset(10., 10.))
             .unwrap();
         canvas
-            .with_texture_canvas(&mut texture, |texture_canvas| {
-                level.render(texture_canvas, &texture_creator)
-            })
+            .draw_fline(target.offset(-10., 10.), target.offset(10., -10.))
+            .unwrap();
+        canvas
+            .draw_frect(FRect::from_center(position, 12., 12.))
             .unwrap();
+        canvas.draw_fline(position, target).unwrap();
+    }
 
-        canvas.copy(&texture, None, None).unwrap();
-        canvas.present();
+    /// Darkens the world by the time of day, then lights it back up in front of the cars'
+    /// headlights and behind their tail lights
+    fn draw_night<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        camera: &Camera,
+        alpha: f64,
+    ) {
+        let tint = self.time.tint();
+        if tint == Color::WHITE {
+            return;
+        }
+        canvas.set_blend_mode(BlendMode::Mod);
+        canvas.set_draw_color(tint);
+        canvas.fill_rect(None).unwrap();
+        canvas.set_blend_mode(BlendMode::None);
 
-        if let Err(_) = level.update(&mut event_pump) {
-            break;
+        let brightness = self.time.lights();
+        if brightness == 0. {
+            return;
+        }
+        let beam = Vector2::new(90., 280.);
+        for car in &self.cars {
+            let center = car.interpolated_center(alpha);
+            let rotation = car.interpolated_rotation(alpha);
+            let angle = rotation.angle().to_degrees();
+            let half = car.spec().dimensions / 2.;
+            for side in [-1., 1.] {
+                // shining forwards from the front corners, the beam starting at its bottom
+                let lamp = center + rotation * Vector2::new(side * half.x * 0.6, -half.y);
+                let beam_center = lamp + rotation * Vector2::new(0., -beam.y / 2.);
+                let mut cone = assets.light_cone();
+                cone.set_color_mod(255, 240, 200);
+                cone.set_alpha_mod((brightness * 190.) as u8);
+                canvas
+                    .copy_ex_f(
+                        &cone,
+                        None,
+                        camera.relative_box(beam_center, beam),
+                        angle,
+                        None,
+                        false,
+                        false,
+                    )
+                    .unwrap();
+
+                // dim while driving, bright and bigger on the brakes
+                let lamp = center + rotation * Vector2::new(side * half.x * 0.6, half.y);
+                let braking = car.braking().min(1.);
+                let size = Vector2::repeat(30. + 30. * braking);
+                let mut glow = assets.glow();
+                glow.set_color_mod(255, 40, 30);
+                glow.set_alpha_mod((brightness * (90. + 165. * braking)) as u8);
+                canvas
+                    .copy_f(&glow, None, camera.relative_box(lamp, size))
+                    .unwrap();
+            }
+        }
+    }
+
+    /// The track's backgrounds, each one as far along as its parallax takes it after the
+    /// camera
+    fn draw_backgrounds<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        camera: &Camera,
+    ) {
+        let (top_left, bottom_right) = camera.visible_area();
+        let center = nalgebra::center(&top_left, &bottom_right);
+        let backgrounds = self.track.backgrounds.iter().zip(&self.background_sprites);
+        for (background, sprite) in backgrounds {
+            let Some(id) = sprite else {
+                continue;
+            };
+            let texture = assets.sprite(*id);
+            let query = texture.query();
+            let size = Vector2::new(query.width as f64, query.height as f64);
+            let origin = Point2::from(background.offset)
+                + center
+                    .coords
+                    .component_mul(&background.parallax.map(|share| 1. - share));
+
+            // the copies that cover the view, going across and down from the first one
+            // there's any of in it
+            let copies = |origin: f64, size: f64, start: f64, end: f64, repeat: bool| {
+                let (first, last) = match repeat {
+                    true => (start - origin, end - origin),
+                    false => (0., 0.),
+                };
+                let (first, last) = ((first / size).floor() as i32, (last / size).floor() as i32);
+                (first..=last).map(move |i| origin + i as f64 * size)
+            };
+            let (across, down) = background.repeat;
+            for x in copies(origin.x, size.x, top_left.x, bottom_right.x, across) {
+                for y in copies(origin.y, size.y, top_left.y, bottom_right.y, down) {
+                    let area = camera.relative_box(Point2::new(x, y) + size / 2., size);
+                    canvas.copy_f(&texture, None, area).unwrap();
+                }
+            }
+        }
+    }
+
+    /// The terrain's tiles the camera can see, anchored to the world and carrying on as
+    /// grass past the grid's edges, with red and white curbs wherever the track meets the
+    /// run-off
+    fn draw_ground<T: RenderTarget>(&self, canvas: &mut Canvas<T>, camera: &Camera) {
+        let terrain = &self.track.terrain;
+        let tile_size = terrain.tile_size() as i32;
+        let (columns, rows) = terrain.tiles_in(camera.visible_area());
+
+        for y in rows.clone() {
+            for x in columns.clone() {
+                let surface = terrain.tile(x, y);
+                canvas.set_draw_color(surface.color((x + y).rem_euclid(2) == 1));
+                let tile = Rect::new(
+                    x * tile_size,
+                    y * tile_size,
+                    tile_size as u32,
+                    tile_size as u32,
+                );
+                canvas.fill_frect(camera.relative_rect(tile)).unwrap();
+            }
+        }
+
+        // along the inside of each edge of a track tile that isn't next to more track,
+        // striped by where they are in the world so the stripes line up from tile to tile
+        let inside = tile_size - CURB_WIDTH as i32;
+        for y in rows {
+            for x in columns.clone() {
+                if !terrain.tile(x, y).is_track() {
+                    continue;
+                }
+                let (left, top) = (x * tile_size, y * tile_size);
+                // the tile on the other side of each edge, where the curb along it starts and
+                // whether it runs across or down
+                let edges = [
+                    ((x - 1, y), (left, top), false),
+                    ((x + 1, y), (left + inside, top), false),
+                    ((x, y - 1), (left, top), true),
+                    ((x, y + 1), (left, top + inside), true),
+                ];
+                for ((next_x, next_y), (start_x, start_y), across) in edges {
+                    if terrain.tile(next_x, next_y).is_track() {
+                        continue;
+                    }
+                    for offset in (0..tile_size).step_by(CURB_STRIPE as usize) {
+                        let stripe = if across {
+                            Rect::new(start_x + offset, start_y, CURB_STRIPE, CURB_WIDTH)
+                        } else {
+                            Rect::new(start_x, start_y + offset, CURB_WIDTH, CURB_STRIPE)
+                        };
+                        let red = (stripe.x() + stripe.y()) / CURB_STRIPE as i32 % 2 == 0;
+                        canvas.set_draw_color(if red {
+                            Color::RGB(200, 30, 30)
+                        } else {
+                            Color::RGB(235, 235, 235)
+                        });
+                        canvas.fill_frect(camera.relative_rect(stripe)).unwrap();
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_hazards<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        camera: &Camera,
+    ) {
+        for hazard in &self.track.hazards {
+            let color = match hazard.kind {
+                HazardKind::Ice => Color::RGBA(210, 240, 255, 200),
+                HazardKind::Oil => Color::RGBA(20, 15, 30, 220),
+            };
+            let rect = camera.relative_box(hazard.area.center, hazard.area.half_extents * 2.);
+            fill_rotated_rect(canvas, assets, rect, hazard.area.rotation, color);
+        }
+    }
+
+    /// A sheen of water over all the ground and darker pools where it's collected
+    fn draw_wet<T: RenderTarget>(&self, canvas: &mut Canvas<T>, assets: &Assets, camera: &Camera) {
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(60, 80, 110, 60));
+        canvas.fill_rect(None).unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        for puddle in &self.track.puddles {
+            let rect = camera.relative_box(puddle.center, puddle.half_extents * 2.);
+            fill_rotated_rect(
+                canvas,
+                assets,
+                rect,
+                puddle.rotation,
+                Color::RGBA(40, 60, 90, 150),
+            );
+            // the sky showing in the middle of it
+            let shine = camera.relative_box(puddle.center, puddle.half_extents);
+            fill_rotated_rect(
+                canvas,
+                assets,
+                shine,
+                puddle.rotation,
+                Color::RGBA(150, 170, 200, 50),
+            );
+        }
+    }
+
+    /// Each mark as a few lines side by side, as wide as a tyre at the camera's zoom
+    fn draw_skids<T: RenderTarget>(&self, canvas: &mut Canvas<T>, camera: &Camera) {
+        let width = (6. * camera.zoom()).round().max(1.) as i32;
+        canvas.set_blend_mode(BlendMode::Blend);
+        for mark in self.skids.marks() {
+            let opacity = (mark.opacity() * 110.) as u8;
+            if opacity == 0 {
+                continue;
+            }
+            canvas.set_draw_color(Color::RGBA(20, 20, 20, opacity));
+            let (a, b) = (camera.relative_point(mark.a), camera.relative_point(mark.b));
+            // spread across whichever way the mark runs less
+            let across = if (b.x - a.x).abs() > (b.y - a.y).abs() {
+                FPoint::new(0., 1.)
+            } else {
+                FPoint::new(1., 0.)
+            };
+            for offset in 0..width {
+                let shift = across * (offset - width / 2) as f32;
+                canvas.draw_fline(a + shift, b + shift).unwrap();
+            }
+        }
+        canvas.set_blend_mode(BlendMode::None);
+    }
+
+    /// The track's racing line, and where the players' cars have been fading out behind them
+    fn draw_trails<T: RenderTarget>(&self, canvas: &mut Canvas<T>, camera: &Camera) {
+        let width = (3. * camera.zoom()).round().max(1.) as i32;
+        let draw_line = |canvas: &mut Canvas<T>, a: FPoint, b: FPoint| {
+            for offset in 0..width {
+                let shift = (offset - width / 2) as f32;
+                let shift = FPoint::new(shift, shift);
+                canvas.draw_fline(a + shift, b + shift).unwrap();
+            }
         };
 
-        std::thread::sleep(Duration::from_secs_f64(1.0 / 60.0));
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(self.trail_spec.racing_line_color);
+        for ends in self.track.waypoints.windows(2) {
+            let (a, b) = (
+                camera.relative_point(ends[0].position),
+                camera.relative_point(ends[1].position),
+            );
+            draw_line(canvas, a, b);
+        }
+
+        let Color { r, g, b, a } = self.trail_spec.color;
+        for trail in &self.trails {
+            let count = trail.points().len();
+            let points: Vec<_> = trail
+                .points()
+                .map(|&point| camera.relative_point(point))
+                .collect();
+            for (i, ends) in points.windows(2).enumerate() {
+                let fade = (i + 1) as f64 / count as f64;
+                canvas.set_draw_color(Color::RGBA(r, g, b, (a as f64 * fade) as u8));
+                draw_line(canvas, ends[0], ends[1]);
+            }
+        }
+        canvas.set_blend_mode(BlendMode::None);
+    }
+
+    fn draw_dust<T: RenderTarget>(&self, canvas: &mut Canvas<T>, camera: &Camera) {
+        canvas.set_blend_mode(BlendMode::Blend);
+        for particle in self.dust.particles() {
+            let opacity = match particle.kind {
+                dust::Kind::Cloud => particle.thickness() * 110.,
+                dust::Kind::Debris => particle.thickness() * 255.,
+            };
+            let color = particle.color;
+            canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, opacity as u8));
+            let size = Vector2::repeat(particle.size());
+            canvas
+                .fill_frect(camera.relative_box(particle.position, size))
+                .unwrap();
+        }
+        canvas.set_blend_mode(BlendMode::None);
+    }
+
+    fn draw_smoke<T: RenderTarget>(&self, canvas: &mut Canvas<T>, camera: &Camera) {
+        canvas.set_blend_mode(BlendMode::Blend);
+        for puff in self.smoke.puffs() {
+            let opacity = (puff.thickness() * 140.) as u8;
+            canvas.set_draw_color(Color::RGBA(225, 225, 225, opacity));
+            let size = Vector2::repeat(puff.size());
+            canvas
+                .fill_frect(camera.relative_box(puff.position, size))
+                .unwrap();
+        }
+        canvas.set_blend_mode(BlendMode::None);
+    }
+
+    fn draw_sparks<T: RenderTarget>(&self, canvas: &mut Canvas<T>, camera: &Camera) {
+        for spark in self.sparks.sparks() {
+            let (size, color) = spark.look();
+            canvas.set_draw_color(color);
+            canvas
+                .fill_frect(camera.relative_box(spark.position, Vector2::repeat(size)))
+                .unwrap();
+        }
+    }
+
+    /// A flashing amber lamp on top of every prop, to see them coming in the dark
+    fn draw_beacons<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        camera: &Camera,
+    ) {
+        let brightness = *self.beacons.frame(&BEACON).unwrap();
+        if brightness == 0 {
+            return;
+        }
+        let mut glow = assets.glow();
+        glow.set_color_mod(255, 170, 30);
+        glow.set_alpha_mod(brightness);
+        for prop in &self.track.props {
+            canvas
+                .copy_f(
+                    &glow,
+                    None,
+                    camera.relative_box(prop.center, Vector2::repeat(60.)),
+                )
+                .unwrap();
+        }
+    }
+
+    fn draw_obstacles<T: RenderTarget>(&self, canvas: &mut Canvas<T>, camera: &Camera) {
+        let to_screen = |point: Point2<f64>| camera.relative_point(point);
+
+        canvas.set_draw_color(Color::WHITE);
+        for wall in &self.track.walls {
+            canvas
+                .draw_fline(to_screen(wall.a), to_screen(wall.b))
+                .unwrap();
+        }
+
+        // ramps get an arrow up the slope
+        canvas.set_draw_color(Color::RGB(230, 190, 40));
+        for ramp in &self.track.ramps {
+            let corners = ramp.area.corners().map(to_screen);
+            let outline = [corners[0], corners[1], corners[2], corners[3], corners[0]];
+            canvas.draw_flines(&outline[..]).unwrap();
+            let [a, b, c, d] = corners;
+            let back = (c + d) * 0.5;
+            canvas.draw_flines(&[back, a, b, back][..]).unwrap();
+        }
+
+        canvas.set_draw_color(Color::RGB(90, 160, 255));
+        for checkpoint in &self.track.checkpoints {
+            let corners = checkpoint.corners().map(to_screen);
+            let outline = [corners[0], corners[1], corners[2], corners[3], corners[0]];
+            canvas.draw_flines(&outline[..]).unwrap();
+        }
+
+        canvas.set_draw_color(Color::RGB(200, 200, 190));
+        for prop in &self.track.props {
+            let corners = prop.corners().map(to_screen);
+            let outline = [corners[0], corners[1], corners[2], corners[3], corners[0]];
+            canvas.draw_flines(&outline[..]).unwrap();
+        }
+
+        // movers with a cross through them, to tell them from what stays put
+        canvas.set_draw_color(Color::RGB(230, 60, 50));
+        for (area, _) in self.track.mover_areas() {
+            let corners = area.corners().map(to_screen);
+            let outline = [corners[0], corners[1], corners[2], corners[3], corners[0]];
+            canvas.draw_flines(&outline[..]).unwrap();
+            canvas.draw_fline(corners[0], corners[2]).unwrap();
+            canvas.draw_fline(corners[1], corners[3]).unwrap();
+        }
+    }
+
+    /// Cones outlined with a white tip, and the round ones with a ring round the top
+    fn draw_loose_props<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        camera: &Camera,
+        alpha: f64,
+    ) {
+        let to_screen = |point: Point2<f64>| camera.relative_point(point);
+        for prop in &self.loose_props {
+            let obb = prop.interpolated_obb(alpha);
+            canvas.set_draw_color(prop.kind.color());
+            let corners = obb.corners().map(to_screen);
+            let outline = [corners[0], corners[1], corners[2], corners[3], corners[0]];
+            canvas.draw_flines(&outline[..]).unwrap();
+            if prop.kind == PropKind::Cone {
+                canvas.set_draw_color(Color::WHITE);
+                canvas
+                    .fill_frect(camera.relative_box(obb.center, Vector2::repeat(6.)))
+                    .unwrap();
+            } else {
+                let top = Obb::new(obb.center, obb.half_extents, obb.rotation);
+                let corners = top.corners().map(to_screen);
+                let outline = [corners[0], corners[1], corners[2], corners[3], corners[0]];
+                canvas.draw_flines(&outline[..]).unwrap();
+            }
+        }
+    }
+
+    /// What's by the side of the track to look at, outlined for the ones without a picture
+    /// or whose picture didn't load
+    fn draw_decorations<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        camera: &Camera,
+    ) {
+        let decorations = self.track.decorations.iter().zip(&self.decoration_sprites);
+        for (decoration, sprite) in decorations {
+            let area = &decoration.area;
+            match sprite {
+                Some(id) => {
+                    let rect = camera.relative_box(area.center, area.half_extents * 2.);
+                    canvas
+                        .copy_ex_f(
+                            &assets.sprite(*id),
+                            None,
+                            rect,
+                            area.rotation.angle().to_degrees(),
+                            None,
+                            false,
+                            false,
+                        )
+                        .unwrap();
+                }
+                None => {
+                    let corners = area.corners().map(|point| camera.relative_point(point));
+                    let outline = [corners[0], corners[1], corners[2], corners[3], corners[0]];
+                    canvas.set_draw_color(decoration.color);
+                    canvas.draw_flines(&outline[..]).unwrap();
+                }
+            }
+        }
+    }
+
+    /// Red for the first player, green for the second and blue for the AI
+    fn car_color(&self, car: usize) -> Color {
+        match car {
+            0 => Color::RED,
+            1 if self.players > 1 => Color::GREEN,
+            _ => Color::BLUE,
+        }
+    }
+
+    fn draw_car<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        camera: &Camera,
+        car: usize,
+        alpha: f64,
+    ) {
+        let color = self.car_color(car);
+        let flame = self.flames[car].frame(&FLAME);
+        let sprite = self.car_sprites[car].map(|id| assets.sprite(id));
+        let car = &self.cars[car];
+        let ground_rect =
+            camera.relative_box(car.interpolated_center(alpha), car.spec().dimensions);
+        let rotation = car.interpolated_rotation(alpha);
+
+        // in the air the car gets closer to the camera and its shadow falls away from it
+        let height = car.interpolated_height(alpha);
+        let mut rect = ground_rect;
+        if car.is_airborne() {
+            let mut shadow = ground_rect;
+            let drop = (height * 0.6 * camera.zoom()) as f32;
+            shadow.offset(drop, drop);
+            fill_rotated_rect(canvas, assets, shadow, rotation, Color::RGBA(0, 0, 0, 100));
+
+            let scale = 1. + height as f32 / 150.;
+            rect.resize(rect.width() * scale, rect.height() * scale);
+            rect.center_on(ground_rect.center());
+        }
+
+        // the more beaten up the car, the darker it gets
+        let shade = |channel: u8| (channel as f64 * (1. - car.damage().amount() * 0.6)) as u8;
+        let color = Color::RGB(shade(color.r), shade(color.g), shade(color.b));
+
+        // the flame sits just behind the rear bumper, flickering
+        if car.nitro().is_active() {
+            let &(length, flame_color) = flame.unwrap();
+            let flame_size = (rect.width() / 2., rect.height() / 3. * length);
+            let offset = rotation * Vector2::new(0., (rect.height() + flame_size.1) as f64 / 2.);
+            let flame = FRect::from_center(
+                rect.center().offset(offset.x as f32, offset.y as f32),
+                flame_size.0,
+                flame_size.1,
+            );
+            fill_rotated_rect(canvas, assets, flame, rotation, flame_color);
+        }
+
+        // the sprite is drawn in white and grey and tinted in the car's color, with a plain
+        // box for cars whose sprite didn't load
+        match sprite {
+            Some(mut sprite) => {
+                sprite.set_color_mod(color.r, color.g, color.b);
+                canvas
+                    .copy_ex_f(
+                        &sprite,
+                        None,
+                        rect,
+                        rotation.angle().to_degrees(),
+                        None,
+                        false,
+                        false,
+                    )
+                    .unwrap();
+            }
+            None => fill_rotated_rect(canvas, assets, rect, rotation, color),
+        }
+    }
+
+    /// The box with its drawbar running up to the car's hitch
+    fn draw_trailer<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        camera: &Camera,
+        trailer: &Trailer,
+        alpha: f64,
+    ) {
+        let to_screen = |point: Point2<f64>| camera.relative_point(point);
+        let rotation = trailer.interpolated_rotation(alpha);
+        let center = trailer.interpolated_center(alpha);
+        let dimensions = trailer.dimensions();
+
+        let front = center + rotation * Vector2::new(0., -dimensions.y / 2.);
+        canvas.set_draw_color(Color::RGB(60, 60, 60));
+        canvas
+            .draw_fline(
+                to_screen(front),
+                to_screen(trailer.interpolated_hitch(alpha)),
+            )
+            .unwrap();
+
+        let rect = camera.relative_box(center, dimensions);
+        fill_rotated_rect(canvas, assets, rect, rotation, Color::RGB(150, 150, 140));
+    }
+
+    fn draw_nitro_meter<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        player: usize,
+        view: (i32, i32),
+    ) {
+        let (width, height) = (300, 20);
+        let margin = 40;
+        let outline = Rect::new(margin, view.1 - margin - height as i32, width, height);
+        let mut fill = outline;
+        fill.set_width((self.cars[player].nitro().charge() * width as f64) as u32);
+
+        canvas.set_draw_color(Color::RGB(30, 200, 255));
+        if fill.width() > 0 {
+            canvas.fill_rect(fill).unwrap();
+        }
+        canvas.set_draw_color(Color::WHITE);
+        canvas.draw_rect(outline).unwrap();
+    }
+
+    /// The points banked and the combo going in the top left corner, and the points made
+    /// drifting floating up from where they were made
+    fn draw_drift_score<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        player: usize,
+        camera: &Camera,
+    ) {
+        let score = &self.drift_scores[player];
+        let margin = 40;
+        canvas.set_blend_mode(BlendMode::Blend);
+        for popup in score.popups() {
+            let position = camera.to_view(popup.position);
+            let rise = popup.age() * 60.;
+            let opacity = ((1. - popup.age()) * 255.) as u8;
+            text::draw_centered(
+                canvas,
+                &format!("+{}", popup.points),
+                (position.x as i32, (position.y - 60. - rise) as i32),
+                3,
+                Color::RGBA(255, 230, 90, opacity),
+            );
+        }
+        canvas.set_blend_mode(BlendMode::None);
+
+        text::draw(
+            canvas,
+            &format!("SCORE {}", score.banked()),
+            (margin, margin),
+            4,
+            Color::WHITE,
+        );
+        let Some(combo) = score.combo() else {
+            return;
+        };
+        let top = margin + text::height(4) as i32 + 16;
+        text::draw(
+            canvas,
+            &format!("{} X{}", combo.points(), combo.multiplier()),
+            (margin, top),
+            3,
+            Color::RGB(255, 230, 90),
+        );
+        // draining once the car grips again
+        let (width, height) = (240, 10);
+        let outline = Rect::new(margin, top + text::height(3) as i32 + 10, width, height);
+        let mut fill = outline;
+        fill.set_width((combo.time_left() * width as f64) as u32);
+        canvas.set_draw_color(Color::RGB(255, 180, 40));
+        if fill.width() > 0 {
+            canvas.fill_rect(fill).unwrap();
+        }
+        canvas.set_draw_color(Color::WHITE);
+        canvas.draw_rect(outline).unwrap();
+    }
+
+    /// The whole track shrunk down in the bottom left corner, next to the tyres: the track's
+    /// tiles, its walls and checkpoints, and a dot for every car with the player's bigger
+    fn draw_minimap<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        assets: &Assets,
+        player: usize,
+        view: (i32, i32),
+    ) {
+        let terrain = &self.track.terrain;
+        let tile_size = terrain.tile_size() as f64;
+        let (columns, rows) = terrain.size();
+        let world = Vector2::new(columns as f64, rows as f64) * tile_size;
+        let (max_width, max_height) = (260., 200.);
+        let scale = (max_width / world.x).min(max_height / world.y);
+        let (width, height) = ((world.x * scale) as f32, (world.y * scale) as f32);
+        // clear of the nitro meter below
+        let (left, bottom) = (140., view.1 as f32 - 80.);
+        let top = bottom - height;
+        let to_map = |point: Point2<f64>| {
+            FPoint::new(
+                left + (point.x * scale) as f32,
+                top + (point.y * scale) as f32,
+            )
+        };
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        if let Some(id) = self.minimap_sprite {
+            let mut sprite = assets.sprite(id);
+            sprite.set_blend_mode(BlendMode::Blend);
+            sprite.set_alpha_mod(200);
+            canvas
+                .copy_f(&sprite, None, FRect::new(left, top, width, height))
+                .unwrap();
+        } else {
+            canvas.set_draw_color(Color::RGBA(0, 0, 0, 150));
+            canvas
+                .fill_frect(FRect::new(left, top, width, height))
+                .unwrap();
+
+            // a rect for each run of track tiles along a row
+            let tile = (tile_size * scale) as f32;
+            let mut runs = Vec::new();
+            for y in 0..rows as i32 {
+                let mut x = 0;
+                while x < columns as i32 {
+                    if !terrain.tile(x, y).is_track() {
+                        x += 1;
+                        continue;
+                    }
+                    let start = x;
+                    while x < columns as i32 && terrain.tile(x, y).is_track() {
+                        x += 1;
+                    }
+                    runs.push(FRect::new(
+                        left + start as f32 * tile,
+                        top + y as f32 * tile,
+                        (x - start) as f32 * tile,
+                        tile,
+                    ));
+                }
+            }
+            canvas.set_draw_color(Color::RGBA(150, 150, 150, 200));
+            canvas.fill_frects(&runs).unwrap();
+        }
+        canvas.set_blend_mode(BlendMode::None);
+
+        canvas.set_draw_color(Color::WHITE);
+        for wall in &self.track.walls {
+            canvas.draw_fline(to_map(wall.a), to_map(wall.b)).unwrap();
+        }
+        canvas.set_draw_color(Color::RGB(90, 160, 255));
+        for checkpoint in &self.track.checkpoints {
+            canvas
+                .fill_frect(FRect::from_center(to_map(checkpoint.center), 4., 4.))
+                .unwrap();
+        }
+        // the player on top of everyone else
+        for car in (0..self.cars.len())
+            .filter(|&car| car != player)
+            .chain([player])
+        {
+            let size = if car == player { 9. } else { 6. };
+            canvas.set_draw_color(self.car_color(car));
+            canvas
+                .fill_frect(FRect::from_center(
+                    to_map(self.cars[car].center()),
+                    size,
+                    size,
+                ))
+                .unwrap();
+        }
+    }
+
+    /// The lap going, the last and the best down the top right corner, and for a few
+    /// seconds after each checkpoint how it compared with the best lap: green for faster
+    /// and red for slower. A last lap that's the best there's been is green as well.
+    fn draw_lap_times<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        player: usize,
+        view: (i32, i32),
+    ) {
+        let timer = &self.lap_timers[player];
+        let Some(lap) = timer.lap() else {
+            return;
+        };
+        let margin = 40;
+        let mut top = margin;
+        let mut line = |canvas: &mut Canvas<T>, line: &str, scale: u32, color: Color| {
+            let left = view.0 - margin - text::width(line, scale) as i32;
+            text::draw(canvas, line, (left, top), scale, color);
+            top += text::height(scale) as i32 + 12;
+        };
+        let (faster, slower) = (Color::RGB(80, 230, 80), Color::RGB(240, 70, 60));
+
+        if let Some(laps) = self.track.laps {
+            let going = (timer.laps_finished() + 1).min(laps);
+            line(canvas, &format!("{going}/{laps}"), 4, Color::WHITE);
+            let position = self
+                .standings
+                .iter()
+                .position(|&car| car == player)
+                .unwrap()
+                + 1;
+            let field = self.cars.len();
+            line(canvas, &format!("P{position}/{field}"), 3, Color::WHITE);
+        }
+
+        // a lap that's missed a checkpoint out won't count
+        line(
+            canvas,
+            &format!("LAP {}", laps::format_time(lap)),
+            4,
+            if timer.is_lap_valid() {
+                Color::WHITE
+            } else {
+                slower
+            },
+        );
+        if self.missed_warnings[player] > 0 {
+            line(canvas, "CHECKPOINT MISSED", 3, slower);
+        }
+        if let Some(last) = timer.last_lap() {
+            let color = if timer.best_lap() == Some(last) {
+                faster
+            } else {
+                Color::WHITE
+            };
+            line(
+                canvas,
+                &format!("LAST {}", laps::format_time(last)),
+                3,
+                color,
+            );
+        }
+        if let Some(best) = timer.best_lap() {
+            line(
+                canvas,
+                &format!("BEST {}", laps::format_time(best)),
+                3,
+                Color::WHITE,
+            );
+        }
+        if let Some((split, since)) = timer.last_split() {
+            if since < 3 * TICKS_PER_SECOND as u32 {
+                let (delta, color) = match split.delta {
+                    Some(delta) => (
+                        laps::format_delta(delta),
+                        if delta <= 0 { faster } else { slower },
+                    ),
+                    None => (laps::format_time(split.time), Color::WHITE),
+                };
+                // the finish line ends the last sector
+                let sector = match split.checkpoint {
+                    0 => self.track.checkpoints.len(),
+                    checkpoint => checkpoint,
+                };
+                line(canvas, &format!("S{sector} {delta}"), 3, color);
+            }
+        }
+    }
+
+    /// A rev counter dial in the bottom right corner, with the speed and gear in the middle
+    fn draw_speedometer<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        player: usize,
+        view: (i32, i32),
+    ) {
+        let car = &self.cars[player];
+        let spec = &car.spec().engine;
+        let engine = car.engine();
+        let radius: f32 = 110.;
+        let margin = 40.;
+        let center = FPoint::new(
+            view.0 as f32 - margin - radius,
+            view.1 as f32 - margin - radius,
+        );
+        // sweeping clockwise over the top from the bottom left, up to the next thousand rpm
+        // past the redline
+        let max_rpm = (spec.redline_rpm / 1000.).floor() * 1000. + 1000.;
+        let around = |rpm: f64, distance: f32| {
+            let angle = (225. - 270. * rpm / max_rpm).to_radians() as f32;
+            center.offset(angle.cos() * distance, -angle.sin() * distance)
+        };
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 120));
+        canvas
+            .fill_frect(FRect::from_center(center, radius * 2.2, radius * 2.2))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let steps = 54;
+        for step in 0..steps {
+            let (from, to) = (
+                max_rpm * step as f64 / steps as f64,
+                max_rpm * (step + 1) as f64 / steps as f64,
+            );
+            canvas.set_draw_color(if from >= spec.redline_rpm {
+                Color::RGB(220, 30, 30)
+            } else {
+                Color::WHITE
+            });
+            canvas
+                .draw_fline(around(from, radius), around(to, radius))
+                .unwrap();
+        }
+        for thousand in 0..=(max_rpm / 1000.) as u32 {
+            let rpm = thousand as f64 * 1000.;
+            canvas.set_draw_color(Color::WHITE);
+            canvas
+                .draw_fline(around(rpm, radius), around(rpm, radius - 14.))
+                .unwrap();
+            let label = around(rpm, radius - 30.);
+            text::draw_centered(
+                canvas,
+                &thousand.to_string(),
+                (label.x as i32, label.y as i32),
+                2,
+                Color::WHITE,
+            );
+        }
+        canvas.set_draw_color(Color::RGB(255, 120, 30));
+        canvas
+            .draw_fline(center, around(engine.rpm().min(max_rpm), radius - 8.))
+            .unwrap();
+
+        let speed = (car.forward_speed().abs() * KMH_PER_SPEED).round();
+        let (x, y) = (center.x as i32, center.y as i32);
+        text::draw_centered(canvas, &format!("{speed}"), (x, y + 35), 5, Color::WHITE);
+        text::draw_centered(canvas, "KM/H", (x, y + 65), 2, Color::GREY);
+        let gear = if engine.is_shifting() {
+            "N".to_string()
+        } else if car.forward_speed() < -0.1 {
+            "R".to_string()
+        } else {
+            (engine.gear() + 1).to_string()
+        };
+        let gear_color = if engine.rpm() >= spec.shift_up_rpm {
+            Color::RGB(255, 80, 60)
+        } else {
+            Color::WHITE
+        };
+        text::draw_centered(canvas, &gear, (x, y - 40), 4, gear_color);
+    }
+
+    /// The seconds left to the start big in the middle of the player's view, and "GO" once
+    /// the lights are green, unless they jumped the start
+    fn draw_countdown<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        player: usize,
+        view: (i32, i32),
+    ) {
+        let Some(start) = &self.start else {
+            return;
+        };
+        let center = (view.0 / 2, view.1 / 3);
+        if start.is_green() && start.is_held(player) {
+            text::draw_centered(canvas, "JUMP START", center, 8, Color::RGB(240, 70, 60));
+        } else if let Some(shown) = start.shown() {
+            let color = if start.is_green() {
+                Color::RGB(80, 230, 80)
+            } else {
+                Color::WHITE
+            };
+            text::draw_centered(canvas, &shown, center, 16, color);
+        }
+    }
+
+    /// A blinking red frame around the player's view right after they leave the track
+    fn draw_off_track_warning<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        player: usize,
+        view: (i32, i32),
+    ) {
+        let blink_ticks = 10;
+        if (self.off_track_warnings[player] / blink_ticks).is_multiple_of(2) {
+            return;
+        }
+        let thickness = 12;
+        let (width, height) = view;
+        canvas.set_draw_color(Color::RGB(220, 30, 30));
+        canvas
+            .fill_rects(&[
+                Rect::new(0, 0, width as u32, thickness),
+                Rect::new(0, height - thickness as i32, width as u32, thickness),
+                Rect::new(0, 0, thickness, height as u32),
+                Rect::new(width - thickness as i32, 0, thickness, height as u32),
+            ])
+            .unwrap();
+    }
+
+    /// One box per tyre laid out like on the car, blue when cold, green in the temperature
+    /// window and red when overheating, and emptying out as the tyre wears down
+    fn draw_tires<T: RenderTarget>(&self, canvas: &mut Canvas<T>, player: usize, view: (i32, i32)) {
+        let car = &self.cars[player];
+        let (width, height) = (30, 50);
+        let (spacing, margin) = (10, 40);
+        let bottom = view.1 - 100;
+
+        for (i, tire) in car.tires().iter().enumerate() {
+            let x = margin + (i as i32 % 2) * (width as i32 + spacing);
+            let y = bottom - (2 - i as i32 / 2) * (height as i32 + spacing);
+            let outline = Rect::new(x, y, width, height);
+
+            let offset = tire.temperature_offset(&car.spec().tires).clamp(-1., 1.);
+            let hot = (offset.max(0.) * 255.) as u8;
+            let cold = (-offset.min(0.) * 255.) as u8;
+            canvas.set_draw_color(Color::RGB(hot, 255 - hot.max(cold), cold));
+            let tread = ((1. - tire.wear()) * height as f64) as u32;
+            if tread > 0 {
+                canvas
+                    .fill_rect(Rect::new(x, y + (height - tread) as i32, width, tread))
+                    .unwrap();
+            }
+            canvas.set_draw_color(Color::WHITE);
+            canvas.draw_rect(outline).unwrap();
+        }
+    }
+}
+
+/// Draws a rect turned by `rotation` around its own center
+fn fill_rotated_rect<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    assets: &Assets,
+    rect: FRect,
+    rotation: Rotation2<f64>,
+    color: Color,
+) {
+    let mut pixel = assets.pixel();
+    pixel.set_color_mod(color.r, color.g, color.b);
+    pixel.set_alpha_mod(color.a);
+    canvas
+        .copy_ex_f(
+            &pixel,
+            None,
+            Some(rect),
+            rotation.angle().to_degrees(),
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+}
+
+impl Scene for Level {
+    fn update(&mut self, inputs: &[TickInput]) -> Transition {
+        // back from the pause menu
+        if let Some((player, outcome)) = self.paused.take() {
+            match outcome.take().map(|paused| self.unpause(player, paused)) {
+                Some(Transition::Stay) | None => {}
+                Some(next) => return next,
+            }
+        }
+        // back from the results
+        if let Some(outcome) = self.results.take() {
+            return match outcome.take() {
+                Some(ResultsChoice::Restart) => Transition::Replace(Box::new(self.restarted())),
+                Some(ResultsChoice::ChangeTrack) => {
+                    let directory = self.tracks.clone().unwrap_or_default();
+                    let next = self.on_track(Track::new());
+                    Transition::Replace(Box::new(TrackMenu::new(&directory).then_drive(next)))
+                }
+                Some(ResultsChoice::Menu) => {
+                    self.menus().map_or(Transition::Pop, Transition::Replace)
+                }
+                Some(ResultsChoice::Quit) | None => Transition::Pop,
+            };
+        }
+        let typing;
+        let inputs = match self.console.take() {
+            Some(mut console) => {
+                if inputs
+                    .iter()
+                    .any(|input| input.commands.contains(&Command::ToggleConsole))
+                {
+                    console.toggle();
+                }
+                let open = console.is_open();
+                if let Some(input) = inputs.first().filter(|_| open) {
+                    console.update(self, input);
+                }
+                self.console = Some(console);
+                if open {
+                    typing = typing_inputs(inputs);
+                    &typing[..]
+                } else {
+                    inputs
+                }
+            }
+            console => {
+                self.console = console;
+                inputs
+            }
+        };
+        let pressed = |wanted| inputs.iter().any(|input| input.commands.contains(&wanted));
+        if pressed(Command::Quit) || self.test_drive && pressed(Command::TestDrive) {
+            return Transition::Pop;
+        }
+        if let Some(track) = self.watch.as_mut().and_then(TrackWatch::poll) {
+            self.reload_track(track);
+        }
+        self.step(inputs);
+        if let Some(player) = self.pausing.take() {
+            return Transition::Push(Box::new(self.pause_menu(player)));
+        }
+        // the results come up a while after the last player's over the line
+        if self.finished_for == RESULTS_DELAY {
+            let results = self.results();
+            self.results = Some(results.outcome());
+            return Transition::Push(Box::new(results));
+        }
+        if let Some(path) = self.next_track.take() {
+            let level = self.on_track(Track::new());
+            return Transition::Replace(Box::new(
+                Loading::new(Source::File(path)).then_drive(level),
+            ));
+        }
+        Transition::Stay
+    }
+
+    fn rumbles(&self) -> &[Rumble] {
+        &self.rumbles
+    }
+
+    fn sounds(&self) -> &[Sound] {
+        &self.sounds
+    }
+
+    fn recorded(&self) -> bool {
+        true
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Loads the sprites the cars and the track are drawn with
+    fn load_sprites(&mut self, assets: &mut Assets) {
+        assets.read_ahead(std::mem::take(&mut self.images));
+        self.minimap_sprite = self
+            .thumbnail
+            .clone()
+            .and_then(|path| assets.load_image(path));
+        self.car_sprites = self
+            .cars
+            .iter()
+            .map(|car| assets.load_sprite(car.spec().sprite.as_deref()?))
+            .collect();
+        self.background_sprites = self
+            .track
+            .backgrounds
+            .iter()
+            .map(|background| assets.load_image(background.image.clone()))
+            .collect();
+        self.decoration_sprites = self
+            .track
+            .decorations
+            .iter()
+            .map(|decoration| assets.load_image(decoration.image.clone()?))
+            .collect();
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>, assets: &Assets, alpha: f64) {
+        // the last tick stays put instead of wobbling between it and the one before
+        let alpha = if self.lost_controller.is_some() {
+            1.
+        } else {
+            alpha
+        };
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+        for (player, camera) in self.cameras.iter().enumerate() {
+            let (width, height) = camera.view;
+            let mut view = assets.target(width as u32, height as u32);
+            canvas
+                .with_texture_canvas(&mut view, |view_canvas| {
+                    self.render_view(view_canvas, assets, player, alpha)
+                })
+                .unwrap();
+            // a thin black line is left between the views
+            let gap = if self.players > 1 { 2 } else { 0 };
+            let area = Rect::new(
+                player as i32 * width + gap,
+                0,
+                (width - 2 * gap) as u32,
+                height as u32,
+            );
+            canvas
+                .copy(&view, Rect::new(gap, 0, area.width(), area.height()), area)
+                .unwrap();
+            assets.give_back(view);
+        }
+        if let Some(player) = self.lost_controller {
+            draw_controller_lost(canvas, player);
+        }
+        if let Some(console) = &self.console {
+            console.render(canvas);
+        }
+    }
+}
+
+/// What the players do while the first one's typing into the console: the keys type rather
+/// than drive or do what they're bound to, leaving the other players only their sticks and
+/// pedals, and everyone their controllers being unplugged
+fn typing_inputs(inputs: &[TickInput]) -> Vec<TickInput> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(player, input)| {
+            let mut input = input.clone();
+            if player == 0 {
+                input.car = CarInput::default();
+                input.aim = None;
+            }
+            input
+                .commands
+                .retain(|command| *command == Command::ControllerLost);
+            input
+        })
+        .collect()
+}
+
+/// Streaks of rain falling past the camera, over the world whichever way it's turned
+fn draw_rain<T: RenderTarget>(canvas: &mut Canvas<T>, rain: &Rain, view: (i32, i32), alpha: f64) {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(190, 200, 225, 110));
+    for (start, end) in rain.streaks(view, alpha) {
+        canvas.draw_fline(start, end).unwrap();
+    }
+    canvas.set_blend_mode(BlendMode::None);
+}
+
+/// A short message in a box at the bottom of the screen, over everyone's views
+fn draw_toast<T: RenderTarget>(canvas: &mut Canvas<T>, message: &str) {
+    let scale = 3;
+    let padding = 16;
+    let (width, height) = (text::width(message, scale), text::height(scale));
+    let center = (SCREEN_DIMENSIONS.0 / 2, SCREEN_DIMENSIONS.1 - 300);
+    let mut area = Rect::new(0, 0, width + padding * 2, height + padding * 2);
+    area.center_on(Point::new(center.0, center.1));
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 180));
+    canvas.fill_rect(area).unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+    text::draw_centered(canvas, message, center, scale, Color::WHITE);
+}
+
+/// How the AI drives `car`: after a point on the racing line a little way ahead, as fast
+/// as the line says to go there, easing off the more it has to turn. On tracks without a
+/// racing line its cars just roll.
+fn ai_input(car: &Car, track: &Track) -> CarInput {
+    let Some(nearest) = track.nearest_waypoint(car.center()) else {
+        return CarInput::default();
+    };
+    let target = track.waypoints[track.waypoint_ahead(nearest, AI_LOOK_AHEAD)];
+    let steering = car.steering_toward(target.position);
+    let speed = target.speed.unwrap_or(AI_TOP_SPEED) * (1. - 0.5 * steering.abs());
+    let over = car.forward_speed() - speed;
+    CarInput {
+        pedals: CarPedals {
+            throttle: if over < 0. { 1. } else { 0. },
+            brake: if over > 1. { 0.5 } else { 0. },
+        },
+        steering,
+        handbrake: false,
+        boosting: false,
+    }
+}
+
+/// Dims the whole screen and says what it takes to carry on
+fn draw_controller_lost<T: RenderTarget>(canvas: &mut Canvas<T>, player: usize) {
+    let (width, height) = SCREEN_DIMENSIONS;
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+    canvas
+        .fill_rect(Rect::new(0, 0, width as u32, height as u32))
+        .unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+
+    let center = (width / 2, height / 2);
+    let title = format!("PLAYER {}'S CONTROLLER WAS UNPLUGGED", player + 1);
+    let hint = "PLUG IT BACK IN AND PRESS START, OR PRESS RETURN";
+    text::draw_centered(canvas, &title, (center.0, center.1 - 40), 8, Color::WHITE);
+    text::draw_centered(
+        canvas,
+        hint,
+        (center.0, center.1 + 40),
+        4,
+        Color::RGB(200, 200, 200),
+    );
+}
+
+/// Resizes the window and switches it to or from fullscreen for `to`, where it's any
+/// different from `from`
+fn apply_display(window: &mut Window, from: &Settings, to: &Settings) {
+    if to.window != from.window {
+        let (width, height) = to
+            .window
+            .unwrap_or_else(|| window_size(window.subsystem().display_usable_bounds(0).ok()));
+        if let Err(error) = window.set_size(width, height) {
+            eprintln!("couldn't resize the window: {error}");
+        }
+    }
+    if to.fullscreen != from.fullscreen {
+        let mode = if to.fullscreen {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Off
+        };
+        if let Err(error) = window.set_fullscreen(mode) {
+            eprintln!("couldn't switch to or from fullscreen: {error}");
+        }
+    }
+}
+
+/// Between filling the screen the window is on and a window of its own. Either way the
+/// picture is drawn at the same size and scaled to fit, so nothing needs making over.
+fn toggle_fullscreen(window: &mut Window) {
+    let mode = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+    if let Err(error) = window.set_fullscreen(mode) {
+        eprintln!("couldn't switch to or from fullscreen: {error}");
+    }
+}
+
+/// How big a window to start with on a display with room for `usable`, in points: most of it,
+/// in the picture's shape
+fn window_size(usable: Option<Rect>) -> (u32, u32) {
+    let Some(usable) = usable else {
+        return (1600, 900);
+    };
+    let picture = letterbox((usable.width(), usable.height()));
+    (picture.width() * 4 / 5, picture.height() * 4 / 5)
+}
+
+/// Where on a window of `window_size` the game's picture goes: as big as it gets while
+/// keeping its shape, with black bars on the sides or top and bottom of windows shaped
+/// differently. In pixels for drawing and in points for the mouse, which only differ on
+/// HiDPI displays.
+fn letterbox(window_size: (u32, u32)) -> Rect {
+    let (width, height) = (SCREEN_DIMENSIONS.0 as f64, SCREEN_DIMENSIONS.1 as f64);
+    let scale = (window_size.0 as f64 / width).min(window_size.1 as f64 / height);
+    let size = (
+        (width * scale).round() as u32,
+        (height * scale).round() as u32,
+    );
+    Rect::new(
+        (window_size.0.saturating_sub(size.0) / 2) as i32,
+        (window_size.1.saturating_sub(size.1) / 2) as i32,
+        size.0.max(1),
+        size.1.max(1),
+    )
+}
+
+/// What the game was started with
+struct Options {
+    players: usize,
+    /// where to save a recording of the run on quitting
+    record: Option<String>,
+    /// a recording to play back instead of letting the players drive
+    replay: Option<String>,
+    /// a Tiled map or TOML track to drive on instead of choosing one
+    track: Option<String>,
+    /// render as fast as we can, for seeing how fast that is
+    uncapped: bool,
+    /// start the race in the rain
+    rain: bool,
+    /// where to find the game's files
+    assets: String,
+    /// a GIF or MP4 to film the replay to, instead of showing it
+    export: Option<String>,
+    /// a TOML track to make or change in the editor, instead of driving
+    edit: Option<String>,
+    /// the seed of a track to make up and drive on
+    generate: Option<u64>,
+}
+
+impl Options {
+    /// `None` if the arguments don't make sense
+    fn from_args() -> Option<Options> {
+        let mut options = Options {
+            players: 1,
+            record: None,
+            replay: None,
+            track: None,
+            uncapped: false,
+            rain: false,
+            assets: "assets".to_string(),
+            export: None,
+            edit: None,
+            generate: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--two-players" => options.players = 2,
+                "--record" => options.record = Some(args.next()?),
+                "--replay" => options.replay = Some(args.next()?),
+                "--track" => options.track = Some(args.next()?),
+                "--uncapped" => options.uncapped = true,
+                "--rain" => options.rain = true,
+                "--assets" => options.assets = args.next()?,
+                "--export" => options.export = Some(args.next()?),
+                "--edit" => options.edit = Some(args.next()?),
+                "--generate" => {
+                    options.generate = Some(match args.next()?.as_str() {
+                        "daily" => generator::daily_seed(),
+                        seed => seed.parse().ok()?,
+                    })
+                }
+                _ => return None,
+            }
+        }
+        // a replay is already a recording, and knows which track it was on and the weather
+        if options.replay.is_some()
+            && (options.record.is_some() || options.track.is_some() || options.rain)
+        {
+            return None;
+        }
+        if options.export.is_some() && options.replay.is_none() {
+            return None;
+        }
+        // replays only know the tracks that are in files
+        if options.generate.is_some()
+            && (options.track.is_some()
+                || options.replay.is_some()
+                || options.record.is_some()
+                || options.edit.is_some())
+        {
+            return None;
+        }
+        // the editor has its own track and nothing to record
+        if options.edit.is_some()
+            && (options.replay.is_some() || options.record.is_some() || options.track.is_some())
+        {
+            return None;
+        }
+        Some(options)
+    }
+}
+
+fn main() {
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+
+    // in the display's points rather than its pixels, which HiDPI displays have more of than
+    // points. Asking for all of them keeps the picture sharp there instead of having it
+    // drawn at the size in points and blown up by the system.
+    let (width, height) = window_size(video_subsystem.display_usable_bounds(0).ok());
+    let window = video_subsystem
+        .window("Sdl2 test", width, height)
+        .resizable()
+        .allow_highdpi()
+        .build()
+        .unwrap();
+
+    let Some(options) = Options::from_args() else {
+        eprintln!(
+            "usage: drifter [--two-players] [--track <file.tmx|file.toml>] [--record <file> | --replay <file> [--export <file.gif|.mp4>] | --edit <file.toml>] [--generate <seed|daily>] [--rain] [--uncapped] [--assets <dir>]"
+        );
+        return;
+    };
+    // one frame per refresh of the display, or as many as it takes when uncapped
+    let frame_time = match window.display_mode() {
+        Ok(mode) if mode.refresh_rate > 0 => Duration::from_secs_f64(1. / mode.refresh_rate as f64),
+        _ => Duration::from_secs_f64(1. / 60.),
+    };
+    let canvas = window.into_canvas();
+    let canvas = if options.uncapped {
+        canvas
+    } else {
+        canvas.present_vsync()
+    };
+    let mut canvas = canvas.build().unwrap();
+    let texture_creator = canvas.texture_creator();
+    let ttf_context = sdl2::ttf::init().unwrap();
+    let mut assets = Assets::new(&texture_creator, &ttf_context, &options.assets);
+    let (font, size) = frame_counter::FONT;
+    let font = assets.load_font(font, size);
+    // free driving, so the trailer stays home until asked for with T
+    let mut seed = 1;
+    let mut towing = false;
+    let mut raining = options.rain;
+    let mut players = options.players;
+    let mut track_path = options.track;
+    let mut playback: Option<Playback> = None;
+    if let Some(path) = &options.replay {
+        match Recording::load(path) {
+            Ok(replay) => {
+                seed = replay.seed;
+                players = replay.players;
+                towing = replay.towing;
+                raining = replay.raining;
+                track_path = replay.track.clone();
+                playback = Some(replay.playback());
+            }
+            Err(error) => {
+                eprintln!("couldn't load the replay {path}: {error}");
+                return;
+            }
+        }
+    }
+    let bindings_path = assets.path("bindings.toml");
+    let mut bindings = Bindings::load(&bindings_path).unwrap_or_else(|error| {
+        eprintln!(
+            "couldn't load {}, using the built-in controls: {error}",
+            bindings_path.display()
+        );
+        Bindings::for_players(players)
+    });
+    bindings.truncate(players);
+    let keyboard: Vec<_> = Action::ALL
+        .into_iter()
+        .flat_map(|action| bindings[0].keys(action).map(move |key| (action, key)))
+        .collect();
+    let mut controls = Controls::new(&sdl_context, canvas.window().size(), bindings).unwrap();
+    let mut audio = Audio::new(&sdl_context);
+
+    let spec_path = assets.path("cars/default.toml");
+    let spec = CarSpec::load(&spec_path).unwrap_or_else(|error| {
+        eprintln!(
+            "couldn't load {}, using the built-in car: {error}",
+            spec_path.display()
+        );
+        CarSpec::default()
+    });
+    let camera_path = assets.path("camera.toml");
+    let camera = CameraSpec::load(&camera_path).unwrap_or_else(|error| {
+        eprintln!(
+            "couldn't load {}, using the built-in camera: {error}",
+            camera_path.display()
+        );
+        CameraSpec::default()
+    });
+    let trail_path = assets.path("trail.toml");
+    let trail = TrailSpec::load(&trail_path).unwrap_or_else(|error| {
+        eprintln!(
+            "couldn't load {}, using the built-in trail: {error}",
+            trail_path.display()
+        );
+        TrailSpec::default()
+    });
+    let video_path = assets.path("video.toml");
+    let video = VideoSpec::load(&video_path).unwrap_or_else(|error| {
+        eprintln!(
+            "couldn't load {}, using the built-in video settings: {error}",
+            video_path.display()
+        );
+        VideoSpec::default()
+    });
+    // changed from the settings some time before, over the files under the assets directory
+    let built_in = Settings {
+        filter: video.filter,
+        ..Settings::default()
+    };
+    let mut settings = built_in.clone();
+    match settings.load(settings::FILE) {
+        // none changed yet
+        Ok(()) | Err(ConfigError::Io(..)) => {}
+        Err(error) => {
+            eprintln!("couldn't load the settings in {}: {error}", settings::FILE);
+            settings = built_in.clone();
+        }
+    }
+    apply_display(canvas.window_mut(), &built_in, &settings);
+    audio.set_volume(settings.volume);
+    for &(action, key) in &settings.keys {
+        controls.bind_key(action, key);
+    }
+    if let Some(path) = &options.edit {
+        // a new track to start with when there's none there yet
+        let file = if Path::new(path).exists() {
+            match TrackFile::load(path) {
+                Ok(file) => file,
+                Err(error) => {
+                    eprintln!("couldn't load the track {path}: {error}");
+                    return;
+                }
+            }
+        } else {
+            TrackFile::new((32, 20), 125.)
+        };
+        let editor = Editor::new(file, PathBuf::from(path), spec, camera, players);
+        let mut frontend = Frontend {
+            canvas: &mut canvas,
+            assets: &mut assets,
+            controls: &mut controls,
+            audio: &mut audio,
+            frame_time: (!options.uncapped).then_some(frame_time),
+            filter: settings.filter,
+            font,
+            settings: settings.clone(),
+        };
+        run(
+            &mut SceneManager::new(Box::new(editor)),
+            &mut frontend,
+            None,
+            None,
+        );
+        return;
+    }
+    // the tracks to choose from, without one asked for, and to choose again from after a race
+    let tracks = assets.path("tracks");
+    let menu = (track_path.is_none() && playback.is_none() && options.generate.is_none())
+        .then(|| TrackMenu::new(&tracks))
+        .filter(TrackMenu::has_tracks);
+    let mut recording = options.record.map(|path| {
+        (
+            path,
+            Recording::new(seed, players, towing, raining, track_path.clone()),
+        )
+    });
+    // set up for every track it goes on to, once it's chosen and loaded
+    let mut level = Level::new(spec, camera, Track::new(), players, towing, seed);
+    level.set_raining(raining);
+    level.set_trail_spec(trail);
+    if playback.is_some() {
+        level.direct_replay();
+    }
+    level.recorded = playback.is_some() || recording.is_some();
+    level.set_settings(settings.clone(), keyboard);
+    // a recording's only of the one race, and only of what the players did while driving
+    if recording.is_some() {
+        level.console = None;
+    } else if menu.is_some() {
+        level.tracks = Some(tracks);
+    }
+    let source = match (&track_path, options.generate) {
+        (Some(path), _) => Source::File(path.clone()),
+        (None, Some(seed)) => Source::Generated(seed),
+        (None, None) => Source::BuiltIn,
+    };
+    // the players choose their car first, but replays and recordings stick to the usual one
+    let garage = (playback.is_none() && recording.is_none())
+        .then(|| Garage::new(&assets.path("cars")))
+        .filter(Garage::has_choice);
+    // to choose again from after a race
+    level.garage = garage.is_some().then(|| assets.path("cars"));
+    // kept hold of to ask which track was chosen once the recording's started
+    let mut chosen_from = None;
+    let first: Box<dyn Scene + '_> = match (garage, menu) {
+        (Some(garage), menu) => Box::new(garage.then(move |spec| {
+            level.setup.spec = spec;
+            match menu {
+                Some(menu) => Box::new(menu.then_drive(level)),
+                None => Box::new(Loading::new(source).then_drive(level)),
+            }
+        })),
+        (None, Some(menu)) => Box::new(chosen_from.insert(menu.then_drive(level))),
+        (None, None) => Box::new(Loading::new(source).then_drive(level)),
+    };
+    let mut scenes = SceneManager::new(first);
+    if let (Some(path), Some(playback)) = (&options.export, playback.take()) {
+        if let Err(error) = export(&mut canvas, &mut assets, scenes, playback, path) {
+            eprintln!("couldn't export the replay to {path}: {error}");
+        }
+        return;
+    }
+
+    let mut frontend = Frontend {
+        canvas: &mut canvas,
+        assets: &mut assets,
+        controls: &mut controls,
+        audio: &mut audio,
+        frame_time: (!options.uncapped).then_some(frame_time),
+        filter: settings.filter,
+        font,
+        settings: settings.clone(),
+    };
+    run(
+        &mut scenes,
+        &mut frontend,
+        playback,
+        recording.as_mut().map(|(_, recording)| recording),
+    );
+    drop(scenes);
+
+    if let Some((path, mut recording)) = recording {
+        if let Some(track) = chosen_from.as_ref().and_then(TrackMenu::chosen) {
+            recording.track = track.map(|path| path.to_string_lossy().into_owned());
+        }
+        if let Err(error) = recording.save(&path) {
+            eprintln!("couldn't save the recording to {path}: {error}");
+        }
+    }
+}
+
+/// What a scene is shown on and played with
+struct Frontend<'a, 'b> {
+    canvas: &'a mut Canvas<Window>,
+    assets: &'a mut Assets<'b>,
+    controls: &'a mut Controls,
+    audio: &'a mut Audio,
+    /// how long to give each frame, `None` to render as fast as we can
+    frame_time: Option<Duration>,
+    /// what the picture goes through on the way to the window
+    filter: Filter,
+    /// what the frame counter's written in
+    font: Option<FontId>,
+    /// as they're in effect
+    settings: Settings,
+}
+
+impl Frontend<'_, '_> {
+    /// Puts whatever's changed in `settings` into effect
+    fn apply(&mut self, settings: &Settings) {
+        apply_display(self.canvas.window_mut(), &self.settings, settings);
+        if settings.filter != self.settings.filter {
+            self.filter = settings.filter;
+        }
+        self.audio.set_volume(settings.volume);
+        for &(action, key) in &settings.keys {
+            if !self.settings.keys.contains(&(action, key)) {
+                self.controls.bind_key(action, key);
+            }
+        }
+        self.settings = settings.clone();
+    }
+}
+
+/// Plays `scenes` until the last one's over, recording the players' input to `recording` or
+/// taking it from `playback` instead while the ones on top are recorded
+fn run(
+    scenes: &mut SceneManager,
+    frontend: &mut Frontend,
+    mut playback: Option<Playback>,
+    mut recording: Option<&mut Recording>,
+) {
+    let tick = Duration::from_secs_f64(1.0 / TICKS_PER_SECOND);
+    // never try to catch up on more than this many ticks at once, or a slow frame makes the
+    // next one even slower
+    let max_ticks_per_frame = 5;
+    let mut unsimulated = Duration::ZERO;
+    let mut last_frame = Instant::now();
+    let mut screen = frontend
+        .assets
+        .target(SCREEN_DIMENSIONS.0 as u32, SCREEN_DIMENSIONS.1 as u32);
+    let mut frame_counter = FrameCounter::new(frontend.font);
+    let mut show_frame_counter = false;
+    // what happened to the last screenshot, shown for a moment after it's taken
+    let mut toast: Option<(String, Instant)> = None;
+    'game: loop {
+        let now = Instant::now();
+        frame_counter.frame(now - last_frame);
+        unsimulated = (unsimulated + (now - last_frame)).min(tick * max_ticks_per_frame);
+        last_frame = now;
+
+        let mut screenshot = false;
+        while unsimulated >= tick {
+            let mut inputs = frontend.controls.next_tick();
+            // the window's business, so they're left out of recordings
+            let mut fullscreen = false;
+            for input in &mut inputs {
+                fullscreen ^= input.commands.contains(&Command::ToggleFullscreen);
+                show_frame_counter ^= input.commands.contains(&Command::ToggleFrameCounter);
+                screenshot |= input.commands.contains(&Command::Screenshot);
+                if input.commands.contains(&Command::NextFilter) {
+                    frontend.filter = frontend.filter.next();
+                }
+                input.commands.retain(|command| {
+                    !matches!(
+                        command,
+                        Command::ToggleFullscreen
+                            | Command::ToggleFrameCounter
+                            | Command::Screenshot
+                            | Command::NextFilter
+                    )
+                });
+            }
+            if fullscreen {
+                toggle_fullscreen(frontend.canvas.window_mut());
+            }
+            let recorded = scenes.recorded();
+            if let Some(playback) = playback.as_mut().filter(|_| recorded) {
+                // the recording does all the driving, but the players can still quit
+                let quitting = inputs
+                    .iter()
+                    .any(|input| input.commands.contains(&Command::Quit));
+                inputs = playback.next_tick();
+                if quitting {
+                    if let Some(first) = inputs.first_mut() {
+                        first.commands.push(Command::Quit);
+                    }
+                }
+            }
+            if let Some(recording) = recording.as_mut().filter(|_| recorded) {
+                recording.push(&inputs);
+            }
+            if !scenes.update(&inputs) {
+                break 'game;
+            };
+            let window = frontend.canvas.window_mut();
+            if let Some(title) = scenes.title().filter(|&title| title != window.title()) {
+                if let Err(error) = window.set_title(title) {
+                    eprintln!("couldn't name the window after the track: {error}");
+                }
+            }
+            // a replay's settings menu is whoever recorded it changing theirs
+            if let Some(settings) = scenes.settings().filter(|_| playback.is_none()) {
+                if *settings != frontend.settings {
+                    frontend.apply(settings);
+                }
+            }
+            frontend.audio.play(frontend.assets, scenes.sounds());
+            unsimulated -= tick;
+        }
+        let alpha = unsimulated.as_secs_f64() / tick.as_secs_f64();
+        frontend.controls.rumble(scenes.rumbles());
+
+        scenes.load_sprites(frontend.assets);
+        let Frontend { canvas, assets, .. } = frontend;
+        canvas
+            .with_texture_canvas(&mut screen, |screen_canvas| {
+                scenes.render(screen_canvas, assets, alpha);
+                if show_frame_counter {
+                    frame_counter.draw(screen_canvas, assets, SCREEN_DIMENSIONS);
+                }
+                if let Some((message, _)) = &toast {
+                    draw_toast(screen_canvas, message);
+                }
+            })
+            .unwrap();
+
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+        let picture = letterbox(canvas.output_size().unwrap());
+        video::present(canvas, assets, &screen, picture, frontend.filter);
+        if screenshot {
+            let message = match screenshot::save(canvas, picture) {
+                Ok(path) => format!("SAVED {}", path.display()),
+                Err(error) => {
+                    eprintln!("couldn't save a screenshot: {error}");
+                    "COULDN'T SAVE THE SCREENSHOT".to_string()
+                }
+            };
+            toast = Some((message, now));
+        }
+        if toast
+            .as_ref()
+            .is_some_and(|(_, shown)| shown.elapsed() > Duration::from_secs(3))
+        {
+            toast = None;
+        }
+        canvas.present();
+
+        // vsync holds `present` back until the display's ready for another frame already,
+        // but not every driver does vsync, and the display could be running at any rate
+        if let Some(frame_time) = frontend.frame_time {
+            if let Some(left) = frame_time.checked_sub(now.elapsed()) {
+                std::thread::sleep(left);
+            }
+        }
+    }
+}
+
+/// Plays `playback` back on the level `scenes` go on to as fast as it'll go, filming it to a
+/// video at `path`
+fn export(
+    canvas: &mut Canvas<Window>,
+    assets: &mut Assets,
+    mut scenes: SceneManager,
+    mut playback: Playback,
+    path: &str,
+) -> Result<(), String> {
+    // GIFs get big quickly, so they're smaller and choppier
+    let gif = path.to_ascii_lowercase().ends_with(".gif");
+    let ((width, height), fps) = if gif {
+        ((640, 360), 20)
+    } else {
+        ((1280, 720), 30)
+    };
+    let ticks_per_frame = TICKS_PER_SECOND as u32 / fps;
+    let mut exporter =
+        Exporter::create(path, width, height, fps).map_err(|error| error.to_string())?;
+
+    let mut screen = assets.target(SCREEN_DIMENSIONS.0 as u32, SCREEN_DIMENSIONS.1 as u32);
+    let mut frame = assets.target(width, height);
+    let mut ticks: u32 = 0;
+    while !playback.is_finished() {
+        // the loading before the level's left out of the video, and takes as long as it takes
+        if !scenes.recorded() {
+            if !scenes.update(&[]) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+            continue;
+        }
+        if !scenes.update(&playback.next_tick()) {
+            break;
+        }
+        ticks += 1;
+        if !ticks.is_multiple_of(ticks_per_frame) {
+            continue;
+        }
+        scenes.load_sprites(assets);
+        // right on the tick, with nothing to smooth out
+        canvas
+            .with_texture_canvas(&mut screen, |screen_canvas| {
+                scenes.render(screen_canvas, assets, 1.)
+            })
+            .map_err(|error| error.to_string())?;
+        let mut pixels = Ok(Vec::new());
+        canvas
+            .with_texture_canvas(&mut frame, |frame_canvas| {
+                frame_canvas.copy(&screen, None, None).unwrap();
+                pixels = frame_canvas.read_pixels(None, PixelFormatEnum::RGB24);
+            })
+            .map_err(|error| error.to_string())?;
+        exporter
+            .frame(&pixels?)
+            .map_err(|error| error.to_string())?;
+    }
+    assets.give_back(screen);
+    assets.give_back(frame);
+    exporter.finish().map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use car::CarPedals;
+
+    /// Some driving with a bit of everything in it: launching, drifting, boosting, towing.
+    /// A second player drives the same, only steering the other way.
+    struct Script {
+        tick: u32,
+        players: usize,
+    }
+
+    impl InputSource for Script {
+        fn next_tick(&mut self) -> Vec<TickInput> {
+            let tick = self.tick;
+            self.tick += 1;
+            (0..self.players)
+                .map(|player| {
+                    let mut input = scripted_input(tick);
+                    if player == 1 {
+                        input.car.steering = -input.car.steering;
+                    }
+                    input
+                })
+                .collect()
+        }
+    }
+
+    fn scripted_input(tick: u32) -> TickInput {
+        let mut commands = Vec::new();
+        if tick == 30 {
+            commands.push(Command::ToggleTowing);
+        }
+        if tick == 200 {
+            commands.push(Command::Respawn);
+        }
+        TickInput {
+            car: CarInput {
+                // rolling onto the throttle, then leaning on the brakes into reversing
+                pedals: if tick % 150 < 120 {
+                    CarPedals {
+                        throttle: (tick % 150) as f64 / 60.,
+                        brake: 0.,
+                    }
+                } else {
+                    CarPedals {
+                        throttle: 0.,
+                        brake: 0.7,
+                    }
+                },
+                steering: match tick / 40 % 3 {
+                    0 => -1.,
+                    1 => 0.,
+                    _ => 0.6,
+                },
+                handbrake: tick % 90 > 80,
+                boosting: tick > 100,
+            },
+            aim: None,
+            commands,
+            pointer: None,
+            key: None,
+            text: String::new(),
+        }
+    }
+
+    fn run(seed: u64, players: usize) -> Vec<(Point2<f64>, f64, f64)> {
+        let mut level = Level::new(
+            CarSpec::default(),
+            CameraSpec::default(),
+            Track::new(),
+            players,
+            false,
+            seed,
+        );
+        let mut script = Script { tick: 0, players };
+        for _ in 0..400 {
+            level.step(&script.next_tick());
+        }
+        level
+            .cars
+            .iter()
+            .map(|car| (car.center(), car.rotation().angle(), car.speed()))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_out_the_same() {
+        assert_eq!(run(7, 1), run(7, 1));
+        assert_eq!(run(7, 2), run(7, 2));
+    }
+
+    #[test]
+    fn seed_changes_the_grid() {
+        assert_ne!(run(7, 1), run(8, 1));
+    }
+
+    #[test]
+    fn the_ai_gets_round_checkpoints_in_order() {
+        let mut level = Level::new(
+            CarSpec::default(),
+            CameraSpec::default(),
+            Track::new(),
+            1,
+            false,
+            7,
+        );
+        for _ in 0..(40. * TICKS_PER_SECOND) as u32 {
+            level.step(&[TickInput::default()]);
+        }
+        assert!(level.lap_timers[1].progress() >= 2);
+        assert!(level.lap_timers[1].is_lap_valid());
+    }
+
+    #[test]
+    fn unplugging_a_controller_pauses_until_unpaused() {
+        let mut level = Level::new(
+            CarSpec::default(),
+            CameraSpec::default(),
+            Track::new(),
+            1,
+            false,
+            7,
+        );
+        let mut script = Script {
+            tick: 0,
+            players: 1,
+        };
+        for _ in 0..100 {
+            level.step(&script.next_tick());
+        }
+        let mut inputs = script.next_tick();
+        inputs[0].commands.push(Command::ControllerLost);
+        level.step(&inputs);
+        let paused_at = level.cars[0].center();
+        for _ in 0..50 {
+            level.step(&script.next_tick());
+        }
+        assert_eq!(level.cars[0].center(), paused_at);
+
+        let mut inputs = script.next_tick();
+        inputs[0].commands.push(Command::TogglePause);
+        level.step(&inputs);
+        assert!(level.lost_controller.is_none());
+        assert_ne!(level.cars[0].center(), paused_at);
+    }
+
+    #[test]
+    fn escape_pauses_everything_until_a_restart_puts_the_cars_back() {
+        let new_level = || {
+            Level::new(
+                CarSpec::default(),
+                CameraSpec::default(),
+                Track::new(),
+                1,
+                false,
+                7,
+            )
+        };
+        let mut level = new_level();
+        let mut script = Script {
+            tick: 0,
+            players: 1,
+        };
+        for _ in 0..300 {
+            level.step(&script.next_tick());
+        }
+        let mut inputs = script.next_tick();
+        inputs[0].commands.push(Command::Back);
+        let paused_at = level.cars[0].center();
+        let Transition::Push(mut menu) = level.update(&inputs) else {
+            panic!("the pause menu didn't open");
+        };
+        assert_eq!(level.cars[0].center(), paused_at);
+
+        // down from resume to restart, and picking it
+        let mut down = TickInput::default();
+        down.car.pedals.brake = 1.;
+        let mut pick = TickInput::default();
+        pick.commands.push(Command::TogglePause);
+        for input in [TickInput::default(), down] {
+            assert!(matches!(menu.update(&[input]), Transition::Stay));
+        }
+        assert!(matches!(menu.update(&[pick]), Transition::Pop));
+        assert!(matches!(
+            level.update(&[TickInput::default()]),
+            Transition::Replace(_)
+        ));
+        assert_eq!(level.cars[0].center(), paused_at);
+        let level = level.restarted();
+        assert!(level.pausing.is_none() && level.paused.is_none());
+        assert_eq!(level.cars[0].center(), new_level().cars[0].center());
+    }
+
+    #[test]
+    fn saved_assists_are_each_players_own_and_left_out_of_recordings() {
+        let new_level = |players| {
+            Level::new(
+                CarSpec::default(),
+                CameraSpec::default(),
+                Track::new(),
+                players,
+                false,
+                7,
+            )
+        };
+        let usual = new_level(1).cars[0].assists();
+        let mut settings = Settings::default();
+        settings.assists.abs = !usual.abs;
+        let mut recorded = new_level(1);
+        recorded.recorded = true;
+        recorded.set_settings(settings.clone(), Vec::new());
+        assert_eq!(recorded.cars[0].assists(), usual);
+
+        // the second player's changed theirs, which they keep on the next track
+        let mut level = new_level(2);
+        level.apply_settings(1, &settings);
+        let level = level.on_track(Track::new());
+        assert_eq!(level.cars[0].assists(), usual);
+        assert_eq!(level.cars[1].assists(), settings.assists);
+    }
+
+    #[test]
+    fn rain_takes_grip_away_and_more_in_puddles() {
+        let mut level = Level::new(
+            CarSpec::default(),
+            CameraSpec::default(),
+            Track::new(),
+            1,
+            false,
+            7,
+        );
+        let puddle = level.track.puddles[0].center;
+        let beside = puddle + Vector2::new(-400., 0.);
+        let dry = level.track.grip_at(beside).lateral;
+        assert_eq!(level.track.grip_at(puddle).lateral, dry);
+
+        let mut inputs = vec![TickInput::default()];
+        inputs[0].commands.push(Command::ToggleRain);
+        level.step(&inputs);
+        let wet = level.track.grip_at(beside).lateral;
+        assert!(wet < dry);
+        assert!(level.track.grip_at(puddle).lateral < wet);
+    }
+
+    #[test]
+    fn the_console_tunes_teleports_and_spawns() {
+        let mut level = Level::new(
+            CarSpec::default(),
+            CameraSpec::default(),
+            Track::new(),
+            1,
+            false,
+            7,
+        );
+        let mut console = Level::console();
+        console.run(&mut level, "SET CAR.ENGINE_FORCE 2.5");
+        assert_eq!(level.cars[0].spec().engine_force, 2.5);
+        assert_eq!(
+            level.cars[1].spec().engine_force,
+            CarSpec::default().engine_force
+        );
+        console.run(&mut level, "tp 300 400");
+        assert_eq!(level.cars[0].center(), Point2::new(300., 400.));
+
+        console.run(&mut level, "spawn ai 2");
+        assert_eq!(level.cars.len(), FIELD_SIZE + 2);
+        assert_eq!(level.standings, [0, 1, 2, 3, 4]);
+        console.run(&mut level, "spawn ai 1000");
+        assert_eq!(level.cars.len(), MAX_CARS);
+        // and the new ones drive with the rest
+        level.step(&[TickInput::default()]);
+
+        console.run(&mut level, "load track no_such_track");
+        assert!(level.next_track.is_none());
+    }
+
+    #[test]
+    fn windows_start_in_the_pictures_shape() {
+        assert_eq!(
+            window_size(Some(Rect::new(0, 25, 2560, 1415))),
+            (2012, 1132)
+        );
+        assert_eq!(window_size(None), (1600, 900));
+    }
+
+    #[test]
+    fn letterbox_keeps_the_picture_in_shape() {
+        assert_eq!(letterbox((2560, 1440)), Rect::new(0, 0, 2560, 1440));
+        // bars on the sides of an ultrawide, and above and below a square window
+        assert_eq!(letterbox((3440, 1440)), Rect::new(440, 0, 2560, 1440));
+        assert_eq!(letterbox((1000, 1000)), Rect::new(0, 218, 1000, 563));
     }
 }
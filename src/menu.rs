@@ -0,0 +1,111 @@
+//! Moving through a menu's options with the pedals and the steering, the same in every menu
+
+use crate::input::TickInput;
+
+/// How far down the throttle or brake has to go, or the steering over, to move
+pub const PRESSED: f64 = 0.5;
+
+/// Two ways to go, one either side, counted once a press rather than every tick they're held
+#[derive(Default)]
+pub struct Presses {
+    /// whether either was held last tick
+    held: bool,
+}
+
+impl Presses {
+    /// Waiting for both to be let go before counting one, for a menu opened in the middle of
+    /// driving, as whatever the player was doing then shouldn't move it straight away
+    pub fn held() -> Presses {
+        Presses { held: true }
+    }
+
+    /// -1 on the tick `back` is pressed, 1 on the tick `forward` is, and 0 otherwise
+    pub fn update(&mut self, back: bool, forward: bool) -> i32 {
+        let step = match (self.held, back, forward) {
+            (true, ..) => 0,
+            (false, true, _) => -1,
+            (false, false, true) => 1,
+            (false, false, false) => 0,
+        };
+        self.held = back || forward;
+        step
+    }
+}
+
+/// Which of a list of options is selected, the throttle going up the list and the brake down
+/// it, round from one end to the other
+pub struct Selection {
+    selected: usize,
+    presses: Presses,
+}
+
+impl Selection {
+    pub fn new(selected: usize) -> Selection {
+        Selection {
+            selected,
+            presses: Presses::default(),
+        }
+    }
+
+    /// Starting on `selected` once the pedals have been let go, like `Presses::held`
+    pub fn held(selected: usize) -> Selection {
+        Selection {
+            selected,
+            presses: Presses::held(),
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Moves by `input`, the tick's, through a list of `count` options
+    pub fn update(&mut self, input: &TickInput, count: usize) {
+        let pedals = input.car.pedals;
+        let step = self
+            .presses
+            .update(pedals.throttle > PRESSED, pedals.brake > PRESSED);
+        if count > 0 {
+            self.selected = (self.selected as i32 + step).rem_euclid(count as i32) as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::car::{CarInput, CarPedals};
+
+    fn pedals(throttle: f64, brake: f64) -> TickInput {
+        TickInput {
+            car: CarInput {
+                pedals: CarPedals { throttle, brake },
+                ..CarInput::default()
+            },
+            ..TickInput::default()
+        }
+    }
+
+    #[test]
+    fn selections_move_once_a_press_and_go_round_the_ends() {
+        let mut selection = Selection::held(0);
+        // held from before the menu, so it takes letting go first
+        selection.update(&pedals(0., 1.), 3);
+        assert_eq!(selection.selected(), 0);
+        selection.update(&pedals(0., 0.), 3);
+        selection.update(&pedals(0., 1.), 3);
+        selection.update(&pedals(0., 1.), 3);
+        assert_eq!(selection.selected(), 1);
+
+        let mut selection = Selection::new(0);
+        selection.update(&pedals(1., 0.), 3);
+        assert_eq!(selection.selected(), 2);
+        selection.update(&pedals(0., 0.), 3);
+        selection.update(&pedals(0., 1.), 3);
+        assert_eq!(selection.selected(), 0);
+        // nothing to choose from stays put
+        selection.update(&pedals(0., 0.), 0);
+        selection.update(&pedals(0., 1.), 0);
+        assert_eq!(selection.selected(), 0);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::config::{ConfigError, Table};
+
+#[derive(Clone)]
+pub struct NitroSpec {
+    /// charge gained per tick spent drifting, a full tank being 1
+    pub charge_rate: f64,
+    /// charge used up per tick of boosting
+    pub burn_rate: f64,
+    /// multiplier on acceleration and top speed while boosting
+    pub boost: f64,
+    /// slip angle in radians from which on the car counts as drifting
+    pub drift_angle: f64,
+    /// the car has to be going at least this fast for a slide to count
+    pub drift_speed: f64,
+}
+
+impl Default for NitroSpec {
+    fn default() -> NitroSpec {
+        NitroSpec {
+            charge_rate: 0.004,
+            burn_rate: 0.01,
+            boost: 1.6,
+            drift_angle: 0.25,
+            drift_speed: 3.,
+        }
+    }
+}
+
+impl NitroSpec {
+    /// Overwrites whatever `table` specifies
+    pub fn read(&mut self, table: &Table) -> Result<(), ConfigError> {
+        table.read_number("charge_rate", &mut self.charge_rate)?;
+        table.read_number("burn_rate", &mut self.burn_rate)?;
+        table.read_number("boost", &mut self.boost)?;
+        table.read_number("drift_angle", &mut self.drift_angle)?;
+        table.read_number("drift_speed", &mut self.drift_speed)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct Nitro {
+    charge: f64,
+    active: bool,
+}
+
+impl Nitro {
+    /// How full the tank is, from 0 to 1
+    pub fn charge(&self) -> f64 {
+        self.charge
+    }
+
+    /// Whether the boost is burning this tick, for drawing the flames
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Fills the tank while drifting and burns it while `boosting` is held. Returns the
+    /// multiplier for acceleration and top speed.
+    pub fn update(&mut self, spec: &NitroSpec, boosting: bool, drifting: bool) -> f64 {
+        if drifting {
+            self.charge = (self.charge + spec.charge_rate).min(1.);
+        }
+
+        self.active = boosting && self.charge > 0.;
+        if self.active {
+            self.charge = (self.charge - spec.burn_rate).max(0.);
+            spec.boost
+        } else {
+            1.
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use sdl2::{
+    keyboard::Scancode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::{
+    assets::Assets,
+    bindings::Action,
+    input::{Command, TickInput},
+    menu::Selection,
+    scene::{Outcome, Scene, Transition},
+    settings::Settings,
+    settings_menu::SettingsMenu,
+    text, SCREEN_DIMENSIONS,
+};
+
+/// What the player wants done once they've chosen from the menu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PauseChoice {
+    Resume,
+    /// the level over again from the start
+    Restart,
+    Quit,
+}
+
+/// How the menu went, for the level it paused
+#[derive(Debug, PartialEq)]
+pub struct Paused {
+    pub choice: PauseChoice,
+    /// as the settings menu was left, `None` if it wasn't opened
+    pub settings: Option<Settings>,
+}
+
+/// `None` being the settings, opened over the menu and coming back to it after
+const OPTIONS: [(&str, Option<PauseChoice>); 4] = [
+    ("RESUME", Some(PauseChoice::Resume)),
+    ("RESTART", Some(PauseChoice::Restart)),
+    ("SETTINGS", None),
+    ("QUIT", Some(PauseChoice::Quit)),
+];
+
+/// What a player who paused the level can do before carrying on, drawn over it, up and down
+/// going by the throttle and brake like in the track menu and the pause button picking one.
+/// Going back carries on.
+pub struct PauseMenu {
+    /// who paused, the only one the menu listens to
+    player: usize,
+    selection: Selection,
+    /// to open the settings menu with, as it was last left
+    settings: Settings,
+    /// the first player's keys in the bindings, for the settings menu
+    keyboard: Vec<(Action, Scancode)>,
+    /// whether the settings menu's been opened
+    changed: bool,
+    /// what the settings menu hands the settings back through
+    from_settings: Outcome<Settings>,
+    outcome: Outcome<Paused>,
+}
+
+impl PauseMenu {
+    /// Paused by `player`, whose `settings` they are to change
+    pub fn new(player: usize, settings: Settings, keyboard: Vec<(Action, Scancode)>) -> PauseMenu {
+        PauseMenu {
+            player,
+            selection: Selection::held(0),
+            settings,
+            keyboard,
+            changed: false,
+            from_settings: Outcome::default(),
+            outcome: Outcome::default(),
+        }
+    }
+
+    /// Where how the menu went is handed back once it's over
+    pub fn outcome(&self) -> Outcome<Paused> {
+        self.outcome.clone()
+    }
+}
+
+impl Scene for PauseMenu {
+    /// Ends once the player's picked something other than the settings
+    fn update(&mut self, inputs: &[TickInput]) -> Transition {
+        if let Some(settings) = self.from_settings.take() {
+            self.settings = settings;
+            self.changed = true;
+        }
+        let Some(input) = inputs.get(self.player) else {
+            return Transition::Stay;
+        };
+        for command in &input.commands {
+            let picked = match command {
+                Command::TogglePause => OPTIONS[self.selection.selected()].1,
+                Command::Back => Some(PauseChoice::Resume),
+                // straight out of the level, as it would be without the menu
+                Command::Quit => Some(PauseChoice::Quit),
+                _ => continue,
+            };
+            let Some(choice) = picked else {
+                let menu =
+                    SettingsMenu::new(self.player, self.settings.clone(), self.keyboard.clone());
+                return Transition::Push(Box::new(menu.reporting_to(self.from_settings.clone())));
+            };
+            self.outcome.set(Paused {
+                choice,
+                settings: self.changed.then(|| self.settings.clone()),
+            });
+            return Transition::Pop;
+        }
+        self.selection.update(input, OPTIONS.len());
+        Transition::Stay
+    }
+
+    fn overlays(&self) -> bool {
+        true
+    }
+
+    fn recorded(&self) -> bool {
+        true
+    }
+
+    /// Dims the whole screen and lists the options over it
+    fn render(&self, canvas: &mut Canvas<Window>, _assets: &Assets, _alpha: f64) {
+        let (width, height) = SCREEN_DIMENSIONS;
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+        canvas
+            .fill_rect(Rect::new(0, 0, width as u32, height as u32))
+            .unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+
+        let title = format!("PAUSED BY PLAYER {}", self.player + 1);
+        text::draw_centered(canvas, &title, (width / 2, 260), 8, Color::WHITE);
+        for (i, (name, _)) in OPTIONS.iter().enumerate() {
+            let (label, color) = if i == self.selection.selected() {
+                (format!("- {name} -"), Color::RGB(255, 220, 0))
+            } else {
+                (name.to_string(), Color::RGB(160, 160, 160))
+            };
+            let top = 420 + i as i32 * 90;
+            text::draw_centered(canvas, &label, (width / 2, top), 5, color);
+        }
+        text::draw_centered(
+            canvas,
+            "UP AND DOWN TO CHOOSE, RETURN OR START TO PICK, ESCAPE TO GO BACK",
+            (width / 2, height - 80),
+            3,
+            Color::RGB(200, 200, 200),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::car::{CarInput, CarPedals};
+
+    fn pressing(command: Command) -> TickInput {
+        TickInput {
+            commands: vec![command],
+            ..TickInput::default()
+        }
+    }
+
+    fn pedals(throttle: f64, brake: f64) -> TickInput {
+        TickInput {
+            car: CarInput {
+                pedals: CarPedals { throttle, brake },
+                ..CarInput::default()
+            },
+            ..TickInput::default()
+        }
+    }
+
+    /// With the second player's `input`, the first one pressing pause all the while
+    fn tick(menu: &mut PauseMenu, input: &TickInput) -> Transition {
+        menu.update(&[pressing(Command::TogglePause), input.clone()])
+    }
+
+    #[test]
+    fn options_are_picked_one_press_at_a_time() {
+        let mut menu = PauseMenu::new(1, Settings::default(), Vec::new());
+        let outcome = menu.outcome();
+        let (idle, up, down) = (pedals(0., 0.), pedals(1., 0.), pedals(0., 1.));
+
+        // down to the settings, holding it only going one option at a time
+        for input in [&down, &idle, &down, &down, &idle, &down] {
+            assert!(matches!(tick(&mut menu, input), Transition::Stay));
+        }
+        assert!(matches!(
+            tick(&mut menu, &pressing(Command::TogglePause)),
+            Transition::Push(_)
+        ));
+        // and back from them turned down
+        let quieter = Settings {
+            volume: 0.5,
+            ..Settings::default()
+        };
+        menu.from_settings.set(quieter.clone());
+
+        // then up to the restart
+        for input in [&idle, &up] {
+            assert!(matches!(tick(&mut menu, input), Transition::Stay));
+        }
+        assert!(matches!(
+            tick(&mut menu, &pressing(Command::TogglePause)),
+            Transition::Pop
+        ));
+        assert_eq!(
+            outcome.take(),
+            Some(Paused {
+                choice: PauseChoice::Restart,
+                settings: Some(quieter),
+            })
+        );
+
+        // going back carries on, with the settings as they were
+        let mut menu = PauseMenu::new(0, Settings::default(), Vec::new());
+        let outcome = menu.outcome();
+        assert!(matches!(
+            menu.update(&[pressing(Command::Back)]),
+            Transition::Pop
+        ));
+        assert_eq!(
+            outcome.take(),
+            Some(Paused {
+                choice: PauseChoice::Resume,
+                settings: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{audio::Sound, TICKS_PER_SECOND};
+
+/// Ticks from the cars lining up on the grid to the lights going green
+const COUNTDOWN: u32 = 3 * TICKS_PER_SECOND as u32;
+/// Ticks "GO" stays up for once they have
+const GO_SHOWN: u32 = TICKS_PER_SECOND as u32;
+/// Ticks a car that jumped the start is held back for once the rest are off
+const PENALTY: u32 = 2 * TICKS_PER_SECOND as u32;
+/// Further down than this, the throttle counts as going
+const JUMP_THROTTLE: f64 = 0.2;
+
+/// The start of a race: the cars held on the grid while it counts down from 3, then let go
+/// when the lights turn green. Going for the throttle before then jumps the start, and the
+/// car's held back for a while after the rest have gone.
+pub struct RaceStart {
+    /// ticks since the cars lined up
+    ticks: u32,
+    /// whether each car went too early
+    jumped: Vec<bool>,
+}
+
+impl RaceStart {
+    pub fn new(cars: usize) -> RaceStart {
+        RaceStart {
+            ticks: 0,
+            jumped: vec![false; cars],
+        }
+    }
+
+    /// Counts on a tick with each car's throttle as far down as in `throttles`, adding any
+    /// beeps to `sounds`
+    pub fn update(&mut self, throttles: &[f64], sounds: &mut Vec<Sound>) {
+        if !self.is_green() {
+            for (jumped, &throttle) in self.jumped.iter_mut().zip(throttles) {
+                if throttle > JUMP_THROTTLE && !*jumped {
+                    *jumped = true;
+                    sounds.push(Sound::JumpStart);
+                }
+            }
+        }
+        if self.ticks.is_multiple_of(TICKS_PER_SECOND as u32) && self.ticks < COUNTDOWN {
+            sounds.push(Sound::Countdown);
+        }
+        self.ticks += 1;
+        if self.ticks == COUNTDOWN {
+            sounds.push(Sound::Go);
+        }
+    }
+
+    pub fn is_green(&self) -> bool {
+        self.ticks >= COUNTDOWN
+    }
+
+    /// Whether `car` has to stay put, before the start or while serving its penalty
+    pub fn is_held(&self, car: usize) -> bool {
+        let penalty = if self.jumped(car) { PENALTY } else { 0 };
+        self.ticks < COUNTDOWN + penalty
+    }
+
+    pub fn jumped(&self, car: usize) -> bool {
+        self.jumped.get(car).copied().unwrap_or(false)
+    }
+
+    /// What the countdown shows: the seconds left, then "GO" for a moment
+    pub fn shown(&self) -> Option<String> {
+        if self.ticks < COUNTDOWN {
+            let left = (COUNTDOWN - self.ticks).div_ceil(TICKS_PER_SECOND as u32);
+            Some(left.to_string())
+        } else if self.ticks < COUNTDOWN + GO_SHOWN {
+            Some("GO".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Whether everyone's away and there's nothing left to show
+    pub fn is_over(&self) -> bool {
+        self.ticks >= COUNTDOWN + GO_SHOWN.max(PENALTY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jumping_the_start_holds_the_car_back() {
+        let mut start = RaceStart::new(2);
+        let mut sounds = Vec::new();
+        // the second car goes for it with a second to go
+        for tick in 0..COUNTDOWN {
+            let early = if tick > 2 * TICKS_PER_SECOND as u32 {
+                1.
+            } else {
+                0.
+            };
+            start.update(&[0., early], &mut sounds);
+        }
+        assert_eq!(
+            sounds,
+            [
+                Sound::Countdown,
+                Sound::Countdown,
+                Sound::Countdown,
+                Sound::JumpStart,
+                Sound::Go
+            ]
+        );
+        assert_eq!(start.shown().as_deref(), Some("GO"));
+        assert!(!start.is_held(0));
+        assert!(start.is_held(1));
+        for _ in 0..PENALTY {
+            start.update(&[1., 1.], &mut sounds);
+        }
+        assert!(!start.is_held(1));
+        assert!(start.is_over());
+    }
+}
@@ -0,0 +1,295 @@
+//! Recordings of everything the players did, tick by tick. The level plays out the same from
+//! the same seed and inputs, so that's all it takes to watch a run again.
+
+use std::{fs, io, path::Path};
+
+use nalgebra::Point2;
+
+use crate::{
+    car::{CarInput, CarPedals},
+    input::{Command, InputSource, TickInput},
+};
+
+/// Start of every recording file, the last byte being the format version
+const MAGIC: &[u8; 8] = b"DRFTREC5";
+
+/// How a level was set up and every player's input for every tick that was played on it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    pub seed: u64,
+    pub players: usize,
+    /// whether the level started with a trailer hitched up
+    pub towing: bool,
+    /// whether it was raining from the start
+    pub raining: bool,
+    /// the map the level was played on, `None` for the built-in track
+    pub track: Option<String>,
+    /// one input per player for every tick
+    ticks: Vec<Vec<TickInput>>,
+}
+
+impl Recording {
+    pub fn new(
+        seed: u64,
+        players: usize,
+        towing: bool,
+        raining: bool,
+        track: Option<String>,
+    ) -> Recording {
+        Recording {
+            seed,
+            players,
+            towing,
+            raining,
+            track,
+            ticks: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, inputs: &[TickInput]) {
+        self.ticks.push(inputs.to_vec());
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Recording> {
+        Recording::decode(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.encode())
+    }
+
+    /// Feeds the recorded ticks back one after the other
+    pub fn playback(self) -> Playback {
+        Playback {
+            players: self.players,
+            ticks: self.ticks.into_iter(),
+        }
+    }
+
+    /// Runs of identical ticks are stored once with how often they repeat, which takes care
+    /// of most of a run spent holding the same keys
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(self.seed.to_le_bytes());
+        bytes.push(self.towing as u8);
+        bytes.push(self.raining as u8);
+        bytes.push(self.players as u8);
+        // its length first, 0 being the built-in track
+        let track = self.track.as_deref().unwrap_or("").as_bytes();
+        bytes.extend((track.len() as u16).to_le_bytes());
+        bytes.extend(track);
+
+        let mut ticks = self.ticks.iter().peekable();
+        while let Some(inputs) = ticks.next() {
+            let mut repeats: u32 = 1;
+            while repeats < u32::MAX && ticks.next_if_eq(&inputs).is_some() {
+                repeats += 1;
+            }
+            bytes.extend(repeats.to_le_bytes());
+            for input in inputs {
+                encode_input(&mut bytes, input);
+            }
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Recording> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a recording, or one from another version"));
+        }
+        let seed = u64::from_le_bytes(reader.array()?);
+        let towing = reader.byte()? != 0;
+        let raining = reader.byte()? != 0;
+        let players = reader.byte()? as usize;
+        if !(1..=2).contains(&players) {
+            return Err(invalid("a recording is of one or two players"));
+        }
+        let track_length = u16::from_le_bytes(reader.array()?) as usize;
+        let track = match reader.take(track_length)? {
+            [] => None,
+            bytes => Some(
+                String::from_utf8(bytes.to_vec())
+                    .map_err(|_| invalid("the track's path isn't text"))?,
+            ),
+        };
+
+        let mut ticks = Vec::new();
+        while !reader.bytes.is_empty() {
+            let repeats = u32::from_le_bytes(reader.array()?);
+            let inputs = (0..players)
+                .map(|_| decode_input(&mut reader))
+                .collect::<io::Result<Vec<_>>>()?;
+            ticks.extend(std::iter::repeat_n(inputs, repeats as usize));
+        }
+
+        Ok(Recording {
+            seed,
+            players,
+            towing,
+            raining,
+            track,
+            ticks,
+        })
+    }
+}
+
+fn encode_input(bytes: &mut Vec<u8>, input: &TickInput) {
+    // whole f64s, as anything rounded would play out differently from the run
+    let car = &input.car;
+    bytes.extend(car.pedals.throttle.to_le_bytes());
+    bytes.extend(car.pedals.brake.to_le_bytes());
+    bytes.extend(car.steering.to_le_bytes());
+    bytes.push(car.handbrake as u8 | (car.boosting as u8) << 1 | (input.aim.is_some() as u8) << 2);
+    if let Some(aim) = input.aim {
+        bytes.extend(aim.x.to_le_bytes());
+        bytes.extend(aim.y.to_le_bytes());
+    }
+    bytes.push(input.commands.len() as u8);
+    bytes.extend(input.commands.iter().map(|command| {
+        Command::ALL
+            .iter()
+            .position(|other| other == command)
+            .unwrap() as u8
+    }));
+}
+
+fn decode_input(reader: &mut Reader) -> io::Result<TickInput> {
+    let throttle = f64::from_le_bytes(reader.array()?);
+    let brake = f64::from_le_bytes(reader.array()?);
+    let steering = f64::from_le_bytes(reader.array()?);
+    let flags = reader.byte()?;
+    let aim = if flags & 4 != 0 {
+        let x = f64::from_le_bytes(reader.array()?);
+        let y = f64::from_le_bytes(reader.array()?);
+        Some(Point2::new(x, y))
+    } else {
+        None
+    };
+    let command_count = reader.byte()?;
+    let commands = (0..command_count)
+        .map(|_| {
+            let index = reader.byte()?;
+            Command::ALL
+                .get(index as usize)
+                .copied()
+                .ok_or_else(|| invalid("unknown command"))
+        })
+        .collect::<io::Result<_>>()?;
+    Ok(TickInput {
+        car: CarInput {
+            pedals: CarPedals { throttle, brake },
+            steering,
+            handbrake: flags & 1 != 0,
+            boosting: flags & 2 != 0,
+        },
+        aim,
+        commands,
+        pointer: None,
+        key: None,
+        text: String::new(),
+    })
+}
+
+/// Plays a recording back. Once it's over the players' cars are left to roll to a stop.
+pub struct Playback {
+    players: usize,
+    ticks: std::vec::IntoIter<Vec<TickInput>>,
+}
+
+impl Playback {
+    pub fn is_finished(&self) -> bool {
+        self.ticks.len() == 0
+    }
+}
+
+impl InputSource for Playback {
+    fn next_tick(&mut self) -> Vec<TickInput> {
+        self.ticks
+            .next()
+            .unwrap_or_else(|| vec![TickInput::default(); self.players])
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads a recording's bytes front to back
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < count {
+            return Err(invalid("recording ends in the middle of a tick"));
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordings_survive_a_round_trip() {
+        let mut recording = Recording::new(
+            42,
+            2,
+            true,
+            true,
+            Some("tracks/figure eight.tmx".to_string()),
+        );
+        let coasting = TickInput::default();
+        let drifting = TickInput {
+            car: CarInput {
+                pedals: CarPedals {
+                    throttle: 0.63,
+                    brake: 0.,
+                },
+                steering: -0.1 / 3.,
+                handbrake: true,
+                boosting: false,
+            },
+            aim: Some(Point2::new(0.25, 0.8)),
+            commands: vec![Command::ShiftUp, Command::Quit],
+            pointer: None,
+            key: None,
+            text: String::new(),
+        };
+        for inputs in [
+            [&coasting, &drifting],
+            [&coasting, &drifting],
+            [&drifting, &coasting],
+            [&coasting, &coasting],
+        ] {
+            recording.push(&inputs.map(TickInput::clone));
+        }
+
+        let bytes = recording.encode();
+        assert_eq!(Recording::decode(&bytes).unwrap(), recording);
+        assert!(Recording::decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn recordings_of_no_players_or_too_many_are_refused() {
+        for players in [0, 9] {
+            let mut bytes = Recording::new(42, 1, false, false, None).encode();
+            // the player count comes after the magic, the seed and the towing and rain flags
+            bytes[MAGIC.len() + 10] = players;
+            assert!(Recording::decode(&bytes).is_err());
+        }
+    }
+}
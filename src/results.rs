@@ -0,0 +1,261 @@
+use sdl2::{pixels::Color, render::Canvas, video::Window};
+
+use crate::{
+    assets::Assets,
+    input::{Command, TickInput},
+    laps,
+    menu::Selection,
+    scene::{Outcome, Scene, Transition},
+    text, SCREEN_DIMENSIONS,
+};
+
+/// What the players want once they've seen how the race went
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResultsChoice {
+    /// the same race over again
+    Restart,
+    /// back to the track menu to race somewhere else
+    ChangeTrack,
+    /// back to the menus the race was set up from, to choose the car again too
+    Menu,
+    Quit,
+}
+
+/// Where a car came in the race
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Finish {
+    pub car: usize,
+    /// ticks from the lights going green to crossing the line for the last time, `None` for
+    /// a car still going when the race ended
+    pub time: Option<u32>,
+}
+
+/// How a player got on over the race
+pub struct PlayerResult {
+    /// how long each lap took
+    pub laps: Vec<u32>,
+    /// the quickest each sector was gone through
+    pub best_sectors: Vec<u32>,
+    pub drift_score: u64,
+    /// starts jumped, checkpoints missed out and cones hit
+    pub penalties: u32,
+}
+
+/// How the race went once every player's over the line, shown in place of the level: where
+/// everyone came, the three first on the podium, and the players' laps and scores. Up and
+/// down go by the throttle and brake like in the other menus and the pause button picks what
+/// to do next, the first player doing the choosing.
+pub struct Results {
+    /// every car, the ones that finished first in the order they did
+    finishes: Vec<Finish>,
+    /// one for each player
+    players: Vec<PlayerResult>,
+    options: Vec<(&'static str, ResultsChoice)>,
+    selection: Selection,
+    outcome: Outcome<ResultsChoice>,
+}
+
+impl Results {
+    /// The results of a race over `finishes`, with changing track left out when there are no
+    /// tracks to change to, and quitting in place of going back to the menus when there are
+    /// none to go back to
+    pub fn new(
+        finishes: Vec<Finish>,
+        players: Vec<PlayerResult>,
+        change_track: bool,
+        menu: bool,
+    ) -> Results {
+        let mut options = vec![("RESTART", ResultsChoice::Restart)];
+        if change_track {
+            options.push(("CHANGE TRACK", ResultsChoice::ChangeTrack));
+        }
+        options.push(if menu {
+            ("MENU", ResultsChoice::Menu)
+        } else {
+            ("QUIT", ResultsChoice::Quit)
+        });
+        Results {
+            finishes,
+            players,
+            options,
+            selection: Selection::held(0),
+            outcome: Outcome::default(),
+        }
+    }
+
+    /// Where what the players picked is handed back once they have
+    pub fn outcome(&self) -> Outcome<ResultsChoice> {
+        self.outcome.clone()
+    }
+
+    /// What the `car`th car's called, the players' first and then the AI's
+    fn name(&self, car: usize) -> String {
+        let players = self.players.len();
+        if car < players {
+            format!("PLAYER {}", car + 1)
+        } else {
+            format!("AI {}", car - players + 1)
+        }
+    }
+}
+
+/// `place` as 1ST, 2ND, 3RD and so on
+fn ordinal(place: usize) -> String {
+    let suffix = match (place % 10, place % 100) {
+        (_, 11..=13) => "TH",
+        (1, _) => "ST",
+        (2, _) => "ND",
+        (3, _) => "RD",
+        _ => "TH",
+    };
+    format!("{place}{suffix}")
+}
+
+impl Scene for Results {
+    /// Ends once the players have picked what to do next
+    fn update(&mut self, inputs: &[TickInput]) -> Transition {
+        // the first player does the choosing
+        let Some(input) = inputs.first() else {
+            return Transition::Stay;
+        };
+        for command in &input.commands {
+            let choice = match command {
+                Command::TogglePause => self.options[self.selection.selected()].1,
+                Command::Quit => ResultsChoice::Quit,
+                _ => continue,
+            };
+            self.outcome.set(choice);
+            return Transition::Pop;
+        }
+        self.selection.update(input, self.options.len());
+        Transition::Stay
+    }
+
+    fn recorded(&self) -> bool {
+        true
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>, _assets: &Assets, _alpha: f64) {
+        let (width, height) = SCREEN_DIMENSIONS;
+        canvas.set_draw_color(Color::RGB(20, 24, 28));
+        canvas.clear();
+        text::draw_centered(canvas, "RESULTS", (width / 2, 100), 8, Color::WHITE);
+
+        // where everyone came down the left, the podium in gold, silver and bronze
+        let podium = [
+            Color::RGB(255, 220, 0),
+            Color::RGB(210, 210, 220),
+            Color::RGB(205, 127, 50),
+        ];
+        for (i, finish) in self.finishes.iter().enumerate() {
+            let color = podium.get(i).copied().unwrap_or(Color::RGB(160, 160, 160));
+            let time = finish.time.map_or("-".to_string(), laps::format_time);
+            let top = 220 + i as i32 * 60;
+            text::draw(canvas, &ordinal(i + 1), (160, top), 4, color);
+            text::draw(canvas, &self.name(finish.car), (300, top), 4, color);
+            text::draw(canvas, &time, (620, top), 4, color);
+        }
+
+        // and each player's laps and scores to the right of them
+        for (player, result) in self.players.iter().enumerate() {
+            let left = 960 + player as i32 * 480;
+            let mut top = 220;
+            let mut line = |canvas: &mut Canvas<Window>, line: &str, scale, color| {
+                text::draw(canvas, line, (left, top), scale, color);
+                top += text::height(scale) as i32 + 14;
+            };
+            line(canvas, &self.name(player), 4, Color::WHITE);
+            let best = result.laps.iter().min();
+            for (lap, &time) in result.laps.iter().enumerate() {
+                let color = if Some(&time) == best {
+                    Color::RGB(80, 230, 80)
+                } else {
+                    Color::RGB(200, 200, 200)
+                };
+                let lap = format!("LAP {} {}", lap + 1, laps::format_time(time));
+                line(canvas, &lap, 3, color);
+            }
+            let sectors: Vec<_> = result
+                .best_sectors
+                .iter()
+                .map(|&sector| laps::format_time(sector))
+                .collect();
+            if !sectors.is_empty() {
+                line(canvas, "BEST SECTORS", 3, Color::WHITE);
+                line(canvas, &sectors.join(" "), 2, Color::RGB(200, 200, 200));
+            }
+            let drift = format!("DRIFT {}", result.drift_score);
+            line(canvas, &drift, 3, Color::WHITE);
+            let penalties = format!("PENALTIES {}", result.penalties);
+            line(canvas, &penalties, 3, Color::WHITE);
+        }
+
+        for (i, (name, _)) in self.options.iter().enumerate() {
+            let (label, color) = if i == self.selection.selected() {
+                (format!("- {name} -"), Color::RGB(255, 220, 0))
+            } else {
+                (name.to_string(), Color::RGB(160, 160, 160))
+            };
+            let top = 800 + i as i32 * 60;
+            text::draw_centered(canvas, &label, (width / 2, top), 4, color);
+        }
+        text::draw_centered(
+            canvas,
+            "UP AND DOWN TO CHOOSE, RETURN OR START TO PICK",
+            (width / 2, height - 50),
+            3,
+            Color::RGB(200, 200, 200),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::car::{CarInput, CarPedals};
+
+    #[test]
+    fn changing_track_and_the_menus_are_only_offered_with_them_to_go_to() {
+        let pick = TickInput {
+            commands: vec![Command::TogglePause],
+            ..TickInput::default()
+        };
+        let down = TickInput {
+            car: CarInput {
+                pedals: CarPedals {
+                    throttle: 0.,
+                    brake: 1.,
+                },
+                ..CarInput::default()
+            },
+            ..TickInput::default()
+        };
+        let idle = TickInput::default();
+        for (change_track, menu, second) in [
+            (true, true, ResultsChoice::ChangeTrack),
+            (false, true, ResultsChoice::Menu),
+            (false, false, ResultsChoice::Quit),
+        ] {
+            let mut results = Results::new(Vec::new(), Vec::new(), change_track, menu);
+            let outcome = results.outcome();
+            // held over the line, so it takes letting go and pressing again to move
+            for input in [&down, &idle, &down] {
+                assert!(matches!(
+                    results.update(std::slice::from_ref(input)),
+                    Transition::Stay
+                ));
+            }
+            assert_eq!(outcome.take(), None);
+            assert!(matches!(
+                results.update(std::slice::from_ref(&pick)),
+                Transition::Pop
+            ));
+            assert_eq!(outcome.take(), Some(second));
+        }
+
+        assert_eq!(
+            [1, 2, 3, 4, 11, 12, 21, 22].map(ordinal),
+            ["1ST", "2ND", "3RD", "4TH", "11TH", "12TH", "21ST", "22ND"]
+        );
+    }
+}
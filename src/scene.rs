@@ -0,0 +1,383 @@
+use std::{cell::Cell, rc::Rc};
+
+use sdl2::{
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+use crate::{
+    assets::Assets,
+    audio::Sound,
+    input::{Rumble, TickInput},
+    settings::Settings,
+    SCREEN_DIMENSIONS, TICKS_PER_SECOND,
+};
+
+/// How long a scene takes to come in from behind an effect, in ticks
+const EFFECT_TICKS: u32 = (0.3 * TICKS_PER_SECOND) as u32;
+
+/// Something played on its own for a while, like a menu, a level or the editor, stacked up
+/// with the ones it was opened from
+pub trait Scene {
+    /// Advances the scene by one tick, given every player's input, saying what's played next
+    fn update(&mut self, inputs: &[TickInput]) -> Transition;
+    /// `alpha` is how far we are between the last tick and the next one, for smoothing out
+    /// movement on displays that refresh faster than we tick
+    fn render(&self, canvas: &mut Canvas<Window>, assets: &Assets, alpha: f64);
+    /// How hard each player's controller should shake
+    fn rumbles(&self) -> &[Rumble] {
+        &[]
+    }
+    /// What to play for the last tick
+    fn sounds(&self) -> &[Sound] {
+        &[]
+    }
+    /// Settings for the game to put into effect whenever they change, `None` to leave them
+    /// as they are
+    fn settings(&self) -> Option<&Settings> {
+        None
+    }
+    /// Loads whatever the scene's drawn with, once it's come on top and before it's first
+    /// drawn
+    fn load_sprites(&mut self, _assets: &mut Assets) {}
+    /// Whether it's drawn over the one under it rather than in place of it, like a menu
+    /// the level's paused with
+    fn overlays(&self) -> bool {
+        false
+    }
+    /// Whether the players' input is recorded while it's on top, and played back to it from
+    /// a recording. The level is, and whatever's opened over it, but not the menus and the
+    /// loading before it.
+    fn recorded(&self) -> bool {
+        false
+    }
+    /// What to call the window while it's on top, `None` to leave it as it is
+    fn title(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// For playing a scene without giving it up, to ask it after how it went
+impl<S: Scene + ?Sized> Scene for &mut S {
+    fn update(&mut self, inputs: &[TickInput]) -> Transition {
+        (**self).update(inputs)
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>, assets: &Assets, alpha: f64) {
+        (**self).render(canvas, assets, alpha)
+    }
+
+    fn rumbles(&self) -> &[Rumble] {
+        (**self).rumbles()
+    }
+
+    fn sounds(&self) -> &[Sound] {
+        (**self).sounds()
+    }
+
+    fn settings(&self) -> Option<&Settings> {
+        (**self).settings()
+    }
+
+    fn load_sprites(&mut self, assets: &mut Assets) {
+        (**self).load_sprites(assets)
+    }
+
+    fn overlays(&self) -> bool {
+        (**self).overlays()
+    }
+
+    fn recorded(&self) -> bool {
+        (**self).recorded()
+    }
+
+    fn title(&self) -> Option<&str> {
+        (**self).title()
+    }
+}
+
+/// How a scene opened on top of another went, shared with the one under it to find out once
+/// it's back on top
+pub struct Outcome<T>(Rc<Cell<Option<T>>>);
+
+impl<T> Outcome<T> {
+    pub fn set(&self, outcome: T) {
+        self.0.set(Some(outcome));
+    }
+
+    /// `None` until it's been set, and again once it's been taken
+    pub fn take(&self) -> Option<T> {
+        self.0.take()
+    }
+}
+
+impl<T> Default for Outcome<T> {
+    fn default() -> Outcome<T> {
+        Outcome(Rc::new(Cell::new(None)))
+    }
+}
+
+impl<T> Clone for Outcome<T> {
+    fn clone(&self) -> Outcome<T> {
+        Outcome(self.0.clone())
+    }
+}
+
+/// What a scene wants played after a tick
+pub enum Transition {
+    /// the same scene again
+    Stay,
+    /// a new scene on top, the one under it carrying on where it left off once it's done
+    Push(Box<dyn Scene>),
+    /// the scene's over, going back to the one under it
+    Pop,
+    /// a new scene in place of this one, as with a level started over
+    Replace(Box<dyn Scene>),
+}
+
+/// How a scene coming on top is brought in over the screen, so going from one to another
+/// isn't a sudden cut
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Effect {
+    /// out of black, for a scene in place of another or the first one
+    Fade,
+    /// from behind black going off to the side, the left for a scene opened on top and the
+    /// right for going back to the one under it
+    Wipe { back: bool },
+}
+
+/// The scenes being played, the one on top getting the input and the screen. Once the last
+/// one's over, so's the game.
+pub struct SceneManager<'a> {
+    scenes: Vec<Box<dyn Scene + 'a>>,
+    /// whether the one on top has loaded its sprites yet
+    loaded: bool,
+    /// what the one on top is coming in with and for how many ticks it has, `None` once
+    /// it's in
+    effect: Option<(Effect, u32)>,
+}
+
+impl<'a> SceneManager<'a> {
+    pub fn new(scene: Box<dyn Scene + 'a>) -> SceneManager<'a> {
+        SceneManager {
+            scenes: vec![scene],
+            loaded: false,
+            effect: Some((Effect::Fade, 0)),
+        }
+    }
+
+    /// Advances the scene on top by a tick and goes on to whichever it asks for, saying
+    /// whether there's any left to play
+    pub fn update(&mut self, inputs: &[TickInput]) -> bool {
+        if let Some((_, ticks)) = &mut self.effect {
+            *ticks += 1;
+        }
+        self.effect.take_if(|(_, ticks)| *ticks >= EFFECT_TICKS);
+        let Some(scene) = self.scenes.last_mut() else {
+            return false;
+        };
+        match scene.update(inputs) {
+            Transition::Stay => {}
+            // the ones drawn over the scene under them come and go without an effect, that
+            // staying in view
+            Transition::Push(scene) => {
+                if !scene.overlays() {
+                    self.effect = Some((Effect::Wipe { back: false }, 0));
+                }
+                self.scenes.push(scene);
+                self.loaded = false;
+            }
+            // the one under it loaded its sprites before it was covered up
+            Transition::Pop => {
+                if self.scenes.pop().is_some_and(|scene| !scene.overlays()) {
+                    self.effect = Some((Effect::Wipe { back: true }, 0));
+                }
+            }
+            Transition::Replace(next) => {
+                *scene = next;
+                self.loaded = false;
+                self.effect = Some((Effect::Fade, 0));
+            }
+        }
+        !self.scenes.is_empty()
+    }
+
+    /// Loads the sprites of the scene on top, unless it has already
+    pub fn load_sprites(&mut self, assets: &mut Assets) {
+        if let Some(scene) = self.scenes.last_mut().filter(|_| !self.loaded) {
+            scene.load_sprites(assets);
+            self.loaded = true;
+        }
+    }
+
+    /// Draws the scene on top, over the ones under it for one that overlays them, and over
+    /// it whatever it's coming in from behind
+    pub fn render(&self, canvas: &mut Canvas<Window>, assets: &Assets, alpha: f64) {
+        let Some((scene, under)) = self.scenes.split_last() else {
+            return;
+        };
+        if scene.overlays() {
+            let shown = under
+                .iter()
+                .rposition(|scene| !scene.overlays())
+                .unwrap_or(0);
+            // they're not ticking, so they stay as they were on their last tick
+            for scene in &under[shown..] {
+                scene.render(canvas, assets, 1.);
+            }
+        }
+        scene.render(canvas, assets, alpha);
+        let Some((effect, ticks)) = self.effect else {
+            return;
+        };
+        let (width, height) = SCREEN_DIMENSIONS;
+        let covered = covered(ticks, alpha);
+        canvas.set_blend_mode(BlendMode::Blend);
+        match effect {
+            Effect::Fade => {
+                canvas.set_draw_color(Color::RGBA(0, 0, 0, (255. * covered) as u8));
+                canvas
+                    .fill_rect(Rect::new(0, 0, width as u32, height as u32))
+                    .unwrap();
+            }
+            Effect::Wipe { back } => {
+                let wide = (width as f64 * covered) as u32;
+                let left = if back { width - wide as i32 } else { 0 };
+                if wide > 0 {
+                    canvas.set_draw_color(Color::BLACK);
+                    canvas
+                        .fill_rect(Rect::new(left, 0, wide, height as u32))
+                        .unwrap();
+                }
+            }
+        }
+        canvas.set_blend_mode(BlendMode::None);
+    }
+
+    pub fn rumbles(&self) -> &[Rumble] {
+        self.scenes.last().map_or(&[], |scene| scene.rumbles())
+    }
+
+    pub fn sounds(&self) -> &[Sound] {
+        self.scenes.last().map_or(&[], |scene| scene.sounds())
+    }
+
+    pub fn settings(&self) -> Option<&Settings> {
+        self.scenes.last()?.settings()
+    }
+
+    pub fn recorded(&self) -> bool {
+        self.scenes.last().is_some_and(|scene| scene.recorded())
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.scenes.last()?.title()
+    }
+}
+
+/// How much of the screen an effect still covers `ticks` into it, from 1 to 0, `alpha` of
+/// the way to the next tick. It eases off towards the end.
+fn covered(ticks: u32, alpha: f64) -> f64 {
+    let through = ((ticks as f64 + alpha) / EFFECT_TICKS as f64).clamp(0., 1.);
+    (1. - through).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    /// Says it was updated each tick and goes through `next` in turn, then stays
+    struct Scripted {
+        name: &'static str,
+        log: Rc<RefCell<Vec<&'static str>>>,
+        next: Vec<Transition>,
+    }
+
+    impl Scene for Scripted {
+        fn update(&mut self, _inputs: &[TickInput]) -> Transition {
+            self.log.borrow_mut().push(self.name);
+            if self.next.is_empty() {
+                Transition::Stay
+            } else {
+                self.next.remove(0)
+            }
+        }
+
+        fn render(&self, _canvas: &mut Canvas<Window>, _assets: &Assets, _alpha: f64) {}
+    }
+
+    #[test]
+    fn scenes_go_back_to_the_one_under_them_until_none_are_left() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let scripted = |name, next| {
+            Box::new(Scripted {
+                name,
+                log: log.clone(),
+                next,
+            })
+        };
+        let pause = scripted("pause", vec![Transition::Stay, Transition::Pop]);
+        let restarted = scripted("restarted", vec![Transition::Pop]);
+        let level = scripted(
+            "level",
+            vec![Transition::Push(pause), Transition::Replace(restarted)],
+        );
+        let mut scenes = SceneManager::new(scripted(
+            "menu",
+            vec![Transition::Stay, Transition::Push(level), Transition::Pop],
+        ));
+
+        let mut ticks = 0;
+        while scenes.update(&[]) {
+            ticks += 1;
+        }
+        assert_eq!(ticks, 7);
+        // the menu came back from under the level just now
+        assert_eq!(scenes.effect, Some((Effect::Wipe { back: true }, 0)));
+        assert_eq!(
+            *log.borrow(),
+            [
+                "menu",
+                "menu",
+                "level",
+                "pause",
+                "pause",
+                "level",
+                "restarted",
+                "menu"
+            ]
+        );
+        assert!(scenes.settings().is_none());
+    }
+
+    #[test]
+    fn scenes_come_in_from_behind_an_effect_that_wears_off() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let next = Box::new(Scripted {
+            name: "next",
+            log: log.clone(),
+            next: Vec::new(),
+        });
+        let mut scenes = SceneManager::new(Box::new(Scripted {
+            name: "first",
+            log,
+            next: vec![Transition::Replace(next)],
+        }));
+        assert_eq!(scenes.effect, Some((Effect::Fade, 0)));
+        assert!(scenes.update(&[]));
+        assert_eq!(scenes.effect, Some((Effect::Fade, 0)));
+        for _ in 0..EFFECT_TICKS {
+            assert!(scenes.effect.is_some());
+            scenes.update(&[]);
+        }
+        assert!(scenes.effect.is_none());
+
+        assert_eq!(covered(0, 0.), 1.);
+        assert!(covered(EFFECT_TICKS / 2, 0.) < 0.5);
+        assert_eq!(covered(EFFECT_TICKS, 0.), 0.);
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use sdl2::{
+    image::SaveSurface, pixels::PixelFormatEnum, rect::Rect, render::Canvas, surface::Surface,
+    video::Window,
+};
+
+/// Where screenshots are saved, next to wherever the game was started from
+const DIRECTORY: &str = "screenshots";
+
+/// Saves what's in `area` of the window as a PNG named after the time it was taken, and
+/// says where
+pub fn save(canvas: &Canvas<Window>, area: Rect) -> Result<PathBuf, String> {
+    let format = PixelFormatEnum::RGB24;
+    let mut pixels = canvas.read_pixels(area, format)?;
+    let pitch = area.width() * format.byte_size_per_pixel() as u32;
+    let surface = Surface::from_data(&mut pixels, area.width(), area.height(), pitch, format)?;
+
+    fs::create_dir_all(DIRECTORY).map_err(|error| error.to_string())?;
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let mut path = PathBuf::from(DIRECTORY).join(format!("{}.png", timestamp(seconds)));
+    // two in the same second don't overwrite each other
+    let mut count = 1;
+    while path.exists() {
+        count += 1;
+        path = PathBuf::from(DIRECTORY).join(format!("{} ({count}).png", timestamp(seconds)));
+    }
+    surface.save(&path)?;
+    Ok(path)
+}
+
+/// `seconds` since the Unix epoch as a UTC date and time that sorts in the order they were
+/// taken, like 2024-03-09_17-05-42
+fn timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // from days to a date in the proleptic Gregorian calendar, going by 400 year eras that
+    // start in March so the leap day comes last
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_dates_and_times() {
+        assert_eq!(timestamp(0), "1970-01-01_00-00-00");
+        // a leap day, and the last second of a year
+        assert_eq!(timestamp(1_709_229_600), "2024-02-29_18-00-00");
+        assert_eq!(timestamp(1_735_689_599), "2024-12-31_23-59-59");
+    }
+}
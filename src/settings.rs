@@ -0,0 +1,174 @@
+use std::{fs, io, path::Path};
+
+use sdl2::keyboard::Scancode;
+
+use crate::{
+    bindings::Action,
+    camera::CameraMode,
+    car::Assists,
+    config::{self, ConfigError, Table, Value},
+    video::Filter,
+};
+
+/// Where the settings changed in the game are kept, next to wherever the game was started
+/// from like the screenshots, over the files under the assets directory
+pub const FILE: &str = "settings.toml";
+
+/// What the players can change from the settings while playing, kept for next time
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// how big the window is in points when it isn't fullscreen, `None` for the size picked
+    /// to suit the display
+    pub window: Option<(u32, u32)>,
+    pub fullscreen: bool,
+    pub filter: Filter,
+    /// how loud everything is, from 0 to 1
+    pub volume: f64,
+    /// what the players' cameras start in
+    pub camera_mode: CameraMode,
+    /// the helpers the players' cars start with
+    pub assists: Assists,
+    /// the first player's keys for the actions they've been rebound for, over the bindings
+    /// file's
+    pub keys: Vec<(Action, Scancode)>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            window: None,
+            fullscreen: false,
+            filter: Filter::default(),
+            volume: 1.,
+            camera_mode: CameraMode::Chase,
+            assists: Assists::default(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Overwrites whatever the file at `path` says, keeping the rest
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let file = config::load(path)?;
+        if let Some(video) = file.table("video")? {
+            if let Some(size) = video.get("window") {
+                let (width, height) = config::as_pair(size).ok_or(ConfigError::WrongType {
+                    key: "video.window".to_string(),
+                    expected: "a [width, height] pair",
+                })?;
+                self.window = Some((width as u32, height as u32));
+            }
+            video.read_bool("fullscreen", &mut self.fullscreen)?;
+            if let Some(name) = video.string("filter")? {
+                self.filter = Filter::from_name(name).ok_or(ConfigError::WrongType {
+                    key: "video.filter".to_string(),
+                    expected: "one of \"none\", \"scanlines\", \"crt\" or \"pixelated\"",
+                })?;
+            }
+        }
+        if let Some(audio) = file.table("audio")? {
+            audio.read_number("volume", &mut self.volume)?;
+            self.volume = self.volume.clamp(0., 1.);
+        }
+        if let Some(driving) = file.table("driving")? {
+            if let Some(name) = driving.string("camera")? {
+                self.camera_mode = CameraMode::from_name(name).ok_or(ConfigError::WrongType {
+                    key: "driving.camera".to_string(),
+                    expected: "one of \"chase\", \"locked\" or \"hood\"",
+                })?;
+            }
+            driving.read_bool("traction_control", &mut self.assists.traction_control)?;
+            driving.read_bool("abs", &mut self.assists.abs)?;
+            driving.read_bool("stability_control", &mut self.assists.stability_control)?;
+        }
+        if let Some(keyboard) = file.table("keyboard")? {
+            for action in Action::ALL {
+                let Some(name) = keyboard.string(action.name())? else {
+                    continue;
+                };
+                let key = Scancode::from_name(name).ok_or(ConfigError::WrongType {
+                    key: format!("keyboard.{}", action.name()),
+                    expected: "the name of a key",
+                })?;
+                self.bind_key(action, key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps the settings for next time
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut video = Table::default();
+        if let Some((width, height)) = self.window {
+            video.set(
+                "window",
+                Value::Array(vec![
+                    Value::Number(width as f64),
+                    Value::Number(height as f64),
+                ]),
+            );
+        }
+        video.set("fullscreen", Value::Bool(self.fullscreen));
+        video.set("filter", Value::String(self.filter.name().to_string()));
+        let mut audio = Table::default();
+        audio.set("volume", Value::Number(self.volume));
+        let mut driving = Table::default();
+        driving.set("camera", Value::String(self.camera_mode.name().to_string()));
+        driving.set(
+            "traction_control",
+            Value::Bool(self.assists.traction_control),
+        );
+        driving.set("abs", Value::Bool(self.assists.abs));
+        driving.set(
+            "stability_control",
+            Value::Bool(self.assists.stability_control),
+        );
+        let mut keyboard = Table::default();
+        for (action, key) in &self.keys {
+            keyboard.set(action.name(), Value::String(key.name().to_string()));
+        }
+
+        let mut file = Table::default();
+        file.set("video", Value::Table(video));
+        file.set("audio", Value::Table(audio));
+        file.set("driving", Value::Table(driving));
+        file.set("keyboard", Value::Table(keyboard));
+        fs::write(path, config::write(&file))
+    }
+
+    /// The first player's key for `action` from now on, in place of the ones it had
+    pub fn bind_key(&mut self, action: Action, key: Scancode) {
+        self.keys.retain(|(bound, _)| *bound != action);
+        self.keys.push((action, key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_settings_load_back_the_same() {
+        let mut settings = Settings {
+            window: Some((1600, 900)),
+            fullscreen: true,
+            filter: Filter::Crt,
+            volume: 0.4,
+            camera_mode: CameraMode::Hood,
+            ..Settings::default()
+        };
+        settings.assists.abs = true;
+        settings.bind_key(Action::Throttle, Scancode::Up);
+        settings.bind_key(Action::Throttle, Scancode::I);
+        settings.bind_key(Action::Back, Scancode::Backspace);
+        assert_eq!(settings.keys.len(), 2);
+
+        let path = std::env::temp_dir().join("drifter_settings_test.toml");
+        settings.save(&path).unwrap();
+        let mut loaded = Settings::default();
+        loaded.load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, settings);
+    }
+}
@@ -0,0 +1,458 @@
+use sdl2::{keyboard::Scancode, pixels::Color, render::Canvas, video::Window};
+
+use crate::{
+    assets::Assets,
+    bindings::Action,
+    input::{Command, TickInput},
+    menu::{Presses, Selection, PRESSED},
+    scene::{Outcome, Scene, Transition},
+    settings::Settings,
+    text, SCREEN_DIMENSIONS, TICKS_PER_SECOND,
+};
+
+/// Ticks a change to the display is kept for without being told to keep it, for one that
+/// leaves the picture unusable to go back by itself
+const CONFIRM_TIME: u32 = 10 * TICKS_PER_SECOND as u32;
+/// The window sizes to choose from, `None` being the one picked to suit the display
+const RESOLUTIONS: [Option<(u32, u32)>; 5] = [
+    None,
+    Some((1280, 720)),
+    Some((1600, 900)),
+    Some((1920, 1080)),
+    Some((2560, 1440)),
+];
+/// Rows shown at once under the tabs, the list scrolling past the rest
+const SHOWN: usize = 9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tab {
+    Video,
+    Audio,
+    Controls,
+}
+
+impl Tab {
+    const ALL: [Tab; 3] = [Tab::Video, Tab::Audio, Tab::Controls];
+
+    fn name(self) -> &'static str {
+        match self {
+            Tab::Video => "VIDEO",
+            Tab::Audio => "AUDIO",
+            Tab::Controls => "CONTROLS",
+        }
+    }
+
+    fn rows(self) -> Vec<Row> {
+        match self {
+            Tab::Video => vec![Row::Resolution, Row::Fullscreen, Row::Apply, Row::Filter],
+            Tab::Audio => vec![Row::Volume],
+            Tab::Controls => [
+                Row::Camera,
+                Row::TractionControl,
+                Row::Abs,
+                Row::StabilityControl,
+            ]
+            .into_iter()
+            .chain(Action::ALL.map(Row::Key))
+            .collect(),
+        }
+    }
+}
+
+/// Something to change on one of the tabs
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Row {
+    Resolution,
+    Fullscreen,
+    /// puts the window size and fullscreen chosen into effect
+    Apply,
+    Filter,
+    Volume,
+    Camera,
+    TractionControl,
+    Abs,
+    StabilityControl,
+    /// the first player's key for the action
+    Key(Action),
+}
+
+/// The window's size and whether it's fullscreen, changed together
+type Display = (Option<(u32, u32)>, bool);
+
+/// Changing the settings while playing, on tabs for the video, the audio and the controls.
+/// Up and down go by the throttle and brake, left and right by the steering change what's
+/// chosen or go between the tabs, the pause button picks and going back leaves. A change to
+/// the display has to be applied, then kept before long or it's put back the way it was.
+pub struct SettingsMenu {
+    /// who's changing them, the only one the menu listens to
+    player: usize,
+    /// as they're in effect
+    settings: Settings,
+    /// the display chosen, only put into effect once it's applied
+    display: Display,
+    /// the display as it was before the last one applied, to go back to if it isn't kept
+    /// before the ticks left run out
+    confirming: Option<(Display, u32)>,
+    tab: Tab,
+    /// 0 being the tabs, and the rows on the tab under them
+    selection: Selection,
+    /// the action waiting for a key to be pressed for it
+    binding: Option<Action>,
+    /// the first player's keys in the bindings, to show for the actions not rebound here
+    keyboard: Vec<(Action, Scancode)>,
+    /// the steering left and right, changing what's chosen
+    sideways: Presses,
+    closed: bool,
+    /// where the settings are handed back once the menu's left
+    outcome: Option<Outcome<Settings>>,
+}
+
+impl SettingsMenu {
+    pub fn new(
+        player: usize,
+        settings: Settings,
+        keyboard: Vec<(Action, Scancode)>,
+    ) -> SettingsMenu {
+        SettingsMenu {
+            player,
+            display: (settings.window, settings.fullscreen),
+            settings,
+            confirming: None,
+            tab: Tab::Video,
+            selection: Selection::held(0),
+            binding: None,
+            keyboard,
+            sideways: Presses::held(),
+            closed: false,
+            outcome: None,
+        }
+    }
+
+    /// Hands the settings to `outcome` as they're left, for the scene it was opened from
+    pub fn reporting_to(mut self, outcome: Outcome<Settings>) -> SettingsMenu {
+        self.outcome = Some(outcome);
+        self
+    }
+
+    fn row(&self) -> Option<Row> {
+        let rows = self.tab.rows();
+        self.selection.selected().checked_sub(1).map(|i| rows[i])
+    }
+
+    fn handle(&mut self, input: &TickInput) {
+        let pressed = |command| input.commands.contains(&command);
+        if let Some(action) = self.binding {
+            if pressed(Command::Back) {
+                self.binding = None;
+            } else if let Some(key) = input.key {
+                self.settings.bind_key(action, key);
+                self.binding = None;
+            }
+            return;
+        }
+        if let Some((before, ticks)) = &mut self.confirming {
+            if pressed(Command::TogglePause) {
+                self.confirming = None;
+            } else if pressed(Command::Back) || *ticks == 0 {
+                let before = *before;
+                self.set_display(before);
+                self.display = before;
+                self.confirming = None;
+            } else {
+                *ticks -= 1;
+            }
+            return;
+        }
+        if pressed(Command::Back) {
+            self.closed = true;
+            return;
+        }
+        if pressed(Command::TogglePause) {
+            self.pick();
+        }
+
+        self.selection.update(input, self.tab.rows().len() + 1);
+        let steering = input.car.steering;
+        let step = self
+            .sideways
+            .update(steering < -PRESSED, steering > PRESSED);
+        if step != 0 {
+            self.change(step);
+        }
+    }
+
+    /// What the pause button does on the row selected
+    fn pick(&mut self) {
+        match self.row() {
+            Some(Row::Apply) => {
+                let before = (self.settings.window, self.settings.fullscreen);
+                if self.display != before {
+                    self.set_display(self.display);
+                    self.confirming = Some((before, CONFIRM_TIME));
+                }
+            }
+            Some(Row::Key(action)) => self.binding = Some(action),
+            _ => self.change(1),
+        }
+    }
+
+    /// Goes `step` one way or the other through the choices on the row selected
+    fn change(&mut self, step: i32) {
+        let by = |index: usize, count: usize| (index as i32 + step).rem_euclid(count as i32);
+        let row = self.row();
+        let settings = &mut self.settings;
+        match row {
+            None => {
+                let index = Tab::ALL.iter().position(|&tab| tab == self.tab).unwrap();
+                self.tab = Tab::ALL[by(index, Tab::ALL.len()) as usize];
+            }
+            Some(Row::Resolution) => {
+                let index = RESOLUTIONS
+                    .iter()
+                    .position(|&size| size == self.display.0)
+                    .unwrap_or(0);
+                self.display.0 = RESOLUTIONS[by(index, RESOLUTIONS.len()) as usize];
+            }
+            Some(Row::Fullscreen) => self.display.1 = !self.display.1,
+            Some(Row::Filter) => settings.filter = settings.filter.next(),
+            Some(Row::Volume) => {
+                settings.volume =
+                    ((settings.volume * 10.).round() + step as f64).clamp(0., 10.) / 10.
+            }
+            Some(Row::Camera) => settings.camera_mode = settings.camera_mode.next(),
+            Some(Row::TractionControl) => {
+                settings.assists.traction_control = !settings.assists.traction_control
+            }
+            Some(Row::Abs) => settings.assists.abs = !settings.assists.abs,
+            Some(Row::StabilityControl) => {
+                settings.assists.stability_control = !settings.assists.stability_control
+            }
+            Some(Row::Apply | Row::Key(_)) => {}
+        }
+    }
+
+    fn set_display(&mut self, (window, fullscreen): Display) {
+        self.settings.window = window;
+        self.settings.fullscreen = fullscreen;
+    }
+
+    fn label(&self, row: Row) -> String {
+        let on_off = |on: bool| if on { "ON" } else { "OFF" };
+        let settings = &self.settings;
+        match row {
+            Row::Resolution => match self.display.0 {
+                Some((width, height)) => format!("WINDOW: {width} X {height}"),
+                None => "WINDOW: TO SUIT THE DISPLAY".to_string(),
+            },
+            Row::Fullscreen => format!("FULLSCREEN: {}", on_off(self.display.1)),
+            Row::Apply => "APPLY".to_string(),
+            Row::Filter => format!("FILTER: {}", settings.filter.name().to_uppercase()),
+            Row::Volume => format!("VOLUME: {}%", (settings.volume * 100.).round()),
+            Row::Camera => format!("CAMERA: {}", settings.camera_mode.name().to_uppercase()),
+            Row::TractionControl => {
+                format!(
+                    "TRACTION CONTROL: {}",
+                    on_off(settings.assists.traction_control)
+                )
+            }
+            Row::Abs => format!("ABS: {}", on_off(settings.assists.abs)),
+            Row::StabilityControl => {
+                format!(
+                    "STABILITY CONTROL: {}",
+                    on_off(settings.assists.stability_control)
+                )
+            }
+            Row::Key(action) => {
+                let name = action.name().replace('_', " ").to_uppercase();
+                if self.binding == Some(action) {
+                    return format!("{name}: PRESS A KEY");
+                }
+                let rebound = settings.keys.iter().find(|(bound, _)| *bound == action);
+                let keys: Vec<_> = match rebound {
+                    Some((_, key)) => vec![key.name().to_uppercase()],
+                    None => self
+                        .keyboard
+                        .iter()
+                        .filter(|(bound, _)| *bound == action)
+                        .map(|(_, key)| key.name().to_uppercase())
+                        .collect(),
+                };
+                if keys.is_empty() {
+                    format!("{name}: NONE")
+                } else {
+                    format!("{name}: {}", keys.join(", "))
+                }
+            }
+        }
+    }
+}
+
+impl Scene for SettingsMenu {
+    /// Ends once the player goes back out of it
+    fn update(&mut self, inputs: &[TickInput]) -> Transition {
+        if let Some(input) = inputs.get(self.player) {
+            self.handle(input);
+        }
+        if self.closed {
+            if let Some(outcome) = &self.outcome {
+                outcome.set(self.settings.clone());
+            }
+            return Transition::Pop;
+        }
+        Transition::Stay
+    }
+
+    fn settings(&self) -> Option<&Settings> {
+        Some(&self.settings)
+    }
+
+    fn recorded(&self) -> bool {
+        true
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>, _assets: &Assets, _alpha: f64) {
+        let (width, height) = SCREEN_DIMENSIONS;
+        canvas.set_draw_color(Color::RGB(20, 24, 28));
+        canvas.clear();
+        text::draw_centered(canvas, "SETTINGS", (width / 2, 100), 8, Color::WHITE);
+        let highlight = |selected: bool| {
+            if selected {
+                Color::RGB(255, 220, 0)
+            } else {
+                Color::RGB(160, 160, 160)
+            }
+        };
+
+        let spacing = width / (Tab::ALL.len() as i32 + 1);
+        for (i, tab) in Tab::ALL.iter().enumerate() {
+            let name = if *tab == self.tab && self.selection.selected() == 0 {
+                format!("- {} -", tab.name())
+            } else {
+                tab.name().to_string()
+            };
+            let center = (spacing * (i as i32 + 1), 220);
+            text::draw_centered(canvas, &name, center, 5, highlight(*tab == self.tab));
+        }
+
+        // scrolled to keep the one selected in view
+        let rows = self.tab.rows();
+        let selected_row = self.selection.selected().saturating_sub(1);
+        let first = selected_row
+            .saturating_sub(SHOWN / 2)
+            .min(rows.len().saturating_sub(SHOWN));
+        for (line, (i, row)) in rows.iter().enumerate().skip(first).take(SHOWN).enumerate() {
+            let selected = self.selection.selected() == i + 1;
+            let label = if selected {
+                format!("- {} -", self.label(*row))
+            } else {
+                self.label(*row)
+            };
+            let top = 340 + line as i32 * 70;
+            text::draw_centered(canvas, &label, (width / 2, top), 4, highlight(selected));
+        }
+
+        let hint = match self.confirming {
+            Some((_, ticks)) => format!(
+                "KEEP THIS DISPLAY? RETURN OR START TO KEEP IT, GOING BACK IN {}",
+                ticks / TICKS_PER_SECOND as u32 + 1
+            ),
+            None => {
+                "UP AND DOWN TO CHOOSE, LEFT AND RIGHT TO CHANGE, ESCAPE TO GO BACK".to_string()
+            }
+        };
+        text::draw_centered(
+            canvas,
+            &hint,
+            (width / 2, height - 80),
+            3,
+            Color::RGB(200, 200, 200),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::car::{CarInput, CarPedals};
+
+    fn pressing(command: Command) -> TickInput {
+        TickInput {
+            commands: vec![command],
+            ..TickInput::default()
+        }
+    }
+
+    fn moving(throttle: f64, brake: f64, steering: f64) -> TickInput {
+        TickInput {
+            car: CarInput {
+                pedals: CarPedals { throttle, brake },
+                steering,
+                ..CarInput::default()
+            },
+            ..TickInput::default()
+        }
+    }
+
+    /// Once, and back to holding nothing
+    fn step(menu: &mut SettingsMenu, input: TickInput) {
+        menu.handle(&input);
+        menu.handle(&TickInput::default());
+    }
+
+    #[test]
+    fn display_changes_go_back_unless_kept_and_keys_are_rebound() {
+        let mut menu = SettingsMenu::new(
+            0,
+            Settings::default(),
+            vec![(Action::Throttle, Scancode::W)],
+        );
+        menu.handle(&TickInput::default());
+
+        // a bigger window applied and left alone goes back to how it was
+        step(&mut menu, moving(0., 1., 0.));
+        step(&mut menu, moving(0., 0., 1.));
+        assert_eq!(menu.settings.window, None);
+        step(&mut menu, moving(0., 1., 0.));
+        step(&mut menu, moving(0., 1., 0.));
+        step(&mut menu, pressing(Command::TogglePause));
+        assert_eq!(menu.settings.window, Some((1280, 720)));
+        for _ in 0..CONFIRM_TIME + 1 {
+            menu.handle(&TickInput::default());
+        }
+        assert_eq!(menu.settings.window, None);
+
+        // and kept when it's kept
+        step(&mut menu, moving(1., 0., 0.));
+        step(&mut menu, moving(1., 0., 0.));
+        step(&mut menu, moving(0., 0., 1.));
+        step(&mut menu, moving(0., 1., 0.));
+        step(&mut menu, moving(0., 1., 0.));
+        step(&mut menu, pressing(Command::TogglePause));
+        step(&mut menu, pressing(Command::TogglePause));
+        for _ in 0..CONFIRM_TIME + 1 {
+            menu.handle(&TickInput::default());
+        }
+        assert_eq!(menu.settings.window, Some((1280, 720)));
+
+        // over to the controls, the first key there being the throttle's
+        menu.selection = Selection::new(0);
+        step(&mut menu, moving(0., 0., -1.));
+        assert_eq!(menu.tab, Tab::Controls);
+        assert_eq!(menu.label(Row::Key(Action::Throttle)), "THROTTLE: W");
+        for _ in 0..5 {
+            step(&mut menu, moving(0., 1., 0.));
+        }
+        assert_eq!(menu.row(), Some(Row::Key(Action::Throttle)));
+        step(&mut menu, pressing(Command::TogglePause));
+        menu.handle(&TickInput {
+            key: Some(Scancode::I),
+            ..TickInput::default()
+        });
+        assert_eq!(menu.settings.keys, vec![(Action::Throttle, Scancode::I)]);
+
+        assert!(matches!(
+            menu.update(&[pressing(Command::Back)]),
+            Transition::Pop
+        ));
+    }
+}
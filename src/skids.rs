@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+
+use nalgebra::Point2;
+
+use crate::{car::Car, TICKS_PER_SECOND};
+
+/// How fast a tyre has to slide sideways before it leaves a mark, in pixels per tick
+pub const SLIP_THRESHOLD: f64 = 1.5;
+/// Sliding this far past the threshold leaves the darkest mark there is
+const FULL_SLIP: f64 = 6.;
+/// Ticks a mark takes to fade away completely
+const MARK_LIFETIME: u32 = 30 * TICKS_PER_SECOND as u32;
+/// Once there are this many marks the oldest ones go first, to keep drawing them cheap
+const MAX_MARKS: usize = 6000;
+/// Further than a tyre can go in one tick, in pixels
+const MAX_STEP: f64 = 60.;
+
+pub struct Mark {
+    pub a: Point2<f64>,
+    pub b: Point2<f64>,
+    /// from 0 for a faint mark to 1 for a black one
+    darkness: f64,
+    age: u32,
+}
+
+impl Mark {
+    /// How dark the mark still is, fading from its darkness when it was laid down to 0
+    pub fn opacity(&self) -> f64 {
+        self.darkness * (1. - self.age as f64 / MARK_LIFETIME as f64)
+    }
+}
+
+/// Rubber left on the ground by sliding and spinning tyres, as short segments from where
+/// each tyre was one tick to where it is the next. Only for show, like the smoke.
+#[derive(Default)]
+pub struct SkidMarks {
+    marks: VecDeque<Mark>,
+    /// where each car's tyres were last tick, for the ones that were leaving a mark
+    last: Vec<[Option<Point2<f64>>; 4]>,
+}
+
+impl SkidMarks {
+    pub fn marks(&self) -> impl Iterator<Item = &Mark> {
+        self.marks.iter()
+    }
+
+    /// Ages every mark and lays down new ones behind the tyres sliding this tick
+    pub fn update(&mut self, cars: &[Car]) {
+        for mark in &mut self.marks {
+            mark.age += 1;
+        }
+        while self
+            .marks
+            .front()
+            .is_some_and(|mark| mark.age >= MARK_LIFETIME)
+        {
+            self.marks.pop_front();
+        }
+
+        self.last.resize(cars.len(), [None; 4]);
+        for (car, last) in cars.iter().zip(&mut self.last) {
+            let wheels = car.wheel_positions().into_iter().zip(car.wheel_slips());
+            for (i, (wheel, slip)) in wheels.enumerate() {
+                if car.is_airborne() || slip < SLIP_THRESHOLD {
+                    last[i] = None;
+                    continue;
+                }
+
+                // a car respawning would otherwise leave a mark all the way across the track
+                if let Some(previous) =
+                    last[i].filter(|previous| (wheel - previous).norm() < MAX_STEP)
+                {
+                    self.marks.push_back(Mark {
+                        a: previous,
+                        b: wheel,
+                        darkness: ((slip - SLIP_THRESHOLD) / FULL_SLIP).clamp(0.2, 1.),
+                        age: 0,
+                    });
+                }
+                last[i] = Some(wheel);
+            }
+        }
+        while self.marks.len() > MAX_MARKS {
+            self.marks.pop_front();
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use nalgebra::{Point2, Vector2};
+
+use crate::rng::Rng;
+
+/// Ticks a puff takes to thin out completely
+const PUFF_LIFETIME: u32 = 50;
+/// How fast a tyre has to slide before it smokes, in pixels per tick
+const SLIP_THRESHOLD: f64 = 1.;
+/// Sliding this much faster than the threshold smokes as hard as a tyre can
+const FULL_SLIP: f64 = 8.;
+/// Share of the tyre's velocity a puff comes out with
+const INHERITED_VELOCITY: f64 = 0.3;
+/// Once there are this many puffs the oldest ones go first
+const MAX_PUFFS: usize = 600;
+
+pub struct Puff {
+    pub position: Point2<f64>,
+    /// pixels per tick
+    velocity: Vector2<f64>,
+    /// width of the puff when it came out, it only gets bigger from there
+    size: f64,
+    /// from 0 to 1, how thick the puff was when it came out
+    density: f64,
+    age: u32,
+}
+
+impl Puff {
+    /// From how dense it came out down to 0 when it's gone
+    pub fn thickness(&self) -> f64 {
+        self.density * (1. - self.age as f64 / PUFF_LIFETIME as f64)
+    }
+
+    pub fn size(&self) -> f64 {
+        let spread = self.age as f64 / PUFF_LIFETIME as f64;
+        self.size * (1. + 2. * spread)
+    }
+}
+
+/// Tyre smoke coming off of sliding and spinning wheels. It's only for show, but it draws on
+/// the level's seeded RNG all the same so a replay looks exactly like the run it recorded.
+#[derive(Default)]
+pub struct Smoke {
+    puffs: Vec<Puff>,
+}
+
+impl Smoke {
+    pub fn puffs(&self) -> &[Puff] {
+        &self.puffs
+    }
+
+    /// Lets out smoke at `position` for a tyre moving at `velocity` and sliding `slip`
+    /// pixels per tick over the ground: the more it slides the likelier a puff is, and the
+    /// bigger and thicker it comes out
+    pub fn emit(
+        &mut self,
+        rng: &mut Rng,
+        position: Point2<f64>,
+        velocity: Vector2<f64>,
+        slip: f64,
+    ) {
+        let amount = ((slip - SLIP_THRESHOLD) / FULL_SLIP).min(1.);
+        if amount <= 0. || rng.range(0., 1.) > amount.sqrt() {
+            return;
+        }
+        let jitter = Vector2::new(rng.range(-4., 4.), rng.range(-4., 4.));
+        let drift = Vector2::new(rng.range(-0.3, 0.3), rng.range(-0.3, 0.3));
+        self.puffs.push(Puff {
+            position: position + jitter,
+            velocity: velocity * INHERITED_VELOCITY + drift,
+            size: 12. + amount * 20.,
+            density: 0.4 + amount * 0.6,
+            age: 0,
+        });
+        if self.puffs.len() > MAX_PUFFS {
+            self.puffs.remove(0);
+        }
+    }
+
+    /// Drifts and thins out every puff, dropping the ones that are gone
+    pub fn update(&mut self) {
+        for puff in &mut self.puffs {
+            puff.position += puff.velocity;
+            puff.velocity *= 0.95;
+            puff.age += 1;
+        }
+        self.puffs.retain(|puff| puff.age < PUFF_LIFETIME);
+    }
+}
@@ -0,0 +1,82 @@
+use nalgebra::{Point2, Vector2};
+use sdl2::pixels::Color;
+
+use crate::{
+    animation::{Animation, Playing},
+    rng::Rng,
+};
+
+/// How hard a car has to hit something to strike sparks, in pixels per tick
+const MIN_IMPACT: f64 = 2.;
+/// Hitting this much harder than that throws out as many as one hit can
+const FULL_IMPACT: f64 = 10.;
+const MAX_SPARKS_PER_HIT: f64 = 24.;
+
+/// White hot when it flies off, cooling to a dull red and shrinking as it goes. Frames are
+/// the spark's size in pixels and its color.
+const SPARK: Animation<(f64, Color)> = Animation {
+    frames: &[
+        ((4., Color::RGB(255, 255, 230)), 3),
+        ((3.5, Color::RGB(255, 230, 120)), 4),
+        ((3., Color::RGB(255, 170, 50)), 5),
+        ((2.5, Color::RGB(230, 90, 20)), 6),
+        ((2., Color::RGB(150, 40, 10)), 6),
+    ],
+    looping: false,
+};
+
+pub struct Spark {
+    pub position: Point2<f64>,
+    /// pixels per tick
+    velocity: Vector2<f64>,
+    playing: Playing,
+}
+
+impl Spark {
+    /// How big the spark is and what color, as far as it's got in cooling down
+    pub fn look(&self) -> (f64, Color) {
+        *self.playing.frame(&SPARK).unwrap()
+    }
+}
+
+/// Sparks flying off of cars hitting walls, props and each other. Only for show, but drawn on
+/// the level's seeded RNG like the smoke.
+#[derive(Default)]
+pub struct Sparks {
+    sparks: Vec<Spark>,
+}
+
+impl Sparks {
+    pub fn sparks(&self) -> &[Spark] {
+        &self.sparks
+    }
+
+    /// Throws sparks out from `point` for a car moving at `velocity` that hit something
+    /// `impact` pixels per tick hard, the harder the more of them
+    pub fn emit(&mut self, rng: &mut Rng, point: Point2<f64>, velocity: Vector2<f64>, impact: f64) {
+        let amount = ((impact - MIN_IMPACT) / FULL_IMPACT).min(1.);
+        if amount <= 0. {
+            return;
+        }
+        for _ in 0..(amount * MAX_SPARKS_PER_HIT).ceil() as usize {
+            let angle = rng.range(0., std::f64::consts::TAU);
+            let speed = rng.range(2., 6. + impact * 0.5);
+            self.sparks.push(Spark {
+                position: point,
+                velocity: velocity * 0.5 + Vector2::new(angle.cos(), angle.sin()) * speed,
+                playing: Playing::default(),
+            });
+        }
+    }
+
+    /// Moves every spark on and lets it cool, dropping the ones that have gone out
+    pub fn update(&mut self) {
+        for spark in &mut self.sparks {
+            spark.position += spark.velocity;
+            spark.velocity *= 0.88;
+            spark.playing.update();
+        }
+        self.sparks
+            .retain(|spark| !spark.playing.is_finished(&SPARK));
+    }
+}
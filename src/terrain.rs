@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use nalgebra::Point2;
+use sdl2::pixels::Color;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Surface {
+    Asphalt,
+    Grass,
+    Gravel,
+    Ice,
+}
+
+/// How much a surface holds on to the car. The friction coefficients scale how much force the
+/// tyres can put down compared to dry asphalt.
+pub struct Grip {
+    /// friction for cornering
+    pub lateral: f64,
+    /// friction for accelerating and braking
+    pub longitudinal: f64,
+    /// share of the forward velocity (and wheel speed) lost every tick
+    pub rolling_resistance: f64,
+}
+
+impl Surface {
+    /// What it's called in track files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Surface::Asphalt => "asphalt",
+            Surface::Grass => "grass",
+            Surface::Gravel => "gravel",
+            Surface::Ice => "ice",
+        }
+    }
+
+    /// The surface called `name` in track files
+    pub fn from_name(name: &str) -> Option<Surface> {
+        match name {
+            "asphalt" => Some(Surface::Asphalt),
+            "grass" => Some(Surface::Grass),
+            "gravel" => Some(Surface::Gravel),
+            "ice" => Some(Surface::Ice),
+            _ => None,
+        }
+    }
+
+    pub fn grip(&self) -> Grip {
+        match self {
+            Surface::Asphalt => Grip {
+                lateral: 1.,
+                longitudinal: 1.,
+                rolling_resistance: 0.02,
+            },
+            Surface::Grass => Grip {
+                lateral: 0.6,
+                longitudinal: 0.6,
+                rolling_resistance: 0.05,
+            },
+            Surface::Gravel => Grip {
+                lateral: 0.5,
+                longitudinal: 0.55,
+                rolling_resistance: 0.04,
+            },
+            Surface::Ice => Grip {
+                lateral: 0.12,
+                longitudinal: 0.1,
+                rolling_resistance: 0.005,
+            },
+        }
+    }
+
+    /// Whether this is part of the circuit rather than the run-off around it
+    pub fn is_track(&self) -> bool {
+        matches!(self, Surface::Asphalt)
+    }
+
+    /// What tyres running over loose ground throw up, nothing for the hard surfaces
+    pub fn dust_color(&self) -> Option<Color> {
+        match self {
+            Surface::Grass => Some(Color::RGB(125, 95, 60)),
+            Surface::Gravel => Some(Color::RGB(190, 170, 135)),
+            Surface::Asphalt | Surface::Ice => None,
+        }
+    }
+
+    /// `alternate` picks the second shade so neighbouring tiles stay distinguishable
+    pub fn color(&self, alternate: bool) -> Color {
+        match (self, alternate) {
+            (Surface::Asphalt, false) => Color::RGB(70, 70, 75),
+            (Surface::Asphalt, true) => Color::RGB(80, 80, 85),
+            (Surface::Grass, false) => Color::RGB(60, 180, 35),
+            (Surface::Grass, true) => Color::RGB(60, 200, 35),
+            (Surface::Gravel, false) => Color::RGB(165, 145, 110),
+            (Surface::Gravel, true) => Color::RGB(175, 155, 120),
+            (Surface::Ice, false) => Color::RGB(190, 225, 240),
+            (Surface::Ice, true) => Color::RGB(200, 235, 250),
+        }
+    }
+}
+
+/// A grid of surfaces covering the world, starting at the origin.
+/// Everything outside of the grid is grass.
+pub struct Terrain {
+    tile_size: u32,
+    width: usize,
+    height: usize,
+    tiles: Vec<Surface>,
+}
+
+impl Terrain {
+    /// A rectangular asphalt loop with gravel run-off on the outside of the corners and an
+    /// ice patch in the infield
+    pub fn new() -> Terrain {
+        let (width, height) = (40, 30);
+        let mut tiles = vec![Surface::Grass; width * height];
+
+        let outer = (6..34, 4..26);
+        let inner = (9..31, 7..23);
+        for y in 0..height {
+            for x in 0..width {
+                let in_outer = outer.0.contains(&x) && outer.1.contains(&y);
+                let in_inner = inner.0.contains(&x) && inner.1.contains(&y);
+                let near_corner = !inner.0.contains(&x) && !inner.1.contains(&y);
+
+                tiles[y * width + x] = if in_outer && !in_inner {
+                    Surface::Asphalt
+                } else if !in_outer && near_corner && (4..36).contains(&x) && (2..28).contains(&y) {
+                    Surface::Gravel
+                } else if (16..24).contains(&x) && (12..18).contains(&y) {
+                    Surface::Ice
+                } else {
+                    Surface::Grass
+                };
+            }
+        }
+
+        Terrain {
+            tile_size: 125,
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    /// A `width` by `height` grid, `tiles` going row by row from the top left
+    pub fn from_tiles(tile_size: u32, width: usize, height: usize, tiles: Vec<Surface>) -> Terrain {
+        assert_eq!(tiles.len(), width * height);
+        Terrain {
+            tile_size,
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// The size of the grid in tiles
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// The columns and rows of the tiles at least partly inside of the area between
+    /// `top_left` and `bottom_right`, going past the edges of the grid where it does
+    pub fn tiles_in(
+        &self,
+        (top_left, bottom_right): (Point2<f64>, Point2<f64>),
+    ) -> (Range<i32>, Range<i32>) {
+        let tile_size = self.tile_size as f64;
+        let span =
+            |from: f64, to: f64| (from / tile_size).floor() as i32..(to / tile_size).ceil() as i32;
+        (
+            span(top_left.x, bottom_right.x),
+            span(top_left.y, bottom_right.y),
+        )
+    }
+
+    /// The tile `x` across and `y` down, with grass all the way out past the grid's edges
+    pub fn tile(&self, x: i32, y: i32) -> Surface {
+        match (usize::try_from(x), usize::try_from(y)) {
+            (Ok(x), Ok(y)) if x < self.width && y < self.height => self.tiles[y * self.width + x],
+            _ => Surface::Grass,
+        }
+    }
+
+    pub fn surface_at(&self, pos: Point2<f64>) -> Surface {
+        let tile_size = self.tile_size as f64;
+        self.tile(
+            (pos.x / tile_size).floor() as i32,
+            (pos.y / tile_size).floor() as i32,
+        )
+    }
+}
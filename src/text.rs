@@ -0,0 +1,156 @@
+//! A blocky built-in font, so there's something to write prompts and numbers with without
+//! shipping font files, and writing in the fonts `Assets` loads for where it's worth having
+//! them
+
+use sdl2::{
+    pixels::Color,
+    rect::Rect,
+    render::{Canvas, RenderTarget},
+};
+
+use crate::assets::{Assets, FontId};
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// blank columns between two characters
+const SPACING: u32 = 1;
+
+/// The rows of a character from the top, the leftmost pixel being the highest of the 5 bits.
+/// Lower case letters are drawn as upper case ones, and anything else missing as a space.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match character.to_ascii_uppercase() {
+        'A' => [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1e],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x0a, 0x04, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00],
+        '/' => [0x01, 0x01, 0x02, 0x04, 0x08, 0x10, 0x10],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '\'' => [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '?' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f],
+        _ => [0; GLYPH_HEIGHT as usize],
+    }
+}
+
+/// How wide `text` comes out at `scale` screen pixels per font pixel
+pub fn width(text: &str, scale: u32) -> u32 {
+    let characters = text.chars().count() as u32;
+    (characters * (GLYPH_WIDTH + SPACING)).saturating_sub(SPACING) * scale
+}
+
+/// How tall a line comes out at `scale`
+pub fn height(scale: u32) -> u32 {
+    GLYPH_HEIGHT * scale
+}
+
+/// Writes `text` on one line with its top left corner at `position`
+pub fn draw<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    text: &str,
+    position: (i32, i32),
+    scale: u32,
+    color: Color,
+) {
+    let mut pixels = Vec::new();
+    for (i, character) in text.chars().enumerate() {
+        let left = position.0 + (i as u32 * (GLYPH_WIDTH + SPACING) * scale) as i32;
+        for (row, bits) in glyph(character).into_iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                    pixels.push(Rect::new(
+                        left + (column * scale) as i32,
+                        position.1 + (row as u32 * scale) as i32,
+                        scale,
+                        scale,
+                    ));
+                }
+            }
+        }
+    }
+    if !pixels.is_empty() {
+        canvas.set_draw_color(color);
+        canvas.fill_rects(&pixels).unwrap();
+    }
+}
+
+/// How wide and tall `text` comes out in `font`
+pub fn size_in(assets: &Assets, font: FontId, text: &str) -> (u32, u32) {
+    assets.font(font).size_of(text).unwrap_or((0, 0))
+}
+
+/// Writes `text` in `font` on one line with its top left corner at `position`
+pub fn draw_in<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    assets: &Assets,
+    font: FontId,
+    text: &str,
+    position: (i32, i32),
+    color: Color,
+) {
+    let Some(texture) = assets.write(font, text, color) else {
+        return;
+    };
+    let query = texture.query();
+    canvas
+        .copy(
+            &texture,
+            None,
+            Rect::new(position.0, position.1, query.width, query.height),
+        )
+        .unwrap();
+}
+
+/// Writes `text` centered on `center`
+pub fn draw_centered<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    text: &str,
+    center: (i32, i32),
+    scale: u32,
+    color: Color,
+) {
+    let position = (
+        center.0 - width(text, scale) as i32 / 2,
+        center.1 - height(scale) as i32 / 2,
+    );
+    draw(canvas, text, position, scale, color);
+}
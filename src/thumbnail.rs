@@ -0,0 +1,145 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use nalgebra::{Point2, Vector2};
+use sdl2::{image::SaveSurface, pixels::PixelFormatEnum, surface::Surface};
+
+use crate::track::Track;
+
+/// Where thumbnails are kept between runs, next to wherever the game was started from like
+/// the screenshots
+const DIRECTORY: &str = "thumbnails";
+/// The most pixels a thumbnail is across and down, the map keeping its shape inside that
+pub const MAX_SIZE: (u32, u32) = (320, 200);
+
+/// A track seen from above, shrunk down: the ground the color it's drawn in and the walls
+/// in white
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    /// red, green and blue for each pixel, a row at a time from the top
+    pub pixels: Vec<u8>,
+}
+
+impl Thumbnail {
+    pub fn new(track: &Track) -> Thumbnail {
+        let terrain = &track.terrain;
+        let (columns, rows) = terrain.size();
+        let world = Vector2::new(columns as f64, rows as f64) * terrain.tile_size() as f64;
+        let scale = (MAX_SIZE.0 as f64 / world.x).min(MAX_SIZE.1 as f64 / world.y);
+        let (width, height) = (
+            ((world.x * scale) as u32).max(1),
+            ((world.y * scale) as u32).max(1),
+        );
+
+        let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let middle = Point2::new(x as f64 + 0.5, y as f64 + 0.5) / scale;
+                let color = terrain.surface_at(middle).color(false);
+                pixels.extend([color.r, color.g, color.b]);
+            }
+        }
+        let mut thumbnail = Thumbnail {
+            width,
+            height,
+            pixels,
+        };
+        for wall in &track.walls {
+            let (a, b) = (wall.a * scale, wall.b * scale);
+            // a pixel at a time along it
+            let steps = (b - a).abs().max().ceil().max(1.) as usize;
+            for step in 0..=steps {
+                let point = a + (b - a) * (step as f64 / steps as f64);
+                thumbnail.plot(point, [255; 3]);
+            }
+        }
+        thumbnail
+    }
+
+    fn plot(&mut self, point: Point2<f64>, color: [u8; 3]) {
+        let (x, y) = (point.x.floor(), point.y.floor());
+        if x < 0. || y < 0. || x >= self.width as f64 || y >= self.height as f64 {
+            return;
+        }
+        let at = ((y as u32 * self.width + x as u32) * 3) as usize;
+        self.pixels[at..at + 3].copy_from_slice(&color);
+    }
+
+    fn save(&mut self, path: &Path) -> Result<(), String> {
+        let format = PixelFormatEnum::RGB24;
+        let pitch = self.width * format.byte_size_per_pixel() as u32;
+        let surface = Surface::from_data(&mut self.pixels, self.width, self.height, pitch, format)?;
+        surface.save(path)
+    }
+}
+
+/// Where the thumbnail of the track loaded from `path` is, `None` being the built-in one,
+/// drawing it first if it hasn't been yet or the track's changed since. `None` if it
+/// couldn't be saved.
+pub fn cached(track: &Track, path: Option<&Path>) -> Option<PathBuf> {
+    let name = path
+        .and_then(Path::file_stem)
+        .map_or("built-in".into(), |stem| stem.to_string_lossy());
+    let thumbnail = PathBuf::from(DIRECTORY).join(format!("{name}.png"));
+
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    let fresh = match (modified(&thumbnail), path.map(modified)) {
+        (Ok(drawn), Some(Ok(changed))) => drawn >= changed,
+        (Ok(_), None) => true,
+        _ => false,
+    };
+    if fresh {
+        return Some(thumbnail);
+    }
+    let saved = fs::create_dir_all(DIRECTORY)
+        .map_err(|error| error.to_string())
+        .and_then(|()| Thumbnail::new(track).save(&thumbnail));
+    match saved {
+        Ok(()) => Some(thumbnail),
+        Err(error) => {
+            eprintln!(
+                "couldn't save a thumbnail to {}: {error}",
+                thumbnail.display()
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::Surface as Ground;
+
+    #[test]
+    fn thumbnails_fit_and_show_the_ground_and_walls() {
+        let track = Track::new();
+        let thumbnail = Thumbnail::new(&track);
+        assert!(thumbnail.width <= MAX_SIZE.0 && thumbnail.height <= MAX_SIZE.1);
+        assert!(thumbnail.width == MAX_SIZE.0 || thumbnail.height == MAX_SIZE.1);
+        assert_eq!(
+            thumbnail.pixels.len(),
+            (thumbnail.width * thumbnail.height * 3) as usize
+        );
+
+        let pixel = |x: u32, y: u32| {
+            let at = ((y * thumbnail.width + x) * 3) as usize;
+            [
+                thumbnail.pixels[at],
+                thumbnail.pixels[at + 1],
+                thumbnail.pixels[at + 2],
+            ]
+        };
+        // the walls go round the edge of the map
+        assert_eq!(pixel(0, thumbnail.height / 2), [255; 3]);
+        let asphalt = Ground::Asphalt.color(false);
+        let scale = thumbnail.width as f64 / 5000.;
+        assert_eq!(
+            pixel((2000. * scale) as u32, (690. * scale) as u32),
+            [asphalt.r, asphalt.g, asphalt.b]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::config::{ConfigError, Table};
+
+/// Simplified Pacejka "magic formula": how much of its peak force a tyre delivers at a given
+/// amount of slip. The force rises roughly linearly, tops out and then falls off a little as
+/// the tyre starts sliding, which is what makes a slide catchable.
+pub struct TireCurve {
+    /// how steeply the force builds up (B)
+    pub stiffness: f64,
+    /// how pronounced the drop after the peak is (C)
+    pub shape: f64,
+    /// how rounded the peak is, close to one keeps the force up at large slip (E)
+    pub curvature: f64,
+}
+
+/// Slip angle in radians to share of the available lateral grip
+pub const LATERAL: TireCurve = TireCurve {
+    stiffness: 10.,
+    shape: 1.9,
+    curvature: 0.97,
+};
+
+/// Slip ratio, how much faster the tyre turns than the ground passes under it relative to the
+/// ground speed, to share of the available longitudinal grip
+pub const LONGITUDINAL: TireCurve = TireCurve {
+    stiffness: 14.,
+    shape: 1.9,
+    curvature: 0.9,
+};
+
+/// Where `LONGITUDINAL` tops out. A wheel spinning any faster only loses grip and makes smoke.
+pub const PEAK_SLIP_RATIO: f64 = 0.12;
+
+impl TireCurve {
+    /// Share of the peak force, odd in `slip`
+    pub fn force(&self, slip: f64) -> f64 {
+        let bx = self.stiffness * slip;
+        (self.shape * (bx - self.curvature * (bx - bx.atan())).atan()).sin()
+    }
+}
+
+/// How the tyres of a car heat up and wear down. Temperatures are in degrees Celsius.
+#[derive(Clone)]
+pub struct TireSpec {
+    /// what the tyres cool down to and start out at
+    pub ambient_temperature: f64,
+    /// the tyres grip best at this temperature
+    pub optimal_temperature: f64,
+    /// how far off the optimum the tyres can get before they're down to `cold_grip`
+    pub temperature_window: f64,
+    /// share of the grip left outside of the temperature window
+    pub cold_grip: f64,
+    /// degrees gained per tick for every pixel per tick of sliding
+    pub heating: f64,
+    /// degrees gained per tick for every pixel per tick of just rolling along
+    pub rolling_heating: f64,
+    /// share of the difference to the ambient temperature lost per tick
+    pub cooling: f64,
+    /// wear per tick for every pixel per tick of sliding, 1 being worn through
+    pub wear_rate: f64,
+    /// share of the grip left on a worn through tyre
+    pub worn_grip: f64,
+}
+
+impl Default for TireSpec {
+    fn default() -> TireSpec {
+        TireSpec {
+            ambient_temperature: 20.,
+            optimal_temperature: 80.,
+            temperature_window: 60.,
+            cold_grip: 0.8,
+            heating: 0.14,
+            rolling_heating: 0.02,
+            cooling: 0.005,
+            wear_rate: 0.00002,
+            worn_grip: 0.6,
+        }
+    }
+}
+
+impl TireSpec {
+    /// Overwrites whatever `table` specifies
+    pub fn read(&mut self, table: &Table) -> Result<(), ConfigError> {
+        table.read_number("ambient_temperature", &mut self.ambient_temperature)?;
+        table.read_number("optimal_temperature", &mut self.optimal_temperature)?;
+        table.read_number("temperature_window", &mut self.temperature_window)?;
+        table.read_number("cold_grip", &mut self.cold_grip)?;
+        table.read_number("heating", &mut self.heating)?;
+        table.read_number("rolling_heating", &mut self.rolling_heating)?;
+        table.read_number("cooling", &mut self.cooling)?;
+        table.read_number("wear_rate", &mut self.wear_rate)?;
+        table.read_number("worn_grip", &mut self.worn_grip)?;
+        Ok(())
+    }
+}
+
+/// The state of a single tyre
+#[derive(Clone, Copy)]
+pub struct Tire {
+    temperature: f64,
+    wear: f64,
+}
+
+impl Tire {
+    pub fn new(spec: &TireSpec) -> Tire {
+        Tire {
+            temperature: spec.ambient_temperature,
+            wear: 0.,
+        }
+    }
+
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// From 0 for a fresh tyre to 1 for one that's worn through
+    pub fn wear(&self) -> f64 {
+        self.wear
+    }
+
+    /// How far the temperature is off the optimum, -1 and 1 being the edges of the window
+    pub fn temperature_offset(&self, spec: &TireSpec) -> f64 {
+        (self.temperature() - spec.optimal_temperature) / spec.temperature_window
+    }
+
+    /// Share of its full grip the tyre has left
+    pub fn grip(&self, spec: &TireSpec) -> f64 {
+        let offset = self.temperature_offset(spec).abs().min(1.);
+        let temperature_grip = 1. - (1. - spec.cold_grip) * offset.powi(2);
+        let wear_grip = 1. - (1. - spec.worn_grip) * self.wear;
+        temperature_grip * wear_grip
+    }
+
+    /// `slip` is how fast the tyre slides over the ground, `speed` how fast it rolls and
+    /// `load` how much of the car's weight rests on it compared to an even share
+    pub fn update(&mut self, spec: &TireSpec, slip: f64, speed: f64, load: f64) {
+        let work = slip * load;
+        self.temperature += (spec.heating * work + spec.rolling_heating * speed * load)
+            - (self.temperature - spec.ambient_temperature) * spec.cooling;
+
+        // an overheated tyre goes off a lot quicker
+        let overheating = self.temperature_offset(spec).max(0.);
+        self.wear = (self.wear + spec.wear_rate * work * (1. + overheating)).min(1.);
+    }
+}
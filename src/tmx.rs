@@ -0,0 +1,539 @@
+//! Tracks made in the Tiled map editor (https://www.mapeditor.org), read from its `.tmx`
+//! files. Only what a track needs is read, with a small reader for the XML Tiled writes.
+//!
+//! The tiles are the ground: each tile in the tileset says what it's made of with its class
+//! (or type, in older versions of Tiled) or a `surface` property, one of `asphalt`, `grass`,
+//! `gravel` or `ice`, and anything else is grass. Tile layers have to be saved as CSV, and
+//! ones further up cover the ones below where they have a tile.
+//!
+//! Objects go by their class too:
+//! - `wall`: a polyline or polygon to crash into, or a rectangle walled in all round
+//! - `checkpoint`: a rectangle the cars have to go through, in the order they're in the map
+//! - `spawn`: a point where a car starts the race, facing up the map when not rotated. The
+//!   first one is the first player's.
+//! - `respawn`: a point to put cars back on the track at, the spawn points doubling as these
+//!   if there aren't any
+//! - `prop`: a rectangle standing in the way
+//! - `cone`, `barrel` and `tires`: a point where one stands for the cars to knock over
+//! - `mover`: a polyline its middle goes back and forth along, or a polygon it goes round and
+//!   round, at `speed` pixels per tick and waiting `wait` seconds at the ends, or the start of
+//!   a polygon. It's `width` across and `length` long and starts `offset` seconds in.
+//! - `puddle`: a rectangle where the water stands when it rains
+//! - `camera`: a point to film replays from
+//! - `racing_line`: a polyline or polygon along the ideal way round, the first one there is.
+//!   Its `speed` property says how fast to go along it in pixels per tick, flat out without
+//!   one, and its `width` how wide the road is along it.
+//!
+//! Image layers are backgrounds, drawn behind the ground in the order they're in the map and
+//! seen past its edges. Their parallax factor is how much of the camera's movement they
+//! follow, smaller ones looking further away, and they can repeat across and down.
+//!
+//! The map's own properties can set the time of day it starts at with `time`, from 0 to 24,
+//! and how many hours go by each second with `hours_per_second`. Without them it's midday
+//! all day. `name`, `author` and `laps` say what the track's called, who made it and how
+//! many laps a race on it is.
+
+use std::{fs, path::Path};
+
+use nalgebra::{Point2, Rotation2, Vector2};
+
+use crate::{
+    collision::{Obb, Segment},
+    config::ConfigError,
+    loose_props::PropKind,
+    terrain::{Surface, Terrain},
+    track::{Background, Mover, PropSpot, RespawnPoint, Track, Waypoint, WAYPOINT_WIDTH},
+    TICKS_PER_SECOND,
+};
+
+/// Tiled keeps whether a tile is flipped in the top bits of its number
+const FLIP_FLAGS: u32 = 0xf000_0000;
+
+pub fn load(path: impl AsRef<Path>) -> Result<Track, ConfigError> {
+    let path = path.as_ref();
+    let map = read_xml(path)?;
+    if map.name != "map" {
+        return Err(map.error("expected a <map>"));
+    }
+    let width = map.number("width")? as usize;
+    let height = map.number("height")? as usize;
+    let tile_size = map.number("tilewidth")?;
+    if map.number("tileheight")? != tile_size {
+        return Err(map.error("tiles have to be square"));
+    }
+
+    // what each tile number stands for, across all the tilesets
+    let mut surfaces = Vec::new();
+    for tileset in map.children("tileset") {
+        let first = tileset.number("firstgid")? as usize;
+        let external;
+        let tileset = match tileset.attribute("source") {
+            Some(source) => {
+                external = read_xml(path.parent().unwrap_or(Path::new("")).join(source))?;
+                &external
+            }
+            None => tileset,
+        };
+        for tile in tileset.children("tile") {
+            let index = first + tile.number("id")? as usize;
+            if surfaces.len() <= index {
+                surfaces.resize(index + 1, Surface::Grass);
+            }
+            surfaces[index] = tile_surface(tile)?;
+        }
+    }
+
+    let mut tiles = vec![Surface::Grass; width * height];
+    for layer in map.children("layer") {
+        let data = layer
+            .children("data")
+            .next()
+            .ok_or_else(|| layer.error("a tile layer without any <data>"))?;
+        if data.attribute("encoding") != Some("csv") {
+            return Err(data.error("tile layers have to be saved as CSV"));
+        }
+        let numbers = data
+            .text
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty());
+        for (i, number) in numbers.enumerate().take(tiles.len()) {
+            let tile = number
+                .parse::<u32>()
+                .map_err(|_| data.error(&format!("`{number}` isn't a tile number")))?
+                & !FLIP_FLAGS;
+            // 0 is no tile at all, leaving whatever the layers below have there
+            if tile != 0 {
+                tiles[i] = surfaces
+                    .get(tile as usize)
+                    .copied()
+                    .unwrap_or(Surface::Grass);
+            }
+        }
+    }
+
+    let size = Vector2::new(width as f64, height as f64) * tile_size;
+    let mut track = Track {
+        terrain: Terrain::from_tiles(tile_size as u32, width, height, tiles),
+        walls: Vec::new(),
+        props: Vec::new(),
+        loose_props: Vec::new(),
+        movers: Vec::new(),
+        clock: 0,
+        ramps: Vec::new(),
+        hazards: Vec::new(),
+        puddles: Vec::new(),
+        wet: false,
+        checkpoints: Vec::new(),
+        waypoints: Vec::new(),
+        spawn_points: Vec::new(),
+        respawn_points: Vec::new(),
+        name: map.string_property("name").map(str::to_string),
+        author: map.string_property("author").map(str::to_string),
+        laps: map.number_property("laps")?.map(|laps| laps as u32),
+        bounds: Some((Point2::origin(), Point2::from(size))),
+        decorations: Vec::new(),
+        backgrounds: Vec::new(),
+        trackside_cameras: Vec::new(),
+        off_track_grip: 0.8,
+        off_track_drag: 0.02,
+        start_hour: map.number_property("time")?.unwrap_or(12.),
+        hours_per_second: map.number_property("hours_per_second")?.unwrap_or(0.),
+    };
+    for layer in map.children("imagelayer") {
+        let Some(image) = layer.children("image").next() else {
+            // Tiled lets image layers go without an image
+            continue;
+        };
+        let source = image
+            .attribute("source")
+            .ok_or_else(|| image.error("an image without a source"))?;
+        track.backgrounds.push(Background {
+            image: path.parent().unwrap_or(Path::new("")).join(source),
+            offset: Vector2::new(
+                layer.number_or("offsetx", 0.)?,
+                layer.number_or("offsety", 0.)?,
+            ),
+            parallax: Vector2::new(
+                layer.number_or("parallaxx", 1.)?,
+                layer.number_or("parallaxy", 1.)?,
+            ),
+            repeat: (
+                layer.attribute("repeatx") == Some("1"),
+                layer.attribute("repeaty") == Some("1"),
+            ),
+        });
+    }
+    for group in map.children("objectgroup") {
+        for object in group.children("object") {
+            read_object(object, &mut track)?;
+        }
+    }
+    track.fill_in_respawn_points();
+    Ok(track)
+}
+
+fn tile_surface(tile: &Element) -> Result<Surface, ConfigError> {
+    let property = tile
+        .property("surface")
+        .and_then(|property| property.attribute("value"));
+    let name = property
+        .or(tile.attribute("class"))
+        .or(tile.attribute("type"));
+    Ok(match name {
+        None => Surface::Grass,
+        Some(name) => match Surface::from_name(name) {
+            Some(surface) => surface,
+            None => return Err(tile.error(&format!("`{name}` isn't a surface"))),
+        },
+    })
+}
+
+fn read_object(object: &Element, track: &mut Track) -> Result<(), ConfigError> {
+    let class = object.attribute("class").or(object.attribute("type"));
+    let origin = Point2::new(object.number("x")?, object.number("y")?);
+    // in degrees clockwise around the object's origin, which is the top left corner of a
+    // rectangle
+    let rotation = Rotation2::new(object.number_or("rotation", 0.)?.to_radians());
+    let size = Vector2::new(
+        object.number_or("width", 0.)?,
+        object.number_or("height", 0.)?,
+    );
+    let rectangle = || Obb::new(origin + rotation * size / 2., size, rotation);
+    let point = || RespawnPoint {
+        position: origin,
+        rotation,
+    };
+
+    match class {
+        Some("wall") => {
+            let line = read_line(object, origin, rotation)?.unwrap_or_else(|| Line {
+                points: rectangle().corners().to_vec(),
+                closed: true,
+            });
+            if line.points.len() < 2 {
+                return Err(object.error("a wall needs at least two points"));
+            }
+            track.walls.extend(
+                line.round()
+                    .windows(2)
+                    .map(|ends| Segment::new(ends[0], ends[1])),
+            );
+        }
+        Some("checkpoint") => track.checkpoints.push(rectangle()),
+        Some("puddle") => track.puddles.push(rectangle()),
+        Some("prop") => track.props.push(rectangle()),
+        Some(name @ ("cone" | "barrel" | "tires")) => track.loose_props.push(PropSpot {
+            kind: PropKind::from_name(name).unwrap(),
+            position: origin,
+            rotation,
+        }),
+        Some("mover") => {
+            let line = read_line(object, origin, rotation)?
+                .ok_or_else(|| object.error("a mover should be a polyline or polygon"))?;
+            let seconds = |name: &str| -> Result<u32, ConfigError> {
+                let seconds = object.number_property(name)?.unwrap_or(0.);
+                Ok((seconds * TICKS_PER_SECOND) as u32)
+            };
+            track.movers.push(Mover {
+                size: Vector2::new(
+                    object.number_property("width")?.unwrap_or(50.),
+                    object.number_property("length")?.unwrap_or(200.),
+                ),
+                path: line.points,
+                closed: line.closed,
+                speed: object.number_property("speed")?.unwrap_or(2.),
+                wait: seconds("wait")?,
+                offset: seconds("offset")?,
+            });
+        }
+        Some("spawn") => track.spawn_points.push(point()),
+        Some("respawn") => track.respawn_points.push(point()),
+        Some("camera") => track.trackside_cameras.push(origin),
+        Some("racing_line") if track.waypoints.is_empty() => {
+            let speed = object.number_property("speed")?;
+            let width = object.number_property("width")?.unwrap_or(WAYPOINT_WIDTH);
+            track.waypoints = read_line(object, origin, rotation)?
+                .ok_or_else(|| object.error("a racing line should be a polyline or polygon"))?
+                .round()
+                .into_iter()
+                .map(|position| Waypoint {
+                    position,
+                    speed,
+                    width,
+                })
+                .collect();
+        }
+        // anything else is left for other tools
+        _ => {}
+    }
+    Ok(())
+}
+
+/// A polyline or polygon, in the world
+struct Line {
+    points: Vec<Point2<f64>>,
+    /// going round back to where it started, like a polygon
+    closed: bool,
+}
+
+impl Line {
+    /// The points in order, with the first one again at the end for closed lines
+    fn round(mut self) -> Vec<Point2<f64>> {
+        if self.closed && !self.points.is_empty() {
+            self.points.push(self.points[0]);
+        }
+        self.points
+    }
+}
+
+/// The polyline or polygon in `object`, if there is one
+fn read_line(
+    object: &Element,
+    origin: Point2<f64>,
+    rotation: Rotation2<f64>,
+) -> Result<Option<Line>, ConfigError> {
+    let line = object
+        .children("polyline")
+        .map(|line| (line, false))
+        .chain(object.children("polygon").map(|line| (line, true)))
+        .next();
+    let Some((line, closed)) = line else {
+        return Ok(None);
+    };
+    let points = line
+        .attribute("points")
+        .ok_or_else(|| line.error("a line without any points"))?;
+    let points = parse_points(points)
+        .ok_or_else(|| line.error("points should be like `0,0 10,5`"))?
+        .into_iter()
+        .map(|point| origin + rotation * point.coords)
+        .collect();
+    Ok(Some(Line { points, closed }))
+}
+
+/// Tiled's `x,y x,y ...` lists
+fn parse_points(points: &str) -> Option<Vec<Point2<f64>>> {
+    points
+        .split_whitespace()
+        .map(|pair| {
+            let (x, y) = pair.split_once(',')?;
+            Some(Point2::new(x.parse().ok()?, y.parse().ok()?))
+        })
+        .collect()
+}
+
+/// A tag with its attributes, what's inside of it and the line it starts on
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+    line: usize,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn number(&self, name: &str) -> Result<f64, ConfigError> {
+        let value = self
+            .attribute(name)
+            .ok_or_else(|| self.error(&format!("<{}> needs a `{name}`", self.name)))?;
+        value
+            .parse()
+            .map_err(|_| self.error(&format!("`{name}` should be a number")))
+    }
+
+    fn number_or(&self, name: &str, default: f64) -> Result<f64, ConfigError> {
+        match self.attribute(name) {
+            Some(_) => self.number(name),
+            None => Ok(default),
+        }
+    }
+
+    /// One of the custom properties set on the element in Tiled, with its `value`
+    fn property(&self, name: &str) -> Option<&Element> {
+        self.children("properties")
+            .flat_map(|properties| properties.children("property"))
+            .find(|property| property.attribute("name") == Some(name))
+    }
+
+    fn string_property(&self, name: &str) -> Option<&str> {
+        self.property(name)?.attribute("value")
+    }
+
+    fn number_property(&self, name: &str) -> Result<Option<f64>, ConfigError> {
+        self.property(name)
+            .map(|property| property.number("value"))
+            .transpose()
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn error(&self, message: &str) -> ConfigError {
+        ConfigError::Parse {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn read_xml(path: impl AsRef<Path>) -> Result<Element, ConfigError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .map_err(|error| ConfigError::Io(path.display().to_string(), error))?;
+    parse_xml(&text)
+}
+
+/// The root element of an XML document. Just enough XML for what Tiled writes: no
+/// doctypes, CDATA or namespaces.
+fn parse_xml(text: &str) -> Result<Element, ConfigError> {
+    let line_at = |position: usize| text[..position].matches('\n').count() + 1;
+    let error = |position: usize, message: &str| ConfigError::Parse {
+        line: line_at(position),
+        message: message.to_string(),
+    };
+
+    // the elements opened but not closed yet, the outermost first
+    let mut open: Vec<Element> = Vec::new();
+    let mut root = None;
+    let mut position = 0;
+    while let Some(start) = text[position..].find('<').map(|offset| position + offset) {
+        if let Some(parent) = open.last_mut() {
+            parent.text.push_str(&unescape(&text[position..start]));
+        }
+        let rest = &text[start..];
+        let skip_to = |end: &str| {
+            rest.find(end)
+                .map(|offset| start + offset + end.len())
+                .ok_or_else(|| error(start, &format!("missing `{end}`")))
+        };
+        if rest.starts_with("<?") {
+            position = skip_to("?>")?;
+            continue;
+        }
+        if rest.starts_with("<!--") {
+            position = skip_to("-->")?;
+            continue;
+        }
+
+        let end = skip_to(">")?;
+        let tag = &text[start + 1..end - 1];
+        position = end;
+        if let Some(name) = tag.strip_prefix('/') {
+            let element = open
+                .pop()
+                .filter(|element| element.name == name.trim())
+                .ok_or_else(|| error(start, &format!("`</{}>` closes nothing", name.trim())))?;
+            match open.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None => root = Some(element),
+            }
+            continue;
+        }
+
+        let (tag, empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let (name, mut attributes_text) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let mut attributes = Vec::new();
+        loop {
+            attributes_text = attributes_text.trim_start();
+            if attributes_text.is_empty() {
+                break;
+            }
+            let (key, value) = attributes_text
+                .split_once('=')
+                .ok_or_else(|| error(start, "expected `name=\"value\"`"))?;
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|quote| *quote == '"' || *quote == '\'')
+                .ok_or_else(|| error(start, "attribute values have to be quoted"))?;
+            let (value, rest) = value[1..]
+                .split_once(quote)
+                .ok_or_else(|| error(start, "unclosed quote"))?;
+            attributes.push((key.trim().to_string(), unescape(value)));
+            attributes_text = rest;
+        }
+
+        let element = Element {
+            name: name.to_string(),
+            attributes,
+            children: Vec::new(),
+            text: String::new(),
+            line: line_at(start),
+        };
+        match (empty, open.last_mut()) {
+            (false, _) => open.push(element),
+            (true, Some(parent)) => parent.children.push(element),
+            (true, None) => root = Some(element),
+        }
+    }
+
+    match (open.last(), root) {
+        (Some(element), _) => Err(element.error(&format!("unclosed <{}>", element.name))),
+        (None, Some(root)) => Ok(root),
+        (None, None) => Err(error(0, "no elements")),
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_sample_map_loads() {
+        let track = load("assets/tracks/oval.tmx").unwrap();
+        assert_eq!(track.terrain.size(), (24, 16));
+        assert_eq!(
+            track.terrain.surface_at(Point2::new(300., 300.)),
+            Surface::Asphalt
+        );
+        assert_eq!(
+            track.terrain.surface_at(Point2::new(1500., 1000.)),
+            Surface::Grass
+        );
+        assert_eq!(
+            track.terrain.surface_at(Point2::new(10., 10.)),
+            Surface::Gravel
+        );
+        // the rectangle all round and the four sides of the infield block
+        assert_eq!(track.walls.len(), 8);
+        assert_eq!(track.checkpoints.len(), 4);
+        assert_eq!(track.spawn_points.len(), 3);
+        assert_eq!(track.respawn_points.len(), 3);
+        assert_eq!(track.props.len(), 1);
+        assert_eq!(track.trackside_cameras, vec![Point2::new(1500., 800.)]);
+        assert_eq!(track.backgrounds.len(), 1);
+        assert_eq!(
+            track.backgrounds[0].image,
+            Path::new("assets/tracks/clouds.png")
+        );
+        assert_eq!(track.backgrounds[0].parallax, Vector2::new(0.5, 0.5));
+        assert_eq!(track.start_hour, 20.);
+    }
+
+    #[test]
+    fn broken_xml_says_where() {
+        let error = parse_xml("<map>\n<layer>\n</map>").err().unwrap();
+        assert!(matches!(error, ConfigError::Parse { line: 3, .. }));
+    }
+}
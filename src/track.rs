@@ -0,0 +1,626 @@
+use std::path::{Path, PathBuf};
+
+use nalgebra::{Point2, Rotation2, Vector2};
+use sdl2::pixels::Color;
+
+use crate::{
+    collision::{Obb, Segment},
+    config::ConfigError,
+    loose_props::PropKind,
+    terrain::{Grip, Surface, Terrain},
+    tmx, track_toml, validation,
+};
+
+/// How much of its grip the ground keeps when it's wet
+const WET_GRIP: f64 = 0.75;
+/// and how much of that is left standing in a puddle
+const PUDDLE_GRIP: f64 = 0.45;
+
+/// A wedge rising along the way it faces, flat on the ground at the back and `height` pixels
+/// up at its front edge. Cars driving off the front fly.
+pub struct Ramp {
+    pub area: Obb,
+    pub height: f64,
+}
+
+impl Ramp {
+    /// `size` is the width across and the length along the slope
+    pub fn new(
+        center: Point2<f64>,
+        size: Vector2<f64>,
+        rotation: Rotation2<f64>,
+        height: f64,
+    ) -> Ramp {
+        Ramp {
+            area: Obb::new(center, size, rotation),
+            height,
+        }
+    }
+
+    /// How high the ramp is at `point`, if it's on the ramp
+    pub fn height_at(&self, point: Point2<f64>) -> Option<f64> {
+        let local = self.area.rotation.inverse() * (point - self.area.center);
+        let half_extents = self.area.half_extents;
+        if local.x.abs() > half_extents.x || local.y.abs() > half_extents.y {
+            return None;
+        }
+        // forward is -y, like for the cars
+        let progress = (half_extents.y - local.y) / (2. * half_extents.y);
+        Some(self.height * progress)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HazardKind {
+    /// about as slippery as it gets
+    Ice,
+    /// leaves the steering dead for a moment when the front wheels run through it
+    Oil,
+}
+
+/// A patch on the ground that throws the cars off
+pub struct Hazard {
+    pub kind: HazardKind,
+    pub area: Obb,
+}
+
+impl Hazard {
+    pub fn new(kind: HazardKind, center: Point2<f64>, size: Vector2<f64>, angle: f64) -> Hazard {
+        Hazard {
+            kind,
+            area: Obb::new(center, size, Rotation2::new(angle)),
+        }
+    }
+}
+
+/// A spot on the track to put a car back on, facing the way the race goes
+pub struct RespawnPoint {
+    pub position: Point2<f64>,
+    pub rotation: Rotation2<f64>,
+}
+
+/// Something solid going round a route on a timetable, like a gate sliding shut, traffic
+/// crossing the track or a train, that pushes the cars out of its way
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mover {
+    /// across and along the way it goes
+    pub size: Vector2<f64>,
+    /// the points its middle goes through
+    pub path: Vec<Point2<f64>>,
+    /// going on from the last point back round to the first, rather than turning back the way
+    /// it came
+    pub closed: bool,
+    /// pixels per tick
+    pub speed: f64,
+    /// ticks it waits at each end of the path, or at the first point of a closed one
+    pub wait: u32,
+    /// ticks into its timetable it starts at, for ones on the same route to go at different
+    /// times
+    pub offset: u32,
+}
+
+impl Mover {
+    /// The points it goes through in order, the first one again at the end when closed
+    fn route(&self) -> Vec<Point2<f64>> {
+        let mut route = self.path.clone();
+        if self.closed && !route.is_empty() {
+            route.push(route[0]);
+        }
+        route
+    }
+
+    /// Where it is and which way it's going `tick` ticks into the level
+    fn place_at(&self, tick: f64) -> (Point2<f64>, Vector2<f64>) {
+        let route = self.route();
+        let length: f64 = route
+            .windows(2)
+            .map(|ends| (ends[1] - ends[0]).norm())
+            .sum();
+        if length == 0. || self.speed <= 0. {
+            return (
+                route.first().copied().unwrap_or_else(Point2::origin),
+                Vector2::y(),
+            );
+        }
+        let (travel, wait) = (length / self.speed, self.wait as f64);
+        let period = if self.closed {
+            travel + wait
+        } else {
+            2. * (travel + wait)
+        };
+        let time = (tick + self.offset as f64).rem_euclid(period);
+        // waiting at the start, going along, then waiting at the end and coming back
+        let gone = if time < wait {
+            0.
+        } else if time < wait + travel {
+            (time - wait) * self.speed
+        } else if time < 2. * wait + travel {
+            length
+        } else {
+            length - (time - 2. * wait - travel) * self.speed
+        };
+
+        let mut left = gone;
+        for ends in route.windows(2) {
+            let along = ends[1] - ends[0];
+            let segment = along.norm();
+            if left <= segment && segment > 0. {
+                return (ends[0] + along * (left / segment), along / segment);
+            }
+            left -= segment;
+        }
+        let last = route.len() - 1;
+        let direction = (route[last] - route[last - 1]).try_normalize(1e-9);
+        (route[last], direction.unwrap_or_else(Vector2::y))
+    }
+
+    pub fn area_at(&self, tick: u32) -> Obb {
+        let (center, direction) = self.place_at(tick as f64);
+        let rotation = Rotation2::rotation_between(&Vector2::y(), &direction);
+        Obb::new(center, self.size, rotation)
+    }
+
+    /// How fast it's going `tick` ticks in, in pixels per tick
+    pub fn velocity_at(&self, tick: u32) -> Vector2<f64> {
+        self.place_at(tick as f64).0 - self.place_at(tick as f64 - 1.).0
+    }
+}
+
+/// Where a loose prop stands before anyone's knocked it over
+pub struct PropSpot {
+    pub kind: PropKind,
+    pub position: Point2<f64>,
+    pub rotation: Rotation2<f64>,
+}
+
+/// A picture behind the ground, like clouds or far off hills, that scrolls slower than the
+/// track to look further away. Seen past the track's edges.
+pub struct Background {
+    /// the file it's drawn from
+    pub image: PathBuf,
+    /// where its top left corner is in the world with the camera on the world's origin
+    pub offset: Vector2<f64>,
+    /// how much of the camera's movement it follows across and down, from 0 for staying put
+    /// on the screen to 1 for moving with the ground
+    pub parallax: Vector2<f64>,
+    /// whether it's laid out over and over across and down, to fill the view
+    pub repeat: (bool, bool),
+}
+
+/// Something by the side of the track that's only there to look at, not to hit
+pub struct Decoration {
+    pub area: Obb,
+    /// the file it's drawn from, `None` for an outline in `color`
+    pub image: Option<PathBuf>,
+    pub color: Color,
+}
+
+/// How far back each car lines up from the one in front past the end of a track's spawn
+/// points, in pixels
+const GRID_SPACING: f64 = 150.;
+
+/// How wide the road is round waypoints that don't say, in pixels
+pub const WAYPOINT_WIDTH: f64 = 300.;
+
+/// A point on the ideal way round, for the AI to drive through and the racing line to be
+/// drawn along
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Waypoint {
+    pub position: Point2<f64>,
+    /// how fast to go through it, in pixels per tick, `None` for as fast as the car goes
+    pub speed: Option<f64>,
+    /// how much room there is to drive across the road here, in all
+    pub width: f64,
+}
+
+impl Waypoint {
+    pub fn new(position: Point2<f64>) -> Waypoint {
+        Waypoint {
+            position,
+            speed: None,
+            width: WAYPOINT_WIDTH,
+        }
+    }
+}
+
+/// Everything static the cars drive on and into
+pub struct Track {
+    pub terrain: Terrain,
+    pub walls: Vec<Segment>,
+    pub props: Vec<Obb>,
+    /// cones and the like for the cars to knock about
+    pub loose_props: Vec<PropSpot>,
+    pub movers: Vec<Mover>,
+    /// ticks since the level started, for where the movers are
+    pub clock: u32,
+    pub ramps: Vec<Ramp>,
+    pub hazards: Vec<Hazard>,
+    /// where water collects when it rains, for the tyres to skate over
+    pub puddles: Vec<Obb>,
+    /// whether it's raining, taking grip away everywhere and even more in the puddles
+    pub wet: bool,
+    /// gates to drive through, in the order they come in a lap
+    pub checkpoints: Vec<Obb>,
+    /// the ideal way round in order, the first one again at the end on a loop. Empty for
+    /// tracks without one.
+    pub waypoints: Vec<Waypoint>,
+    /// where the cars start, the first player's first. Cars without one line up behind the
+    /// last one.
+    pub spawn_points: Vec<RespawnPoint>,
+    pub respawn_points: Vec<RespawnPoint>,
+    /// what the track's called and who made it, for tracks that say
+    pub name: Option<String>,
+    pub author: Option<String>,
+    /// how many laps a race on the track is, for tracks that say
+    pub laps: Option<u32>,
+    /// top left and bottom right corner of the playable area, for tracks that have edges
+    pub bounds: Option<(Point2<f64>, Point2<f64>)>,
+    /// drawn behind the ground, furthest away first
+    pub backgrounds: Vec<Background>,
+    pub decorations: Vec<Decoration>,
+    /// spots by the side of the track to film replays from
+    pub trackside_cameras: Vec<Point2<f64>>,
+    /// share of the grip left when leaving the track, on top of what the surface takes
+    pub off_track_grip: f64,
+    /// extra share of the speed lost every tick off the track
+    pub off_track_drag: f64,
+    /// the time of day the level starts at, from 0 to 24
+    pub start_hour: f64,
+    /// how many hours go by every second, 0 for the time to stand still
+    pub hours_per_second: f64,
+}
+
+impl Track {
+    /// A TOML track or else a Tiled map, going by the extension, checked over for being
+    /// playable
+    pub fn load(path: impl AsRef<Path>) -> Result<Track, ConfigError> {
+        Track::load_reporting(path, |_, _| {})
+    }
+
+    /// Loads like `load`, saying how far along it is from 0 to 1 and what it's doing as it
+    /// goes on to each part
+    pub fn load_reporting(
+        path: impl AsRef<Path>,
+        progress: impl Fn(f64, &'static str),
+    ) -> Result<Track, ConfigError> {
+        let path = path.as_ref();
+        progress(0., "READING THE TRACK");
+        let track = if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            track_toml::load(path)?
+        } else {
+            tmx::load(path)?
+        };
+        progress(0.8, "CHECKING THE TRACK");
+        validation::check(&track)?;
+        Ok(track)
+    }
+
+    /// The waypoint closest to `point`, for where on the way round a car is. `None` on tracks
+    /// without any.
+    pub fn nearest_waypoint(&self, point: Point2<f64>) -> Option<usize> {
+        (0..self.waypoints.len()).min_by(|&a, &b| {
+            let distance = |i: usize| (self.waypoints[i].position - point).norm_squared();
+            distance(a).total_cmp(&distance(b))
+        })
+    }
+
+    /// The waypoint at least `distance` pixels further on along the way from the `from`th,
+    /// going round and round loops and stopping at the end of ones that aren't
+    pub fn waypoint_ahead(&self, from: usize, distance: f64) -> usize {
+        let count = self.waypoints.len();
+        let looped = count > 1 && self.waypoints[0].position == self.waypoints[count - 1].position;
+        let (mut at, mut gone) = (from, 0.);
+        while gone < distance {
+            let next = match at + 1 {
+                // the last one's the first one again
+                next if next >= count - 1 && looped => (next + 1) % count,
+                next if next >= count => return at,
+                next => next,
+            };
+            gone += (self.waypoints[next].position - self.waypoints[at].position).norm();
+            at = next;
+            if at == from {
+                break;
+            }
+        }
+        at
+    }
+
+    pub fn new() -> Track {
+        let terrain = Terrain::new();
+
+        let tile_size = terrain.tile_size() as f64;
+        let (width, height) = terrain.size();
+        let (width, height) = (width as f64 * tile_size, height as f64 * tile_size);
+        let corners = [
+            Point2::new(0., 0.),
+            Point2::new(width, 0.),
+            Point2::new(width, height),
+            Point2::new(0., height),
+        ];
+        let walls = (0..corners.len())
+            .map(|i| Segment::new(corners[i], corners[(i + 1) % corners.len()]))
+            .collect();
+
+        // a few concrete blocks in the infield to bounce off of
+        let props = [
+            (Point2::new(1500., 1300.), 0.),
+            (Point2::new(3500., 1300.), 0.4),
+            (Point2::new(2500., 2500.), -0.8),
+        ]
+        .into_iter()
+        .map(|(center, angle)| Obb::new(center, Vector2::new(150., 60.), Rotation2::new(angle)))
+        .collect();
+
+        // a slalom down the right straight past the ice, barrels on the inside of the top
+        // right corner and tyres round the outside of the bottom right one
+        let cones = (0..5).map(|i| (PropKind::Cone, 4062.5, 2150. + 150. * i as f64));
+        let barrels =
+            [(3830., 920.), (3785., 965.), (3830., 980.)].map(|(x, y)| (PropKind::Barrel, x, y));
+        let tires =
+            [(4330., 3280.), (4370., 3340.), (4300., 3400.)].map(|(x, y)| (PropKind::Tires, x, y));
+        let loose_props = cones
+            .chain(barrels)
+            .chain(tires)
+            .map(|(kind, x, y)| PropSpot {
+                kind,
+                position: Point2::new(x, y),
+                rotation: Rotation2::identity(),
+            })
+            .collect();
+
+        // one jump on the top straight and one on the bottom straight, both going clockwise
+        let ramps = vec![
+            Ramp::new(
+                Point2::new(2000., 690.),
+                Vector2::new(300., 200.),
+                Rotation2::new(std::f64::consts::FRAC_PI_2),
+                30.,
+            ),
+            Ramp::new(
+                Point2::new(2500., 3060.),
+                Vector2::new(300., 200.),
+                Rotation2::new(-std::f64::consts::FRAC_PI_2),
+                30.,
+            ),
+        ];
+
+        // black ice on the right straight and an oil slick on the left one
+        let hazards = vec![
+            Hazard::new(
+                HazardKind::Ice,
+                Point2::new(4060., 1500.),
+                Vector2::new(375., 300.),
+                0.,
+            ),
+            Hazard::new(
+                HazardKind::Oil,
+                Point2::new(940., 2000.),
+                Vector2::new(200., 150.),
+                0.3,
+            ),
+        ];
+
+        // in the braking zones at the ends of the top and bottom straights
+        let puddles = [Point2::new(3700., 690.), Point2::new(1300., 3060.)]
+            .into_iter()
+            .map(|center| Obb::new(center, Vector2::new(260., 180.), Rotation2::identity()))
+            .collect();
+
+        // across each straight going clockwise, starting and finishing on the top one past
+        // the grid and clear of the jumps
+        let checkpoints = [
+            (Point2::new(2600., 687.5), Vector2::new(40., 375.)),
+            (Point2::new(4062.5, 1875.), Vector2::new(375., 40.)),
+            (Point2::new(1800., 3062.5), Vector2::new(40., 375.)),
+            (Point2::new(937.5, 1875.), Vector2::new(375., 40.)),
+        ]
+        .into_iter()
+        .map(|(center, size)| Obb::new(center, size, Rotation2::identity()))
+        .collect();
+
+        // every few car lengths down the middle of the asphalt loop, going clockwise
+        let loop_corners = [
+            (937.5, 687.5),
+            (4062.5, 687.5),
+            (4062.5, 3062.5),
+            (937.5, 3062.5),
+        ]
+        .map(|(x, y)| Point2::new(x, y));
+        // down the middle of the straights, swinging wide enough round the corners to clip the
+        // inside of each one
+        let radius = 550.;
+        let inwards = [(1., 1.), (-1., 1.), (-1., -1.), (1., -1.)];
+        let mut racing_line: Vec<_> = loop_corners
+            .iter()
+            .zip(inwards)
+            .enumerate()
+            .flat_map(|(i, (corner, (x, y)))| {
+                let center = corner + Vector2::new(x, y) * radius;
+                let start = std::f64::consts::PI * (1. + i as f64 / 2.);
+                (0..=8).map(move |step| {
+                    let angle = start + std::f64::consts::FRAC_PI_2 * step as f64 / 8.;
+                    center + Vector2::new(angle.cos(), angle.sin()) * radius
+                })
+            })
+            .collect();
+        racing_line.push(racing_line[0]);
+        let waypoints = racing_line.into_iter().map(Waypoint::new).collect();
+
+        let spacing = 250.;
+        let mut candidates = Vec::new();
+        for i in 0..loop_corners.len() {
+            let (start, end) = (loop_corners[i], loop_corners[(i + 1) % loop_corners.len()]);
+            let direction = (end - start).normalize();
+            // cars face -y when unturned
+            let rotation = Rotation2::rotation_between(&Vector2::new(0., -1.), &direction);
+            let count = ((end - start).norm() / spacing) as usize;
+            candidates.extend((0..count).map(|step| RespawnPoint {
+                position: start + direction * spacing * step as f64,
+                rotation,
+            }));
+        }
+
+        let mut track = Track {
+            terrain,
+            walls,
+            props,
+            loose_props,
+            movers: Vec::new(),
+            clock: 0,
+            ramps,
+            hazards,
+            puddles,
+            wet: false,
+            checkpoints,
+            waypoints,
+            // on the top straight short of the line, facing along it
+            spawn_points: [(1025., 750.), (1325., 650.), (1625., 650.)]
+                .into_iter()
+                .map(|(x, y)| RespawnPoint {
+                    position: Point2::new(x, y),
+                    rotation: Rotation2::new(std::f64::consts::FRAC_PI_2),
+                })
+                .collect(),
+            respawn_points: Vec::new(),
+            name: None,
+            author: None,
+            laps: None,
+            // walled in all round
+            bounds: Some((corners[0], corners[2])),
+            backgrounds: Vec::new(),
+            decorations: Vec::new(),
+            // in the infield, looking out at the corners and the middle of the long straights
+            trackside_cameras: [
+                (1450., 1200.),
+                (2500., 1200.),
+                (3550., 1200.),
+                (3550., 2550.),
+                (2500., 2550.),
+                (1450., 2550.),
+            ]
+            .map(|(x, y)| Point2::new(x, y))
+            .to_vec(),
+            off_track_grip: 0.8,
+            off_track_drag: 0.02,
+            // late afternoon, with the sun going down after a couple of minutes and a whole
+            // day going by in eight
+            start_hour: 16.,
+            hours_per_second: 0.05,
+        };
+        // nobody wants to be dropped onto a ramp or into the oil
+        track.respawn_points = candidates
+            .into_iter()
+            .filter(|point: &RespawnPoint| {
+                track.ground_height(point.position) == 0.
+                    && track.hazard_at(point.position).is_none()
+            })
+            .collect();
+        track
+    }
+
+    /// Where the `i`th car on the grid starts: its spawn point, or a few car lengths behind
+    /// the last one for cars past the end of them. Without any at all the cars line up from
+    /// the first respawn point, or else from the middle of the map.
+    pub fn grid_slot(&self, i: usize) -> RespawnPoint {
+        let last = self.spawn_points.len().min(i + 1).checked_sub(1);
+        let (base, behind) = match last {
+            Some(last) => (&self.spawn_points[last], i - last),
+            None => match self.respawn_points.first() {
+                Some(point) => (point, i),
+                None => {
+                    let middle = self.bounds.map_or(Point2::origin(), |(low, high)| {
+                        Point2::from((low.coords + high.coords) / 2.)
+                    });
+                    return RespawnPoint {
+                        position: middle + Vector2::new(0., GRID_SPACING * i as f64),
+                        rotation: Rotation2::identity(),
+                    };
+                }
+            },
+        };
+        // cars face -y when unturned
+        RespawnPoint {
+            position: base.position
+                + base.rotation * Vector2::new(0., GRID_SPACING * behind as f64),
+            rotation: base.rotation,
+        }
+    }
+
+    /// Moves the movers on a tick
+    pub fn update(&mut self) {
+        self.clock += 1;
+    }
+
+    /// Every mover where it is now, with how fast it's going
+    pub fn mover_areas(&self) -> impl Iterator<Item = (Obb, Vector2<f64>)> + '_ {
+        self.movers
+            .iter()
+            .map(|mover| (mover.area_at(self.clock), mover.velocity_at(self.clock)))
+    }
+
+    /// Makes the spawn points double as respawn points, for tracks that don't have any of
+    /// the latter
+    pub fn fill_in_respawn_points(&mut self) {
+        if self.respawn_points.is_empty() {
+            self.respawn_points = self
+                .spawn_points
+                .iter()
+                .map(|point| RespawnPoint {
+                    position: point.position,
+                    rotation: point.rotation,
+                })
+                .collect();
+        }
+    }
+
+    /// Height of whatever the cars drive on at `point`, the flat ground being 0
+    pub fn ground_height(&self, point: Point2<f64>) -> f64 {
+        self.ramps
+            .iter()
+            .filter_map(|ramp| ramp.height_at(point))
+            .fold(0., f64::max)
+    }
+
+    pub fn hazard_at(&self, point: Point2<f64>) -> Option<HazardKind> {
+        self.hazards
+            .iter()
+            .find(|hazard| hazard.area.contains(point))
+            .map(|hazard| hazard.kind)
+    }
+
+    /// How well the ground at `point` grips, worse off the track than the surface alone
+    pub fn grip_at(&self, point: Point2<f64>) -> Grip {
+        let mut grip = match self.hazard_at(point) {
+            Some(HazardKind::Ice) => Surface::Ice.grip(),
+            Some(HazardKind::Oil) => {
+                let mut grip = self.terrain.surface_at(point).grip();
+                grip.lateral *= 0.3;
+                grip.longitudinal *= 0.3;
+                grip
+            }
+            None => self.terrain.surface_at(point).grip(),
+        };
+        if self.wet {
+            let mut wet_grip = WET_GRIP;
+            if self.puddles.iter().any(|puddle| puddle.contains(point)) {
+                wet_grip *= PUDDLE_GRIP;
+            }
+            grip.lateral *= wet_grip;
+            grip.longitudinal *= wet_grip;
+        }
+        if !self.is_on_track(point) {
+            grip.lateral *= self.off_track_grip;
+            grip.longitudinal *= self.off_track_grip;
+            grip.rolling_resistance += self.off_track_drag;
+        }
+        grip
+    }
+
+    pub fn is_on_track(&self, point: Point2<f64>) -> bool {
+        self.terrain.surface_at(point).is_track()
+    }
+}
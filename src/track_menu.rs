@@ -0,0 +1,265 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+
+use crate::{
+    assets::Assets,
+    assets::SpriteId,
+    config::{self, Table, Value},
+    input::{Command, TickInput},
+    loading::{Loading, Source},
+    menu::Selection,
+    scene::{Scene, Transition},
+    text, thumbnail,
+    track::Track,
+    Level, SCREEN_DIMENSIONS,
+};
+
+/// Where the last track chosen is kept, next to wherever the game was started from like the
+/// screenshots
+const LAST_TRACK: &str = "last_track.toml";
+/// Tracks shown at once, the list scrolling past the rest
+const SHOWN: usize = 9;
+
+/// A track to choose, `path` being `None` for the built-in one
+struct Entry {
+    path: Option<PathBuf>,
+    name: String,
+    /// who made it and how many laps it's raced over
+    about: String,
+    track: Track,
+    /// the track from above, once `load_sprites` has drawn it
+    thumbnail: Option<SpriteId>,
+}
+
+/// Choosing which track to drive on before the level starts, from the tracks in a directory
+/// and the built-in one. Up and down go by the throttle and brake and the pause button picks
+/// one. The one picked is remembered for next time.
+pub struct TrackMenu {
+    entries: Vec<Entry>,
+    selection: Selection,
+    chosen: Option<usize>,
+    /// set up like the level to drive on the track chosen, straight after loading it, rather
+    /// than ending the menu for whoever opened it to ask what was chosen
+    then: Option<Box<Level>>,
+}
+
+impl TrackMenu {
+    /// Every Tiled map and TOML track in `directory` that loads, in order of their file names
+    pub fn new(directory: &Path) -> TrackMenu {
+        let mut paths: Vec<_> = fs::read_dir(directory)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| {
+                        path.extension()
+                            .is_some_and(|extension| extension == "tmx" || extension == "toml")
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        let mut entries = vec![Entry {
+            path: None,
+            name: "BUILT-IN TRACK".to_string(),
+            about: String::new(),
+            track: Track::new(),
+            thumbnail: None,
+        }];
+        for path in paths {
+            let track = match Track::load(&path) {
+                Ok(track) => track,
+                Err(error) => {
+                    eprintln!("couldn't load the track {}: {error}", path.display());
+                    continue;
+                }
+            };
+            let file_name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let name = track.name.clone().unwrap_or_else(|| file_name.into_owned());
+            let mut about = Vec::new();
+            if let Some(author) = &track.author {
+                about.push(format!("BY {author}"));
+            }
+            if let Some(laps) = track.laps {
+                about.push(format!("{laps} LAPS"));
+            }
+            entries.push(Entry {
+                path: Some(path),
+                name: name.to_uppercase(),
+                about: about.join(", ").to_uppercase(),
+                track,
+                thumbnail: None,
+            });
+        }
+
+        let last = last_track();
+        let selected = entries
+            .iter()
+            .position(|entry| entry.path.is_some() && entry.path == last)
+            .unwrap_or(0);
+        TrackMenu {
+            entries,
+            selection: Selection::new(selected),
+            chosen: None,
+            then: None,
+        }
+    }
+
+    /// Goes on to drive on the track chosen with a level set up like `level`
+    pub fn then_drive(mut self, level: Level) -> TrackMenu {
+        self.then = Some(Box::new(level));
+        self
+    }
+
+    /// Whether there's anything to choose from besides the built-in track
+    pub fn has_tracks(&self) -> bool {
+        self.entries.len() > 1
+    }
+
+    /// The track chosen, `Some(None)` for the built-in one and `None` before one's chosen
+    pub fn chosen(&self) -> Option<Option<&Path>> {
+        self.chosen
+            .map(|chosen| self.entries[chosen].path.as_deref())
+    }
+}
+
+/// The track chosen last time, if one was
+fn last_track() -> Option<PathBuf> {
+    let table = config::load(LAST_TRACK).ok()?;
+    Some(PathBuf::from(table.string("track").ok()??))
+}
+
+/// Remembers `path` for next time, or forgets the last one for the built-in track
+fn remember(path: Option<&Path>) {
+    let mut table = Table::default();
+    if let Some(path) = path {
+        table.set("track", Value::String(path.to_string_lossy().into_owned()));
+    }
+    if let Err(error) = fs::write(LAST_TRACK, config::write(&table)) {
+        eprintln!("couldn't remember the track chosen in {LAST_TRACK}: {error}");
+    }
+}
+
+impl Scene for TrackMenu {
+    /// Ends once a track's chosen, as well as when a player wants out
+    fn update(&mut self, inputs: &[TickInput]) -> Transition {
+        // the first player does the choosing
+        let Some(input) = inputs.first() else {
+            return Transition::Stay;
+        };
+        for command in &input.commands {
+            match command {
+                Command::Quit | Command::Back => return Transition::Pop,
+                Command::TogglePause => {
+                    let selected = self.selection.selected();
+                    self.chosen = Some(selected);
+                    let path = self.entries[selected].path.as_deref();
+                    remember(path);
+                    let Some(level) = self.then.take() else {
+                        return Transition::Pop;
+                    };
+                    let source = match path {
+                        Some(path) => Source::File(path.to_string_lossy().into_owned()),
+                        None => Source::BuiltIn,
+                    };
+                    return Transition::Replace(Box::new(Loading::new(source).then_drive(*level)));
+                }
+                _ => {}
+            }
+        }
+        self.selection.update(input, self.entries.len());
+        Transition::Stay
+    }
+
+    /// Draws every track from above to show next to the list, or loads the ones drawn last
+    /// time for tracks that haven't changed since
+    fn load_sprites(&mut self, assets: &mut Assets) {
+        for entry in &mut self.entries {
+            entry.thumbnail = thumbnail::cached(&entry.track, entry.path.as_deref())
+                .and_then(|path| assets.load_image(path));
+        }
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>, assets: &Assets, _alpha: f64) {
+        let (width, height) = SCREEN_DIMENSIONS;
+        canvas.set_draw_color(Color::RGB(20, 24, 28));
+        canvas.clear();
+        text::draw_centered(canvas, "CHOOSE A TRACK", (width / 2, 120), 8, Color::WHITE);
+
+        // scrolled to keep the one selected in view
+        let selected = self.selection.selected();
+        let first = selected
+            .saturating_sub(SHOWN / 2)
+            .min(self.entries.len().saturating_sub(SHOWN));
+        // the list to the left of the middle, the one selected shown on the right
+        let list = width * 3 / 8;
+        let spacing = 80;
+        for (row, (i, entry)) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(SHOWN)
+            .enumerate()
+        {
+            let top = 240 + row as i32 * spacing;
+            let color = if i == selected {
+                Color::RGB(255, 220, 0)
+            } else {
+                Color::RGB(160, 160, 160)
+            };
+            let name = if i == selected {
+                format!("- {} -", entry.name)
+            } else {
+                entry.name.clone()
+            };
+            text::draw_centered(canvas, &name, (list, top), 5, color);
+            if !entry.about.is_empty() {
+                text::draw_centered(canvas, &entry.about, (list, top + 34), 2, color);
+            }
+        }
+        if let Some(id) = self.entries[selected].thumbnail {
+            let sprite = assets.sprite(id);
+            let query = sprite.query();
+            // twice as big, keeping its shape
+            let (thumbnail_width, thumbnail_height) = (query.width * 2, query.height * 2);
+            let destination = Rect::new(
+                width * 3 / 4 - thumbnail_width as i32 / 2,
+                240,
+                thumbnail_width,
+                thumbnail_height,
+            );
+            canvas.copy(&sprite, None, destination).unwrap();
+        }
+        text::draw_centered(
+            canvas,
+            "UP AND DOWN TO CHOOSE, RETURN OR START TO DRIVE",
+            (width / 2, height - 80),
+            3,
+            Color::RGB(200, 200, 200),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_sample_tracks_are_listed_after_the_built_in_one() {
+        let menu = TrackMenu::new(Path::new("assets/tracks"));
+        let names: Vec<_> = menu
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names[0], "BUILT-IN TRACK");
+        assert!(names.contains(&"SQUARE"));
+        assert!(menu.has_tracks());
+        assert_eq!(menu.chosen(), None);
+    }
+}
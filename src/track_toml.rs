@@ -0,0 +1,742 @@
+//! Tracks written by hand in TOML, for ones simple enough not to need a map editor. Distances
+//! are in pixels, points are `[x, y]` pairs and angles are in degrees clockwise.
+//!
+//! At the top:
+//! - `name`, `author` and `laps`: what the track's called, who made it and how many laps a
+//!   race on it is, all of them optional
+//! - `size`: how many tiles the ground is across and down, and `tile_size` how big each one is
+//! - `ground`: what it's made of where no `[[surfaces]]` say otherwise, grass if left out
+//! - `time` and `hours_per_second`: the time of day it starts at and how fast it goes by
+//! - `racing_line`: the points along the ideal way round, or else `[[waypoints]]` below
+//! - `cameras`: points to film replays from
+//!
+//! Then any number of:
+//! - `[[surfaces]]`: a `surface` (`asphalt`, `grass`, `gravel` or `ice`) over the tiles whose
+//!   middles are in the rectangle `area = [x, y, width, height]`, or within `width` of the
+//!   `line` through the points given. Later ones go over earlier ones.
+//! - `[[walls]]`: a line through `points` to crash into, going back round to the first one
+//!   with `closed = true`
+//! - `[[checkpoints]]`: a `center`, `size` and `angle`, in the order they come in a lap
+//! - `[[props]]` and `[[puddles]]`: the same, for something standing in the way and where
+//!   the water stands when it rains
+//! - `[[respawns]]`: a `position` and `angle` to put cars back on the track at
+//! - `[[loose_props]]`: the same for where a `kind` of thing (`cone`, `barrel` or `tires`)
+//!   stands for the cars to knock over
+//! - `[[movers]]`: something `size` big going back and forth along the `path` through the
+//!   points given, or round and round it with `closed = true`, at `speed` pixels per tick.
+//!   It waits `wait` seconds at the ends, or the start of a closed one, and starts `offset`
+//!   seconds in.
+//! - `[[decorations]]`: a `center`, `size` and `angle` for something to look at, drawn from
+//!   the `image` next to the track file or outlined in its `color`
+//! - `[[waypoints]]`: the racing line a `position` at a time, each with the `speed` to go
+//!   through it at in pixels per tick (flat out without one) and how much `width` of road
+//!   there is there
+//!
+//! And a `[grid]` for where the cars start: the `pole` position, the `angle` they face (0 for
+//! up the map), `slots` on it, and how far back each one is from the last with `spacing`,
+//! every other one off to the right by `across`.
+
+use std::{fs, io, path::Path};
+
+use nalgebra::{Point2, Rotation2, Vector2};
+use sdl2::pixels::Color;
+
+use crate::{
+    collision::{Obb, Segment},
+    config::{self, ConfigError, Table, Value},
+    loose_props::PropKind,
+    terrain::{Surface, Terrain},
+    track::{Decoration, Mover, PropSpot, RespawnPoint, Track, Waypoint, WAYPOINT_WIDTH},
+    TICKS_PER_SECOND,
+};
+
+/// A track as it's written in its file, to edit and save again before turning it into a
+/// `Track` to drive on
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackFile {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub laps: Option<u32>,
+    /// tiles across and down
+    pub size: (usize, usize),
+    pub tile_size: f64,
+    pub ground: Surface,
+    pub time: f64,
+    pub hours_per_second: f64,
+    pub surfaces: Vec<Patch>,
+    pub walls: Vec<Wall>,
+    pub checkpoints: Vec<Shape>,
+    pub props: Vec<Shape>,
+    pub puddles: Vec<Shape>,
+    pub respawns: Vec<Spot>,
+    pub loose_props: Vec<LooseSpot>,
+    pub movers: Vec<Mover>,
+    pub decorations: Vec<DecorationFile>,
+    pub grid: Option<Grid>,
+    pub waypoints: Vec<Waypoint>,
+    pub cameras: Vec<Point2<f64>>,
+}
+
+/// A box turned `angle` degrees clockwise around its middle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shape {
+    pub center: Point2<f64>,
+    pub size: Vector2<f64>,
+    pub angle: f64,
+}
+
+/// A surface over part of the ground
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patch {
+    pub surface: Surface,
+    pub zone: Zone,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Zone {
+    /// the tiles with their middles in a rectangle from its top left corner
+    Area {
+        corner: Point2<f64>,
+        size: Vector2<f64>,
+    },
+    /// the tiles with their middles within half `width` of a line through `points`
+    Line {
+        points: Vec<Point2<f64>>,
+        width: f64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wall {
+    pub points: Vec<Point2<f64>>,
+    /// going back round to the first point
+    pub closed: bool,
+}
+
+/// Where a car goes, facing `angle` degrees clockwise from up the map
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spot {
+    pub position: Point2<f64>,
+    pub angle: f64,
+}
+
+/// Where a loose prop stands
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LooseSpot {
+    pub kind: PropKind,
+    pub spot: Spot,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecorationFile {
+    pub shape: Shape,
+    /// next to the track file
+    pub image: Option<String>,
+    pub color: Option<(u8, u8, u8)>,
+}
+
+/// Where the cars line up, two by two with the one on the right half a slot further back
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grid {
+    pub pole: Spot,
+    pub slots: usize,
+    /// how far back each slot is from the one before
+    pub spacing: f64,
+    /// how far right every other slot is
+    pub across: f64,
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Track, ConfigError> {
+    let path = path.as_ref();
+    Ok(TrackFile::load(path)?.track(path.parent().unwrap_or(Path::new(""))))
+}
+
+impl TrackFile {
+    /// An empty field of `ground` `size` tiles big, walled in all round
+    pub fn new(size: (usize, usize), tile_size: f64) -> TrackFile {
+        let (width, height) = (size.0 as f64 * tile_size, size.1 as f64 * tile_size);
+        TrackFile {
+            name: None,
+            author: None,
+            laps: None,
+            size,
+            tile_size,
+            ground: Surface::Grass,
+            time: 12.,
+            hours_per_second: 0.,
+            surfaces: Vec::new(),
+            walls: vec![Wall {
+                points: vec![
+                    Point2::new(0., 0.),
+                    Point2::new(width, 0.),
+                    Point2::new(width, height),
+                    Point2::new(0., height),
+                ],
+                closed: true,
+            }],
+            checkpoints: Vec::new(),
+            props: Vec::new(),
+            puddles: Vec::new(),
+            respawns: Vec::new(),
+            loose_props: Vec::new(),
+            movers: Vec::new(),
+            decorations: Vec::new(),
+            grid: None,
+            waypoints: Vec::new(),
+            cameras: Vec::new(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<TrackFile, ConfigError> {
+        TrackFile::read(&config::load(path)?)
+    }
+
+    fn read(file: &Table) -> Result<TrackFile, ConfigError> {
+        let (width, height) = pair(file, "size")?;
+        let surfaces = file
+            .tables("surfaces")?
+            .into_iter()
+            .map(|patch| {
+                let surface = surface(required(patch.string("surface")?, "surface")?, "surface")?;
+                let zone = match patch.array("area")? {
+                    Some(area) => {
+                        let [x, y, width, height] = numbers(area, "area")?;
+                        Zone::Area {
+                            corner: Point2::new(x, y),
+                            size: Vector2::new(width, height),
+                        }
+                    }
+                    None => Zone::Line {
+                        points: points(required(patch.array("line")?, "line")?, "line")?,
+                        width: required(patch.number("width")?, "width")?,
+                    },
+                };
+                Ok(Patch { surface, zone })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+        let walls = file
+            .tables("walls")?
+            .into_iter()
+            .map(|wall| {
+                let points = points(required(wall.array("points")?, "points")?, "points")?;
+                if points.len() < 2 {
+                    return Err(wrong_type("points", "at least two points"));
+                }
+                Ok(Wall {
+                    points,
+                    closed: wall.boolean("closed")?.unwrap_or(false),
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+        let shapes = |key: &str| -> Result<Vec<Shape>, ConfigError> {
+            file.tables(key)?.into_iter().map(shape).collect()
+        };
+        let decorations = file
+            .tables("decorations")?
+            .into_iter()
+            .map(|decoration| {
+                Ok(DecorationFile {
+                    shape: shape(decoration)?,
+                    image: decoration.string("image")?.map(str::to_string),
+                    color: match decoration.array("color")? {
+                        Some(color) => {
+                            let [r, g, b] = numbers(color, "color")?;
+                            Some((r as u8, g as u8, b as u8))
+                        }
+                        None => None,
+                    },
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+        let loose_props = file
+            .tables("loose_props")?
+            .into_iter()
+            .map(|prop| {
+                let kind = required(prop.string("kind")?, "kind")?;
+                Ok(LooseSpot {
+                    kind: PropKind::from_name(kind).ok_or_else(|| {
+                        wrong_type("kind", "one of \"cone\", \"barrel\" or \"tires\"")
+                    })?,
+                    spot: spot(prop)?,
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+        let seconds = |table: &Table, key: &str| -> Result<u32, ConfigError> {
+            Ok((table.number(key)?.unwrap_or(0.) * TICKS_PER_SECOND) as u32)
+        };
+        let movers = file
+            .tables("movers")?
+            .into_iter()
+            .map(|mover| {
+                let path = points(required(mover.array("path")?, "path")?, "path")?;
+                if path.len() < 2 {
+                    return Err(wrong_type("path", "at least two points"));
+                }
+                let (width, length) = pair(mover, "size")?;
+                Ok(Mover {
+                    size: Vector2::new(width, length),
+                    path,
+                    closed: mover.boolean("closed")?.unwrap_or(false),
+                    speed: required(mover.number("speed")?, "speed")?,
+                    wait: seconds(mover, "wait")?,
+                    offset: seconds(mover, "offset")?,
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+        let grid = match file.table("grid")? {
+            Some(grid) => Some(Grid {
+                pole: Spot {
+                    position: point(grid, "pole")?,
+                    angle: grid.number("angle")?.unwrap_or(0.),
+                },
+                slots: grid.number("slots")?.unwrap_or(6.) as usize,
+                spacing: grid.number("spacing")?.unwrap_or(150.),
+                across: grid.number("across")?.unwrap_or(150.),
+            }),
+            None => None,
+        };
+        let optional_points = |key: &str| match file.array(key)? {
+            Some(values) => points(values, key),
+            None => Ok(Vec::new()),
+        };
+        let mut waypoints = file
+            .tables("waypoints")?
+            .into_iter()
+            .map(|waypoint| {
+                Ok(Waypoint {
+                    position: point(waypoint, "position")?,
+                    speed: waypoint.number("speed")?,
+                    width: waypoint.number("width")?.unwrap_or(WAYPOINT_WIDTH),
+                })
+            })
+            .collect::<Result<Vec<_>, ConfigError>>()?;
+        if waypoints.is_empty() {
+            waypoints = optional_points("racing_line")?
+                .into_iter()
+                .map(Waypoint::new)
+                .collect();
+        }
+
+        Ok(TrackFile {
+            name: file.string("name")?.map(str::to_string),
+            author: file.string("author")?.map(str::to_string),
+            laps: file.number("laps")?.map(|laps| laps as u32),
+            size: (width as usize, height as usize),
+            tile_size: file.number("tile_size")?.unwrap_or(125.),
+            ground: match file.string("ground")? {
+                Some(name) => surface(name, "ground")?,
+                None => Surface::Grass,
+            },
+            time: file.number("time")?.unwrap_or(12.),
+            hours_per_second: file.number("hours_per_second")?.unwrap_or(0.),
+            surfaces,
+            walls,
+            checkpoints: shapes("checkpoints")?,
+            props: shapes("props")?,
+            puddles: shapes("puddles")?,
+            respawns: file
+                .tables("respawns")?
+                .into_iter()
+                .map(spot)
+                .collect::<Result<_, ConfigError>>()?,
+            loose_props,
+            movers,
+            decorations,
+            grid,
+            waypoints,
+            cameras: optional_points("cameras")?,
+        })
+    }
+
+    /// Writes the track out the way `load` reads it back
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, config::write(&self.table()))
+    }
+
+    fn table(&self) -> Table {
+        let mut file = Table::default();
+        let number = |number: f64| Value::Number(number);
+        let string = |string: &str| Value::String(string.to_string());
+        let point = |point: &Point2<f64>| Value::Array(vec![number(point.x), number(point.y)]);
+        let points = |points: &[Point2<f64>]| Value::Array(points.iter().map(point).collect());
+        let tables =
+            |tables: Vec<Table>| Value::Array(tables.into_iter().map(Value::Table).collect());
+        let shape = |shape: &Shape| {
+            let mut table = Table::default();
+            table.set("center", point(&shape.center));
+            table.set(
+                "size",
+                Value::Array(vec![number(shape.size.x), number(shape.size.y)]),
+            );
+            table.set("angle", number(shape.angle));
+            table
+        };
+        let shapes = |shapes: &[Shape]| tables(shapes.iter().map(shape).collect());
+
+        if let Some(name) = &self.name {
+            file.set("name", string(name));
+        }
+        if let Some(author) = &self.author {
+            file.set("author", string(author));
+        }
+        if let Some(laps) = self.laps {
+            file.set("laps", number(laps as f64));
+        }
+        file.set(
+            "size",
+            Value::Array(vec![number(self.size.0 as f64), number(self.size.1 as f64)]),
+        );
+        file.set("tile_size", number(self.tile_size));
+        file.set("ground", string(self.ground.name()));
+        file.set("time", number(self.time));
+        file.set("hours_per_second", number(self.hours_per_second));
+        // as just the points when there's nothing more to them
+        if self
+            .waypoints
+            .iter()
+            .all(|waypoint| *waypoint == Waypoint::new(waypoint.position))
+        {
+            let line: Vec<_> = self
+                .waypoints
+                .iter()
+                .map(|waypoint| waypoint.position)
+                .collect();
+            file.set("racing_line", points(&line));
+        } else {
+            let waypoints = self.waypoints.iter().map(|waypoint| {
+                let mut table = Table::default();
+                table.set("position", point(&waypoint.position));
+                if let Some(speed) = waypoint.speed {
+                    table.set("speed", number(speed));
+                }
+                table.set("width", number(waypoint.width));
+                table
+            });
+            file.set("waypoints", tables(waypoints.collect()));
+        }
+        file.set("cameras", points(&self.cameras));
+
+        let patches = self.surfaces.iter().map(|patch| {
+            let mut table = Table::default();
+            table.set("surface", string(patch.surface.name()));
+            match &patch.zone {
+                Zone::Area { corner, size } => table.set(
+                    "area",
+                    Value::Array([corner.x, corner.y, size.x, size.y].map(number).to_vec()),
+                ),
+                Zone::Line {
+                    points: line,
+                    width,
+                } => {
+                    table.set("line", points(line));
+                    table.set("width", number(*width));
+                }
+            }
+            table
+        });
+        file.set("surfaces", tables(patches.collect()));
+        let walls = self.walls.iter().map(|wall| {
+            let mut table = Table::default();
+            table.set("points", points(&wall.points));
+            table.set("closed", Value::Bool(wall.closed));
+            table
+        });
+        file.set("walls", tables(walls.collect()));
+        file.set("checkpoints", shapes(&self.checkpoints));
+        file.set("props", shapes(&self.props));
+        file.set("puddles", shapes(&self.puddles));
+        let spot = |spot: &Spot| {
+            let mut table = Table::default();
+            table.set("position", point(&spot.position));
+            table.set("angle", number(spot.angle));
+            table
+        };
+        file.set("respawns", tables(self.respawns.iter().map(spot).collect()));
+        let loose_props = self.loose_props.iter().map(|prop| {
+            let mut table = spot(&prop.spot);
+            table.set("kind", string(prop.kind.name()));
+            table
+        });
+        file.set("loose_props", tables(loose_props.collect()));
+        let seconds = |ticks: u32| number(ticks as f64 / TICKS_PER_SECOND);
+        let movers = self.movers.iter().map(|mover| {
+            let mut table = Table::default();
+            table.set(
+                "size",
+                Value::Array(vec![number(mover.size.x), number(mover.size.y)]),
+            );
+            table.set("path", points(&mover.path));
+            table.set("closed", Value::Bool(mover.closed));
+            table.set("speed", number(mover.speed));
+            table.set("wait", seconds(mover.wait));
+            table.set("offset", seconds(mover.offset));
+            table
+        });
+        file.set("movers", tables(movers.collect()));
+        let decorations = self.decorations.iter().map(|decoration| {
+            let mut table = shape(&decoration.shape);
+            if let Some(image) = &decoration.image {
+                table.set("image", string(image));
+            }
+            if let Some((r, g, b)) = decoration.color {
+                table.set(
+                    "color",
+                    Value::Array([r, g, b].map(|c| number(c as f64)).to_vec()),
+                );
+            }
+            table
+        });
+        file.set("decorations", tables(decorations.collect()));
+        if let Some(grid) = &self.grid {
+            let mut table = Table::default();
+            table.set("pole", point(&grid.pole.position));
+            table.set("angle", number(grid.pole.angle));
+            table.set("slots", number(grid.slots as f64));
+            table.set("spacing", number(grid.spacing));
+            table.set("across", number(grid.across));
+            file.set("grid", Value::Table(table));
+        }
+        file
+    }
+
+    /// The track to drive on, looking for images in `directory`
+    pub fn track(&self, directory: &Path) -> Track {
+        let (width, height) = self.size;
+        let mut tiles = vec![self.ground; width * height];
+        for patch in &self.surfaces {
+            for (i, tile) in tiles.iter_mut().enumerate() {
+                let middle = Point2::new((i % width) as f64 + 0.5, (i / width) as f64 + 0.5);
+                if patch.zone.covers(middle * self.tile_size) {
+                    *tile = patch.surface;
+                }
+            }
+        }
+
+        let mut walls = Vec::new();
+        for wall in &self.walls {
+            let mut corners = wall.points.clone();
+            if wall.closed {
+                corners.push(corners[0]);
+            }
+            walls.extend(
+                corners
+                    .windows(2)
+                    .map(|ends| Segment::new(ends[0], ends[1])),
+            );
+        }
+        let boxes = |shapes: &[Shape]| shapes.iter().map(Shape::obb).collect();
+        let decorations = self
+            .decorations
+            .iter()
+            .map(|decoration| Decoration {
+                area: decoration.shape.obb(),
+                image: decoration.image.as_ref().map(|image| directory.join(image)),
+                color: match decoration.color {
+                    Some((r, g, b)) => Color::RGB(r, g, b),
+                    None => Color::WHITE,
+                },
+            })
+            .collect();
+
+        let size = Vector2::new(width as f64, height as f64) * self.tile_size;
+        let mut track = Track {
+            terrain: Terrain::from_tiles(self.tile_size as u32, width, height, tiles),
+            walls,
+            props: boxes(&self.props),
+            loose_props: self
+                .loose_props
+                .iter()
+                .map(|prop| {
+                    let RespawnPoint { position, rotation } = prop.spot.respawn_point();
+                    PropSpot {
+                        kind: prop.kind,
+                        position,
+                        rotation,
+                    }
+                })
+                .collect(),
+            movers: self.movers.clone(),
+            clock: 0,
+            ramps: Vec::new(),
+            hazards: Vec::new(),
+            puddles: boxes(&self.puddles),
+            wet: false,
+            checkpoints: boxes(&self.checkpoints),
+            waypoints: self.waypoints.clone(),
+            spawn_points: self.grid.map(|grid| grid.slots()).unwrap_or_default(),
+            respawn_points: self.respawns.iter().map(Spot::respawn_point).collect(),
+            name: self.name.clone(),
+            author: self.author.clone(),
+            laps: self.laps,
+            bounds: Some((Point2::origin(), Point2::from(size))),
+            backgrounds: Vec::new(),
+            decorations,
+            trackside_cameras: self.cameras.clone(),
+            off_track_grip: 0.8,
+            off_track_drag: 0.02,
+            start_hour: self.time,
+            hours_per_second: self.hours_per_second,
+        };
+        track.fill_in_respawn_points();
+        track
+    }
+}
+
+impl Shape {
+    pub fn obb(&self) -> Obb {
+        Obb::new(
+            self.center,
+            self.size,
+            Rotation2::new(self.angle.to_radians()),
+        )
+    }
+}
+
+impl Zone {
+    fn covers(&self, point: Point2<f64>) -> bool {
+        match self {
+            Zone::Area { corner, size } => {
+                (corner.x..corner.x + size.x).contains(&point.x)
+                    && (corner.y..corner.y + size.y).contains(&point.y)
+            }
+            Zone::Line { points, width } => points
+                .windows(2)
+                .any(|ends| Segment::new(ends[0], ends[1]).distance_to(point) <= width / 2.),
+        }
+    }
+}
+
+impl Spot {
+    fn respawn_point(&self) -> RespawnPoint {
+        RespawnPoint {
+            position: self.position,
+            rotation: Rotation2::new(self.angle.to_radians()),
+        }
+    }
+}
+
+impl Grid {
+    pub fn slots(&self) -> Vec<RespawnPoint> {
+        let rotation = Rotation2::new(self.pole.angle.to_radians());
+        // cars face -y when unturned
+        let (back, right) = (
+            rotation * Vector2::new(0., 1.),
+            rotation * Vector2::new(1., 0.),
+        );
+        (0..self.slots)
+            .map(|slot| RespawnPoint {
+                position: self.pole.position
+                    + back * self.spacing * slot as f64
+                    + right * self.across * (slot % 2) as f64,
+                rotation,
+            })
+            .collect()
+    }
+}
+
+fn shape(table: &Table) -> Result<Shape, ConfigError> {
+    let (width, height) = pair(table, "size")?;
+    Ok(Shape {
+        center: point(table, "center")?,
+        size: Vector2::new(width, height),
+        angle: table.number("angle")?.unwrap_or(0.),
+    })
+}
+
+fn spot(table: &Table) -> Result<Spot, ConfigError> {
+    Ok(Spot {
+        position: point(table, "position")?,
+        angle: table.number("angle")?.unwrap_or(0.),
+    })
+}
+
+fn point(table: &Table, key: &str) -> Result<Point2<f64>, ConfigError> {
+    let (x, y) = pair(table, key)?;
+    Ok(Point2::new(x, y))
+}
+
+fn pair(table: &Table, key: &str) -> Result<(f64, f64), ConfigError> {
+    table
+        .get(key)
+        .and_then(config::as_pair)
+        .ok_or_else(|| wrong_type(key, "a pair of numbers like [100, 200]"))
+}
+
+fn points(values: &[Value], key: &str) -> Result<Vec<Point2<f64>>, ConfigError> {
+    values
+        .iter()
+        .map(|value| {
+            let (x, y) = config::as_pair(value)
+                .ok_or_else(|| wrong_type(key, "a list of points like [[0, 0], [100, 200]]"))?;
+            Ok(Point2::new(x, y))
+        })
+        .collect()
+}
+
+/// `N` numbers, like the corners of an area or the channels of a color
+fn numbers<const N: usize>(values: &[Value], key: &str) -> Result<[f64; N], ConfigError> {
+    let numbers: Option<Vec<f64>> = values.iter().map(Value::as_number).collect();
+    numbers
+        .and_then(|numbers| numbers.try_into().ok())
+        .ok_or_else(|| wrong_type(key, "a list of numbers of the right length"))
+}
+
+fn surface(name: &str, key: &str) -> Result<Surface, ConfigError> {
+    Surface::from_name(name)
+        .ok_or_else(|| wrong_type(key, "one of \"asphalt\", \"grass\", \"gravel\" or \"ice\""))
+}
+
+fn required<T>(value: Option<T>, key: &str) -> Result<T, ConfigError> {
+    value.ok_or_else(|| wrong_type(key, "there"))
+}
+
+fn wrong_type(key: &str, expected: &'static str) -> ConfigError {
+    ConfigError::WrongType {
+        key: key.to_string(),
+        expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_sample_track_loads() {
+        let track = load("assets/tracks/square.toml").unwrap();
+        assert_eq!(track.name.as_deref(), Some("Square"));
+        assert_eq!(track.laps, Some(3));
+        assert_eq!(track.terrain.size(), (32, 20));
+        assert_eq!(
+            track.terrain.surface_at(Point2::new(2000., 600.)),
+            Surface::Asphalt
+        );
+        assert_eq!(
+            track.terrain.surface_at(Point2::new(2000., 1250.)),
+            Surface::Grass
+        );
+        assert_eq!(
+            track.terrain.surface_at(Point2::new(3750., 1250.)),
+            Surface::Gravel
+        );
+        assert_eq!(track.walls.len(), 8);
+        assert_eq!(track.checkpoints.len(), 4);
+        assert_eq!(track.spawn_points.len(), 6);
+        // every other slot is off to the right, which is down the map facing right
+        assert!((track.spawn_points[1].position - Point2::new(1450., 660.)).norm() < 1e-6);
+        assert_eq!(track.respawn_points.len(), 6);
+        assert_eq!(track.loose_props.len(), 5);
+        assert_eq!(track.movers.len(), 1);
+        assert_eq!(track.decorations.len(), 3);
+    }
+
+    #[test]
+    fn saved_tracks_load_the_same() {
+        let file = TrackFile::load("assets/tracks/square.toml").unwrap();
+        let text = config::write(&file.table());
+        assert_eq!(
+            TrackFile::read(&config::parse(&text).unwrap()).unwrap(),
+            file
+        );
+    }
+}
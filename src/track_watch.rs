@@ -0,0 +1,126 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    loading::{Loading, Source},
+    scene::{Scene, Transition},
+    track::Track,
+    TICKS_PER_SECOND,
+};
+
+/// Ticks between looks at the file, looking every tick being a waste
+const CHECK_INTERVAL: u32 = TICKS_PER_SECOND as u32;
+
+/// Keeps an eye on the file a track was loaded from, for it to be loaded again whenever it's
+/// saved from some other editor. It's loaded again on a worker thread like the first time, so
+/// the level carries on while it is.
+pub struct TrackWatch {
+    path: PathBuf,
+    /// when the file was last changed, as of the last look
+    modified: Option<SystemTime>,
+    since_check: u32,
+    /// the file being loaded again since it last changed
+    loading: Option<Loading>,
+}
+
+impl TrackWatch {
+    pub fn new(path: impl AsRef<Path>) -> TrackWatch {
+        let path = path.as_ref().to_path_buf();
+        TrackWatch {
+            modified: modified(&path),
+            path,
+            since_check: 0,
+            loading: None,
+        }
+    }
+
+    /// The track all over again once it's loaded, if its file changed since the last look.
+    /// One that doesn't load, like one saved halfway through a change, is left until it's
+    /// saved again.
+    pub fn poll(&mut self) -> Option<Track> {
+        if let Some(loading) = &mut self.loading {
+            if let Transition::Stay = loading.update(&[]) {
+                return None;
+            }
+            let loaded = loading.take();
+            self.loading = None;
+            return match loaded? {
+                Ok(loaded) => Some(loaded.track),
+                Err(error) => {
+                    eprintln!("{error}");
+                    None
+                }
+            };
+        }
+
+        self.since_check += 1;
+        if self.since_check < CHECK_INTERVAL {
+            return None;
+        }
+        self.since_check = 0;
+
+        let modified = modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        let path = self.path.to_string_lossy().into_owned();
+        self.loading = Some(Loading::new(Source::File(path)));
+        None
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn saving_the_file_again_loads_it_again() {
+        let path = std::env::temp_dir().join("drifter_track_watch_test.toml");
+        let original = fs::read_to_string("assets/tracks/square.toml").unwrap();
+        fs::write(&path, &original).unwrap();
+        let mut watch = TrackWatch::new(&path);
+        // a look at the file, and then as long as it takes to load it again if it changed
+        let poll = |watch: &mut TrackWatch| {
+            let reloaded = (0..CHECK_INTERVAL).find_map(|_| watch.poll());
+            if reloaded.is_some() {
+                return reloaded;
+            }
+            while watch.loading.is_some() {
+                if let Some(track) = watch.poll() {
+                    return Some(track);
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            None
+        };
+        assert!(poll(&mut watch).is_none());
+
+        // the clock on some filesystems is too coarse to tell two quick saves apart
+        let save = |text: &str, later: u64| {
+            fs::write(&path, text).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::now() + Duration::from_secs(later))
+                .unwrap();
+        };
+        save(&original.replace("laps = 3", "laps = 5"), 10);
+        assert_eq!(poll(&mut watch).unwrap().laps, Some(5));
+        assert!(poll(&mut watch).is_none());
+
+        // broken halfway through being saved
+        save("size = [", 20);
+        assert!(poll(&mut watch).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
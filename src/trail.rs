@@ -0,0 +1,109 @@
+use std::{collections::VecDeque, path::Path};
+
+use nalgebra::Point2;
+use sdl2::pixels::Color;
+
+use crate::{
+    config::{self, ConfigError, Table},
+    TICKS_PER_SECOND,
+};
+
+/// Further than a car can go in one tick, in pixels, so a jump means it was put back on the
+/// track
+const MAX_STEP: f64 = 60.;
+
+/// How the trail behind the players' cars looks, for practising lines
+#[derive(Clone)]
+pub struct TrailSpec {
+    /// shown from the start, before anyone toggles it
+    pub shown: bool,
+    /// how far back the trail goes, in seconds
+    pub length: f64,
+    /// what the newest end of the trail is drawn in, fading out towards the oldest
+    pub color: Color,
+    /// what the track's racing line is drawn in, on tracks that have one
+    pub racing_line_color: Color,
+}
+
+impl Default for TrailSpec {
+    fn default() -> TrailSpec {
+        TrailSpec {
+            shown: false,
+            length: 5.,
+            color: Color::RGBA(255, 220, 0, 220),
+            racing_line_color: Color::RGBA(0, 200, 255, 160),
+        }
+    }
+}
+
+impl TrailSpec {
+    /// Reads a spec from a TOML file. Anything the file leaves out keeps its default.
+    pub fn load(path: impl AsRef<Path>) -> Result<TrailSpec, ConfigError> {
+        let file = config::load(path)?;
+        let mut spec = TrailSpec::default();
+        file.read_bool("shown", &mut spec.shown)?;
+        file.read_number("length", &mut spec.length)?;
+        read_color(&file, "color", &mut spec.color)?;
+        read_color(&file, "racing_line_color", &mut spec.racing_line_color)?;
+        Ok(spec)
+    }
+
+    fn ticks(&self) -> usize {
+        (self.length * TICKS_PER_SECOND).max(0.) as usize
+    }
+}
+
+/// Overwrites `target` with the `[r, g, b]` or `[r, g, b, a]` under `key` if there is one
+fn read_color(table: &Table, key: &str, target: &mut Color) -> Result<(), ConfigError> {
+    let Some(values) = table.array(key)? else {
+        return Ok(());
+    };
+    let channels: Option<Vec<u8>> = values
+        .iter()
+        .map(|value| {
+            value
+                .as_number()
+                .filter(|number| (0. ..=255.).contains(number))
+                .map(|number| number as u8)
+        })
+        .collect();
+    *target = match channels.as_deref() {
+        Some(&[r, g, b]) => Color::RGB(r, g, b),
+        Some(&[r, g, b, a]) => Color::RGBA(r, g, b, a),
+        _ => {
+            return Err(ConfigError::WrongType {
+                key: key.to_string(),
+                expected: "a color like [255, 220, 0] or [255, 220, 0, 128]",
+            })
+        }
+    };
+    Ok(())
+}
+
+/// Where a car has been lately, oldest first. Only for show, like the skid marks.
+#[derive(Default)]
+pub struct Trail {
+    points: VecDeque<Point2<f64>>,
+}
+
+impl Trail {
+    pub fn points(&self) -> impl ExactSizeIterator<Item = &Point2<f64>> {
+        self.points.iter()
+    }
+
+    /// Adds where the car is this tick, dropping whatever is older than `spec` keeps
+    pub fn update(&mut self, position: Point2<f64>, spec: &TrailSpec) {
+        // a car respawning would otherwise leave a line all the way across the track
+        if self
+            .points
+            .back()
+            .is_some_and(|last| (position - last).norm() > MAX_STEP)
+        {
+            self.points.clear();
+        }
+        self.points.push_back(position);
+        while self.points.len() > spec.ticks() {
+            self.points.pop_front();
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use nalgebra::{Matrix2, Point2, Rotation2, Vector2};
+
+use crate::{
+    car::Car,
+    collision::{self, Obb},
+    tire,
+    track::Track,
+};
+
+/// How far the trailer can swing round before its drawbar hits the car's bumper, in radians
+const JACKKNIFE_ANGLE: f64 = 1.9;
+
+/// A single axle trailer hinged to the back of a car. It's pulled along through the hitch
+/// only, so it swings out in corners, can jackknife and shoves the car when braking.
+pub struct Trailer {
+    center: Point2<f64>,
+    rotation: Rotation2<f64>,
+    previous_center: Point2<f64>,
+    previous_rotation: Rotation2<f64>,
+    velocity: Vector2<f64>,
+    /// radians per tick
+    angular_velocity: f64,
+    /// width and length of the box
+    dimensions: Vector2<f64>,
+    mass: f64,
+    /// the hitch, relative to the center. Its forward is -y, like for the cars.
+    hitch: Vector2<f64>,
+    /// the axle, relative to the center
+    axle: Vector2<f64>,
+}
+
+impl Trailer {
+    /// A trailer standing straight behind `car`, coupled to its hitch
+    pub fn hitched_to(car: &Car) -> Trailer {
+        let dimensions = Vector2::new(46., 120.);
+        let hitch = Vector2::new(0., -dimensions.y / 2. - 30.);
+        let rotation = car.rotation();
+        let center = car.hitch() - rotation * hitch;
+        Trailer {
+            center,
+            rotation,
+            previous_center: center,
+            previous_rotation: rotation,
+            velocity: car.point_velocity(car.hitch()),
+            angular_velocity: 0.,
+            dimensions,
+            mass: 600.,
+            hitch,
+            axle: Vector2::new(0., 20.),
+        }
+    }
+
+    pub fn dimensions(&self) -> Vector2<f64> {
+        self.dimensions
+    }
+
+    pub fn hitch(&self) -> Point2<f64> {
+        self.center + self.rotation * self.hitch
+    }
+
+    pub fn interpolated_center(&self, alpha: f64) -> Point2<f64> {
+        self.previous_center
+            .coords
+            .lerp(&self.center.coords, alpha)
+            .into()
+    }
+
+    pub fn interpolated_rotation(&self, alpha: f64) -> Rotation2<f64> {
+        self.previous_rotation.slerp(&self.rotation, alpha)
+    }
+
+    /// Where the hitch was `alpha` of the way between the last two ticks
+    pub fn interpolated_hitch(&self, alpha: f64) -> Point2<f64> {
+        self.interpolated_center(alpha) + self.interpolated_rotation(alpha) * self.hitch
+    }
+
+    pub fn obb(&self) -> Obb {
+        Obb::new(self.center, self.dimensions, self.rotation)
+    }
+
+    /// Moment of inertia of a uniform box
+    fn inertia(&self) -> f64 {
+        self.mass * self.dimensions.norm_squared() / 12.
+    }
+
+    fn point_velocity(&self, point: Point2<f64>) -> Vector2<f64> {
+        let arm = point - self.center;
+        self.velocity + Vector2::new(-arm.y, arm.x) * self.angular_velocity
+    }
+
+    /// Pushes the trailer at `point` with `impulse`, in mass times pixels per tick
+    fn apply_impulse(&mut self, point: Point2<f64>, impulse: Vector2<f64>) {
+        let arm = point - self.center;
+        self.velocity += impulse / self.mass;
+        self.angular_velocity += arm.perp(&impulse) / self.inertia();
+    }
+
+    /// Follows `car`, which has to have finished its own update for the tick
+    pub fn update(&mut self, car: &mut Car, track: &Track) {
+        self.previous_center = self.center;
+        self.previous_rotation = self.rotation;
+
+        self.roll(track);
+        self.pull(car);
+
+        self.center += self.velocity;
+        self.rotation *= Rotation2::new(self.angular_velocity);
+
+        self.limit_swing(car);
+        // whatever the hinge couldn't catch up on is taken up by the drawbar
+        self.center += car.hitch() - self.hitch();
+
+        self.collide(track);
+    }
+
+    /// The tyres keep the axle from sliding sideways, which is what makes the trailer follow
+    fn roll(&mut self, track: &Track) {
+        let axle = self.center + self.rotation * self.axle;
+        let grip = track.grip_at(axle);
+        let sideways = self.rotation * Vector2::x();
+        let rolling = self.rotation * -Vector2::y();
+
+        let axle_velocity = self.point_velocity(axle);
+        let sliding_speed = axle_velocity.dot(&sideways);
+        let slip_angle = sliding_speed.atan2(axle_velocity.dot(&rolling).abs());
+
+        let leverage = (axle - self.center).perp(&sideways);
+        let stiffness = 1. + leverage.powi(2) * self.mass / self.inertia();
+        let capacity = 0.9 * grip.lateral;
+        // never push past zero, or the trailer would wobble from side to side
+        let force =
+            (tire::LATERAL.force(slip_angle.abs()) * capacity).min(sliding_speed.abs() / stiffness);
+        self.apply_impulse(axle, sideways * -force * sliding_speed.signum() * self.mass);
+
+        let rolling_speed = self.velocity.dot(&rolling);
+        self.velocity -= rolling * rolling_speed * grip.rolling_resistance;
+    }
+
+    /// Makes the two hitches move together, which drags the trailer along and lets it push
+    /// the car just as hard
+    fn pull(&mut self, car: &mut Car) {
+        let hitch = self.hitch();
+        let compliance = hitch_compliance(self.mass, self.inertia(), hitch - self.center)
+            + hitch_compliance(car.spec().mass, car.inertia(), car.hitch() - car.center());
+        let Some(stiffness) = compliance.try_inverse() else {
+            return;
+        };
+
+        let impulse = stiffness * (car.point_velocity(car.hitch()) - self.point_velocity(hitch));
+        self.apply_impulse(hitch, impulse);
+        car.apply_impulse(car.hitch(), -impulse);
+    }
+
+    /// Stops the trailer where the drawbar would hit the car
+    fn limit_swing(&mut self, car: &Car) {
+        let angle = (car.rotation().inverse() * self.rotation).angle();
+        if angle.abs() <= JACKKNIFE_ANGLE {
+            return;
+        }
+        self.rotation = car.rotation() * Rotation2::new(JACKKNIFE_ANGLE * angle.signum());
+        self.angular_velocity = 0.;
+    }
+
+    /// Pushes the trailer out of walls and props and stops it going further in
+    fn collide(&mut self, track: &Track) {
+        let walls = track
+            .walls
+            .iter()
+            .map(|wall| collision::obb_vs_segment(&self.obb(), wall));
+        let props = track
+            .props
+            .iter()
+            .map(|prop| collision::obb_vs_obb(&self.obb(), prop));
+        let contacts: Vec<_> = walls.chain(props).flatten().collect();
+
+        for contact in contacts {
+            self.center += contact.normal * contact.depth;
+            let normal_speed = self.velocity.dot(&contact.normal);
+            if normal_speed < 0. {
+                self.velocity -= contact.normal * normal_speed;
+            }
+        }
+    }
+}
+
+/// How a body's point at `arm` from its center gives way to an impulse, moving and turning the
+/// body at once
+fn hitch_compliance(mass: f64, inertia: f64, arm: Vector2<f64>) -> Matrix2<f64> {
+    Matrix2::new(
+        1. / mass + arm.y * arm.y / inertia,
+        -arm.x * arm.y / inertia,
+        -arm.x * arm.y / inertia,
+        1. / mass + arm.x * arm.x / inertia,
+    )
+}
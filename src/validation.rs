@@ -0,0 +1,314 @@
+use std::collections::VecDeque;
+
+use nalgebra::{Point2, Vector2};
+
+use crate::{
+    collision::{self, Obb, Segment},
+    config::ConfigError,
+    track::Track,
+};
+
+/// How far apart the points are that the way round a track is followed between, in pixels.
+/// Gaps between walls narrower than this may look closed, but no car fits through those.
+const CELL: f64 = 50.;
+/// The most tiles a track can be across or down
+const MAX_TILES: usize = 2000;
+/// Roughly as big as a car, across and along, for every grid slot to need room for one
+const CAR_SIZE: (f64, f64) = (50., 100.);
+
+/// Checks a freshly loaded track over for anything that'd leave it unplayable: sizes that
+/// make no sense, cars on the grid on top of each other or in a wall, gaps in the walls to
+/// drive off the map through and checkpoints that can't be got round in order
+pub fn check(track: &Track) -> Result<(), ConfigError> {
+    check_sizes(track)?;
+    check_grid(track)?;
+
+    let map = Map::new(track);
+    let start = track.grid_slot(0).position;
+    if track.bounds.is_some() && map.search(start, |_| false, |step| !map.in_bounds(step.b)) {
+        return Err(invalid(
+            "the walls",
+            "leave a gap to drive off the map through",
+        ));
+    }
+
+    // every checkpoint from the one before, or from the grid for the first one, without
+    // having to go through any of the others
+    let gates: Vec<_> = track.checkpoints.iter().map(gate).collect();
+    let mut legs: Vec<_> = (0..gates.len()).map(|i| (i.checked_sub(1), i)).collect();
+    if track.laps.is_some() && gates.len() > 1 {
+        legs.push((Some(gates.len() - 1), 0));
+    }
+    for (from, to) in legs {
+        let others = |step: &Segment| {
+            (0..gates.len())
+                .filter(|&i| i != to && Some(i) != from)
+                .any(|i| gates[i].crosses(step))
+        };
+        let reached =
+            |step: &Segment| gates[to].crosses(step) || track.checkpoints[to].contains(step.b);
+        let origin = from.map_or(start, |from| track.checkpoints[from].center);
+        if !map.search(origin, others, reached) {
+            let after = from.map_or("the grid".to_string(), |from| {
+                format!("checkpoint {}", from + 1)
+            });
+            return Err(invalid(
+                &format!("checkpoint {}", to + 1),
+                &format!("can't be got to from {after} without going through a wall or another checkpoint"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_sizes(track: &Track) -> Result<(), ConfigError> {
+    let (width, height) = track.terrain.size();
+    if track.terrain.tile_size() == 0 || width == 0 || height == 0 {
+        return Err(invalid(
+            "the map",
+            "has to be at least a tile across and down",
+        ));
+    }
+    if width > MAX_TILES || height > MAX_TILES {
+        return Err(invalid(
+            "the map",
+            &format!("can't be more than {MAX_TILES} tiles across or down"),
+        ));
+    }
+    let boxes = [
+        ("checkpoint", &track.checkpoints),
+        ("prop", &track.props),
+        ("puddle", &track.puddles),
+    ];
+    for (name, boxes) in boxes {
+        if let Some(i) = boxes.iter().position(|area| area.half_extents.min() <= 0.) {
+            return Err(invalid(&format!("{name} {}", i + 1), "has no size"));
+        }
+    }
+    if let Some(i) = track.movers.iter().position(|mover| mover.speed <= 0.) {
+        return Err(invalid(&format!("mover {}", i + 1), "has to have a speed"));
+    }
+    if track.laps.is_some() && track.checkpoints.is_empty() {
+        return Err(invalid("the laps", "need checkpoints to count them"));
+    }
+    Ok(())
+}
+
+/// Room for a car at every spawn point, clear of the walls and props
+fn check_grid(track: &Track) -> Result<(), ConfigError> {
+    let car = |i: usize| {
+        let spawn = &track.spawn_points[i];
+        Obb::new(
+            spawn.position,
+            Vector2::new(CAR_SIZE.0, CAR_SIZE.1),
+            spawn.rotation,
+        )
+    };
+    for i in 0..track.spawn_points.len() {
+        let name = format!("spawn point {}", i + 1);
+        if let Some(other) =
+            (0..i).find(|&other| collision::obb_vs_obb(&car(i), &car(other)).is_some())
+        {
+            return Err(invalid(
+                &name,
+                &format!("is on top of spawn point {}", other + 1),
+            ));
+        }
+        let walled = track
+            .walls
+            .iter()
+            .any(|wall| collision::obb_vs_segment(&car(i), wall).is_some());
+        let propped = track
+            .props
+            .iter()
+            .any(|prop| collision::obb_vs_obb(&car(i), prop).is_some());
+        if walled || propped {
+            return Err(invalid(&name, "is in a wall"));
+        }
+    }
+    Ok(())
+}
+
+/// The line across the middle of a checkpoint the long way, what the cars have to cross
+fn gate(checkpoint: &Obb) -> Segment {
+    let [x, y] = checkpoint.axes();
+    let half = checkpoint.half_extents;
+    let across = if half.x >= half.y {
+        x * half.x
+    } else {
+        y * half.y
+    };
+    Segment::new(checkpoint.center - across, checkpoint.center + across)
+}
+
+fn invalid(element: &str, problem: &str) -> ConfigError {
+    ConfigError::Invalid {
+        element: element.to_string(),
+        problem: problem.to_string(),
+    }
+}
+
+/// The track cut up into squares, to follow the way the cars could go from one to the next
+struct Map {
+    bounds: Option<(Point2<f64>, Point2<f64>)>,
+    /// the middle of the top left square
+    origin: Point2<f64>,
+    size: (usize, usize),
+    /// whether there's a wall in the way going right from each square, and going down
+    walled: [Vec<bool>; 2],
+}
+
+impl Map {
+    fn new(track: &Track) -> Map {
+        // a square past the edges all round, to go off the map into
+        let (low, high) = track.bounds.unwrap_or_else(|| {
+            let points = track
+                .walls
+                .iter()
+                .flat_map(|wall| [wall.a, wall.b])
+                .chain(track.checkpoints.iter().map(|checkpoint| checkpoint.center))
+                .chain(track.spawn_points.iter().map(|spawn| spawn.position));
+            points.fold(
+                (
+                    Point2::new(f64::MAX, f64::MAX),
+                    Point2::new(f64::MIN, f64::MIN),
+                ),
+                |(low, high), point| (low.inf(&point), high.sup(&point)),
+            )
+        });
+        let origin = low - Vector2::repeat(CELL / 2.);
+        let extent = (high - low) / CELL;
+        let mut map = Map {
+            bounds: track.bounds,
+            origin,
+            size: (extent.x.ceil() as usize + 2, extent.y.ceil() as usize + 2),
+            walled: Default::default(),
+        };
+
+        // only the steps round each wall can go through it
+        let squares = map.size.0 * map.size.1;
+        map.walled = [vec![false; squares], vec![false; squares]];
+        let square = |point: Point2<f64>| {
+            let square = (point - origin) / CELL;
+            (square.x.max(0.) as usize, square.y.max(0.) as usize)
+        };
+        for wall in &track.walls {
+            let (from, to) = (square(wall.a.inf(&wall.b)), square(wall.a.sup(&wall.b)));
+            for y in from.1..=(to.1 + 1).min(map.size.1 - 1) {
+                for x in from.0..=(to.0 + 1).min(map.size.0 - 1) {
+                    let here = map.middle((x, y));
+                    let steps = [Vector2::new(CELL, 0.), Vector2::new(0., CELL)];
+                    for (walled, step) in map.walled.iter_mut().zip(steps) {
+                        if wall.crosses(&Segment::new(here, here + step)) {
+                            walled[y * map.size.0 + x] = true;
+                        }
+                    }
+                }
+            }
+        }
+        map
+    }
+
+    /// Whether a wall's in the way going from one square to the one next to it
+    fn is_walled(&self, (x, y): (usize, usize), (next_x, next_y): (usize, usize)) -> bool {
+        let (square, direction) = match (next_x.cmp(&x), next_y.cmp(&y)) {
+            (std::cmp::Ordering::Greater, _) => ((x, y), 0),
+            (std::cmp::Ordering::Less, _) => ((next_x, next_y), 0),
+            (_, std::cmp::Ordering::Greater) => ((x, y), 1),
+            _ => ((next_x, next_y), 1),
+        };
+        self.walled[direction][square.1 * self.size.0 + square.0]
+    }
+
+    fn in_bounds(&self, point: Point2<f64>) -> bool {
+        self.bounds.is_none_or(|(low, high)| {
+            (low.x..=high.x).contains(&point.x) && (low.y..=high.y).contains(&point.y)
+        })
+    }
+
+    fn middle(&self, (x, y): (usize, usize)) -> Point2<f64> {
+        self.origin + Vector2::new(x as f64, y as f64) * CELL
+    }
+
+    /// Whether there's a way from `from` for which some step between squares is `done`,
+    /// without going through a wall or a step that's `blocked`
+    fn search(
+        &self,
+        from: Point2<f64>,
+        blocked: impl Fn(&Segment) -> bool,
+        done: impl Fn(&Segment) -> bool,
+    ) -> bool {
+        let square = (from - self.origin) / CELL;
+        let (x, y) = (square.x.round(), square.y.round());
+        if x < 0. || y < 0. || x as usize >= self.size.0 || y as usize >= self.size.1 {
+            return false;
+        }
+        let mut seen = vec![false; self.size.0 * self.size.1];
+        let mut queue = VecDeque::from([(x as usize, y as usize)]);
+        seen[y as usize * self.size.0 + x as usize] = true;
+        while let Some((x, y)) = queue.pop_front() {
+            let neighbours = [
+                x.checked_sub(1).map(|x| (x, y)),
+                (x + 1 < self.size.0).then_some((x + 1, y)),
+                y.checked_sub(1).map(|y| (x, y)),
+                (y + 1 < self.size.1).then_some((x, y + 1)),
+            ];
+            for next in neighbours.into_iter().flatten() {
+                let step = Segment::new(self.middle((x, y)), self.middle(next));
+                if self.is_walled((x, y), next) || blocked(&step) {
+                    continue;
+                }
+                // even onto a square already got to some other way
+                if done(&step) {
+                    return true;
+                }
+                if seen[next.1 * self.size.0 + next.0] {
+                    continue;
+                }
+                // the checkpoints are only followed round the map, not off of it
+                if !self.in_bounds(step.b) {
+                    continue;
+                }
+                seen[next.1 * self.size.0 + next.0] = true;
+                queue.push_back(next);
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generator, track_toml::TrackFile};
+
+    fn square() -> TrackFile {
+        TrackFile::load("assets/tracks/square.toml").unwrap()
+    }
+
+    fn problem(file: &TrackFile) -> Option<String> {
+        check(&file.track(std::path::Path::new("")))
+            .err()
+            .map(|error| error.to_string())
+    }
+
+    #[test]
+    fn playable_tracks_pass_and_broken_ones_say_what_is_wrong() {
+        assert!(check(&Track::new()).is_ok());
+        assert_eq!(problem(&square()), None);
+        assert_eq!(problem(&generator::generate(3)), None);
+
+        let mut gap = square();
+        gap.walls[0].closed = false;
+        assert!(problem(&gap).unwrap().starts_with("the walls"));
+
+        // the generated ones' checkpoints go from wall to wall, with no way round them
+        let mut swapped = generator::generate(3);
+        swapped.checkpoints.swap(1, 2);
+        assert!(problem(&swapped).unwrap().starts_with("checkpoint 2 "));
+
+        let mut crowded = square();
+        crowded.grid.as_mut().unwrap().spacing = 20.;
+        assert!(problem(&crowded).unwrap().starts_with("spawn point 3"));
+    }
+}
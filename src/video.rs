@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use sdl2::{
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas, RenderTarget, Texture},
+};
+
+use crate::{
+    assets::Assets,
+    config::{self, ConfigError},
+};
+
+/// How much a CRT's glass bows the picture, as the share it shrinks by at the corners
+const CURVATURE: f64 = 0.06;
+/// Pieces the picture is cut into to bow it, across and down
+const CURVE_STRIPS: (u32, u32) = (160, 120);
+/// Scanlines down the whole picture, however big it ends up on the window
+const SCANLINES: u32 = 360;
+/// How much of the game's resolution is left with the pixelated look
+const PIXELATION: u32 = 4;
+
+/// What the picture is put through on its way to the window, for an arcade look
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Filter {
+    /// as it's drawn
+    #[default]
+    None,
+    /// with dark lines across it like an old monitor
+    Scanlines,
+    /// scanlines on a picture bowed like the glass of a CRT
+    Crt,
+    /// big chunky pixels
+    Pixelated,
+}
+
+impl Filter {
+    pub fn next(self) -> Filter {
+        match self {
+            Filter::None => Filter::Scanlines,
+            Filter::Scanlines => Filter::Crt,
+            Filter::Crt => Filter::Pixelated,
+            Filter::Pixelated => Filter::None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Filter> {
+        match name {
+            "none" => Some(Filter::None),
+            "scanlines" => Some(Filter::Scanlines),
+            "crt" => Some(Filter::Crt),
+            "pixelated" => Some(Filter::Pixelated),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::None => "none",
+            Filter::Scanlines => "scanlines",
+            Filter::Crt => "crt",
+            Filter::Pixelated => "pixelated",
+        }
+    }
+}
+
+/// How the game shows up on the window
+#[derive(Clone, Default)]
+pub struct VideoSpec {
+    pub filter: Filter,
+}
+
+impl VideoSpec {
+    /// Reads a spec from a TOML file. Anything the file leaves out keeps its default.
+    pub fn load(path: impl AsRef<Path>) -> Result<VideoSpec, ConfigError> {
+        let file = config::load(path)?;
+        let mut spec = VideoSpec::default();
+        if let Some(name) = file.string("filter")? {
+            spec.filter = Filter::from_name(name).ok_or(ConfigError::WrongType {
+                key: "filter".to_string(),
+                expected: "one of \"none\", \"scanlines\", \"crt\" or \"pixelated\"",
+            })?;
+        }
+        Ok(spec)
+    }
+}
+
+/// Copies `screen` onto `picture` on `canvas` through `filter`
+pub fn present<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    assets: &Assets,
+    screen: &Texture,
+    picture: Rect,
+    filter: Filter,
+) {
+    let query = screen.query();
+    let size = (query.width, query.height);
+    match filter {
+        Filter::None => canvas.copy(screen, None, picture).unwrap(),
+        Filter::Scanlines => {
+            canvas.copy(screen, None, picture).unwrap();
+            draw_scanlines(canvas, picture);
+        }
+        Filter::Crt => {
+            // bowed top and bottom first, then the sides on the way to the window
+            let mut bowed = assets.target(size.0, size.1);
+            canvas
+                .with_texture_canvas(&mut bowed, |bowed_canvas| {
+                    bowed_canvas.set_draw_color(Color::BLACK);
+                    bowed_canvas.clear();
+                    let strips = CURVE_STRIPS.0;
+                    for i in 0..strips {
+                        let (from, to) = (size.0 * i / strips, size.0 * (i + 1) / strips);
+                        let height = (size.1 as f64 * bow(i, strips)).round() as u32;
+                        let column = Rect::new(from as i32, 0, to - from, size.1);
+                        let area = Rect::new(
+                            from as i32,
+                            ((size.1 - height) / 2) as i32,
+                            to - from,
+                            height,
+                        );
+                        bowed_canvas.copy(screen, column, area).unwrap();
+                    }
+                })
+                .unwrap();
+
+            let strips = CURVE_STRIPS.1;
+            for i in 0..strips {
+                let row = Rect::new(
+                    0,
+                    (size.1 * i / strips) as i32,
+                    size.0,
+                    size.1 * (i + 1) / strips - size.1 * i / strips,
+                );
+                let (from, to) = (
+                    picture.height() * i / strips,
+                    picture.height() * (i + 1) / strips,
+                );
+                let width = (picture.width() as f64 * bow(i, strips)).round() as u32;
+                let area = Rect::new(
+                    picture.x() + ((picture.width() - width) / 2) as i32,
+                    picture.y() + from as i32,
+                    width,
+                    to - from,
+                );
+                canvas.copy(&bowed, row, area).unwrap();
+            }
+            assets.give_back(bowed);
+            draw_scanlines(canvas, picture);
+        }
+        Filter::Pixelated => {
+            // shrunk down and blown back up without smoothing, like everything's drawn
+            let mut small = assets.target(size.0 / PIXELATION, size.1 / PIXELATION);
+            canvas
+                .with_texture_canvas(&mut small, |small_canvas| {
+                    small_canvas.copy(screen, None, None).unwrap();
+                })
+                .unwrap();
+            canvas.copy(&small, None, picture).unwrap();
+            assets.give_back(small);
+        }
+    }
+}
+
+/// How much of its full length the `i`th of `strips` strips across the picture keeps, the
+/// ones towards the edges shrinking the most
+fn bow(i: u32, strips: u32) -> f64 {
+    // from -1 at one edge to 1 at the other
+    let along = (i as f64 + 0.5) / strips as f64 * 2. - 1.;
+    1. - CURVATURE * along * along
+}
+
+fn draw_scanlines<T: RenderTarget>(canvas: &mut Canvas<T>, picture: Rect) {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 90));
+    let thickness = (picture.height() / SCANLINES / 2).max(1);
+    for i in 0..SCANLINES {
+        let y = picture.y() + (picture.height() * i / SCANLINES) as i32;
+        canvas
+            .fill_rect(Rect::new(picture.x(), y, picture.width(), thickness))
+            .unwrap();
+    }
+    canvas.set_blend_mode(BlendMode::None);
+}
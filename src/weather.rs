@@ -0,0 +1,47 @@
+//! Rain coming down over the players' views. The wet track it leaves is the track's to deal
+//! with, this is only how it looks.
+
+use sdl2::rect::FPoint;
+
+/// Streaks on screen at once in each view
+const DROPS: u32 = 250;
+/// how far a streak falls each tick and how far the wind blows it sideways, in pixels
+const FALL: f32 = 28.;
+const WIND: f32 = 6.;
+
+#[derive(Default)]
+pub struct Rain {
+    ticks: u32,
+}
+
+impl Rain {
+    pub fn update(&mut self) {
+        self.ticks = self.ticks.wrapping_add(1);
+    }
+
+    /// Where each streak starts and ends in a `view` sized view, `alpha` of the way to the
+    /// next tick. They're scattered the same every time, falling and wrapping back round to
+    /// the top, so there's nothing to keep track of from one frame to the next.
+    pub fn streaks(&self, view: (i32, i32), alpha: f64) -> impl Iterator<Item = (FPoint, FPoint)> {
+        let (width, height) = (view.0 as f32, view.1 as f32);
+        let time = self.ticks as f32 + alpha as f32;
+        (0..DROPS).map(move |drop| {
+            // some drops are closer and so fall faster and come out longer
+            let speed = 0.6 + 0.4 * scatter(drop, 2);
+            let x = (scatter(drop, 0) * width + time * WIND * speed).rem_euclid(width);
+            let y = (scatter(drop, 1) * height + time * FALL * speed).rem_euclid(height);
+            let start = FPoint::new(x, y);
+            (start, start.offset(WIND * speed, FALL * speed))
+        })
+    }
+}
+
+/// A number from 0 to 1 that's the same for the same `drop` and `salt` but all over the place
+/// from one to the next
+fn scatter(drop: u32, salt: u32) -> f32 {
+    let mut hash = drop.wrapping_mul(0x9e37_79b9) ^ salt.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2c1b_3c6d);
+    hash ^= hash >> 12;
+    (hash % 10_000) as f32 / 10_000.
+}
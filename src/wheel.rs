@@ -0,0 +1,118 @@
+use sdl2::joystick::Joystick;
+
+use crate::config::{self, ConfigError, Table};
+
+/// How to read one axis of a wheel or pedal set. Every device reports a different range, and
+/// pedals often read highest when they're up, so each axis gets calibrated on its own.
+#[derive(Clone)]
+pub struct AxisSpec {
+    /// which of the device's axes it is, counting from 0
+    pub axis: u32,
+    /// the raw reading with the pedal up or the wheel at full lock to the left
+    pub from: f64,
+    /// the raw reading with the pedal floored or the wheel at full lock to the right
+    pub to: f64,
+    /// exponent on the response, above 1 makes the first part of the travel gentler
+    pub linearity: f64,
+}
+
+impl AxisSpec {
+    fn new(axis: u32, from: f64, to: f64) -> AxisSpec {
+        AxisSpec {
+            axis,
+            from,
+            to,
+            linearity: 1.,
+        }
+    }
+
+    /// Overwrites whatever `table` specifies
+    fn read(&mut self, table: &Table) -> Result<(), ConfigError> {
+        let mut axis = self.axis as f64;
+        table.read_number("axis", &mut axis)?;
+        self.axis = axis as u32;
+        if let Some(range) = table.get("range") {
+            (self.from, self.to) = config::as_pair(range).ok_or(ConfigError::WrongType {
+                key: "range".to_string(),
+                expected: "a [from, to] pair of numbers",
+            })?;
+        }
+        table.read_number("linearity", &mut self.linearity)?;
+        Ok(())
+    }
+
+    /// How far along from `from` to `to` the axis is, from 0 to 1. Reads 0 for an axis the
+    /// device doesn't have.
+    fn travel(&self, joystick: &Joystick) -> f64 {
+        match joystick.axis(self.axis) {
+            Ok(raw) => ((raw as f64 - self.from) / (self.to - self.from)).clamp(0., 1.),
+            Err(_) => 0.,
+        }
+    }
+}
+
+/// A racing wheel and pedal set, read as a plain joystick
+#[derive(Clone)]
+pub struct WheelSpec {
+    pub steering: AxisSpec,
+    /// how far the wheel turns from lock to lock, in degrees
+    pub rotation: f64,
+    /// how far it has to be turned from lock to lock for full lock on the car, in degrees.
+    /// Lower than `rotation` gets full lock without turning the wheel all the way.
+    pub steering_rotation: f64,
+    pub throttle: AxisSpec,
+    pub brake: AxisSpec,
+}
+
+impl Default for WheelSpec {
+    fn default() -> WheelSpec {
+        WheelSpec {
+            steering: AxisSpec::new(0, -32768., 32767.),
+            rotation: 900.,
+            steering_rotation: 540.,
+            throttle: AxisSpec::new(1, 32767., -32768.),
+            brake: AxisSpec::new(2, 32767., -32768.),
+        }
+    }
+}
+
+impl WheelSpec {
+    /// Overwrites whatever `table` specifies
+    pub fn read(&mut self, table: &Table) -> Result<(), ConfigError> {
+        table.read_number("rotation", &mut self.rotation)?;
+        table.read_number("steering_rotation", &mut self.steering_rotation)?;
+        if let Some(steering) = table.table("steering")? {
+            self.steering.read(steering)?;
+        }
+        if let Some(throttle) = table.table("throttle")? {
+            self.throttle.read(throttle)?;
+        }
+        if let Some(brake) = table.table("brake")? {
+            self.brake.read(brake)?;
+        }
+        Ok(())
+    }
+
+    /// From -1 for full lock to the left to 1 for full lock to the right
+    pub fn steering(&self, joystick: &Joystick) -> f64 {
+        if joystick.axis(self.steering.axis).is_err() {
+            return 0.;
+        }
+        let turned = self.steering.travel(joystick) * 2. - 1.;
+        let lock = (turned * self.rotation / self.steering_rotation).clamp(-1., 1.);
+        lock.abs().powf(self.steering.linearity) * lock.signum()
+    }
+
+    /// How far the throttle pedal is down, from 0 to 1
+    pub fn throttle(&self, joystick: &Joystick) -> f64 {
+        pedal(&self.throttle, joystick)
+    }
+
+    pub fn brake(&self, joystick: &Joystick) -> f64 {
+        pedal(&self.brake, joystick)
+    }
+}
+
+fn pedal(spec: &AxisSpec, joystick: &Joystick) -> f64 {
+    spec.travel(joystick).powf(spec.linearity)
+}